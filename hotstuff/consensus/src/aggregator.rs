@@ -1,22 +1,36 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
+
+use tokio::time::Instant;
 
 use hotstuff_primitives::{AuthorityId, AuthorityList, AuthoritySignature};
 use sp_runtime::traits::Block;
 
 use crate::{
-	message::{Timeout, Vote, QC, TC},
+	message::{is_quorum, Timeout, Vote, VoteTimestamp, QC, TC},
 	primitives::{HotstuffError, HotstuffError::*, ViewNumber},
 };
 
+// Votes retained per proposal beyond the size of the committee.
+pub const VOTE_RETENTION_SLACK: usize = 2;
+
 #[derive(Default)]
 pub struct Aggregator<B: Block> {
 	votes_aggregator: HashMap<ViewNumber, HashMap<B::Hash, QCMaker>>,
-	timeouts_aggregators: HashMap<ViewNumber, TCMaker>,
+	timeouts_aggregators: HashMap<ViewNumber, TCMaker<B>>,
+	// When the last vote or timeout of each view was received.
+	received: HashMap<ViewNumber, Instant>,
+	// Votes not retained, as their proposal had all the votes it may have.
+	dropped_votes: u64,
 }
 
 impl<B: Block> Aggregator<B> {
 	pub fn new() -> Self {
-		Self { votes_aggregator: HashMap::new(), timeouts_aggregators: HashMap::new() }
+		Self {
+			votes_aggregator: HashMap::new(),
+			timeouts_aggregators: HashMap::new(),
+			received: HashMap::new(),
+			dropped_votes: 0,
+		}
 	}
 
 	pub fn add_vote(
@@ -24,12 +38,42 @@ impl<B: Block> Aggregator<B> {
 		vote: Vote<B>,
 		authorities: &AuthorityList,
 	) -> Result<Option<QC<B>>, HotstuffError> {
-		self.votes_aggregator
+		self.received.insert(vote.view, Instant::now());
+		let maker = self
+			.votes_aggregator
 			.entry(vote.view)
 			.or_default()
 			.entry(vote.digest())
-			.or_default()
-			.append(vote, authorities)
+			.or_default();
+
+		// Only the votes of the committee count, and a full committee fits the cap, so a flood
+		// of votes can't keep a legitimate quorum out.
+		let cap = authorities.len() + VOTE_RETENTION_SLACK;
+		if maker.votes.len() >= cap || !authorities.iter().any(|(id, _)| *id == vote.voter) {
+			self.dropped_votes += 1;
+			return Ok(None);
+		}
+		maker.append(vote, authorities)
+	}
+
+	// The timestamps of the votes of `qc` still held.
+	pub fn vote_timestamps(&self, qc: &QC<B>) -> Vec<VoteTimestamp> {
+		let Some(maker) =
+			self.votes_aggregator.get(&qc.view).and_then(|makers| makers.get(&qc.digest()))
+		else {
+			return Vec::new();
+		};
+		maker
+			.timestamps
+			.iter()
+			.filter(|timestamp| qc.votes.iter().any(|(voter, _)| *voter == timestamp.voter))
+			.cloned()
+			.collect()
+	}
+
+	// The votes not retained since the start.
+	pub fn dropped_votes(&self) -> u64 {
+		self.dropped_votes
 	}
 
 	pub fn add_timeout(
@@ -38,21 +82,41 @@ impl<B: Block> Aggregator<B> {
 		authorities: &AuthorityList,
 	) -> Result<Option<TC<B>>, HotstuffError> {
 		// Add the new timeout to our aggregator and see if we have a TC.
+		self.received.insert(timeout.view, Instant::now());
 		self.timeouts_aggregators
 			.entry(timeout.view)
 			.or_default()
 			.append(timeout.clone(), authorities)
 	}
+
+	// Drop the votes and timeouts of the views before `current` which received none for `ttl`,
+	// returning how many were dropped. The current view and later ones are always kept.
+	pub fn prune_expired(&mut self, now: Instant, ttl: Duration, current: ViewNumber) -> usize {
+		let before = self.buffered_messages();
+		self.received.retain(|view, received| *view >= current || *received + ttl > now);
+
+		let received = &self.received;
+		self.votes_aggregator.retain(|view, _| received.contains_key(view));
+		self.timeouts_aggregators.retain(|view, _| received.contains_key(view));
+		before - self.buffered_messages()
+	}
+
+	// The votes and timeouts held.
+	pub fn buffered_messages(&self) -> usize {
+		let votes = self.votes_aggregator.values().flat_map(HashMap::values);
+		votes.map(|maker| maker.votes.len()).sum::<usize>()
+			+ self.timeouts_aggregators.values().map(|maker| maker.votes.len()).sum::<usize>()
+	}
 }
 
 pub struct QCMaker {
-	weight: u64,
 	votes: Vec<(AuthorityId, AuthoritySignature)>,
+	timestamps: Vec<VoteTimestamp>,
 }
 
 impl QCMaker {
 	pub fn new() -> Self {
-		QCMaker { weight: 0, votes: Vec::new() }
+		QCMaker { votes: Vec::new(), timestamps: Vec::new() }
 	}
 
 	pub fn append<B: Block>(
@@ -60,16 +124,16 @@ impl QCMaker {
 		vote: Vote<B>,
 		authorities: &AuthorityList,
 	) -> Result<Option<QC<B>>, HotstuffError> {
-		// TODO check voter weather in authorities?
 		if self.votes.iter().any(|(id, _)| id.eq(&vote.voter)) {
 			return Ok(None);
 		}
 
+		let timestamp = vote.vote_timestamp();
 		self.votes
 			.push((vote.voter, vote.signature.ok_or(HotstuffError::NullSignature)?));
-		self.weight += 1;
+		self.timestamps.extend(timestamp);
 
-		if self.weight < (authorities.len() * 2 / 3 + 1) as u64 {
+		if !is_quorum(self.votes.iter().map(|(id, _)| id), authorities) {
 			return Ok(None);
 		}
 
@@ -87,17 +151,18 @@ impl Default for QCMaker {
 	}
 }
 
-pub struct TCMaker {
-	weight: u64,
+pub struct TCMaker<B: Block> {
 	votes: Vec<(AuthorityId, AuthoritySignature, ViewNumber)>,
+	// The highest high QC of the timeouts.
+	high_qc: QC<B>,
 }
 
-impl TCMaker {
+impl<B: Block> TCMaker<B> {
 	pub fn new() -> Self {
-		Self { weight: 0, votes: Vec::new() }
+		Self { votes: Vec::new(), high_qc: QC::default() }
 	}
 
-	pub fn append<B: Block>(
+	pub fn append(
 		&mut self,
 		timeout: Timeout<B>,
 		authorities: &AuthorityList,
@@ -108,22 +173,27 @@ impl TCMaker {
 			return Ok(None);
 		}
 
-		self.votes.push((voter, timeout.signature.ok_or(NullSignature)?, timeout.view));
-		self.weight += 1;
+		// The signature of a timeout covers its view and the view of its high QC.
+		self.votes
+			.push((voter, timeout.signature.ok_or(NullSignature)?, timeout.high_qc.view));
+		if timeout.high_qc.view > self.high_qc.view {
+			self.high_qc = timeout.high_qc;
+		}
 
-		if self.weight < (authorities.len() * 2 / 3 + 1) as u64 {
+		if !is_quorum(self.votes.iter().map(|(id, _, _)| id), authorities) {
 			return Ok(None);
 		}
 
 		Ok(Some(TC::<B> {
 			view: timeout.view,
 			votes: self.votes.clone(),
+			high_qc: self.high_qc.clone(),
 			_phantom: std::marker::PhantomData,
 		}))
 	}
 }
 
-impl Default for TCMaker {
+impl<B: Block> Default for TCMaker<B> {
 	fn default() -> Self {
 		Self::new()
 	}
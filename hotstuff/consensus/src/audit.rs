@@ -0,0 +1,284 @@
+// An append-only log of every message signed by the local authority, for forensics and to prove
+// the node never signed conflicting messages. It's stored in segments of length prefixed SCALE
+// records: the current segment at the path of the log, and the rotated ones next to it with
+// increasing sequence numbers, `<path>.1`, `<path>.2`, ...
+use std::{
+	fmt,
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::{Path, PathBuf},
+	str::FromStr,
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use parity_scale_codec::{Decode, Encode};
+
+use crate::primitives::{HotstuffError, ViewNumber};
+
+#[cfg(test)]
+#[path = "tests/audit_tests.rs"]
+pub mod audit_tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum SignedKind {
+	Proposal,
+	Vote,
+	Timeout,
+}
+
+impl fmt::Display for SignedKind {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Proposal => write!(f, "proposal"),
+			Self::Vote => write!(f, "vote"),
+			Self::Timeout => write!(f, "timeout"),
+		}
+	}
+}
+
+impl FromStr for SignedKind {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"proposal" => Ok(Self::Proposal),
+			"vote" => Ok(Self::Vote),
+			"timeout" => Ok(Self::Timeout),
+			_ => Err(format!("unknown signed message kind {}", s)),
+		}
+	}
+}
+
+/// An entry of the audit log, displayed as `<unix millis> <kind> <view> <digest>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+	/// Milliseconds since the unix epoch at signing.
+	pub timestamp: u64,
+	pub kind: SignedKind,
+	pub view: ViewNumber,
+	/// Hex of the signed digest.
+	pub digest: String,
+}
+
+impl fmt::Display for AuditEntry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{} {} {} {}", self.timestamp, self.kind, self.view, self.digest)
+	}
+}
+
+impl FromStr for AuditEntry {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || format!("invalid audit entry {}", s);
+		let mut fields = s.split(' ');
+		let mut next = || fields.next().ok_or_else(invalid);
+
+		let timestamp = next()?.parse().map_err(|_| invalid())?;
+		let kind = next()?.parse()?;
+		let view = next()?.parse().map_err(|_| invalid())?;
+		let digest = next()?.to_string();
+
+		Ok(Self { timestamp, kind, view, digest })
+	}
+}
+
+// An entry as stored, with the raw digest.
+#[derive(Encode, Decode)]
+struct AuditRecord {
+	timestamp: u64,
+	kind: SignedKind,
+	view: ViewNumber,
+	digest: Vec<u8>,
+}
+
+impl From<AuditRecord> for AuditEntry {
+	fn from(record: AuditRecord) -> Self {
+		Self {
+			timestamp: record.timestamp,
+			kind: record.kind,
+			view: record.view,
+			digest: array_bytes::bytes2hex("0x", record.digest),
+		}
+	}
+}
+
+/// When the current segment of the audit log is rotated, and how many rotated segments are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRotation {
+	/// Bytes the current segment grows to before it's rotated.
+	pub max_segment_bytes: u64,
+	/// How long the current segment is written to before it's rotated, whatever its size.
+	pub max_segment_age: Option<Duration>,
+	/// Rotated segments kept, the oldest are deleted first.
+	pub retained_segments: usize,
+}
+
+impl Default for AuditRotation {
+	fn default() -> Self {
+		Self { max_segment_bytes: 64 * 1024 * 1024, max_segment_age: None, retained_segments: 16 }
+	}
+}
+
+pub struct AuditLog {
+	path: PathBuf,
+	file: File,
+	rotation: AuditRotation,
+	// The size of the current segment, and when it was opened.
+	segment_bytes: u64,
+	segment_opened: Instant,
+}
+
+impl AuditLog {
+	/// Open the log at `path` for appending, creating it and its directory when missing.
+	pub fn open(path: &Path) -> io::Result<Self> {
+		Self::open_with(path, AuditRotation::default())
+	}
+
+	/// Open the log at `path`, rotating its segments following `rotation`.
+	pub fn open_with(path: &Path, rotation: AuditRotation) -> io::Result<Self> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		let segment_bytes = file.metadata()?.len();
+		Ok(Self {
+			path: path.to_path_buf(),
+			file,
+			rotation,
+			segment_bytes,
+			segment_opened: Instant::now(),
+		})
+	}
+
+	/// Durably record a signed message. It must not be released to the network when this fails.
+	pub fn record(
+		&mut self,
+		kind: SignedKind,
+		view: ViewNumber,
+		digest: &[u8],
+	) -> Result<(), HotstuffError> {
+		let timestamp = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|duration| duration.as_millis() as u64)
+			.unwrap_or_default();
+		let record = AuditRecord { timestamp, kind, view, digest: digest.to_vec() }.encode();
+
+		let mut data = (record.len() as u32).to_le_bytes().to_vec();
+		data.extend(record);
+		self.file
+			.write_all(&data)
+			.and_then(|_| self.file.sync_data())
+			.map_err(|e| HotstuffError::AuditLog(e.to_string()))?;
+		self.segment_bytes += data.len() as u64;
+
+		// The entry is durable in the current segment, rotating only moves it.
+		if self.rotation_due() {
+			self.rotate().map_err(|e| HotstuffError::AuditLog(e.to_string()))?;
+		}
+		Ok(())
+	}
+
+	fn rotation_due(&self) -> bool {
+		self.segment_bytes >= self.rotation.max_segment_bytes
+			|| self
+				.rotation
+				.max_segment_age
+				.map_or(false, |age| self.segment_opened.elapsed() >= age)
+	}
+
+	// Move the current segment after the rotated ones and start a new one, deleting the oldest
+	// rotated segments beyond the retention.
+	fn rotate(&mut self) -> io::Result<()> {
+		let mut rotated = Self::rotated_segments(&self.path)?;
+		let sequence = rotated.last().map_or(1, |(sequence, _)| sequence + 1);
+		let segment = segment_path(&self.path, sequence);
+		fs::rename(&self.path, &segment)?;
+		rotated.push((sequence, segment));
+
+		self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+		self.segment_bytes = 0;
+		self.segment_opened = Instant::now();
+
+		let expired = rotated.len().saturating_sub(self.rotation.retained_segments);
+		for (_, segment) in rotated.drain(..expired) {
+			fs::remove_file(segment)?;
+		}
+		Ok(())
+	}
+
+	// The rotated segments of the log at `path`, oldest first.
+	fn rotated_segments(path: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+		let Some(name) = path.file_name() else {
+			return Ok(Vec::new());
+		};
+		let prefix = format!("{}.", name.to_string_lossy());
+		let dir = match path.parent() {
+			Some(dir) if !dir.as_os_str().is_empty() => dir,
+			_ => Path::new("."),
+		};
+
+		let mut segments = Vec::new();
+		for entry in fs::read_dir(dir)? {
+			let entry = entry?;
+			let file_name = entry.file_name();
+			let sequence = file_name
+				.to_str()
+				.and_then(|file_name| file_name.strip_prefix(&prefix))
+				.and_then(|sequence| sequence.parse::<u64>().ok());
+			if let Some(sequence) = sequence {
+				segments.push((sequence, segment_path(path, sequence)));
+			}
+		}
+		segments.sort();
+		Ok(segments)
+	}
+
+	/// Read the entries of the log at `path` in signing order, across its segments.
+	pub fn read_entries(path: &Path) -> io::Result<Vec<AuditEntry>> {
+		let mut entries = Vec::new();
+		for (_, segment) in Self::rotated_segments(path)? {
+			entries.extend(read_segment(&segment)?);
+		}
+		entries.extend(read_segment(path)?);
+		Ok(entries)
+	}
+
+	/// The entries of the log at `path` signed at `view`.
+	pub fn entries_at_view(path: &Path, view: ViewNumber) -> io::Result<Vec<AuditEntry>> {
+		let mut entries = Self::read_entries(path)?;
+		entries.retain(|entry| entry.view == view);
+		Ok(entries)
+	}
+}
+
+fn segment_path(path: &Path, sequence: u64) -> PathBuf {
+	let mut segment = path.as_os_str().to_owned();
+	segment.push(format!(".{}", sequence));
+	segment.into()
+}
+
+// The records of a segment. A record cut short by a crash while it was written is dropped, as
+// the message it records was never released.
+fn read_segment(path: &Path) -> io::Result<Vec<AuditEntry>> {
+	let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+	let data = match fs::read(path) {
+		Ok(data) => data,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+
+	let mut entries = Vec::new();
+	let mut rest = &data[..];
+	while rest.len() >= 4 {
+		let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+		let Some(mut record) = rest.get(4..4 + len) else {
+			break;
+		};
+		let record = AuditRecord::decode(&mut record).map_err(|e| invalid(e.to_string()))?;
+		entries.push(record.into());
+		rest = &rest[4 + len..];
+	}
+	Ok(entries)
+}
@@ -1,10 +1,18 @@
-use std::{fmt::Debug, marker::PhantomData, ops::Add};
+use std::{collections::HashSet, fmt::Debug, marker::PhantomData, ops::Add};
 
+use log::warn;
 use parity_scale_codec::{Decode, Encode};
 use parking_lot::MappedMutexGuard;
 use sc_consensus::shared_data::{SharedData, SharedDataLocked};
+use sp_runtime::{generic::OpaqueDigestItemId, traits::Header as HeaderT};
 
-use hotstuff_primitives::AuthorityList;
+use hotstuff_primitives::{AuthorityId, AuthorityList, ConsensusLog, HOTSTUFF_ENGINE_ID};
+
+use crate::primitives::{HotstuffError, HotstuffError::*};
+
+#[cfg(test)]
+#[path = "tests/authorities_tests.rs"]
+pub mod authorities_tests;
 
 /// A shared authority set.
 pub struct SharedAuthoritySet<H, N> {
@@ -22,6 +30,12 @@ where
 	}
 }
 
+impl<H, N> Clone for SharedAuthoritySet<H, N> {
+	fn clone(&self) -> Self {
+		SharedAuthoritySet { inner: self.inner.clone() }
+	}
+}
+
 impl<H, N> SharedAuthoritySet<H, N> {
 	pub fn inner(&self) -> MappedMutexGuard<AuthoritySet<H, N>> {
 		self.inner.shared_data()
@@ -62,19 +76,92 @@ impl<H, N> From<AuthoritySet<H, N>> for SharedAuthoritySet<H, N> {
 	}
 }
 
+// authority sets must have some weight, authorities of weight 0 are listed but don't count
+fn invalid_authority_list(authorities: &AuthorityList) -> bool {
+	authorities.iter().all(|(_, w)| *w == 0)
+}
+
+/// What loading an authority set does with an authority listed more than once, which breaks
+/// the quorum and leader election math.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateAuthorities {
+	/// Reject the set, to surface the bug of the runtime.
+	#[default]
+	Reject,
+	/// Keep the first entry of the authority with the weights of every entry summed, and warn.
+	Merge,
+}
+
+/// Load `authorities` as listed by the runtime, resolving repeated authorities by `policy`.
+pub fn load_authority_set(
+	authorities: AuthorityList,
+	policy: DuplicateAuthorities,
+) -> Result<AuthorityList, HotstuffError> {
+	let mut loaded: AuthorityList = Vec::with_capacity(authorities.len());
+	for (id, weight) in authorities {
+		let Some(first) = loaded.iter_mut().find(|(member, _)| *member == id) else {
+			loaded.push((id, weight));
+			continue;
+		};
+		if policy == DuplicateAuthorities::Reject {
+			return Err(DuplicateAuthorityInSet(id));
+		}
+
+		warn!(target: "Hotstuff", "authority {} is listed more than once in the authority set, merging its weights", id);
+		first.1 = first.1.saturating_add(weight);
+	}
+	Ok(loaded)
+}
+
+/// The authorities `header` announces in its digest, which the runtime hands over to. The runtime
+/// doesn't weigh its authorities, so each one counts the same.
+pub fn announced_authorities<H: HeaderT>(header: &H) -> Option<AuthorityList> {
+	header.digest().logs().iter().find_map(|log| {
+		match log.try_to(OpaqueDigestItemId::Consensus(&HOTSTUFF_ENGINE_ID)) {
+			Some(ConsensusLog::<AuthorityId>::AuthoritiesChange(authorities)) => {
+				Some(authorities.into_iter().map(|id| (id, 1)).collect())
+			},
+			_ => None,
+		}
+	})
+}
+
+/// The number of authorities of `new` which are also in `old`.
+pub fn shared_members(old: &AuthorityList, new: &AuthorityList) -> usize {
+	new.iter().filter(|(id, _)| old.iter().any(|(member, _)| member == id)).count()
+}
+
+/// Check the authorities can hand over from `old` to `new`. Without a member in common nobody
+/// carries the locked QC into the new set, which leaves a safety gap unless the sets run a
+/// handshake, so such a transition is warned about.
+pub fn verify_set_transition(
+	old: &AuthorityList,
+	new: &AuthorityList,
+) -> Result<(), HotstuffError> {
+	if invalid_authority_list(new) {
+		return Err(InvalidAuthoritySet("empty or without weight".into()));
+	}
+
+	let mut seen = HashSet::new();
+	if let Some((id, _)) = new.iter().find(|(id, _)| !seen.insert(id)) {
+		return Err(InvalidAuthoritySet(format!("{} is listed twice", id)));
+	}
+
+	if shared_members(old, new) == 0 {
+		warn!(target: "Hotstuff", "new authority set shares no member with the old set, safety relies on a handshake");
+	}
+
+	Ok(())
+}
+
 impl<H, N> AuthoritySet<H, N>
 where
 	H: PartialEq,
 	N: Ord + Clone,
 {
-	// authority sets must be non-empty and all weights must be greater than 0
-	fn invalid_authority_list(authorities: &AuthorityList) -> bool {
-		authorities.is_empty() || authorities.iter().any(|(_, w)| *w == 0)
-	}
-
 	/// Get a genesis set with given authorities.
 	pub(crate) fn genesis(initial: AuthorityList) -> Option<Self> {
-		if Self::invalid_authority_list(&initial) {
+		if invalid_authority_list(&initial) {
 			return None;
 		}
 
@@ -91,7 +178,7 @@ where
 		authorities: AuthorityList,
 		authority_set_changes: AuthoritySetChanges<N>,
 	) -> Option<Self> {
-		if Self::invalid_authority_list(&authorities) {
+		if invalid_authority_list(&authorities) {
 			return None;
 		}
 
@@ -101,4 +188,45 @@ where
 			_phantom: PhantomData,
 		})
 	}
+
+	/// Hand over to the `new` authorities after block `number`, the last block of the current
+	/// set. The block must be committed, so every honest authority switches at the same block.
+	pub fn apply_transition(
+		&mut self,
+		new: AuthorityList,
+		number: N,
+		finalized: N,
+	) -> Result<(), HotstuffError>
+	where
+		N: Debug,
+	{
+		if number > finalized {
+			return Err(UncommittedSetTransition(format!("{:?}", number)));
+		}
+		verify_set_transition(&self.current_authorities, &new)?;
+
+		let set_id = self.authority_set_changes.0.len() as u64;
+		self.authority_set_changes.0.push((set_id, number));
+		self.current_authorities = new;
+
+		Ok(())
+	}
+}
+
+/// The encoding of `authorities` to hash into a digest, the same whatever their order so every
+/// node agrees on it: sorted by the bytes of their id, each id followed by its weight as eight
+/// little endian bytes.
+pub fn canonical_authorities_encoding(authorities: &AuthorityList) -> Vec<u8> {
+	let mut sorted = authorities
+		.iter()
+		.map(|(id, weight)| (AsRef::<[u8]>::as_ref(id), *weight))
+		.collect::<Vec<_>>();
+	sorted.sort();
+
+	let mut encoded = Vec::with_capacity(sorted.iter().map(|(id, _)| id.len() + 8).sum());
+	for (id, weight) in sorted {
+		encoded.extend_from_slice(id);
+		encoded.extend_from_slice(&weight.to_le_bytes());
+	}
+	encoded
 }
@@ -26,7 +26,7 @@ where
 	let genesis_authorities = genesis_authorities()?;
 
 	let genesis_set = AuthoritySet::genesis(genesis_authorities)
-		.expect("genesis authorities is non-empty; all weights are non-zero; qed.");
+		.expect("genesis authorities is non-empty; every authority has weight 1; qed.");
 
 	Ok(PersistentData { authority_set: genesis_set.into() })
 }
@@ -0,0 +1,376 @@
+// An in-memory tree of the proposals known to the local node, used for debugging forks.
+use std::{
+	cmp::Reverse,
+	collections::{HashMap, HashSet},
+	sync::Arc,
+};
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+use hotstuff_primitives::AuthorityList;
+
+use crate::{
+	message::{bitfield_hex, signer_bitfield, unknown_signers, Payload, Proposal, QC},
+	primitives::ViewNumber,
+};
+
+#[cfg(test)]
+#[path = "tests/block_tree_tests.rs"]
+pub mod block_tree_tests;
+
+/// A block tree shared between the consensus worker and RPC.
+pub type SharedBlockTree<B> = Arc<RwLock<BlockTree<B>>>;
+
+/// The signers of a QC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QcSigners {
+	/// Hex encoded [`signer_bitfield`] over the authorities.
+	pub bitfield: String,
+	/// Signers which are not in the authorities.
+	pub unknown: Vec<String>,
+	/// Number of votes in the QC.
+	pub votes: usize,
+}
+
+impl QcSigners {
+	pub fn new<B: BlockT>(qc: &QC<B>, authorities: &AuthorityList) -> Self {
+		Self {
+			bitfield: bitfield_hex(&signer_bitfield(qc, authorities)),
+			unknown: unknown_signers(qc, authorities).iter().map(|id| id.to_string()).collect(),
+			votes: qc.votes.len(),
+		}
+	}
+}
+
+/// A node of the [`BlockTreeView`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTreeNode<H, N> {
+	/// Hotstuff proposal digest.
+	pub hash: H,
+	pub view: ViewNumber,
+	/// The substrate block carried by the proposal.
+	pub block_hash: H,
+	pub block_number: N,
+	/// Signers of the known QC certifying this proposal.
+	pub qc_signers: Option<QcSigners>,
+	pub committed: bool,
+	/// Hex encoded metadata of the proposer.
+	pub metadata: Option<String>,
+	pub children: Vec<BlockTreeNode<H, N>>,
+}
+
+/// A structured snapshot of the known proposals.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockTreeView<H, N> {
+	/// Digest of the last committed proposal, `Default::default()` before anything has been
+	/// committed.
+	pub committed: H,
+	/// Proposals rooted at the last committed proposal. Before anything has been committed
+	/// these are the proposals built on the genesis QC.
+	pub roots: Vec<BlockTreeNode<H, N>>,
+	/// Proposals whose parent is unknown, grouped under a synthetic "pending" root.
+	pub pending: Vec<BlockTreeNode<H, N>>,
+}
+
+/// A proposal seen but not committed yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposalSummary<H, N> {
+	/// Hotstuff proposal digest.
+	pub hash: H,
+	pub view: ViewNumber,
+	pub block_hash: H,
+	pub block_number: N,
+	/// Votes of the known QC certifying this proposal, zero when it is not certified.
+	pub votes: usize,
+}
+
+/// A branch of the proposals above the last committed one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkInfo<H, N> {
+	/// Hotstuff digest of the last proposal of the branch.
+	pub tip: H,
+	pub tip_view: ViewNumber,
+	pub block_hash: H,
+	pub block_number: N,
+	/// View of the highest certified proposal of the branch, none when none is certified.
+	pub high_qc_view: Option<ViewNumber>,
+	/// Uncommitted proposals of the branch, from the last committed proposal to the tip.
+	pub depth: usize,
+}
+
+/// The proposal to extend: the certified proposal of the highest view connected to the last
+/// committed one, the lowest digest first between proposals of the same view. That's the last
+/// committed proposal when none is certified, as in an empty tree.
+pub fn fork_choice<H: Copy + Ord, N>(tree: &BlockTreeView<H, N>) -> H {
+	let mut head: Option<(ViewNumber, Reverse<H>)> = None;
+	let mut stack = tree.roots.iter().collect::<Vec<_>>();
+	while let Some(node) = stack.pop() {
+		if node.qc_signers.is_some() {
+			head = head.max(Some((node.view, Reverse(node.hash))));
+		}
+		stack.extend(node.children.iter());
+	}
+
+	head.map_or(tree.committed, |(_, Reverse(hash))| hash)
+}
+
+struct ProposalNode<B: BlockT> {
+	parent: B::Hash,
+	view: ViewNumber,
+	payload: Payload<B>,
+	committed: bool,
+	metadata: Option<Vec<u8>>,
+}
+
+pub struct BlockTree<B: BlockT> {
+	// Digest of the last committed proposal, `Default::default()` is the genesis QC.
+	committed: B::Hash,
+	nodes: HashMap<B::Hash, ProposalNode<B>>,
+	// Proposals certified by a known QC, with the view of the QC. A QC may be seen before the
+	// proposal it certifies.
+	certified: HashMap<B::Hash, (ViewNumber, QcSigners)>,
+}
+
+impl<B: BlockT> Default for BlockTree<B> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<B: BlockT> BlockTree<B> {
+	pub fn new() -> Self {
+		Self { committed: Default::default(), nodes: HashMap::new(), certified: HashMap::new() }
+	}
+
+	pub fn shared() -> SharedBlockTree<B> {
+		Arc::new(RwLock::new(Self::new()))
+	}
+
+	pub fn committed_hash(&self) -> B::Hash {
+		self.committed
+	}
+
+	pub fn insert(&mut self, proposal: &Proposal<B>, authorities: &AuthorityList) {
+		let hash = proposal.digest();
+		if self.nodes.contains_key(&hash) {
+			return;
+		}
+
+		// The proposal carries the QC of its parent.
+		if proposal.qc != QC::<B>::default() {
+			self.insert_qc(&proposal.qc, authorities);
+		}
+
+		self.nodes.insert(
+			hash,
+			ProposalNode {
+				parent: proposal.parent_hash(),
+				view: proposal.view,
+				payload: proposal.payload.clone(),
+				committed: false,
+				metadata: proposal.metadata.clone(),
+			},
+		);
+	}
+
+	pub fn insert_qc(&mut self, qc: &QC<B>, authorities: &AuthorityList) {
+		self.certified
+			.entry(qc.proposal_hash)
+			.or_insert_with(|| (qc.view, QcSigners::new(qc, authorities)));
+	}
+
+	/// Mark the proposal and its ancestors as committed, then drop every proposal which can no
+	/// longer be committed.
+	pub fn commit(&mut self, hash: B::Hash) {
+		let mut cursor = hash;
+		while let Some(node) = self.nodes.get_mut(&cursor) {
+			if node.committed {
+				break;
+			}
+			node.committed = true;
+			cursor = node.parent;
+		}
+
+		let committed_view = match self.nodes.get(&hash) {
+			Some(node) => node.view,
+			None => return,
+		};
+		self.committed = hash;
+
+		let children = self.children_index();
+		let mut keep = HashSet::new();
+		let mut stack = vec![hash];
+		for orphan in self.orphans() {
+			if self.nodes.get(&orphan).map_or(false, |node| node.view > committed_view) {
+				stack.push(orphan);
+			}
+		}
+		while let Some(current) = stack.pop() {
+			if keep.insert(current) {
+				stack.extend(children.get(&current).into_iter().flatten());
+			}
+		}
+
+		// A QC of an unknown proposal at or below the committed view certifies one which can no
+		// longer be committed, like one carried by a timeout.
+		self.certified
+			.retain(|hash, (view, _)| keep.contains(hash) || *view > committed_view);
+		self.nodes.retain(|hash, _| keep.contains(hash));
+	}
+
+	pub fn view(&self) -> BlockTreeView<B::Hash, NumberFor<B>> {
+		let children = self.children_index();
+
+		let roots = if self.nodes.contains_key(&self.committed) {
+			vec![self.build_node(&self.committed, &children)]
+		} else {
+			self.build_children(&self.committed, &children)
+		};
+
+		let mut pending = self
+			.orphans()
+			.into_iter()
+			.map(|hash| self.build_node(&hash, &children))
+			.collect::<Vec<_>>();
+		pending.sort_by(|a, b| (a.view, a.hash).cmp(&(b.view, b.hash)));
+
+		BlockTreeView { committed: self.committed, roots, pending }
+	}
+
+	/// The uncommitted proposals ordered by view. Proposals which can no longer be committed were
+	/// already pruned by [`Self::commit`].
+	pub fn pending_proposals(&self) -> Vec<ProposalSummary<B::Hash, NumberFor<B>>> {
+		let mut pending = self
+			.nodes
+			.iter()
+			.filter(|(_, node)| !node.committed)
+			.map(|(hash, node)| ProposalSummary {
+				hash: *hash,
+				view: node.view,
+				block_hash: node.payload.block_hash,
+				block_number: node.payload.block_number,
+				votes: self.certified.get(hash).map_or(0, |(_, signers)| signers.votes),
+			})
+			.collect::<Vec<_>>();
+		pending.sort_by(|a, b| (a.view, a.hash).cmp(&(b.view, b.hash)));
+		pending
+	}
+
+	/// The uncommitted proposals above `high_qc_view` without a known QC, from the highest view.
+	/// Their views timed out before they were certified.
+	pub fn uncertified_proposals(
+		&self,
+		high_qc_view: ViewNumber,
+	) -> Vec<ProposalSummary<B::Hash, NumberFor<B>>> {
+		let mut uncertified = self
+			.pending_proposals()
+			.into_iter()
+			.filter(|summary| {
+				summary.view > high_qc_view && !self.certified.contains_key(&summary.hash)
+			})
+			.collect::<Vec<_>>();
+		uncertified.reverse();
+		uncertified
+	}
+
+	/// Every branch above the last committed proposal by its tip, from the deepest. A single
+	/// chain is one branch, and there's none when no proposal is above the committed one.
+	/// Proposals of unknown parents aren't on any branch.
+	pub fn active_forks(&self) -> Vec<ForkInfo<B::Hash, NumberFor<B>>> {
+		let children = self.children_index();
+		let mut forks = Vec::new();
+		let mut stack = children
+			.get(&self.committed)
+			.into_iter()
+			.flatten()
+			.map(|hash| (*hash, 0, None))
+			.collect::<Vec<_>>();
+		while let Some((hash, depth, high_qc_view)) = stack.pop() {
+			let node = &self.nodes[&hash];
+			let depth = depth + usize::from(!node.committed);
+			let high_qc_view = if self.certified.contains_key(&hash) {
+				high_qc_view.max(Some(node.view))
+			} else {
+				high_qc_view
+			};
+
+			match children.get(&hash) {
+				Some(next) => stack.extend(next.iter().map(|child| (*child, depth, high_qc_view))),
+				None => forks.push(ForkInfo {
+					tip: hash,
+					tip_view: node.view,
+					block_hash: node.payload.block_hash,
+					block_number: node.payload.block_number,
+					high_qc_view,
+					depth,
+				}),
+			}
+		}
+
+		forks.sort_by(|a, b| {
+			b.depth.cmp(&a.depth).then((a.tip_view, a.tip).cmp(&(b.tip_view, b.tip)))
+		});
+		forks
+	}
+
+	// Proposals whose parent is neither known nor the committed root.
+	fn orphans(&self) -> Vec<B::Hash> {
+		self.nodes
+			.iter()
+			.filter(|(hash, node)| {
+				**hash != self.committed
+					&& node.parent != self.committed
+					&& !self.nodes.contains_key(&node.parent)
+			})
+			.map(|(hash, _)| *hash)
+			.collect()
+	}
+
+	fn children_index(&self) -> HashMap<B::Hash, Vec<B::Hash>> {
+		let mut children = HashMap::<B::Hash, Vec<B::Hash>>::new();
+		for (hash, node) in self.nodes.iter() {
+			children.entry(node.parent).or_default().push(*hash);
+		}
+		children
+	}
+
+	fn build_children(
+		&self,
+		parent: &B::Hash,
+		children: &HashMap<B::Hash, Vec<B::Hash>>,
+	) -> Vec<BlockTreeNode<B::Hash, NumberFor<B>>> {
+		let mut nodes = children
+			.get(parent)
+			.into_iter()
+			.flatten()
+			.map(|hash| self.build_node(hash, children))
+			.collect::<Vec<_>>();
+		nodes.sort_by(|a, b| (a.view, a.hash).cmp(&(b.view, b.hash)));
+		nodes
+	}
+
+	fn build_node(
+		&self,
+		hash: &B::Hash,
+		children: &HashMap<B::Hash, Vec<B::Hash>>,
+	) -> BlockTreeNode<B::Hash, NumberFor<B>> {
+		let node = &self.nodes[hash];
+		BlockTreeNode {
+			hash: *hash,
+			view: node.view,
+			block_hash: node.payload.block_hash,
+			block_number: node.payload.block_number,
+			qc_signers: self.certified.get(hash).map(|(_, signers)| signers.clone()),
+			committed: node.committed,
+			metadata: node.metadata.as_ref().map(|metadata| array_bytes::bytes2hex("0x", metadata)),
+			children: self.build_children(hash, children),
+		}
+	}
+}
@@ -0,0 +1,55 @@
+// The certificates the voter holds, which decide the proposals it votes for, to diagnose why a
+// node does or doesn't vote.
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{
+	message::{QC, TC},
+	primitives::ViewNumber,
+};
+
+/// A QC by the view and digest of the proposal it certifies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QcSummary<Hash> {
+	pub view: ViewNumber,
+	pub proposal_hash: Hash,
+	pub signers: usize,
+}
+
+impl<B: BlockT> From<&QC<B>> for QcSummary<B::Hash> {
+	fn from(qc: &QC<B>) -> Self {
+		Self { view: qc.view, proposal_hash: qc.proposal_hash, signers: qc.votes.len() }
+	}
+}
+
+/// A TC by its view and the highest QC of its timeouts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TcSummary<Hash> {
+	pub view: ViewNumber,
+	pub high_qc: QcSummary<Hash>,
+	pub signers: usize,
+}
+
+impl<B: BlockT> From<&TC<B>> for TcSummary<B::Hash> {
+	fn from(tc: &TC<B>) -> Self {
+		Self { view: tc.view, high_qc: (&tc.high_qc).into(), signers: tc.votes.len() }
+	}
+}
+
+/// The certificates of the voter. It's locked on its highest QC: a proposal justified by a TC
+/// must extend a QC at least as high as those the TC knows of.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Certificates<Hash> {
+	pub locked_qc: QcSummary<Hash>,
+	/// None once a QC of its view or a later one is known.
+	pub high_tc: Option<TcSummary<Hash>>,
+}
+
+/// The certificates published by the voter, cheap to clone.
+pub type SharedCertificates<B> = Arc<RwLock<Option<Certificates<<B as BlockT>::Hash>>>>;
@@ -0,0 +1,47 @@
+// A trusted checkpoint a new node starts the voter from instead of from genesis, the weak
+// subjectivity of BFT chains.
+use std::{fs, path::Path};
+
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+use hotstuff_primitives::AuthorityList;
+
+use crate::{
+	committee::CommitteeSampler,
+	justification::HotstuffJustification,
+	message::SigningContext,
+	primitives::{HotstuffError, HotstuffError::*},
+};
+
+#[cfg(test)]
+#[path = "tests/checkpoint_tests.rs"]
+pub mod checkpoint_tests;
+
+/// A committed block, the proof of its commit and the authorities which committed it.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct TrustedCheckpoint<B: BlockT> {
+	pub hash: B::Hash,
+	pub number: NumberFor<B>,
+	pub justification: HotstuffJustification<B>,
+	pub authorities: AuthorityList,
+}
+
+impl<B: BlockT> TrustedCheckpoint<B> {
+	/// Read a SCALE encoded checkpoint from `path`.
+	pub fn load(path: &Path) -> Result<Self, HotstuffError> {
+		let data = fs::read(path).map_err(|e| InvalidCheckpoint(e.to_string()))?;
+		Decode::decode(&mut &data[..]).map_err(|e| InvalidCheckpoint(e.to_string()))
+	}
+
+	/// Verify the authorities of the checkpoint committed its block.
+	pub fn verify(
+		&self,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
+		self.justification
+			.verify(self.hash, self.number, &self.authorities, sampler, context)
+			.map_err(|e| InvalidCheckpoint(format!("{:?}", e)))
+	}
+}
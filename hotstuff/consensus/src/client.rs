@@ -4,8 +4,8 @@ use std::{marker::PhantomData, sync::Arc};
 use parity_scale_codec::Decode;
 
 use sc_client_api::{
-	AuxStore, Backend, BlockchainEvents, CallExecutor, ExecutorProvider, Finalizer, LockImportRun,
-	StorageProvider,
+	AuxStore, Backend, BlockBackend, BlockchainEvents, CallExecutor, ExecutorProvider, Finalizer,
+	LockImportRun, StorageProvider,
 };
 use sc_consensus::BlockImport;
 use sp_api::ProvideRuntimeApi;
@@ -16,9 +16,22 @@ use sp_runtime::{
 	traits::{Block as BlockT, NumberFor, Zero},
 };
 
-use hotstuff_primitives::AuthorityId;
-
-use crate::{authorities::SharedAuthoritySet, aux_schema, import::HotstuffBlockImport};
+use hotstuff_primitives::{AuthorityId, AuthorityList};
+
+use crate::{
+	authorities::SharedAuthoritySet,
+	aux_schema,
+	block_tree::{BlockTree, SharedBlockTree},
+	certificates::SharedCertificates,
+	events::EventSink,
+	finality::SharedCommittedBlocks,
+	import::HotstuffBlockImport,
+	inclusion::{InclusionIndex, SharedInclusionIndex},
+	pacemaker::SharedPacemaker,
+	participation::{SharedVoteParticipation, VoteParticipation},
+	pause::PauseHandle,
+	signing::{SharedSigningStatus, SigningStatus},
+};
 
 /// A trait that includes all the client functionalities hotstuff requires.
 /// Ideally this would be a trait alias, we're not there yet.
@@ -29,6 +42,7 @@ pub trait ClientForHotstuff<Block, BE>:
 	+ AuxStore
 	+ HeaderMetadata<Block, Error = sp_blockchain::Error>
 	+ HeaderBackend<Block>
+	+ BlockBackend<Block>
 	+ BlockchainEvents<Block>
 	+ ProvideRuntimeApi<Block>
 	+ ExecutorProvider<Block>
@@ -49,6 +63,7 @@ where
 		+ AuxStore
 		+ HeaderMetadata<Block, Error = sp_blockchain::Error>
 		+ HeaderBackend<Block>
+		+ BlockBackend<Block>
 		+ BlockchainEvents<Block>
 		+ ProvideRuntimeApi<Block>
 		+ ExecutorProvider<Block>
@@ -78,6 +93,15 @@ pub struct LinkHalf<Block: BlockT, C, SC> {
 	pub client: Arc<C>,
 	pub select_chain: Option<PhantomData<SC>>,
 	pub(crate) persistent_data: aux_schema::PersistentData<Block>,
+	pub(crate) block_tree: SharedBlockTree<Block>,
+	pub(crate) events: EventSink<Block>,
+	pub(crate) inclusion_index: SharedInclusionIndex<Block>,
+	pub(crate) pause: PauseHandle,
+	pub(crate) signing_status: SharedSigningStatus,
+	pub(crate) participation: SharedVoteParticipation,
+	pub(crate) pacemaker: SharedPacemaker,
+	pub(crate) certificates: SharedCertificates<Block>,
+	pub(crate) committed_blocks: Option<SharedCommittedBlocks<Block>>,
 }
 
 impl<Block: BlockT, C, SC> LinkHalf<Block, C, SC> {
@@ -85,12 +109,60 @@ impl<Block: BlockT, C, SC> LinkHalf<Block, C, SC> {
 	pub fn shared_authority_set(&self) -> &SharedAuthoritySet<Block::Hash, NumberFor<Block>> {
 		&self.persistent_data.authority_set
 	}
+
+	/// Get the shared block tree, which is kept up to date by the voter.
+	pub fn shared_block_tree(&self) -> SharedBlockTree<Block> {
+		self.block_tree.clone()
+	}
+
+	/// Get the sink of consensus events, e.g. to subscribe to safety alarms.
+	pub fn event_sink(&self) -> EventSink<Block> {
+		self.events.clone()
+	}
+
+	/// Get the index proving the transactions of committed blocks, kept up to date by the voter
+	/// when inclusion proofs are enabled.
+	pub fn shared_inclusion_index(&self) -> SharedInclusionIndex<Block> {
+		self.inclusion_index.clone()
+	}
+
+	/// Get the handle pausing and resuming the voter.
+	pub fn pause_handle(&self) -> PauseHandle {
+		self.pause.clone()
+	}
+
+	/// Get the signing status of the node, kept up to date by the voter.
+	pub fn shared_signing_status(&self) -> SharedSigningStatus {
+		self.signing_status.clone()
+	}
+
+	/// Get the vote participation of the authorities, kept up to date by the voter.
+	pub fn shared_vote_participation(&self) -> SharedVoteParticipation {
+		self.participation.clone()
+	}
+
+	/// Get the state of the pacemaker, published by the voter.
+	pub fn shared_pacemaker(&self) -> SharedPacemaker {
+		self.pacemaker.clone()
+	}
+
+	/// Get the locked QC and highest TC of the voter, published by the voter.
+	pub fn shared_certificates(&self) -> SharedCertificates<Block> {
+		self.certificates.clone()
+	}
+
+	/// Finalize the blocks `mapping` returns for each committed proposal, in one transaction,
+	/// instead of the block of the proposal only.
+	pub fn with_committed_blocks(mut self, mapping: SharedCommittedBlocks<Block>) -> Self {
+		self.committed_blocks = Some(mapping);
+		self
+	}
 }
 
 /// Provider for the Hotstuff authority set configured on the genesis block.
 pub trait GenesisAuthoritySetProvider<Block: BlockT> {
-	/// Get the authority set at the genesis block.
-	fn get(&self) -> Result<Vec<AuthorityId>, ClientError>;
+	/// Get the authority set at the genesis block, with the weights of the authorities.
+	fn get(&self) -> Result<AuthorityList, ClientError>;
 }
 
 impl<Block: BlockT, E, Client> GenesisAuthoritySetProvider<Block> for Arc<Client>
@@ -98,8 +170,9 @@ where
 	E: CallExecutor<Block>,
 	Client: ExecutorProvider<Block, Executor = E> + HeaderBackend<Block>,
 {
-	fn get(&self) -> Result<Vec<AuthorityId>, ClientError> {
-		self.executor()
+	fn get(&self) -> Result<AuthorityList, ClientError> {
+		let authorities: Vec<AuthorityId> = self
+			.executor()
 			.call(
 				self.expect_block_hash_from_id(&BlockId::Number(Zero::zero()))?,
 				"HotstuffApi_authorities",
@@ -113,7 +186,10 @@ where
 						err,
 					)
 				})
-			})
+			})?;
+
+		// The runtime doesn't weigh its authorities, so each one counts the same.
+		Ok(authorities.into_iter().map(|authority_id| (authority_id, 1)).collect())
 	}
 }
 
@@ -134,19 +210,31 @@ where
 		&*client,
 		genesis_hash,
 		<NumberFor<Block>>::zero(),
-		move || {
-			let authorities = genesis_authorities_provider.get()?;
-
-			// TODO just for dev.
-			let authorities =
-				authorities.iter().map(|p| (p.clone(), 1)).collect::<Vec<(AuthorityId, u64)>>();
-
-			Ok(authorities)
-		},
+		move || genesis_authorities_provider.get(),
 	)?;
 
+	let import = HotstuffBlockImport::new(
+		client.clone(),
+		persistent_data.authority_set.clone(),
+		Default::default(),
+	)
+	.with_config(&Default::default());
+
 	Ok((
-		HotstuffBlockImport::new(client.clone()),
-		LinkHalf { client, select_chain: None, persistent_data },
+		import,
+		LinkHalf {
+			client,
+			select_chain: None,
+			persistent_data,
+			block_tree: BlockTree::shared(),
+			events: EventSink::new(),
+			inclusion_index: InclusionIndex::shared(),
+			pause: PauseHandle::new(),
+			signing_status: SigningStatus::shared(),
+			participation: VoteParticipation::shared(),
+			pacemaker: SharedPacemaker::new(),
+			certificates: Default::default(),
+			committed_blocks: None,
+		},
 	))
 }
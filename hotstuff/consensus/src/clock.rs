@@ -0,0 +1,103 @@
+// The clock the votes of the node are timestamped with, and the time of a commit: the median of
+// the clocks of a quorum when the votes certifying the committed proposal carry them, so every
+// node notifies the same time for a block.
+use std::{
+	fmt,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use sp_runtime::traits::Block as BlockT;
+
+use crate::message::Proposal;
+
+#[cfg(test)]
+#[path = "tests/clock_tests.rs"]
+pub mod clock_tests;
+
+// Milliseconds since the unix epoch on the local clock.
+pub(crate) fn unix_millis() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_millis() as u64)
+		.unwrap_or_default()
+}
+
+/// A clock in milliseconds since the unix epoch, such as the local clock or one provided by the
+/// chain.
+#[derive(Clone)]
+pub struct TimeSource(Arc<dyn Fn() -> u64 + Send + Sync>);
+
+impl TimeSource {
+	pub fn new(now: impl Fn() -> u64 + Send + Sync + 'static) -> Self {
+		Self(Arc::new(now))
+	}
+
+	/// The local clock.
+	pub fn system() -> Self {
+		Self::new(unix_millis)
+	}
+
+	pub fn now(&self) -> u64 {
+		(self.0)()
+	}
+}
+
+impl fmt::Debug for TimeSource {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("TimeSource").field(&self.now()).finish()
+	}
+}
+
+/// A clock which only moves when set, such as to the recorded time of each event of a replayed
+/// journal. Its clones read the same time.
+#[derive(Debug, Clone, Default)]
+pub struct MockClock(Arc<AtomicU64>);
+
+impl MockClock {
+	pub fn new(now: u64) -> Self {
+		Self(Arc::new(AtomicU64::new(now)))
+	}
+
+	pub fn set(&self, now: u64) {
+		self.0.store(now, Ordering::SeqCst);
+	}
+
+	pub fn now(&self) -> u64 {
+		self.0.load(Ordering::SeqCst)
+	}
+
+	pub fn time_source(&self) -> TimeSource {
+		let clock = self.clone();
+		TimeSource::new(move || clock.now())
+	}
+}
+
+/// When a block was committed, in milliseconds since the unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitTime {
+	pub timestamp: u64,
+	/// Whether `timestamp` is the local clock of the node at the commit, as the votes certifying
+	/// the committed proposal carry no timestamps. Nodes may disagree on it.
+	pub local: bool,
+}
+
+impl CommitTime {
+	/// The time of the commit certified by the QC of `child`, the median of the clocks of its
+	/// voters, the local clock without them.
+	pub fn certified_by<B: BlockT>(child: &Proposal<B>) -> Self {
+		match child.qc_median_timestamp() {
+			Some(timestamp) => Self { timestamp, local: false },
+			None => Self::local(),
+		}
+	}
+
+	pub fn local() -> Self {
+		Self { timestamp: unix_millis(), local: true }
+	}
+}
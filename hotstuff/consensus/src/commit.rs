@@ -0,0 +1,151 @@
+// Convenience futures resolving on blocks committed by hotstuff, built on the finality
+// notifications of the client.
+use std::{future::Future, marker::PhantomData, sync::Arc, time::Duration};
+
+use futures::{stream, Stream, StreamExt};
+use tokio::time::sleep;
+
+use sc_client_api::{BlockchainEvents, FinalityNotification};
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT, NumberFor},
+	SaturatedConversion,
+};
+
+/// A block finalized by a hotstuff commit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedBlock<B: BlockT> {
+	pub hash: B::Hash,
+	pub number: NumberFor<B>,
+}
+
+/// The blocks finalized by one or more hotstuff commits, delivered together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedRange<B: BlockT> {
+	/// The number of the first block of the range.
+	pub first: NumberFor<B>,
+	/// The highest finalized block, the last of the range.
+	pub last: CommittedBlock<B>,
+	/// The hashes of the blocks of the range in ascending order, every block finalized since the
+	/// previous range.
+	pub hashes: Vec<B::Hash>,
+}
+
+impl<B: BlockT> CommittedRange<B> {
+	fn extend(&mut self, range: Self) {
+		self.last = range.last;
+		self.hashes.extend(range.hashes);
+	}
+}
+
+impl<B: BlockT> From<FinalityNotification<B>> for CommittedRange<B> {
+	fn from(notification: FinalityNotification<B>) -> Self {
+		// The tree route holds the blocks finalized along with the notified one.
+		let number = *notification.header.number();
+		let mut hashes = notification.tree_route.to_vec();
+		hashes.push(notification.hash);
+
+		CommittedRange {
+			first: number - (hashes.len() as u64 - 1).saturated_into(),
+			last: CommittedBlock { hash: notification.hash, number },
+			hashes,
+		}
+	}
+}
+
+/// Awaits the commits of the local hotstuff voter.
+pub struct CommitWatcher<B: BlockT, C> {
+	client: Arc<C>,
+	// How long the commits following one are awaited to be delivered with it.
+	coalescing_window: Option<Duration>,
+	_phantom: PhantomData<B>,
+}
+
+impl<B, C> CommitWatcher<B, C>
+where
+	B: BlockT,
+	C: BlockchainEvents<B> + HeaderBackend<B>,
+{
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, coalescing_window: None, _phantom: PhantomData }
+	}
+
+	/// Deliver the commits made within `window` of each other as one range in [`Self::commits`],
+	/// so slow consumers catch up in one step under high throughput.
+	pub fn with_coalescing_window(mut self, window: Duration) -> Self {
+		self.coalescing_window = Some(window);
+		self
+	}
+
+	/// Stream the blocks finalized by the commits made from now on, ending when the client shuts
+	/// down. A range covers every block finalized since the previous one, so a consumer following
+	/// the ranges misses no ancestor.
+	pub fn commits(&self) -> impl Stream<Item = CommittedRange<B>> {
+		let finality = self.client.finality_notification_stream().fuse();
+		let window = self.coalescing_window;
+
+		stream::unfold(finality, move |mut finality| async move {
+			let mut range = CommittedRange::from(finality.next().await?);
+
+			if let Some(window) = window {
+				let deadline = sleep(window);
+				tokio::pin!(deadline);
+				loop {
+					tokio::select! {
+						notification = finality.next() => match notification {
+							Some(notification) => range.extend(notification.into()),
+							None => break,
+						},
+						_ = &mut deadline => break,
+					}
+				}
+			}
+
+			Some((range, finality))
+		})
+	}
+
+	/// Resolve on the next commit, `None` if the client shuts down first.
+	///
+	/// The subscription is made when this is called rather than when the future is first polled,
+	/// so a commit in between is not missed. Dropping the future cancels the subscription.
+	pub fn next_commit(&self) -> impl Future<Output = Option<CommittedBlock<B>>> {
+		let mut finality = self.client.finality_notification_stream();
+
+		async move {
+			finality.next().await.map(|notification| CommittedBlock {
+				hash: notification.hash,
+				number: *notification.header.number(),
+			})
+		}
+	}
+
+	/// Resolve once a block at `height` or above is committed, `None` if the client shuts down
+	/// first. Resolves immediately when such a block is already finalized.
+	pub fn commit_at_least(
+		&self,
+		height: NumberFor<B>,
+	) -> impl Future<Output = Option<CommittedBlock<B>>> {
+		// Subscribe before reading the finalized block, so no commit falls in between.
+		let finality = self.client.finality_notification_stream();
+		let info = self.client.info();
+
+		async move {
+			if info.finalized_number >= height {
+				return Some(CommittedBlock {
+					hash: info.finalized_hash,
+					number: info.finalized_number,
+				});
+			}
+
+			finality
+				.map(|notification| CommittedBlock {
+					hash: notification.hash,
+					number: *notification.header.number(),
+				})
+				.filter(|block| futures::future::ready(block.number >= height))
+				.next()
+				.await
+		}
+	}
+}
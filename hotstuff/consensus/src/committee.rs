@@ -0,0 +1,62 @@
+// Deterministic sub-sampling of the voting committee of each view from a large authority set.
+use std::borrow::Cow;
+
+use parity_scale_codec::Encode;
+use sp_core::hashing::blake2_256;
+
+use hotstuff_primitives::AuthorityList;
+
+use crate::primitives::ViewNumber;
+
+#[cfg(test)]
+#[path = "tests/committee_tests.rs"]
+pub mod committee_tests;
+
+/// Draws the voting committee of a view. The draw only depends on the seed, the view and the
+/// authorities, so every node computes the same committee and no leader can choose it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitteeSampler {
+	size: usize,
+	// Randomness shared by all the authorities.
+	seed: Vec<u8>,
+}
+
+impl CommitteeSampler {
+	pub fn new(size: usize, seed: Vec<u8>) -> Self {
+		Self { size, seed }
+	}
+
+	/// The committee of `view` in the order of `authorities`. It is every authority when the
+	/// set is no larger than the committee.
+	pub fn committee(&self, authorities: &AuthorityList, view: ViewNumber) -> AuthorityList {
+		if authorities.len() <= self.size {
+			return authorities.clone();
+		}
+
+		// Rank the authorities by a hash of the seed, view and authority, keep the lowest.
+		let mut ranked = authorities
+			.iter()
+			.enumerate()
+			.map(|(index, (authority_id, _))| {
+				(blake2_256(&(&self.seed, view, authority_id).encode()), index)
+			})
+			.collect::<Vec<_>>();
+		ranked.sort();
+
+		let mut selected = ranked[..self.size].iter().map(|(_, index)| *index).collect::<Vec<_>>();
+		selected.sort();
+		selected.into_iter().map(|index| authorities[index].clone()).collect()
+	}
+}
+
+/// The authorities voting at `view`, all of them without a sampler.
+pub fn voting_committee<'a>(
+	authorities: &'a AuthorityList,
+	sampler: Option<&CommitteeSampler>,
+	view: ViewNumber,
+) -> Cow<'a, AuthorityList> {
+	match sampler {
+		Some(sampler) => Cow::Owned(sampler.committee(authorities, view)),
+		None => Cow::Borrowed(authorities),
+	}
+}
@@ -1,10 +1,249 @@
 #![allow(clippy::borrowed_box)]
 
+use std::path::PathBuf;
+
+use parity_scale_codec::Encode;
+use prometheus_endpoint::Registry;
 use sc_chain_spec::ChainSpec;
 use sc_network::types::ProtocolName;
 
+use hotstuff_primitives::AuthorityId;
+
+use crate::{
+	audit::AuditRotation,
+	authorities::DuplicateAuthorities,
+	clock::TimeSource,
+	committee::CommitteeSampler,
+	dedup::{EquivocationPolicy, PROPOSAL_VERIFICATION_BUDGET},
+	election::LeaderElection,
+	finality::FinalizeRetry,
+	message::SigningContext,
+	participation::DEFAULT_PARTICIPATION_WINDOW,
+	pool::{PoolStatus, ProposalPacing, EMPTY_PROPOSAL_THRESHOLD},
+	reputation::ReputationConfig,
+	scheme::SchemeConfig,
+	synchronizer::SyncRequestLimit,
+	variant::ConsensusVariant,
+};
+
 pub(crate) const HOTSTUFF_PROTOCOL_NAME: &str = "/hotstuff/1";
 
+/// Configuration of the hotstuff voter.
+#[derive(Debug, Clone)]
+pub struct Config {
+	/// Duration of the local timer in milliseconds, can be overridden by the `HOTSTUFF_DURATION`
+	/// environment variable.
+	pub local_timer_duration: u64,
+	/// Number of threads verifying the signatures of incoming proposals and votes. When zero they
+	/// are verified on the consensus loop.
+	pub verification_threads: usize,
+	/// Most messages pending verification on the threads. Once reached, incoming messages wait
+	/// in the network layer.
+	pub verification_queue_depth: usize,
+	/// Milliseconds the verification queue stays full before the node is overloaded: it reads
+	/// messages again, sheds old votes and redundant gossip, and verifies the proposals of the
+	/// current view on the consensus loop, until the queue drains. Never overloaded without it.
+	pub verification_overload_after: Option<u64>,
+	/// Learn the timeout from the observed latencies from proposals to their QCs, starting at
+	/// `local_timer_duration`.
+	pub adaptive_timeout: bool,
+	/// Milliseconds added to every local timeout to absorb the clock drift between nodes, at
+	/// most `local_timer_duration`.
+	pub clock_skew_tolerance: u64,
+	/// Append-only log recording every message signed by the local authority before it is sent.
+	pub audit_log_path: Option<PathBuf>,
+	/// When the audit log moves to a new segment, and how many old segments it keeps.
+	pub audit_rotation: AuditRotation,
+	/// Journal recording every message and timer event the voter handles, so a replay of it
+	/// reproduces the decisions of the voter. Off by default, it grows with the traffic.
+	pub journal_path: Option<PathBuf>,
+	/// Size of the voting committee sampled from the authorities for each view, every authority
+	/// votes when `None`.
+	pub committee_size: Option<usize>,
+	/// Milliseconds the reachable authorities may stay below a quorum before the node stops
+	/// proposing and raises an alarm.
+	pub quorum_unreachable_after: u64,
+	/// Index the transactions of committed blocks to serve inclusion proofs.
+	pub inclusion_proofs: bool,
+	/// Let the leader after a view change propose again the block of the highest proposal which
+	/// timed out before being certified, so its transactions aren't held back.
+	pub repropose_uncommitted: bool,
+	/// Fork id of the chain, mixed with the genesis hash into every signed digest so signatures
+	/// can't be replayed on another chain.
+	pub fork_id: Option<String>,
+	/// Finalize committed blocks with a justification carrying the committing QCs, for
+	/// consumers of GRANDPA style justifications.
+	pub finality_justifications: bool,
+	/// Most finalized blocks a committed block may conflict with before the node refuses to
+	/// commit it and raises an alarm.
+	pub max_reorg_depth: u32,
+	/// How a failure to finalize a committed block is retried before raising an alarm.
+	pub finalize_retry: FinalizeRetry,
+	/// Most blocks committed while catching up on buffered proposals which are finalized in one
+	/// backend transaction, all or none of them. Disabled when `None`.
+	pub finalize_batch_size: Option<usize>,
+	/// The phase structure of consensus, which decides when a proposal commits.
+	pub consensus_variant: ConsensusVariant,
+	/// Milliseconds a proposal certifying an unknown proposal waits for it to be fetched
+	/// before it's dropped.
+	pub proposal_fetch_timeout: u64,
+	/// How many unknown proposals are fetched from the peers at a time, and how a fetch which
+	/// got no answer is retried. The other fetches wait in a queue.
+	pub sync_request_limit: SyncRequestLimit,
+	/// Milliseconds the votes and timeouts of a past view are buffered after the last of them is
+	/// received, whether or not the views advance. Keep it well above the round time.
+	pub message_ttl: u64,
+	/// Milliseconds the leader collecting the votes of a proposal waits for a quorum of them
+	/// before it times out the view, sooner than the local timer. Disabled when `None`.
+	pub vote_collection_timeout: Option<u64>,
+	/// Backups proposing in turn in place of a silent leader, the leaders of the following views.
+	/// Every authority must agree on it, none when zero.
+	pub fallback_proposers: usize,
+	/// Milliseconds each backup proposer waits for a proposal after the one before it, from the
+	/// start of the view.
+	pub fallback_proposer_delay: u64,
+	/// How the leader of each view is elected from the authorities, and how ties between
+	/// authorities due at once are broken. Every authority must agree on it.
+	pub leader_election: LeaderElection,
+	/// Milliseconds an own vote waits before it's sent to the next leader, so the votes of a
+	/// view reach it together. At most a tenth of `local_timer_duration`, and not applied with
+	/// fewer than [`VOTE_DELAY_MIN_AUTHORITIES`](crate::consensus::VOTE_DELAY_MIN_AUTHORITIES)
+	/// authorities. Disabled when `None`.
+	pub vote_broadcast_delay: Option<u64>,
+	/// Time out a view which brought no proposal with a nil vote rather than a timeout, telling
+	/// the next leader the node saw nothing from one which is silent. Both form the TC.
+	pub nil_votes: bool,
+	/// Costs of the misbehaviors of gossiping peers, and the score banning them.
+	pub reputation: ReputationConfig,
+	/// Catch a panic of the voter, raising an alarm and halting the voter instead of letting its
+	/// task vanish, so its state can be inspected.
+	pub catch_panics: bool,
+	/// Send the messages signed while handling one message at once, in the order they were
+	/// signed and journaled in the audit log.
+	pub ordered_signing: bool,
+	/// QCs of committed proposals kept to be queried by view, the oldest are pruned first.
+	pub committed_qc_retention: usize,
+	/// Views with votes over which the vote participation of each authority is measured.
+	pub participation_window: usize,
+	/// SCALE encoded [`TrustedCheckpoint`](crate::checkpoint::TrustedCheckpoint) the voter
+	/// starts from instead of genesis. The voter doesn't start if its commit doesn't verify.
+	pub trusted_checkpoint: Option<PathBuf>,
+	/// Opaque data attached to the proposals of the node, such as its client version. At most
+	/// [`MAX_PROPOSAL_METADATA`](crate::message::MAX_PROPOSAL_METADATA) bytes.
+	pub proposal_metadata: Option<Vec<u8>>,
+	/// Registry of the Prometheus metrics of the voter, none are recorded without it.
+	pub prometheus_registry: Option<Registry>,
+	/// Which proposal of an equivocating leader, if any, the node continues with, the first
+	/// seen by default.
+	pub equivocation_policy: EquivocationPolicy,
+	/// Distinct proposals of each proposer of a view verified, those after them are dropped.
+	/// Under honest operation a proposer sends a single proposal in a view.
+	pub proposal_verification_budget: usize,
+	/// Carry the vote of the leader for its proposal in the proposal, instead of sending it
+	/// apart.
+	pub proposal_self_vote: bool,
+	/// Timestamp the proposals of the node, and reject proposals whose timestamp is more than
+	/// this many milliseconds from the local clock, which limits the replay of old or future
+	/// dated proposals. Proposals without a timestamp are accepted. Disabled by default, as it
+	/// makes liveness depend on the clocks of the nodes.
+	pub proposal_time_window: Option<u64>,
+	/// Timestamp the votes of the node with this clock, such as [`TimeSource::system`]. A commit
+	/// is notified at the median of the clocks of a quorum of the votes certifying it when they
+	/// carry them, and at the local clock, flagged as such, otherwise.
+	pub vote_time_source: Option<TimeSource>,
+	/// Views on each side of an authority set change whose proposals are verified against the
+	/// set the activation schedule gives their view, and may be sent for the set before or after
+	/// the change. Other proposals must be sent for the current set. Without it, every proposal
+	/// is verified against the current set.
+	pub set_transition_window: Option<u64>,
+	/// The signature schemes the node advertises to its peers, the scheme of the authority keys
+	/// by default. Messages of peers advertising no scheme in common are dropped.
+	pub signature_schemes: SchemeConfig,
+	/// Transactions ready in the pool, to warn when the proposals of the node keep carrying no
+	/// block while some wait. Nothing is known to wait without it.
+	pub pool_status: Option<PoolStatus>,
+	/// Consecutive empty proposals of the node with transactions ready before it warns.
+	pub empty_proposal_threshold: u32,
+	/// Pace the proposals of the node after a QC by how full the pool of `pool_status` is,
+	/// rather than proposing right away.
+	pub proposal_pacing: Option<ProposalPacing>,
+	/// The authority the node signs as. When set, the voter doesn't start unless its key is in
+	/// the keystore and it's in the genesis authority set.
+	pub authority_id: Option<AuthorityId>,
+	/// Whether an authority listed more than once in the genesis authority set fails the start
+	/// of the voter, or is merged into one entry of the summed weights.
+	pub duplicate_authorities: DuplicateAuthorities,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			local_timer_duration: 3000,
+			verification_threads: 0,
+			verification_queue_depth: 1024,
+			verification_overload_after: None,
+			adaptive_timeout: false,
+			clock_skew_tolerance: 0,
+			audit_log_path: None,
+			audit_rotation: Default::default(),
+			journal_path: None,
+			committee_size: None,
+			quorum_unreachable_after: 30000,
+			inclusion_proofs: false,
+			repropose_uncommitted: false,
+			fork_id: None,
+			finality_justifications: false,
+			max_reorg_depth: 0,
+			finalize_retry: Default::default(),
+			finalize_batch_size: None,
+			consensus_variant: ConsensusVariant::Chained,
+			proposal_fetch_timeout: 6000,
+			sync_request_limit: Default::default(),
+			message_ttl: 60000,
+			vote_collection_timeout: None,
+			fallback_proposers: 0,
+			fallback_proposer_delay: 1000,
+			leader_election: LeaderElection::default(),
+			vote_broadcast_delay: None,
+			nil_votes: false,
+			reputation: Default::default(),
+			catch_panics: false,
+			ordered_signing: false,
+			committed_qc_retention: 4096,
+			participation_window: DEFAULT_PARTICIPATION_WINDOW,
+			trusted_checkpoint: None,
+			proposal_metadata: None,
+			prometheus_registry: None,
+			equivocation_policy: EquivocationPolicy::FirstSeen,
+			proposal_verification_budget: PROPOSAL_VERIFICATION_BUDGET,
+			proposal_self_vote: false,
+			proposal_time_window: None,
+			vote_time_source: None,
+			set_transition_window: None,
+			signature_schemes: Default::default(),
+			pool_status: None,
+			empty_proposal_threshold: EMPTY_PROPOSAL_THRESHOLD,
+			proposal_pacing: None,
+			authority_id: None,
+			duplicate_authorities: DuplicateAuthorities::default(),
+		}
+	}
+}
+
+impl Config {
+	/// The context of the signatures on the chain with `genesis_hash`.
+	pub fn signing_context(&self, genesis_hash: &[u8]) -> SigningContext {
+		SigningContext::new(genesis_hash, self.fork_id.as_deref())
+	}
+
+	/// The sampler of the voting committees on the chain with `genesis_hash`, if any.
+	pub fn committee_sampler(&self, genesis_hash: &[u8]) -> Option<CommitteeSampler> {
+		// Every node knows the genesis hash, and no leader can influence it.
+		self.committee_size
+			.map(|size| CommitteeSampler::new(size, genesis_hash.encode()))
+	}
+}
+
 pub fn standard_name<Hash: AsRef<[u8]>>(
 	genesis_hash: &Hash,
 	chain_spec: &Box<dyn ChainSpec>,
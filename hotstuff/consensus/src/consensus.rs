@@ -1,21 +1,39 @@
 use std::{
+	any::Any,
+	borrow::Cow,
 	cmp::max,
-	collections::VecDeque,
+	collections::{BTreeMap, VecDeque},
 	env,
+	panic::AssertUnwindSafe,
 	pin::Pin,
 	sync::{Arc, Mutex},
 	task::{Context, Poll},
+	time::Duration,
 };
 
 use async_recursion::async_recursion;
-use futures::{channel::mpsc::Receiver as Recv, Future, StreamExt};
+use futures::{
+	channel::mpsc::Receiver as Recv,
+	future::{self, BoxFuture},
+	Future, FutureExt, StreamExt,
+};
 
-use log::{debug, error, info, trace};
+use log::{debug, error, info, trace, warn};
 use parity_scale_codec::{Decode, Encode};
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::{
+	sync::{
+		mpsc::{
+			channel,
+			error::{SendError, TrySendError},
+			OwnedPermit, Receiver, Sender,
+		},
+		watch,
+	},
+	time::{sleep, sleep_until, Instant, Sleep},
+};
 
 use sc_client_api::{Backend, CallExecutor};
-use sc_network::types::ProtocolName;
+use sc_network::{types::ProtocolName, PeerId};
 use sc_network_gossip::TopicNotification;
 use sp_application_crypto::AppCrypto;
 use sp_blockchain::BlockStatus;
@@ -23,17 +41,55 @@ use sp_core::{crypto::ByteArray, traits::CallContext};
 use sp_keystore::KeystorePtr;
 use sp_runtime::{
 	generic::BlockId,
-	traits::{Block as BlockT, Hash as HashT, Header as HeaderT, Zero},
+	traits::{Block as BlockT, Hash as HashT, NumberFor, SaturatedConversion, Zero},
+	Justification,
 };
 
+#[cfg(test)]
+use crate::network::SimulatedDelivery;
 use crate::{
 	aggregator::Aggregator,
+	audit::{AuditLog, SignedKind},
+	authorities::{announced_authorities, load_authority_set, verify_set_transition},
+	block_tree::{BlockTreeView, SharedBlockTree},
+	certificates::{Certificates, SharedCertificates},
+	checkpoint::TrustedCheckpoint,
 	client::{ClientForHotstuff, LinkHalf},
+	clock::{CommitTime, MockClock, TimeSource},
+	committee::{voting_committee, CommitteeSampler},
+	config::Config,
+	dedup::{EquivocationPolicy, ProposalDedup, Seen, VerificationBudget, DEDUP_VIEW_WINDOW},
+	election::LeaderElection,
+	events::{ConsensusEvent, EventSink},
+	finality::{
+		check_contiguous, finalize_batch, finalize_with_retry, FinalityBlocks, FinalizeRetry,
+		SharedCommittedBlocks,
+	},
 	import::{BlockInfo, PendingFinalizeBlockQueue},
-	message::{ConsensusMessage, ConsensusMessage::*, Payload, Proposal, Timeout, Vote, QC, TC},
+	inclusion::SharedInclusionIndex,
+	journal::{Journal, JournalEntry, JournalEvent},
+	justification::HotstuffJustification,
+	liveness::{QuorumMonitor, QuorumTransition},
+	message::{
+		bitfield_hex, check_metadata, is_quorum, qcs_conflict, signer_bitfield, unknown_signers,
+		ConsensusMessage, ConsensusMessage::*, GossipMessage, Hasher, Payload, Proposal,
+		SigningContext, Timeout, Vote, VoteTimestamp, QC, TC,
+	},
+	metrics::Metrics,
 	network::{HotstuffNetworkBridge, Network as NetworkT, Syncing as SyncingT},
-	primitives::{HotstuffError, HotstuffError::*, ViewNumber},
-	synchronizer::{Synchronizer, Timer},
+	pacemaker::{PacemakerState, SharedPacemaker},
+	participation::{SharedVoteParticipation, VoteParticipation},
+	pause::PauseHandle,
+	pool::{EmptyProposalMonitor, PoolStatus, ProposalPacing, PACING_POLL_INTERVAL},
+	primitives::{HotstuffError, HotstuffError::*, SetId, ViewNumber},
+	signing::{keystore_selftest, SharedSigningStatus, SigningStatus},
+	synchronizer::{SyncRequests, Synchronizer, Timer},
+	trace::{ViewTrace, ViewTraces, VoteDecision, VoteWithheld},
+	variant::ConsensusVariant,
+	verifier::{
+		next_verified, OverloadMonitor, OverloadTransition, Verifiable, VerificationPool,
+		VerifierSet,
+	},
 };
 use hotstuff_primitives::{AuthorityId, AuthorityList, AuthoritySignature, HOTSTUFF_KEY_TYPE};
 
@@ -43,15 +99,71 @@ pub mod consensus_tests;
 
 pub(crate) const EMPTY_PAYLOAD: &[u8] = b"hotstuff/empty_payload";
 
+// Authorities below which votes are sent at once, too few to be worth batching.
+pub const VOTE_DELAY_MIN_AUTHORITIES: usize = 8;
+// A vote waits at most this fraction of the local timer before it's sent.
+const VOTE_DELAY_TIMER_FRACTION: u64 = 10;
+
+// The activation view of an authority set change, with the id and authorities of the set before
+// and after it.
+type SetTransition<'a> = (ViewNumber, (SetId, &'a AuthorityList), (SetId, &'a AuthorityList));
+
+// A commit made while catching up, with the QC certifying it, finalized later in a batch.
+type DeferredCommit<B> = (Proposal<B>, Option<Justification>, QC<B>, CommitTime);
+
 // the core of hotstuff
 pub struct ConsensusState<B: BlockT> {
 	keystore: KeystorePtr,
 	authorities: AuthorityList,
+	set_id: SetId,
+	// The authorities taking over at a view, such as to rotate the key of an authority.
+	pending_set_change: Option<(ViewNumber, AuthorityList)>,
+	// The authorities before the last set change and the view it activated at, which verify
+	// the QCs of the views before it.
+	previous_authorities: Option<(ViewNumber, AuthorityList)>,
+	// The key signed with last, the highest view it signed at, and the highest view signed with
+	// the keys before it.
+	last_signer: Option<(AuthorityId, ViewNumber, Option<ViewNumber>)>,
+	// Messages rejected for coming from an outdated authority set.
+	regressed_messages: u64,
 	view: ViewNumber,
 	last_voted_view: ViewNumber,
-	// last_committed_round: ViewNumber,
+	// The digest and view of the highest proposal known to be committed, none before the first
+	// commit.
+	last_committed: Option<(B::Hash, ViewNumber)>,
 	high_qc: QC<B>,
+	// The TC of the highest timed out view.
+	high_tc: Option<TC<B>>,
 	aggregator: Aggregator<B>,
+	audit_log: Option<AuditLog>,
+	// Draws the voting committee of each view, every authority votes without it.
+	committee: Option<CommitteeSampler>,
+	// Backups which may propose after the leader of a view.
+	fallback_proposers: usize,
+	election: LeaderElection,
+	// Attached to the proposals of the node.
+	proposal_metadata: Option<Vec<u8>>,
+	// Whether the proposals of the node carry its vote for them.
+	proposal_self_vote: bool,
+	// Milliseconds the timestamp of a proposal may be away from the local clock, proposals
+	// aren't timestamped without it.
+	proposal_time_window: Option<u64>,
+	// The clock the votes of the node are timestamped with, they carry no timestamp without it.
+	vote_time_source: Option<TimeSource>,
+	// The clock the proposals of the node are timestamped with and checked against.
+	local_clock: TimeSource,
+	// The authority the node is configured to sign as, checked at startup.
+	configured_authority: Option<AuthorityId>,
+	// Views on each side of an authority set change whose proposals are verified against the
+	// set the activation schedule gives their view, none to verify against the current set.
+	transition_window: Option<ViewNumber>,
+	// Binds the signatures to this chain.
+	signing_context: SigningContext,
+	signing_status: SharedSigningStatus,
+	// The votes of each authority over the last views.
+	participation: SharedVoteParticipation,
+	// The digests of the messages signed since the worker last took them, in signing order.
+	signed: Vec<B::Hash>,
 }
 
 impl<B: BlockT> ConsensusState<B> {
@@ -59,13 +171,241 @@ impl<B: BlockT> ConsensusState<B> {
 		Self {
 			keystore,
 			authorities,
+			set_id: 0,
+			pending_set_change: None,
+			previous_authorities: None,
+			last_signer: None,
+			regressed_messages: 0,
 			view: 0,
 			last_voted_view: 0,
+			last_committed: None,
 			high_qc: Default::default(),
+			high_tc: None,
 			aggregator: Aggregator::<B>::new(),
+			audit_log: None,
+			committee: None,
+			fallback_proposers: 0,
+			election: LeaderElection::default(),
+			proposal_metadata: None,
+			proposal_self_vote: false,
+			proposal_time_window: None,
+			vote_time_source: None,
+			local_clock: TimeSource::system(),
+			configured_authority: None,
+			transition_window: None,
+			signing_context: Default::default(),
+			signing_status: SigningStatus::shared(),
+			participation: VoteParticipation::shared(),
+			signed: Vec::new(),
 		}
 	}
 
+	// Start from the commit of a trusted checkpoint once it verifies: its authorities take over,
+	// and the QC of its commit is the high QC.
+	pub fn with_checkpoint(
+		mut self,
+		checkpoint: &TrustedCheckpoint<B>,
+	) -> Result<Self, HotstuffError> {
+		checkpoint.verify(self.committee.as_ref(), &self.signing_context)?;
+
+		let qc = &checkpoint.justification.qc;
+		self.authorities = checkpoint.authorities.clone();
+		self.view = max(self.view, qc.view + 1);
+		self.last_voted_view = max(self.last_voted_view, qc.view);
+		self.update_high_qc(qc);
+		let committed = &checkpoint.justification.committed;
+		self.record_commit(committed.digest(), committed.view);
+		Ok(self)
+	}
+
+	// Sign and verify every message in `context`.
+	pub fn with_signing_context(mut self, context: SigningContext) -> Self {
+		self.signing_context = context;
+		self
+	}
+
+	pub fn signing_context(&self) -> &SigningContext {
+		&self.signing_context
+	}
+
+	// Sub-sample the voting committee of each view with `sampler`.
+	pub fn with_committee(mut self, sampler: CommitteeSampler) -> Self {
+		self.committee = Some(sampler);
+		self
+	}
+
+	pub fn committee_sampler(&self) -> Option<&CommitteeSampler> {
+		self.committee.as_ref()
+	}
+
+	// Let `count` backups propose in turn after the leader of each view.
+	pub fn with_fallback_proposers(mut self, count: usize) -> Self {
+		self.fallback_proposers = count;
+		self
+	}
+
+	pub fn with_leader_election(mut self, election: LeaderElection) -> Self {
+		self.election = election;
+		self
+	}
+
+	// Let the proposals of the node carry its vote for them.
+	pub fn with_proposal_self_vote(mut self, enabled: bool) -> Self {
+		self.proposal_self_vote = enabled;
+		self
+	}
+
+	// Timestamp the proposals of the node, and reject those more than `window` milliseconds
+	// away from the local clock.
+	pub fn with_proposal_time_window(mut self, window: Option<u64>) -> Self {
+		self.proposal_time_window = window;
+		self
+	}
+
+	// Timestamp the votes of the node with `clock`, so the commits they certify are timed by
+	// the median of the clocks of their voters.
+	pub fn with_vote_time_source(mut self, clock: Option<TimeSource>) -> Self {
+		self.vote_time_source = clock;
+		self
+	}
+
+	// Read the local time from `clock` instead of the system clock.
+	pub fn with_local_clock(mut self, clock: TimeSource) -> Self {
+		self.local_clock = clock;
+		self
+	}
+
+	// Verify the proposals within `window` views of an authority set change against the set
+	// active at their view, as nodes may briefly disagree about the active set around it.
+	pub fn with_transition_window(mut self, window: Option<ViewNumber>) -> Self {
+		self.transition_window = window;
+		self
+	}
+
+	pub fn with_authority_id(mut self, authority_id: Option<AuthorityId>) -> Self {
+		self.configured_authority = authority_id;
+		self
+	}
+
+	// Check the authority the node is configured as has its key in the keystore and is in the
+	// authority set, as a node missing either would silently never sign.
+	pub fn verify_self_authority(&self) -> Result<(), HotstuffError> {
+		let Some(authority_id) = self.configured_authority.as_ref() else { return Ok(()) };
+
+		if !self.keystore.has_keys(&[(authority_id.to_raw_vec(), HOTSTUFF_KEY_TYPE)]) {
+			return Err(MissingSigningKey(authority_id.clone()));
+		}
+		if !self.authorities.iter().any(|(id, _)| id == authority_id) {
+			return Err(SelfNotInAuthoritySet(authority_id.clone()));
+		}
+		Ok(())
+	}
+
+	// Sign a test message with the key of the authority the node is configured as, or else
+	// finds in its keystore, and verify the signature.
+	pub fn keystore_selftest(&self) -> Result<(), HotstuffError> {
+		let authority_id = self
+			.configured_authority
+			.clone()
+			.or_else(|| self.local_authority_id())
+			.ok_or(NotAuthority)?;
+		keystore_selftest(&self.keystore, &authority_id)
+	}
+
+	// Attach `metadata` to the proposals of the node, rejected if it couldn't be attached.
+	pub fn with_proposal_metadata(mut self, metadata: Vec<u8>) -> Result<Self, HotstuffError> {
+		check_metadata(&metadata)?;
+		self.proposal_metadata = Some(metadata);
+		Ok(self)
+	}
+
+	// The authorities which voted at `view`, the set before the last change for the views before
+	// it activated.
+	pub fn authorities_at(&self, view: ViewNumber) -> AuthorityList {
+		match &self.previous_authorities {
+			Some((activation_view, previous)) if view < *activation_view => previous.clone(),
+			_ => self.authorities.clone(),
+		}
+	}
+
+	// The current and previous authorities, to verify messages off the consensus loop.
+	pub fn verifier_set(&self) -> VerifierSet {
+		VerifierSet {
+			set_id: self.set_id,
+			authorities: self.authorities.clone(),
+			previous: self.previous_authorities.clone(),
+		}
+	}
+
+	// The authorities voting at `view`, while leaders and timeouts involve every authority.
+	pub fn voting_committee(&self, view: ViewNumber) -> Cow<'_, AuthorityList> {
+		voting_committee(&self.authorities, self.committee.as_ref(), view)
+	}
+
+	// Record every message signed from now on in `audit_log`.
+	pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+		self.audit_log = Some(audit_log);
+		self
+	}
+
+	// Durably record a signed message in the audit log, if any, before it can be sent.
+	fn audit(
+		&mut self,
+		kind: SignedKind,
+		view: ViewNumber,
+		digest: B::Hash,
+	) -> Result<(), HotstuffError> {
+		self.audit_log
+			.as_mut()
+			.map_or(Ok(()), |log| log.record(kind, view, digest.as_ref()))?;
+		self.signing_status.write().record(kind, view);
+		self.signed.push(digest);
+		Ok(())
+	}
+
+	// The digests of the messages signed since the last call, in signing order.
+	pub fn take_signed(&mut self) -> Vec<B::Hash> {
+		std::mem::take(&mut self.signed)
+	}
+
+	// Count the signed messages in `status`, shared e.g. with the RPC.
+	pub fn with_signing_status(mut self, status: SharedSigningStatus) -> Self {
+		self.signing_status = status;
+		self
+	}
+
+	// Measure the vote participation of the authorities in `participation`, shared e.g. with the
+	// RPC.
+	pub fn with_vote_participation(mut self, participation: SharedVoteParticipation) -> Self {
+		self.participation = participation;
+		self
+	}
+
+	// The share of the views each authority could vote in which it voted in.
+	pub fn participation(&self) -> Vec<(AuthorityId, f64)> {
+		self.participation.read().participation()
+	}
+
+	// Note the signers of a QC as voters of its view, including votes this node missed.
+	pub fn record_qc_participation(&self, qc: &QC<B>) {
+		let committee = self.voting_committee(qc.view);
+		self.participation
+			.write()
+			.record(qc.view, &committee, qc.votes.iter().map(|(id, _)| id));
+	}
+
+	// Check the keystore for the key of an authority of the current set, which may be inserted
+	// while the node runs.
+	pub fn refresh_signing_status(&self) {
+		let authority = self.local_authority_id();
+		self.signing_status.write().set_authority(authority);
+	}
+
+	pub fn signing_status(&self) -> SigningStatus {
+		self.refresh_signing_status();
+		self.signing_status.read().clone()
+	}
+
 	// find local authority id. If the result is None, local node is not authority.
 	// TODO no loop. just init in construct function ?
 	pub fn local_authority_id(&self) -> Option<AuthorityId> {
@@ -75,12 +415,69 @@ impl<B: BlockT> ConsensusState<B> {
 			.map(|(p, _)| p.clone())
 	}
 
+	pub fn last_voted_view(&self) -> ViewNumber {
+		self.last_voted_view
+	}
+
 	pub fn increase_last_voted_view(&mut self) {
 		self.last_voted_view = max(self.last_voted_view, self.view)
 	}
 
-	pub fn make_timeout(&self) -> Result<Timeout<B>, HotstuffError> {
+	// Hand over to `authorities` once the node enters `activation_view`. A node rotating its key
+	// lists the new key in place of the old one, and keeps both in its keystore: it signs with
+	// the old key before the view and with the new key from it. The worker schedules the
+	// changes the runtime announces in the digests of committed blocks.
+	pub fn schedule_set_change(
+		&mut self,
+		authorities: AuthorityList,
+		activation_view: ViewNumber,
+	) -> Result<(), HotstuffError> {
+		verify_set_transition(&self.authorities, &authorities)?;
+		self.pending_set_change = Some((activation_view, authorities));
+		self.apply_pending_set_change();
+		Ok(())
+	}
+
+	fn apply_pending_set_change(&mut self) {
+		if self.pending_set_change.as_ref().map_or(true, |(view, _)| self.view < *view) {
+			return;
+		}
+
+		if let Some((view, authorities)) = self.pending_set_change.take() {
+			let previous = std::mem::replace(&mut self.authorities, authorities);
+			self.previous_authorities = Some((view, previous));
+			self.set_id += 1;
+			info!(target: "Hotstuff", "authority set {} activated at view {}", self.set_id, view);
+			self.refresh_signing_status();
+		}
+	}
+
+	// Refuse to sign at `view` with `signer` if another key of the node signed this view or a
+	// later one, so no view is ever signed with two keys.
+	fn check_signer(
+		&mut self,
+		signer: &AuthorityId,
+		view: ViewNumber,
+	) -> Result<(), HotstuffError> {
+		let (highest, floor) = match &self.last_signer {
+			Some((last, highest, floor)) if last == signer => {
+				if floor.map_or(false, |floor| view <= floor) {
+					return Err(SigningKeyOverlap(view));
+				}
+				(max(view, *highest), *floor)
+			},
+			Some((_, highest, _)) if view <= *highest => return Err(SigningKeyOverlap(view)),
+			Some((_, highest, _)) => (view, Some(*highest)),
+			None => (view, None),
+		};
+
+		self.last_signer = Some((signer.clone(), highest, floor));
+		Ok(())
+	}
+
+	pub fn make_timeout(&mut self) -> Result<Timeout<B>, HotstuffError> {
 		let authority_id = self.local_authority_id().ok_or(NotAuthority)?;
+		self.check_signer(&authority_id, self.view)?;
 
 		let mut tc: Timeout<B> = Timeout {
 			high_qc: self.high_qc.clone(),
@@ -95,20 +492,39 @@ impl<B: BlockT> ConsensusState<B> {
 				AuthorityId::ID,
 				AuthorityId::CRYPTO_ID,
 				authority_id.as_ref(),
-				tc.digest().as_ref(),
+				self.signing_context.signing_digest::<B>(tc.digest()).as_ref(),
 			)
 			.map_err(|e| Other(e.to_string()))?
 			.and_then(|data| AuthoritySignature::try_from(data).ok());
 
+		if tc.signature.is_some() {
+			self.audit(SignedKind::Timeout, tc.view, tc.digest())?;
+		}
+
 		Ok(tc)
 	}
 
 	pub fn make_proposal(
-		&self,
+		&mut self,
 		payload: Payload<B>,
 		tc: Option<TC<B>>,
 	) -> Result<Proposal<B>, HotstuffError> {
 		let author_id = self.local_authority_id().ok_or(NotAuthority)?;
+		self.check_signer(&author_id, self.view)?;
+
+		// Without a QC of the previous view, the proposal is justified by a TC of that view. A QC
+		// is always preferred, even when the view also timed out at some nodes.
+		let tc = if self.high_qc.view + 1 == self.view {
+			None
+		} else {
+			Some(
+				tc.into_iter()
+					.chain(self.high_tc().cloned())
+					.find(|tc| tc.view + 1 == self.view)
+					.ok_or(MissingTimeoutJustification)?,
+			)
+		};
+
 		let mut block = Proposal::<B>::new(
 			self.high_qc.clone(),
 			tc,
@@ -117,6 +533,18 @@ impl<B: BlockT> ConsensusState<B> {
 			author_id.clone(),
 			None,
 		);
+		if let Some(metadata) = self.proposal_metadata.clone() {
+			block = block.with_metadata(metadata)?;
+		}
+		if self.proposal_time_window.is_some() {
+			block = block.with_timestamp(self.local_clock.now());
+		}
+		// Only the clocks of a quorum time the commit of the parent.
+		let timestamps = self.aggregator.vote_timestamps(&self.high_qc);
+		let committee = self.voting_committee(self.high_qc.view);
+		if is_quorum(timestamps.iter().map(|timestamp| &timestamp.voter), &committee) {
+			block = block.with_qc_timestamps(timestamps);
+		}
 
 		block.signature = self
 			.keystore
@@ -124,63 +552,288 @@ impl<B: BlockT> ConsensusState<B> {
 				AuthorityId::ID,
 				AuthorityId::CRYPTO_ID,
 				author_id.as_slice(),
-				block.digest().as_ref(),
+				self.signing_context.signing_digest::<B>(block.digest()).as_ref(),
 			)
 			.map_err(|e| Other(e.to_string()))?
 			.and_then(|data| AuthoritySignature::try_from(data).ok());
 
+		if block.signature.is_some() {
+			self.audit(SignedKind::Proposal, block.view, block.digest())?;
+			if self.proposal_self_vote {
+				block.self_vote = self.make_vote(&block).map(Box::new);
+			}
+		}
+
 		Ok(block)
 	}
 
+	// Why the node wouldn't vote for `proposal`, none if it only depends on signing.
+	pub fn vote_withheld_reason(&self, proposal: &Proposal<B>) -> Option<VoteWithheld> {
+		let author_id = match self.local_authority_id() {
+			Some(id) => id,
+			None => return Some(VoteWithheld::NotAuthority),
+		};
+		if proposal.view <= self.last_voted_view {
+			return Some(VoteWithheld::AlreadyVoted);
+		}
+		if !self.voting_committee(proposal.view).iter().any(|(id, _)| *id == author_id) {
+			return Some(VoteWithheld::NotInCommittee);
+		}
+		None
+	}
+
 	pub fn make_vote(&mut self, proposal: &Proposal<B>) -> Option<Vote<B>> {
-		let author_id = self.local_authority_id()?;
+		self.make_votes(std::slice::from_ref(proposal)).pop()
+	}
 
-		if proposal.view <= self.last_voted_view {
-			return None;
+	// Vote for buffered proposals, such as when catching up, in view order. Proposals of views
+	// already voted are skipped.
+	pub fn make_votes(&mut self, proposals: &[Proposal<B>]) -> Vec<Vote<B>> {
+		let author_id = match self.local_authority_id() {
+			Some(id) => id,
+			None => return Vec::new(),
+		};
+
+		let mut proposals = proposals.iter().collect::<Vec<_>>();
+		proposals.sort_by_key(|proposal| proposal.view);
+
+		let mut votes = Vec::new();
+		for proposal in proposals {
+			if proposal.view <= self.last_voted_view {
+				continue;
+			}
+
+			if !self.voting_committee(proposal.view).iter().any(|(id, _)| *id == author_id) {
+				continue;
+			}
+
+			if let Err(e) = self.check_signer(&author_id, proposal.view) {
+				warn!(target: "Hotstuff", "skip the vote for view {}, {:?}", proposal.view, e);
+				continue;
+			}
+
+			// TODO how process TC of proposal.
+			self.last_voted_view = max(self.last_voted_view, proposal.view);
+			votes.push(Vote::<B>::new(proposal.digest(), proposal.view, author_id.clone()));
 		}
 
-		// TODO how process TC of proposal.
-		self.last_voted_view = max(self.last_voted_view, proposal.view);
+		let digests = votes.iter().map(|vote| vote.digest()).collect::<Vec<_>>();
+		let votes = votes
+			.into_iter()
+			.zip(self.sign_digests(&author_id, &digests))
+			.filter_map(|(mut vote, signature)| {
+				vote.signature = signature.ok()?;
+				if vote.signature.is_some() {
+					self.audit(SignedKind::Vote, vote.view, vote.digest()).ok()?;
+				}
+				Some(vote)
+			})
+			.collect();
+		self.timestamp_votes(&author_id, votes)
+	}
 
-		let mut vote = Vote::<B>::new(proposal.digest(), proposal.view, author_id.clone());
+	// Sign the clock of the vote time source into `votes`. A vote whose timestamp can't be signed
+	// is sent without it.
+	fn timestamp_votes(&self, author_id: &AuthorityId, mut votes: Vec<Vote<B>>) -> Vec<Vote<B>> {
+		let Some(clock) = self.vote_time_source.as_ref() else { return votes };
 
-		vote.signature = self
-			.keystore
-			.sign_with(
-				AuthorityId::ID,
-				AuthorityId::CRYPTO_ID,
-				author_id.as_slice(),
-				vote.digest().as_ref(),
-			)
-			.ok()?
-			.and_then(|data| AuthoritySignature::try_from(data).ok());
+		let timestamp = clock.now();
+		let digests = votes
+			.iter()
+			.map(|vote| VoteTimestamp::digest::<B>(vote.digest(), timestamp))
+			.collect::<Vec<_>>();
+		for (vote, signature) in votes.iter_mut().zip(self.sign_digests(author_id, &digests)) {
+			if let Ok(Some(signature)) = signature {
+				vote.timestamp = Some((timestamp, signature));
+			}
+		}
+		votes
+	}
 
-		Some(vote)
+	// Sign each digest with the key of `author_id`. The keystore has no batch signing, so this
+	// signs them in turn.
+	fn sign_digests(
+		&self,
+		author_id: &AuthorityId,
+		digests: &[B::Hash],
+	) -> Vec<Result<Option<AuthoritySignature>, HotstuffError>> {
+		digests
+			.iter()
+			.map(|digest| {
+				self.keystore
+					.sign_with(
+						AuthorityId::ID,
+						AuthorityId::CRYPTO_ID,
+						author_id.as_slice(),
+						self.signing_context.signing_digest::<B>(*digest).as_ref(),
+					)
+					.map(|signature| {
+						signature.and_then(|data| AuthoritySignature::try_from(data).ok())
+					})
+					.map_err(|e| Other(e.to_string()))
+			})
+			.collect()
 	}
 
 	pub fn view(&self) -> ViewNumber {
 		self.view
 	}
 
+	pub fn set_id(&self) -> SetId {
+		self.set_id
+	}
+
+	pub fn regressed_messages(&self) -> u64 {
+		self.regressed_messages
+	}
+
+	// Messages of the previous authority set are tolerated, as their senders may be in the
+	// middle of the transition.
+	pub fn check_set_id(&mut self, set_id: SetId) -> Result<(), HotstuffError> {
+		if set_id + 1 < self.set_id {
+			self.regressed_messages += 1;
+			return Err(AuthoritySetRegression(set_id));
+		}
+
+		Ok(())
+	}
+
+	// The activation view of the pending authority set change, or else of the last one, with the
+	// id and authorities of the set before and after it.
+	fn set_transition(&self) -> Option<SetTransition<'_>> {
+		if let Some((view, next)) = self.pending_set_change.as_ref() {
+			return Some((*view, (self.set_id, &self.authorities), (self.set_id + 1, next)));
+		}
+		self.previous_authorities.as_ref().map(|(view, previous)| {
+			(*view, (self.set_id.saturating_sub(1), previous), (self.set_id, &self.authorities))
+		})
+	}
+
+	// Whether `view` is within the transition window of an authority set change.
+	pub fn in_transition_window(&self, view: ViewNumber) -> bool {
+		match (self.transition_window, self.set_transition()) {
+			(Some(window), Some((activation, _, _))) => view.abs_diff(activation) <= window,
+			_ => false,
+		}
+	}
+
+	// The id and authorities of the set proposing at `view`: within the transition window of a
+	// set change, the set before it for the views before its activation and the set after it
+	// from its activation on, and the current set otherwise.
+	pub fn proposal_set(&self, view: ViewNumber) -> (SetId, &AuthorityList) {
+		match self.set_transition() {
+			Some((activation, before, after)) if self.in_transition_window(view) => {
+				if view < activation {
+					before
+				} else {
+					after
+				}
+			},
+			_ => (self.set_id, &self.authorities),
+		}
+	}
+
+	// With a transition window, a proposal within it may be sent for the set before or after
+	// the change, as its sender may not have switched yet, and any other proposal only for the
+	// current set.
+	pub fn check_proposal_set_id(
+		&self,
+		proposal: &Proposal<B>,
+		set_id: SetId,
+	) -> Result<(), HotstuffError> {
+		if self.transition_window.is_none() {
+			return Ok(());
+		}
+
+		let accepted = match self.set_transition() {
+			Some((_, (before, _), (after, _))) if self.in_transition_window(proposal.view) => {
+				set_id == before || set_id == after
+			},
+			_ => set_id == self.set_id,
+		};
+		if !accepted {
+			return Err(SetIdOutsideTransition(set_id));
+		}
+		Ok(())
+	}
+
 	pub fn verify_timeout(&self, timeout: &Timeout<B>) -> Result<(), HotstuffError> {
-		timeout.verify(&self.authorities)
+		timeout.verify_with_resolver(
+			|view| self.authorities_at(view),
+			self.committee.as_ref(),
+			&self.signing_context,
+		)
+	}
+
+	pub fn authorities(&self) -> &AuthorityList {
+		&self.authorities
 	}
 
 	pub fn verify_proposal(&self, proposal: &Proposal<B>) -> Result<(), HotstuffError> {
-		if !proposal.author.eq(&self.view_leader(proposal.view)) {
+		self.verify_proposer(proposal)?;
+
+		if self.transition_window.is_none() {
+			return proposal.verify_with(
+				&self.authorities,
+				self.committee.as_ref(),
+				&self.signing_context,
+			);
+		}
+		proposal.verify_with_resolver(
+			|view| self.proposal_set(view).1.clone(),
+			self.committee.as_ref(),
+			&self.signing_context,
+		)
+	}
+
+	// The checks of `verify_proposal` which don't depend on signatures.
+	pub fn verify_proposer(&self, proposal: &Proposal<B>) -> Result<(), HotstuffError> {
+		let (_, authorities) = self.proposal_set(proposal.view);
+		let proposers =
+			self.election
+				.fallback_chain(authorities, proposal.view, self.fallback_proposers);
+		if !proposers.contains(&proposal.author) {
 			return Err(WrongProposer);
 		}
+		proposal.verify_view()?;
+		if let Some(window) = self.proposal_time_window {
+			proposal.verify_timestamp(self.local_clock.now(), window)?;
+		}
 
-		// TODO how process authority changed.
-		proposal.verify(&self.authorities)
+		// A proposal skipping the previous view must carry the TC of that view, and extend the
+		// highest QC the TC knows of.
+		if proposal.qc.view + 1 != proposal.view {
+			match proposal.tc.as_ref().filter(|tc| tc.view + 1 == proposal.view) {
+				None => return Err(MissingTimeoutJustification),
+				Some(tc) if proposal.qc.view < tc.high_qc.view => return Err(TcHighQcMismatch),
+				Some(_) => {},
+			}
+		}
+
+		Ok(())
 	}
 
 	pub fn verify_vote(&self, vote: &Vote<B>) -> Result<(), HotstuffError> {
+		self.verify_vote_view(vote)?;
+
+		vote.verify_with(&self.voting_committee(vote.view), &self.signing_context)
+	}
+
+	// The checks of `verify_vote` which don't depend on signatures.
+	pub fn verify_vote_view(&self, vote: &Vote<B>) -> Result<(), HotstuffError> {
 		if vote.view < self.view {
 			return Err(ExpiredVote);
 		}
 
-		vote.verify(&self.authorities)
+		Ok(())
+	}
+
+	pub fn verify_qc(&self, qc: &QC<B>) -> Result<(), HotstuffError> {
+		qc.verify_with_resolver(
+			|view| self.authorities_at(view),
+			self.committee.as_ref(),
+			&self.signing_context,
+		)
 	}
 
 	pub fn verify_tc(&self, tc: &TC<B>) -> Result<(), HotstuffError> {
@@ -188,7 +841,11 @@ impl<B: BlockT> ConsensusState<B> {
 			return Err(InvalidTC);
 		}
 
-		tc.verify(&self.authorities)
+		tc.verify_with_resolver(
+			|view| self.authorities_at(view),
+			self.committee.as_ref(),
+			&self.signing_context,
+		)
 	}
 
 	// add a verified timeout then try return a TC.
@@ -196,35 +853,114 @@ impl<B: BlockT> ConsensusState<B> {
 		self.aggregator.add_timeout(timeout, &self.authorities)
 	}
 
-	// add a verified vote and try return a QC.
+	// Drop the buffered votes and timeouts of past views which received none for `ttl`.
+	pub fn prune_expired_messages(&mut self, now: Instant, ttl: Duration) -> usize {
+		self.aggregator.prune_expired(now, ttl, self.view)
+	}
+
+	// add a verified vote and try return a QC. A nil vote is added as the timeout it stands for.
 	pub fn add_vote(&mut self, vote: &Vote<B>) -> Result<Option<QC<B>>, HotstuffError> {
-		self.aggregator.add_vote(vote.clone(), &self.authorities)
+		if vote.is_nil() {
+			return Err(NilCertificate);
+		}
+
+		let committee = voting_committee(&self.authorities, self.committee.as_ref(), vote.view);
+		self.participation.write().record(vote.view, &committee, [&vote.voter]);
+		self.aggregator.add_vote(vote.clone(), &committee)
+	}
+
+	// The votes dropped as their proposal had all the votes it may have.
+	pub fn dropped_votes(&self) -> u64 {
+		self.aggregator.dropped_votes()
+	}
+
+	// Note the proposal `digest` of `view` is committed, the highest one is kept.
+	pub fn record_commit(&mut self, digest: B::Hash, view: ViewNumber) {
+		if self.last_committed.map_or(true, |(_, committed_view)| view > committed_view) {
+			self.last_committed = Some((digest, view));
+		}
+	}
+
+	// Verify `proposal` descends from the highest proposal known to be committed, with its
+	// ancestors looked up by `get`. Before the first commit every proposal descends from genesis.
+	pub fn verify_extends_committed<F>(
+		&self,
+		proposal: &Proposal<B>,
+		get: F,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(B::Hash) -> Result<Option<Proposal<B>>, HotstuffError>,
+	{
+		match self.last_committed {
+			Some((committed, view)) => proposal.verify_extends(committed, view, get),
+			None => Ok(()),
+		}
+	}
+
+	pub fn high_qc(&self) -> &QC<B> {
+		&self.high_qc
+	}
+
+	pub fn high_qc_view(&self) -> ViewNumber {
+		self.high_qc.view
 	}
 
 	pub fn update_high_qc(&mut self, qc: &QC<B>) {
 		if qc.view > self.high_qc.view {
-			self.high_qc = qc.clone()
+			self.high_qc = qc.clone();
+			// A TC justifies nothing once a QC of its view or a later one is known.
+			self.high_tc = self.high_tc.take().filter(|high_tc| high_tc.view > qc.view);
 		}
 	}
 
+	pub fn high_tc(&self) -> Option<&TC<B>> {
+		self.high_tc.as_ref()
+	}
+
+	// Keep `tc` if it's of a later view than both the high TC and the high QC, returns whether
+	// it's kept.
+	pub fn update_high_tc(&mut self, tc: &TC<B>) -> bool {
+		// A proposal justified by the TC must extend its high QC.
+		self.update_high_qc(&tc.high_qc);
+
+		let justified_view = self
+			.high_tc
+			.as_ref()
+			.map_or(self.high_qc.view, |high_tc| high_tc.view.max(self.high_qc.view));
+		if tc.view <= justified_view {
+			return false;
+		}
+
+		self.high_tc = Some(tc.clone());
+		true
+	}
+
 	pub fn advance_view_from_target(&mut self, view: ViewNumber) {
 		if self.view >= view {
 			self.view = view + 1;
 		}
+		self.apply_pending_set_change();
 	}
 
+	// The leader elected for `view`, never an authority of weight zero.
 	pub fn view_leader(&self, view: ViewNumber) -> AuthorityId {
-		let leader_index = view % self.authorities.len() as ViewNumber;
-		self.authorities[leader_index as usize].0.clone()
+		self.election
+			.leader(&self.authorities, view)
+			.expect("authority sets are verified to have some weight; qed")
+	}
+
+	// The authorities which may propose at `view` in the order they take over: the leader, then
+	// the backups, the leaders of the following views. Every node derives the same chain.
+	pub fn fallback_chain(&self, view: ViewNumber) -> Vec<AuthorityId> {
+		self.election.fallback_chain(&self.authorities, view, self.fallback_proposers)
 	}
 
 	// hotstuff consensus leader, not substrate block author.
 	pub fn is_leader(&self) -> bool {
-		let leader_index = self.view % self.authorities.len() as ViewNumber;
-		let leader_id = &self.authorities[leader_index as usize].0;
+		let leader_id = self.view_leader(self.view);
 
 		if let Some(id) = self.local_authority_id() {
-			return id.eq(leader_id);
+			return id.eq(&leader_id);
 		}
 
 		false
@@ -244,14 +980,116 @@ pub struct ConsensusWorker<
 	client: Arc<C>,
 	sync: S,
 	local_timer: Timer,
+	// The view voted last and when its proposal was handled, to measure the round latency.
+	round_start: Option<(ViewNumber, Instant)>,
+	// When this node entered the current view.
+	view_start: Instant,
+	// The view of the proposal whose votes this node collects, and when it stops waiting.
+	vote_deadline: Option<(ViewNumber, Pin<Box<Sleep>>)>,
+	// The view this node backs up a silent leader of, and when it proposes in its place.
+	fallback_deadline: Option<(ViewNumber, Pin<Box<Sleep>>)>,
+	// The view this leader waits for the pool to fill at, and when it checks the pool again.
+	pacing_deadline: Option<(ViewNumber, Pin<Box<Sleep>>)>,
+	// The own vote waiting to be sent to the next leader, and when it's sent.
+	delayed_vote: Option<Vote<B>>,
+	vote_broadcast_deadline: Option<(ViewNumber, Pin<Box<Sleep>>)>,
+	// Whether a view which brought no proposal times out with a nil vote.
+	nil_votes: bool,
 	synchronizer: Synchronizer<B, BE, C>,
-	_consensus_msg_tx: Sender<ConsensusMessage<B>>,
-	consensus_msg_rx: Receiver<ConsensusMessage<B>>,
+	_consensus_msg_tx: Sender<GossipMessage<B>>,
+	consensus_msg_rx: Receiver<GossipMessage<B>>,
 
 	processing_block: Option<BlockInfo<B>>,
 	pending_finalize_queue: Arc<Mutex<VecDeque<BlockInfo<B>>>>,
 
 	proposal_hash_queue: Vec<B::Hash>,
+
+	block_tree: SharedBlockTree<B>,
+
+	// The proposals handled recently, so redundant gossip is handled once.
+	seen_proposals: ProposalDedup<B::Hash>,
+	// Bounds the distinct proposals of each author in a view verified.
+	proposal_budget: VerificationBudget,
+	// The first QC learnt at each recent view, which every later QC of the view is checked
+	// against.
+	recent_qcs: BTreeMap<ViewNumber, QC<B>>,
+
+	// Verifies incoming proposals and votes off the consensus loop when enabled.
+	verification_pool: Option<VerificationPool<B>>,
+	// Sheds messages while the verification pool can't keep up, when enabled with it.
+	overload: Option<OverloadMonitor>,
+
+	events: EventSink<B>,
+
+	// Stops proposals while too few authorities are reachable to form a quorum.
+	quorum_monitor: QuorumMonitor,
+
+	// Warns when the proposals of the node keep carrying no block while transactions wait.
+	empty_proposals: EmptyProposalMonitor,
+	proposal_pacing: Option<ProposalPacing>,
+	pool_status: Option<PoolStatus>,
+
+	// Indexes the transactions of committed blocks when inclusion proofs are enabled.
+	inclusion_index: Option<SharedInclusionIndex<B>>,
+
+	repropose_uncommitted: bool,
+
+	finality_justifications: bool,
+
+	max_reorg_depth: u32,
+	finalize_retry: FinalizeRetry,
+	// Most commits made while catching up on buffered proposals finalized in one batch.
+	finalize_batch_size: Option<usize>,
+	// How many certified proposals in a row commit the first of them.
+	variant: ConsensusVariant,
+	// Whether buffered proposals are being handled, and the commits they made meanwhile.
+	catching_up: bool,
+	deferred_commits: Vec<DeferredCommit<B>>,
+
+	// How long a proposal waits for its parent to be fetched.
+	proposal_fetch_timeout: Duration,
+	// The fetches of unknown proposals, a bounded number of them sent at a time.
+	sync_requests: SyncRequests<B::Hash>,
+	// When the next fetch fails or is due.
+	sync_deadline: Option<(ViewNumber, Pin<Box<Sleep>>)>,
+	// How long the votes and timeouts of a past view are kept after the last one is received.
+	message_ttl: Duration,
+
+	vote_collection_timeout: Option<Duration>,
+
+	fallback_proposer_delay: Duration,
+
+	// How long an own vote waits so the votes of a view reach the next leader together.
+	vote_broadcast_delay: Option<Duration>,
+
+	pause: PauseHandle,
+	// Changes when the voter is paused or resumed.
+	pause_rx: watch::Receiver<bool>,
+
+	pacemaker: SharedPacemaker,
+	certificates: SharedCertificates<B>,
+	// The blocks each committed proposal stands for, its own block only without it.
+	committed_blocks: Option<SharedCommittedBlocks<B>>,
+	// Local timeouts since a QC last advanced the view.
+	consecutive_timeouts: u64,
+
+	catch_panics: bool,
+	// The message handled last, to log with a panic.
+	last_message: Option<String>,
+
+	ordered_signing: bool,
+	// The signed messages waiting for the end of the step to be sent in signing order.
+	outbox: Vec<ConsensusMessage<B>>,
+
+	metrics: Option<Metrics>,
+
+	equivocation_policy: EquivocationPolicy,
+	// The view the node stopped voting in, as its leader equivocated.
+	halted_view: Option<ViewNumber>,
+	// What the node decided at the last views.
+	traces: ViewTraces<B>,
+	// Records the inputs of the voter, to replay them.
+	journal: Option<Journal>,
 }
 
 impl<B, BE, C, N, S> ConsensusWorker<B, BE, C, N, S>
@@ -270,17 +1108,57 @@ where
 		sync: S,
 		network: HotstuffNetworkBridge<B, N, S>,
 		synchronizer: Synchronizer<B, BE, C>,
-		local_timer_duration: u64,
-		consensus_msg_tx: Sender<ConsensusMessage<B>>,
-		consensus_msg_rx: Receiver<ConsensusMessage<B>>,
+		config: Config,
+		consensus_msg_tx: Sender<GossipMessage<B>>,
+		consensus_msg_rx: Receiver<GossipMessage<B>>,
 		pending_finalize_queue: Arc<Mutex<VecDeque<BlockInfo<B>>>>,
+		block_tree: SharedBlockTree<B>,
+		events: EventSink<B>,
+		inclusion_index: SharedInclusionIndex<B>,
+		pause: PauseHandle,
+		pacemaker: SharedPacemaker,
+		certificates: SharedCertificates<B>,
+		committed_blocks: Option<SharedCommittedBlocks<B>>,
 	) -> Self {
 		let pending_block = pending_finalize_queue.lock().ok().and_then(|q| q.front().cloned());
+		consensus_state.refresh_signing_status();
+		let verification_pool = (config.verification_threads > 0).then(|| {
+			VerificationPool::new(
+				config.verification_threads,
+				config.verification_queue_depth,
+				consensus_state.verifier_set(),
+				consensus_state.committee_sampler().cloned(),
+				consensus_state.signing_context().clone(),
+			)
+		});
+		let overload = config
+			.verification_overload_after
+			.filter(|_| verification_pool.is_some())
+			.map(|after| OverloadMonitor::new(Duration::from_millis(after)));
+
+		let metrics = config.prometheus_registry.as_ref().and_then(|registry| {
+			Metrics::register(registry)
+				.map_err(|e| debug!(target: "Hotstuff", "failed to register metrics: {:?}", e))
+				.ok()
+		});
 
 		Self {
 			state: consensus_state,
 			network,
-			local_timer: Timer::new(local_timer_duration),
+			local_timer: if config.adaptive_timeout {
+				Timer::adaptive(config.local_timer_duration)
+			} else {
+				Timer::new(config.local_timer_duration)
+			}
+			.with_skew_tolerance(config.clock_skew_tolerance),
+			round_start: None,
+			view_start: Instant::now(),
+			vote_deadline: None,
+			fallback_deadline: None,
+			pacing_deadline: None,
+			delayed_vote: None,
+			vote_broadcast_deadline: None,
+			nil_votes: config.nil_votes,
 			_consensus_msg_tx: consensus_msg_tx,
 			consensus_msg_rx,
 			client,
@@ -289,45 +1167,543 @@ where
 			processing_block: pending_block,
 			pending_finalize_queue,
 			proposal_hash_queue: Vec::new(),
+			block_tree,
+			seen_proposals: ProposalDedup::default(),
+			proposal_budget: VerificationBudget::new(config.proposal_verification_budget),
+			recent_qcs: BTreeMap::new(),
+			verification_pool,
+			overload,
+			events,
+			quorum_monitor: QuorumMonitor::new(
+				Duration::from_millis(config.local_timer_duration * 2),
+				Duration::from_millis(config.quorum_unreachable_after),
+			),
+			empty_proposals: EmptyProposalMonitor::new(
+				config.pool_status.clone(),
+				config.empty_proposal_threshold,
+			),
+			proposal_pacing: config.proposal_pacing.clone(),
+			pool_status: config.pool_status.clone(),
+			inclusion_index: config.inclusion_proofs.then_some(inclusion_index),
+			repropose_uncommitted: config.repropose_uncommitted,
+			finality_justifications: config.finality_justifications,
+			max_reorg_depth: config.max_reorg_depth,
+			finalize_retry: config.finalize_retry.clone(),
+			finalize_batch_size: config.finalize_batch_size.filter(|size| *size > 1),
+			variant: config.consensus_variant,
+			catching_up: false,
+			deferred_commits: Vec::new(),
+			proposal_fetch_timeout: Duration::from_millis(config.proposal_fetch_timeout),
+			sync_requests: SyncRequests::new(config.sync_request_limit.clone()),
+			sync_deadline: None,
+			message_ttl: Duration::from_millis(config.message_ttl),
+			vote_collection_timeout: config.vote_collection_timeout.map(Duration::from_millis),
+			fallback_proposer_delay: Duration::from_millis(config.fallback_proposer_delay),
+			vote_broadcast_delay: config.vote_broadcast_delay.map(|delay| {
+				Duration::from_millis(
+					delay.min(config.local_timer_duration / VOTE_DELAY_TIMER_FRACTION),
+				)
+			}),
+			pause_rx: pause.subscribe(),
+			pause,
+			pacemaker,
+			certificates,
+			committed_blocks,
+			consecutive_timeouts: 0,
+			catch_panics: config.catch_panics,
+			last_message: None,
+			ordered_signing: config.ordered_signing,
+			outbox: Vec::new(),
+			metrics,
+			equivocation_policy: config.equivocation_policy,
+			halted_view: None,
+			traces: ViewTraces::default(),
+			journal: None,
 		}
 	}
 
-	pub async fn run(mut self) {
+	// Record every input of the voter from now on in `journal`.
+	pub fn with_journal(mut self, journal: Journal) -> Self {
+		self.journal = Some(journal);
+		self
+	}
+
+	pub async fn run(self) {
+		self.run_with(Self::step).await
+	}
+
+	// Run the loop, taking a `step` at a time. With `catch_panics`, a panicking step halts the
+	// voter instead of unwinding out of its task, and the voter stays alive for diagnosis.
+	pub(crate) async fn run_with<F>(mut self, mut step: F)
+	where
+		F: for<'a> FnMut(&'a mut Self) -> BoxFuture<'a, ()>,
+	{
 		loop {
+			if !self.catch_panics {
+				step(&mut self).await;
+				continue;
+			}
+
+			if let Err(panic) = AssertUnwindSafe(step(&mut self)).catch_unwind().await {
+				self.halt(panic);
+				future::pending::<()>().await;
+			}
+		}
+	}
+
+	// Log a panic of the loop with its context, and raise the alarm.
+	fn halt(&self, panic: Box<dyn Any + Send>) {
+		let panic = match panic.downcast::<String>() {
+			Ok(panic) => *panic,
+			Err(panic) => {
+				panic.downcast_ref::<&str>().map_or("Box<dyn Any>", |panic| panic).to_string()
+			},
+		};
+		let view = self.state.view();
+		error!(target: "Hotstuff", "!!! voter halted at view {} panicking on {}: {}",
+			view, self.last_message.as_deref().unwrap_or("no message"), panic);
+
+		self.events.emit(ConsensusEvent::WorkerHalted {
+			view,
+			last_message: self.last_message.clone(),
+			panic,
+		});
+	}
+
+	fn step(&mut self) -> BoxFuture<'_, ()> {
+		Box::pin(async move {
+			self.observe_overload();
 			let _ = tokio::select! {
-				_ = &mut self.local_timer => self.handle_local_timer().await,
-				Some(message) = self.consensus_msg_rx.recv()=> match message {
-					Propose(proposal) => {
-						match self.handle_proposal(&proposal).await{
-							Ok(_) => {},
-							Err(e) =>  debug!(target: "Hotstuff","{:#?} handle_proposal has error {:#?}",self.state.local_authority_id(), e),
-						};
-						Ok(())
-					},
-					Vote(vote) => {
-						match self.handle_vote(&vote).await{
-							Ok(_) => {},
-							Err(e) => debug!(target: "Hotstuff","handle_vote has error {:#?}", e),
-						};
-						Ok(())
-					},
-					Timeout(timeout) => {
-						match self.handle_timeout(&timeout).await{
-							Ok(_) => {},
-							Err(e) => debug!(target: "Hotstuff","{:#?} handle_timeout has error {:#?}",self.state.local_authority_id(), e),
-						};
-						Ok(())
-					},
-					TC(tc) => {
-						match self.handle_tc(&tc).await{
-							Ok(_) => {},
-							Err(e) =>  debug!(target: "Hotstuff","handle_tc has error {:#?}", e),
-						}
-						Ok(())
-					},
-					_ => Ok(()),
-				}
+				// No view times out while paused.
+				_ = &mut self.local_timer, if !self.pause.is_paused() => self.handle_event(JournalEvent::LocalTimer).await,
+				view = deadline_expired(&mut self.vote_deadline), if !self.pause.is_paused() => {
+					self.handle_event(JournalEvent::VoteCollectionTimeout(view)).await
+				},
+				view = deadline_expired(&mut self.fallback_deadline), if !self.pause.is_paused() => {
+					self.handle_event(JournalEvent::FallbackTurn(view)).await
+				},
+				view = deadline_expired(&mut self.pacing_deadline), if !self.pause.is_paused() => {
+					self.handle_event(JournalEvent::ProposalPacing(view)).await
+				},
+				view = deadline_expired(&mut self.vote_broadcast_deadline), if !self.pause.is_paused() => {
+					self.handle_event(JournalEvent::VoteBroadcast(view)).await
+				},
+				_ = deadline_expired(&mut self.sync_deadline) => {
+					self.handle_event(JournalEvent::SyncDeadline).await
+				},
+				Ok(()) = self.pause_rx.changed() => {
+					let paused = *self.pause_rx.borrow_and_update();
+					self.handle_event(JournalEvent::Paused(paused)).await
+				},
+				Some((message, result)) = next_verified(&mut self.verification_pool) => {
+					if self.catch_panics {
+						self.last_message = Some(ConsensusMessage::from(message.clone()).summary());
+					}
+					self.journal(|| JournalEvent::Message(Box::new(message.clone().into())));
+					match self.handle_verified(message, result).await {
+						Ok(_) => {},
+						Err(e) => debug!(target: "Hotstuff","handle_verified has error {:#?}", e),
+					};
+					Ok(())
+				},
+				// Stop reading while the verification queue is full, which pushes back on the network,
+				// unless overloaded.
+				Some(GossipMessage { set_id, message }) = self.consensus_msg_rx.recv(), if !self.verification_saturated() => {
+					if self.catch_panics {
+						self.last_message = Some(message.summary());
+					}
+					match message {
+						_ if self.state.check_set_id(set_id).is_err() => {
+							debug!(target: "Hotstuff","discard message of authority set {}, self.set_id {}", set_id, self.state.set_id());
+							Ok(())
+						},
+						Propose(proposal) if self.is_duplicate(&proposal) => {
+							trace!(target: "Hotstuff","discard duplicate proposal {}", proposal.digest());
+							Ok(())
+						},
+						Propose(proposal) if self.state.check_proposal_set_id(&proposal, set_id).is_err() => {
+							debug!(target: "Hotstuff","discard proposal {} of authority set {} outside the set transition window", proposal.digest(), set_id);
+							Ok(())
+						},
+						// Checked before its signatures, so proposals of other authors don't use up the budget of the
+						// leader.
+						Propose(proposal) if self.state.verify_proposer(&proposal).is_err() => {
+							debug!(target: "Hotstuff","discard proposal {} of {}, not a proposer of view {}", proposal.digest(), proposal.author, proposal.view);
+							Ok(())
+						},
+						Propose(proposal) if !self.proposal_budget.spend(proposal.view, &proposal.author) => {
+							debug!(target: "Hotstuff","drop proposal {}, over the verification budget of {} in view {}", proposal.digest(), proposal.author, proposal.view);
+							Ok(())
+						},
+						message if self.should_shed(&message) => {
+							trace!(target: "Hotstuff","shed {} while overloaded", message.summary());
+							if let Some(metrics) = self.metrics.as_ref() {
+								metrics.observe_shed(message.kind());
+							}
+							Ok(())
+						},
+						// The verification pool only knows the sets activated so far. Overloaded, the proposals left
+						// are verified without waiting behind its queue.
+						Propose(proposal) if self.verification_pool.is_some() && !self.state.in_transition_window(proposal.view) && !self.is_overloaded() => {
+							self.submit_verification(Verifiable::Proposal(proposal));
+							Ok(())
+						},
+						Vote(vote) if self.verification_pool.is_some() => {
+							self.submit_verification(Verifiable::Vote(vote));
+							Ok(())
+						},
+						message => self.handle_event(JournalEvent::Message(Box::new(message))).await,
+					}
+				},
 			};
+
+			self.flush_outbox();
+			self.refresh_verifier_set();
+			self.pacemaker.publish(self.pacemaker_state(), self.local_timer.deadline());
+			*self.certificates.write() = Some(self.certificates_state());
+		})
+	}
+
+	// Handle an input of the voter, recording it in the journal first.
+	async fn handle_event(&mut self, event: JournalEvent<B>) -> Result<(), HotstuffError> {
+		self.journal(|| event.clone());
+		match event {
+			JournalEvent::Message(message) => {
+				self.dispatch(*message).await;
+				Ok(())
+			},
+			JournalEvent::LocalTimer => self.handle_local_timer().await,
+			JournalEvent::VoteCollectionTimeout(view) => {
+				self.handle_vote_collection_timeout(view).await
+			},
+			JournalEvent::FallbackTurn(view) => self.handle_fallback_turn(view).await,
+			JournalEvent::ProposalPacing(view) => self.handle_proposal_pacing(view).await,
+			JournalEvent::VoteBroadcast(view) => self.handle_vote_broadcast(view),
+			JournalEvent::SyncDeadline => {
+				self.send_sync_requests();
+				Ok(())
+			},
+			JournalEvent::Paused(paused) => {
+				self.handle_pause_change(paused);
+				Ok(())
+			},
+		}
+	}
+
+	// Handle a message which passed the checks of its authority set, duplicates and load,
+	// verifying its signatures.
+	async fn dispatch(&mut self, message: ConsensusMessage<B>) {
+		match message {
+			Propose(proposal) => {
+				if let Err(e) = self.handle_proposal(&proposal).await {
+					debug!(target: "Hotstuff","{:#?} handle_proposal has error {:#?}",self.state.local_authority_id(), e);
+					self.report_forged(&Propose(proposal), &e);
+				}
+			},
+			Vote(vote) => {
+				if let Err(e) = self.handle_vote(&vote).await {
+					debug!(target: "Hotstuff","handle_vote has error {:#?}", e);
+					self.report_forged(&Vote(vote), &e);
+				}
+			},
+			Timeout(timeout) => {
+				if let Err(e) = self.handle_timeout(&timeout).await {
+					debug!(target: "Hotstuff","{:#?} handle_timeout has error {:#?}",self.state.local_authority_id(), e);
+					self.report_forged(&Timeout(timeout), &e);
+				}
+			},
+			SyncRequest(digest, _) => {
+				if let Err(e) = self.handle_sync_request(digest) {
+					debug!(target: "Hotstuff","handle_sync_request has error {:#?}", e);
+				}
+			},
+			TC(tc) => {
+				if let Err(e) = self.handle_tc(&tc).await {
+					debug!(target: "Hotstuff","handle_tc has error {:#?}", e);
+					self.report_forged(&TC(tc), &e);
+				}
+			},
+			_ => {},
+		}
+	}
+
+	// Record an input in the journal, if any. The voter carries on when it can't be recorded.
+	fn journal(&mut self, event: impl FnOnce() -> JournalEvent<B>) {
+		let Some(journal) = self.journal.as_mut() else { return };
+		let entry = JournalEntry { timestamp: self.state.local_clock.now(), event: event() };
+		if let Err(e) = journal.record(&entry) {
+			warn!(target: "Hotstuff", "failed to journal an input at view {}: {:?}", self.state.view(), e);
+		}
+	}
+
+	/// Feed the entries of a journal to the worker in their order, reproducing the decisions of
+	/// the voter which recorded them when the worker starts from the same state, with its blocks.
+	/// The local clock, and the vote clock if any, read the time each entry was recorded at.
+	/// Messages verified by the pool of the voter are verified again, inline.
+	pub async fn replay(&mut self, entries: impl IntoIterator<Item = JournalEntry<B>>) {
+		let clock = MockClock::default();
+		self.state.local_clock = clock.time_source();
+		if self.state.vote_time_source.is_some() {
+			self.state.vote_time_source = Some(clock.time_source());
+		}
+
+		for JournalEntry { timestamp, event } in entries {
+			clock.set(timestamp);
+			if let JournalEvent::Paused(paused) = event {
+				if paused {
+					self.pause.pause();
+				} else {
+					self.pause.resume();
+				}
+				self.pause_rx.borrow_and_update();
+			}
+			if let Err(e) = self.handle_event(event).await {
+				debug!(target: "Hotstuff", "replayed input has error {:#?}", e);
+			}
+			self.flush_outbox();
+		}
+	}
+
+	/// Proposals dropped unverified for being over the verification budget of their author in
+	/// their view.
+	pub fn proposals_over_budget(&self) -> u64 {
+		self.proposal_budget.dropped()
+	}
+
+	fn is_duplicate(&self, proposal: &Proposal<B>) -> bool {
+		self.seen_proposals
+			.contains(proposal.view, &proposal.author, &proposal.digest())
+	}
+
+	// Record a proposal whose signature was checked, reporting a conflicting one of its leader.
+	// Whether the node continues with a conflicting proposal follows the equivocation policy.
+	fn record_proposal(&mut self, proposal: &Proposal<B>) -> Result<(), HotstuffError> {
+		let digest = proposal.digest();
+		match self.seen_proposals.insert(proposal.view, proposal.author.clone(), digest) {
+			Seen::Equivocation { first } => {
+				warn!(target: "Hotstuff", "leader {} of view {} equivocated, proposals {} and {}",
+					proposal.author, proposal.view, first, digest);
+				self.events.emit(ConsensusEvent::ProposalEquivocation {
+					view: proposal.view,
+					author: proposal.author.clone(),
+					first,
+					second: digest,
+				});
+
+				if self.equivocation_policy == EquivocationPolicy::Halt {
+					self.halt_view(proposal.view);
+				}
+				if self.equivocation_policy.continues_with(&first, &digest) {
+					return Ok(());
+				}
+				Err(ProposalEquivocation(proposal.author.clone()))
+			},
+			Seen::New | Seen::Duplicate => Ok(()),
+		}
+	}
+
+	// Stop voting in `view`, withdrawing the own vote of the view not sent yet.
+	fn halt_view(&mut self, view: ViewNumber) {
+		warn!(target: "Hotstuff", "stop voting in view {}", view);
+		self.halted_view = Some(view);
+		if self.delayed_vote.as_ref().map_or(false, |vote| vote.view == view) {
+			self.delayed_vote = None;
+			self.vote_broadcast_deadline = None;
+		}
+	}
+
+	fn handle_pause_change(&mut self, paused: bool) {
+		if paused {
+			info!(target: "Hotstuff", "voter paused at view {}", self.state.view());
+		} else {
+			info!(target: "Hotstuff", "voter resumed at view {}", self.state.view());
+			// Give the current view a full timeout again.
+			self.local_timer.reset();
+		}
+	}
+
+	// Lower the reputation of the peer which sent a message with an invalid signature.
+	fn report_forged(&mut self, message: &ConsensusMessage<B>, error: &HotstuffError) {
+		if matches!(error, InvalidSignature(_) | NonCanonicalSignature(_) | NullSignature) {
+			self.network.report_invalid_signature(message);
+			// A forgery in the name of its author doesn't use up the budget of the author.
+			if let Propose(proposal) = message {
+				self.proposal_budget.refund(proposal.view, &proposal.author);
+			}
+		}
+	}
+
+	// Buffer a proposal whose parent is unknown, and ask the peers for the parent.
+	fn fetch_parent(&mut self, proposal: &Proposal<B>) {
+		let deadline = Instant::now() + self.proposal_fetch_timeout;
+		if !self.synchronizer.buffer_orphan(proposal.clone(), deadline) {
+			debug!(target: "Hotstuff", "~~ drop proposal {}, too many proposals wait for their parent", proposal.digest());
+			return;
+		}
+
+		debug!(target: "Hotstuff", "~~ fetch parent {} of proposal {}", proposal.parent_hash(), proposal.digest());
+		if self.state.local_authority_id().is_some() {
+			self.sync_requests.request(proposal.parent_hash(), Instant::now());
+			self.send_sync_requests();
+		}
+	}
+
+	// Send the fetches due while slots are free, and wait for the next fetch to fail or be due.
+	fn send_sync_requests(&mut self) {
+		let Some(local) = self.state.local_authority_id() else { return };
+		for digest in self.sync_requests.poll(Instant::now()) {
+			self.gossip(ConsensusMessage::SyncRequest(digest, local.clone()), true);
+		}
+		if self.sync_requests.queued() > 0 {
+			trace!(target: "Hotstuff", "~~ {} fetches in flight, {} queued", self.sync_requests.in_flight(), self.sync_requests.queued());
+		}
+
+		let view = self.state.view();
+		self.sync_deadline =
+			self.sync_requests.next_deadline().map(|at| (view, Box::pin(sleep_until(at))));
+	}
+
+	// Send a proposal back to the peers which asked for it.
+	fn handle_sync_request(&self, digest: B::Hash) -> Result<(), HotstuffError> {
+		let requesters = self.network.take_sync_requesters(&digest);
+		if requesters.is_empty() {
+			return Ok(());
+		}
+		if let Some(proposal) = self.synchronizer.get_proposal(digest)? {
+			self.send_to(requesters, ConsensusMessage::Propose(proposal));
+		}
+		Ok(())
+	}
+
+	fn verification_saturated(&self) -> bool {
+		!self.is_overloaded()
+			&& self.verification_pool.as_ref().map_or(false, |pool| pool.is_full())
+	}
+
+	pub(crate) fn is_overloaded(&self) -> bool {
+		self.overload.as_ref().map_or(false, |overload| overload.is_overloaded())
+	}
+
+	fn observe_overload(&mut self) {
+		let (Some(overload), Some(pool)) =
+			(self.overload.as_mut(), self.verification_pool.as_ref())
+		else {
+			return;
+		};
+		let transition = overload.observe(pool.pending(), pool.capacity(), Instant::now());
+		match transition {
+			Some(OverloadTransition::Overloaded) => warn!(
+				target: "Hotstuff",
+				"verification pool overloaded with {} messages pending, shedding old votes and redundant gossip",
+				pool.pending()
+			),
+			Some(OverloadTransition::Recovered) => info!(
+				target: "Hotstuff",
+				"verification pool recovered, {} messages shed",
+				overload.shed()
+			),
+			None => return,
+		}
+		if let Some(metrics) = self.metrics.as_ref() {
+			metrics.set_overloaded(transition == Some(OverloadTransition::Overloaded));
+		}
+	}
+
+	fn should_shed(&mut self, message: &ConsensusMessage<B>) -> bool {
+		let view = self.state.view();
+		let full = self.verification_pool.as_ref().map_or(false, |pool| pool.is_full());
+		self.overload
+			.as_mut()
+			.map_or(false, |overload| overload.should_shed(message, view, full))
+	}
+
+	fn gossip(&self, message: ConsensusMessage<B>, force: bool) {
+		let kind = message.kind();
+		let message = GossipMessage { set_id: self.state.set_id(), message }.encode();
+		// Messages are gossiped uncompressed.
+		if let Some(metrics) = self.metrics.as_ref() {
+			metrics.observe_message_size(kind, message.len(), None);
+		}
+
+		self.network.gossip_engine.lock().gossip_message(
+			ConsensusMessage::<B>::gossip_topic(),
+			message,
+			force,
+		);
+	}
+
+	// Send a message to `peers` only, without gossiping it.
+	fn send_to(&self, peers: Vec<PeerId>, message: ConsensusMessage<B>) {
+		let kind = message.kind();
+		let message = GossipMessage { set_id: self.state.set_id(), message }.encode();
+		if let Some(metrics) = self.metrics.as_ref() {
+			metrics.observe_message_size(kind, message.len(), None);
+		}
+
+		self.network.gossip_engine.lock().send_message(peers, message);
+	}
+
+	// Send a message signed by the local authority. With ordered signing it waits in the outbox
+	// for the end of the step, so the messages signed in a step are sent in the order of the
+	// audit log whichever handler signed them.
+	fn gossip_signed(&mut self, message: ConsensusMessage<B>, force: bool) {
+		if self.ordered_signing {
+			self.outbox.push(message);
+		} else {
+			self.gossip(message, force);
+		}
+	}
+
+	// The messages of the outbox in signing order. Those signed but handled only locally, such as
+	// the votes of the next leader, aren't in it.
+	pub(crate) fn take_outbox(&mut self) -> Vec<ConsensusMessage<B>> {
+		let signed = self.state.take_signed();
+		let mut outbox = std::mem::take(&mut self.outbox);
+		outbox.sort_by_key(|message| {
+			message
+				.signed_digest()
+				.and_then(|digest| signed.iter().position(|signed| *signed == digest))
+				.unwrap_or(usize::MAX)
+		});
+		outbox
+	}
+
+	fn flush_outbox(&mut self) {
+		for message in self.take_outbox() {
+			// Timeouts are forced out like when sent directly.
+			let force = matches!(message, ConsensusMessage::Timeout(_));
+			self.gossip(message, force);
+		}
+	}
+
+	// Hand the authority set activated in the step, if any, to the verification pool.
+	fn refresh_verifier_set(&self) {
+		let Some(pool) = self.verification_pool.as_ref() else { return };
+		if pool.set_id() != self.state.set_id() {
+			pool.set_authorities(self.state.verifier_set());
+		}
+	}
+
+	fn submit_verification(&mut self, message: Verifiable<B>) {
+		if let Some(pool) = self.verification_pool.as_mut() {
+			pool.submit(message);
+		}
+	}
+
+	// Continue handling a message whose signatures were checked by the verification pool.
+	pub async fn handle_verified(
+		&mut self,
+		message: Verifiable<B>,
+		result: Result<(), HotstuffError>,
+	) -> Result<(), HotstuffError> {
+		if let Err(e) = result {
+			self.report_forged(&message.into(), &e);
+			return Err(e);
+		}
+
+		match message {
+			Verifiable::Proposal(proposal) => self.process_proposal(&proposal, true).await,
+			Verifiable::Vote(vote) => self.process_vote(&vote, true).await,
 		}
 	}
 
@@ -335,20 +1711,85 @@ where
 		debug!(target: "Hotstuff","$L$ handle_local_timer. self.view {}", self.state.view());
 
 		self.local_timer.reset();
+		self.consecutive_timeouts += 1;
+		self.events.emit(ConsensusEvent::TimedOut { view: self.state.view() });
+		self.state.refresh_signing_status();
+		let saw_proposal = self.state.last_voted_view() >= self.state.view();
 		self.state.increase_last_voted_view();
+		self.check_quorum();
+
+		let dropped = self.synchronizer.prune_orphans(Instant::now());
+		if dropped > 0 {
+			debug!(target: "Hotstuff", "~~ dropped {} proposals whose parent wasn't fetched", dropped);
+			// The parents no proposal awaits anymore aren't fetched.
+			let synchronizer = &self.synchronizer;
+			self.sync_requests.retain(|digest| synchronizer.is_awaited(digest));
+			self.send_sync_requests();
+		}
+		// A backstop for the view based pruning, should the views stall.
+		let expired = self.state.prune_expired_messages(Instant::now(), self.message_ttl);
+		if expired > 0 {
+			debug!(target: "Hotstuff", "~~ dropped {} expired votes and timeouts", expired);
+		}
 
+		// Keep sending timeouts while the quorum is unreachable, to sync views on recovery.
 		let timeout = self.state.make_timeout()?;
-		let message = ConsensusMessage::Timeout(timeout.clone());
-
-		self.network.gossip_engine.lock().gossip_message(
-			ConsensusMessage::<B>::gossip_topic(),
-			message.encode(),
-			true,
-		);
+		let message = if self.nil_votes && !saw_proposal {
+			ConsensusMessage::Vote(Vote::nil(timeout.clone()))
+		} else {
+			ConsensusMessage::Timeout(timeout.clone())
+		};
+		self.gossip_signed(message, true);
 
 		self.handle_timeout(&timeout).await
 	}
 
+	// The votes for the proposal of `view` didn't form a QC in time, so the view times out without
+	// waiting for the local timer.
+	pub async fn handle_vote_collection_timeout(
+		&mut self,
+		view: ViewNumber,
+	) -> Result<(), HotstuffError> {
+		self.vote_deadline = None;
+		if view != self.state.view() {
+			return Ok(());
+		}
+
+		debug!(target: "Hotstuff", "~~ handle_vote_collection_timeout. no QC of view {}", view);
+		self.handle_local_timer().await
+	}
+
+	// The own vote of `view` waited long enough, send it to the next leader.
+	pub fn handle_vote_broadcast(&mut self, view: ViewNumber) -> Result<(), HotstuffError> {
+		self.vote_broadcast_deadline = None;
+		if let Some(vote) = self.delayed_vote.take() {
+			trace!(target: "Hotstuff", "~~ send the vote of view {} after {:?}", view, self.vote_broadcast_delay);
+			self.gossip_signed(ConsensusMessage::Vote(vote), false);
+		}
+		Ok(())
+	}
+
+	// How long an own vote waits before it's sent, not at all in small authority sets whose
+	// votes arrive together anyway.
+	fn vote_delay(&self) -> Option<Duration> {
+		self.vote_broadcast_delay
+			.filter(|_| self.state.authorities().len() >= VOTE_DELAY_MIN_AUTHORITIES)
+	}
+
+	// No proposal of `view` arrived from the proposers before this node in its fallback chain, so
+	// it proposes in their place. A late proposal of the leader competes for the votes of the
+	// view, which form a QC for at most one of them.
+	pub async fn handle_fallback_turn(&mut self, view: ViewNumber) -> Result<(), HotstuffError> {
+		self.fallback_deadline = None;
+		if view != self.state.view() {
+			return Ok(());
+		}
+
+		info!(target: "Hotstuff", "leader {} of view {} is silent, propose in its place",
+			self.state.view_leader(view), view);
+		self.generate_proposal(None).await
+	}
+
 	pub async fn handle_timeout(&mut self, timeout: &Timeout<B>) -> Result<(), HotstuffError> {
 		debug!(target: "Hotstuff","~~ handle_timeout. self.view {}, timeout.view {}, timeout.author {}, timeout.qc.view {}",
 			self.state.view(), timeout.view, timeout.voter, timeout.high_qc.view);
@@ -358,12 +1799,15 @@ where
 		}
 
 		self.state.verify_timeout(timeout)?;
+		self.observe_authority(&timeout.voter);
 
 		self.handle_qc(&timeout.high_qc);
 
 		// self.reset_processing_block();
 
 		if let Some(tc) = self.state.add_timeout(timeout)? {
+			self.state.update_high_tc(&tc);
+			self.traces.note_tc(tc.view);
 			if tc.view >= self.state.view() {
 				debug!(target: "Hotstuff","~~ handle_timeout. get TC. self.view {}, tc.view {}, timeout.qc.view {}",
 					self.state.view(), tc.view, timeout.high_qc.view);
@@ -377,13 +1821,7 @@ where
 			// This Timeout has received sufficient votes to form a TC (Timeout Certificate)
 			// and is broadcasted into the network. Nodes that voted for this Timeout will consider
 			// it as "expired."
-			let message = ConsensusMessage::TC(tc.clone());
-
-			self.network.gossip_engine.lock().gossip_message(
-				ConsensusMessage::<B>::gossip_topic(),
-				message.encode(),
-				true,
-			);
+			self.gossip(ConsensusMessage::TC(tc.clone()), true);
 
 			if self.state.is_leader() {
 				debug!(target: "Hotstuff","@L@ handle_timeout. leader propose. self.view {}, TC.view {}",
@@ -398,8 +1836,16 @@ where
 		Ok(())
 	}
 
-	#[async_recursion]
 	pub async fn handle_proposal(&mut self, proposal: &Proposal<B>) -> Result<(), HotstuffError> {
+		self.process_proposal(proposal, false).await
+	}
+
+	#[async_recursion]
+	async fn process_proposal(
+		&mut self,
+		proposal: &Proposal<B>,
+		signature_verified: bool,
+	) -> Result<(), HotstuffError> {
 		debug!(target: "Hotstuff","~~ handle_proposal. self.view {}, proposal[ view:{},  payload:{}, author {}, digest {}]",
 			self.state.view(),
 			proposal.view,
@@ -425,11 +1871,50 @@ where
 			}
 		}
 
-		self.state.verify_proposal(proposal)?;
+		if self.is_duplicate(proposal) {
+			return Ok(());
+		}
+
+		if signature_verified {
+			self.state.verify_proposer(proposal)?;
+		} else {
+			self.state.verify_proposal(proposal)?;
+		}
+		self.observe_authority(&proposal.author);
+		if self
+			.fallback_deadline
+			.as_ref()
+			.map_or(false, |(view, _)| proposal.view >= *view)
+		{
+			self.fallback_deadline = None;
+		}
+
+		// A fetched proposal frees the slot of its fetch, even while its own parent is unknown.
+		if self.sync_requests.received(&proposal.digest()) {
+			self.send_sync_requests();
+		}
+
+		// The QC is proven by its signatures, but the proposal needs the proposal it certifies
+		// to check its chain for commits.
+		if proposal.qc != QC::default()
+			&& self.synchronizer.get_proposal(proposal.parent_hash())?.is_none()
+		{
+			self.handle_qc(&proposal.qc);
+			self.fetch_parent(proposal);
+			return Ok(());
+		}
+
+		self.state
+			.verify_extends_committed(proposal, |hash| self.synchronizer.get_proposal(hash))?;
+		self.record_proposal(proposal)?;
+		self.traces
+			.note_proposal(proposal.view, proposal.digest(), proposal.author.clone());
 
 		self.handle_qc(&proposal.qc);
 
 		if let Some(tc) = proposal.tc.as_ref() {
+			self.state.update_high_tc(tc);
+			self.traces.note_tc(tc.view);
 			if tc.view > self.state.view() {
 				self.advance_view(tc.view);
 				self.local_timer.reset();
@@ -437,40 +1922,94 @@ where
 		}
 
 		self.synchronizer.save_proposal(proposal)?;
+		self.block_tree.write().insert(proposal, self.state.authorities());
 
 		// Try get proposal ancestors. If we can't get them from local store,
 		// then get them by network. So should we block here.
 		// TODO
-		if let Err(e) =
-			self.synchronizer
-				.get_proposal_ancestors(proposal)
-				.and_then(|(parent, grandpa)| {
-					if parent.view == grandpa.view + 1 {
-						debug!(target: "Hotstuff","~~ handle_proposal. block {} can finalize", grandpa.payload);
-
-						// TODO check weather this block has already finalize.
-						if grandpa.payload.block_hash != Self::empty_payload_hash()
-							&& grandpa.payload.block_hash != self.client.info().finalized_hash
-						{
-							info!(target: "Hotstuff", "^^_^^. block {} can finalize", grandpa.payload);
-							self.client
-								.finalize_block(grandpa.payload.block_hash, None, true)
-								.map_err(|e| FinalizeBlock(e.to_string()))?;
-						}
+		let chain = self.synchronizer.get_proposal_chain(proposal, self.variant.chain_length());
+		let committed = match chain {
+			Ok(chain) => match self.variant.committed(&chain) {
+				Some((committed, child, qc)) => {
+					debug!(target: "Hotstuff","~~ handle_proposal. block {} can finalize", committed.payload);
+
+					let justification = self.finality_justifications.then(|| {
+						HotstuffJustification::new(committed.clone(), child.clone(), qc.clone())
+							.into_justification()
+					});
+					let (committed, qc) = (committed.clone(), child.qc.clone());
+					let time = CommitTime::certified_by(child);
+					match self.finalize_batch_size {
+						Some(batch_size) if self.catching_up => {
+							self.deferred_commits.push((committed, justification, qc, time));
+							if self.deferred_commits.len() >= batch_size {
+								self.commit_deferred().await;
+							}
+							Ok(())
+						},
+						_ => self
+							.finalize_committed(&committed, justification, time)
+							.await
+							.map(|()| self.note_commit(&committed, qc)),
 					}
-					Ok(())
-				}) {
+				},
+				None => Ok(()),
+			},
+			Err(e) => Err(e),
+		};
+		if let Err(e) = committed {
 			debug!(target: "Hotstuff", "~~ handle_proposal. has error when finalize block {:#?}", e);
 		}
 
-		if proposal.view != self.state.view() {
+		// The proposals which waited for this one. The blocks they commit while the node catches
+		// up are finalized in batches.
+		let catching_up = std::mem::replace(&mut self.catching_up, true);
+		for orphan in self.synchronizer.take_orphans(&proposal.digest()) {
+			if let Err(e) = self.process_proposal(&orphan, true).await {
+				debug!(target: "Hotstuff", "~~ handle_proposal. buffered proposal {} has error {:#?}", orphan.digest(), e);
+			}
+		}
+		if !catching_up {
+			self.catching_up = false;
+			self.commit_deferred().await;
+		}
+
+		// The vote of the author was verified with the proposal.
+		let committee = self.state.voting_committee(proposal.view).into_owned();
+		if let Some(vote) = proposal.committee_self_vote(&committee) {
+			if let Err(e) = self.process_vote(vote, true).await {
+				debug!(target: "Hotstuff", "~~ handle_proposal. self vote of {} has error {:#?}", proposal.author, e);
+			}
+		}
+
+		let withheld = if proposal.view != self.state.view() {
+			Some(VoteWithheld::StaleView)
+		} else if self.pause.is_paused() {
+			Some(VoteWithheld::Paused)
+		} else if self.halted_view == Some(proposal.view) {
+			Some(VoteWithheld::Equivocation)
+		} else {
+			None
+		};
+		if let Some(reason) = withheld {
+			self.traces.note_vote(proposal.view, VoteDecision::Withheld(reason));
 			return Ok(());
 		}
 
-		if let Some(vote) = self.state.make_vote(proposal) {
+		let withheld = self.state.vote_withheld_reason(proposal);
+		let vote = self.state.make_vote(proposal);
+		self.traces.note_vote(
+			proposal.view,
+			match vote {
+				Some(_) => VoteDecision::Cast,
+				None => VoteDecision::Withheld(withheld.unwrap_or(VoteWithheld::SigningFailed)),
+			},
+		);
+		if let Some(vote) = vote {
 			debug!(target: "Hotstuff","~~ handle proposal. make vote. vote.view {}", vote.view);
 
 			self.proposal_hash_queue.push(proposal.payload.block_hash);
+			self.round_start = Some((vote.view, Instant::now()));
 
 			self.processing_block = Some(BlockInfo {
 				hash: Some(proposal.payload.block_hash),
@@ -482,15 +2021,354 @@ where
 			// If the current authority is the leader of the next view, it directly processes the
 			// vote. Otherwise, it sends the vote to the next leader.
 			if self.state.local_authority_id().map_or(false, |id| id == next_leader_id) {
+				if let Some(timeout) = self.vote_collection_timeout {
+					self.vote_deadline = Some((vote.view, Box::pin(sleep(timeout))));
+				}
 				self.handle_vote(&vote).await?;
+			} else if let Some(delay) = self.vote_delay() {
+				// A vote still waiting is of an earlier view, it goes out at once.
+				if let Some(view) = self.vote_broadcast_deadline.as_ref().map(|(view, _)| *view) {
+					self.handle_vote_broadcast(view)?;
+				}
+				self.vote_broadcast_deadline = Some((vote.view, Box::pin(sleep(delay))));
+				self.delayed_vote = Some(vote);
 			} else {
-				let vote_message = ConsensusMessage::Vote(vote);
+				self.gossip_signed(ConsensusMessage::Vote(vote), false);
+			}
+		}
 
-				self.network.gossip_engine.lock().gossip_message(
-					ConsensusMessage::<B>::gossip_topic(),
-					vote_message.encode(),
-					false,
-				);
+		Ok(())
+	}
+
+	// Note the commit of `proposal`, whose block is finalized, keeping the QC certifying it.
+	fn note_commit(&mut self, proposal: &Proposal<B>, qc: QC<B>) {
+		self.schedule_announced_set_change(proposal);
+		self.block_tree.write().commit(proposal.digest());
+		self.traces.note_commit(proposal.view);
+		self.state.record_commit(qc.proposal_hash, qc.view);
+		if let Err(e) = self.synchronizer.save_committed_qc(&qc) {
+			debug!(target: "Hotstuff", "~~ handle_proposal. has error when save committed QC {:#?}", e);
+		}
+	}
+
+	// Schedule the authority set change announced by a block of the committed `proposal`, if any.
+	// It activates a few views after the proposal, by when the nodes following the chain saw it
+	// committed, the others activate it as they commit it.
+	fn schedule_announced_set_change(&mut self, proposal: &Proposal<B>) {
+		let blocks = match self.finality_blocks(proposal, None) {
+			Ok(blocks) => blocks,
+			Err(e) => {
+				debug!(target: "Hotstuff", "~~ blocks of committed proposal {} are unknown {:#?}", proposal.payload, e);
+				return;
+			},
+		};
+		for (hash, _) in blocks {
+			let Ok(Some(header)) = self.client.header(hash) else { continue };
+			let Some(authorities) = announced_authorities(&header) else { continue };
+
+			let activation_view = proposal.view + self.variant.chain_length() as ViewNumber + 1;
+			info!(target: "Hotstuff", "block {} hands over to {} authorities at view {}",
+				hash, authorities.len(), activation_view);
+			if let Err(e) = self.state.schedule_set_change(authorities, activation_view) {
+				warn!(target: "Hotstuff", "authority set announced by block {} is invalid, {:?}", hash, e);
+			}
+		}
+	}
+
+	// Finalize the blocks of the deferred commits in one batch, up to the first which can't be
+	// finalized. When the batch fails nothing of it is finalized, and its blocks are finalized one
+	// by one instead.
+	async fn commit_deferred(&mut self) {
+		let mut deferred = std::mem::take(&mut self.deferred_commits);
+		let mut finalizable = Vec::with_capacity(deferred.len());
+		for (proposal, ..) in deferred.iter() {
+			match self.check_finalizable(proposal) {
+				Ok(check) => finalizable.push(check),
+				Err(e) => {
+					debug!(target: "Hotstuff", "~~ commit_deferred. has error when finalize block {:#?}", e);
+					break;
+				},
+			}
+		}
+		deferred.truncate(finalizable.len());
+		if deferred.is_empty() {
+			return;
+		}
+
+		let batch = deferred
+			.iter()
+			.zip(finalizable.iter())
+			.filter(|(_, finalizable)| **finalizable)
+			.map(|((proposal, justification, ..), _)| {
+				self.finality_blocks(proposal, justification.clone())
+			})
+			.collect::<Result<Vec<_>, _>>()
+			.and_then(|blocks| {
+				finalize_batch(&*self.client, &blocks.concat())
+					.map_err(|e| FinalizeBlock(e.to_string()))
+			});
+		if let Err(e) = batch {
+			warn!(target: "Hotstuff", "finalizing a batch of {} commits failed, finalizing them one by one, {:?}",
+				deferred.len(), e);
+			for (proposal, justification, qc, time) in deferred {
+				if let Err(e) = self.finalize_committed(&proposal, justification, time).await {
+					debug!(target: "Hotstuff", "~~ commit_deferred. has error when finalize block {:#?}", e);
+					break;
+				}
+				self.note_commit(&proposal, qc);
+			}
+			return;
+		}
+
+		for ((proposal, _, qc, time), finalized) in deferred.into_iter().zip(finalizable) {
+			if finalized {
+				if let Err(e) = self.note_finalized(&proposal, time) {
+					debug!(target: "Hotstuff", "~~ commit_deferred. has error when index block {:#?}", e);
+				}
+			}
+			self.note_commit(&proposal, qc);
+		}
+	}
+
+	// Finalize in order the blocks committed by `chain`, QCs each certifying a known child of the
+	// proposal certified by the QC before it, such as fetched by a node recovering a gap in its
+	// finality. At a broken link the blocks committed by the chain before it are still finalized,
+	// and the link is returned as the error. The finalized height is returned otherwise.
+	pub async fn finalize_from_qc_chain(&mut self, chain: &[QC<B>]) -> Result<u64, HotstuffError> {
+		let mut certified: Vec<(Proposal<B>, QC<B>)> = Vec::with_capacity(chain.len());
+		let mut broken = None;
+		for qc in chain {
+			let link = self.state.verify_qc(qc).and_then(|()| {
+				let proposal =
+					self.synchronizer.get_proposal(qc.proposal_hash)?.ok_or_else(|| {
+						BrokenQcChain(format!(
+							"proposal {} certified at view {} is unknown",
+							qc.proposal_hash, qc.view
+						))
+					})?;
+				match certified.last() {
+					Some((parent, _)) if proposal.qc.proposal_hash != parent.digest() => {
+						Err(BrokenQcChain(format!(
+							"proposal {} certified at view {} doesn't extend {}",
+							qc.proposal_hash,
+							qc.view,
+							parent.digest()
+						)))
+					},
+					_ => Ok(proposal),
+				}
+			});
+			match link {
+				Ok(proposal) => certified.push((proposal, qc.clone())),
+				Err(e) => {
+					warn!(target: "Hotstuff", "QC chain breaks at view {}, finalizing the blocks before it, {:?}", qc.view, e);
+					broken = Some(e);
+					break;
+				},
+			}
+		}
+
+		// A proposal commits once it starts a run of certified proposals of consecutive views as
+		// long as the chain of the variant.
+		let length = self.variant.chain_length();
+		for index in 0..certified.len().saturating_sub(length - 1) {
+			let run = &certified[index..index + length];
+			if !run.windows(2).all(|pair| pair[1].0.view == pair[0].0.view + 1) {
+				continue;
+			}
+
+			let (committed, qc) = &run[0];
+			let (child, child_qc) = &run[1];
+			let justification = self.finality_justifications.then(|| {
+				HotstuffJustification::new(committed.clone(), child.clone(), child_qc.clone())
+					.into_justification()
+			});
+			let time = CommitTime::certified_by(child);
+			self.finalize_committed(committed, justification, time).await?;
+			self.note_commit(committed, qc.clone());
+		}
+
+		match broken {
+			Some(e) => Err(e),
+			None => Ok(self.client.info().finalized_number.saturated_into()),
+		}
+	}
+
+	// The blocks to finalize for a committed proposal with the justification on the last one: its
+	// own block, or the blocks it stands for, checked to be contiguous.
+	fn finality_blocks(
+		&self,
+		proposal: &Proposal<B>,
+		justification: Option<Justification>,
+	) -> Result<FinalityBlocks<B>, HotstuffError> {
+		let payload = &proposal.payload;
+		let Some(mapping) = self.committed_blocks.as_ref() else {
+			return Ok(vec![(payload.block_hash, justification)]);
+		};
+
+		let block = self
+			.client
+			.block(payload.block_hash)
+			.map_err(|e| ClientError(e.to_string()))?
+			.ok_or_else(|| UncommittableBlock(payload.block_hash.to_string()))?
+			.block;
+		let blocks = mapping.committed_blocks(proposal, &block);
+		if blocks.is_empty() {
+			return Err(NonContiguousBlocks(format!("no block stands for proposal {}", payload)));
+		}
+		check_contiguous(&*self.client, &blocks)?;
+
+		let last = blocks.len() - 1;
+		Ok(blocks
+			.into_iter()
+			.enumerate()
+			.map(|(index, hash)| (hash, (index == last).then(|| justification.clone()).flatten()))
+			.collect())
+	}
+
+	fn is_hash_finalized(&self, hash: B::Hash) -> bool {
+		match self.client.number(hash) {
+			Ok(Some(block_number)) => {
+				self.is_finalized(&Payload { block_hash: hash, block_number })
+			},
+			_ => false,
+		}
+	}
+
+	// Whether the block of `payload` is finalized, itself or as an ancestor of the finalized block.
+	fn is_finalized(&self, payload: &Payload<B>) -> bool {
+		payload.block_number <= self.client.info().finalized_number
+			&& self.client.hash(payload.block_number).ok().flatten() == Some(payload.block_hash)
+	}
+
+	// Finalize the block of a committed proposal, notified as committed at `time`. A failure to
+	// finalize is retried, and raises an alarm once the retries run out.
+	pub(crate) async fn finalize_committed(
+		&self,
+		proposal: &Proposal<B>,
+		justification: Option<Justification>,
+		time: CommitTime,
+	) -> Result<(), HotstuffError> {
+		if !self.check_finalizable(proposal)? {
+			return Ok(());
+		}
+
+		let payload = &proposal.payload;
+		info!(target: "Hotstuff", "^^_^^. block {} can finalize", payload);
+		let blocks = self.finality_blocks(proposal, justification)?;
+		let finalized = finalize_with_retry(
+			&self.finalize_retry,
+			|| {
+				let result = match blocks.as_slice() {
+					[(hash, justification)] => {
+						self.client.finalize_block(*hash, justification.clone(), true)
+					},
+					blocks => finalize_batch(&*self.client, blocks),
+				};
+				if let Err(e) = result.as_ref() {
+					warn!(target: "Hotstuff", "finalizing block {} failed, {}", payload, e);
+				}
+				result
+			},
+			|| blocks.iter().all(|(hash, _)| self.is_hash_finalized(*hash)),
+		)
+		.await;
+		if let Err((attempts, e)) = finalized {
+			error!(target: "Hotstuff", "!!! committed block {} at view {} isn't finalized after {} attempts, consensus is ahead of finality",
+				payload, proposal.view, attempts);
+
+			self.events.emit(ConsensusEvent::FinalizationFailed {
+				view: proposal.view,
+				block_hash: payload.block_hash,
+				block_number: payload.block_number,
+				attempts,
+			});
+			return Err(FinalizeBlock(e.to_string()));
+		}
+		self.note_finalized(proposal, time)
+	}
+
+	// Whether the block of a committed proposal is still to be finalized. An honest majority only
+	// commits blocks which import, so a committed block missing from the chain raises a safety
+	// alarm.
+	fn check_finalizable(&self, proposal: &Proposal<B>) -> Result<bool, HotstuffError> {
+		let payload = &proposal.payload;
+
+		if payload.block_hash == Self::empty_payload_hash() || self.is_finalized(payload) {
+			return Ok(false);
+		}
+
+		match self.client.status(payload.block_hash) {
+			Ok(BlockStatus::InChain) => {},
+			Ok(BlockStatus::Unknown) => {
+				error!(target: "Hotstuff", "!!! committed block {} at view {} is not imported, refusing to finalize it",
+					payload, proposal.view);
+
+				self.events.emit(ConsensusEvent::UncommittableBlock {
+					view: proposal.view,
+					block_hash: payload.block_hash,
+					block_number: payload.block_number,
+				});
+				return Err(UncommittableBlock(payload.block_hash.to_string()));
+			},
+			Err(e) => return Err(ClientError(e.to_string())),
+		}
+
+		let info = self.client.info();
+		let ancestor = sp_blockchain::lowest_common_ancestor(
+			&*self.client,
+			info.finalized_hash,
+			payload.block_hash,
+		)
+		.map_err(|e| ClientError(e.to_string()))?;
+		// The committed block is neither a descendant nor an ancestor of the finalized block.
+		if ancestor.hash != info.finalized_hash && ancestor.hash != payload.block_hash {
+			let depth: u32 = (info.finalized_number - ancestor.number).saturated_into();
+			if depth > self.max_reorg_depth {
+				error!(target: "Hotstuff", "!!! committed block {} at view {} reverts {} finalized blocks, refusing to finalize it",
+					payload, proposal.view, depth);
+
+				self.events.emit(ConsensusEvent::ReorgTooDeep {
+					view: proposal.view,
+					block_hash: payload.block_hash,
+					block_number: payload.block_number,
+					depth,
+				});
+				return Err(ReorgTooDeep(payload.block_hash.to_string()));
+			}
+		}
+
+		Ok(true)
+	}
+
+	// Announce the newly finalized block of a committed proposal, and index its transactions.
+	fn note_finalized(
+		&self,
+		proposal: &Proposal<B>,
+		time: CommitTime,
+	) -> Result<(), HotstuffError> {
+		let payload = &proposal.payload;
+		if time.local {
+			debug!(target: "Hotstuff", "block {} committed at view {} without vote timestamps, timed by the local clock",
+				payload, proposal.view);
+		}
+		self.events.emit(ConsensusEvent::Committed {
+			view: proposal.view,
+			block_hash: payload.block_hash,
+			block_number: payload.block_number,
+			time,
+		});
+
+		if let Some(index) = self.inclusion_index.as_ref() {
+			let header =
+				self.client.header(payload.block_hash).map_err(|e| ClientError(e.to_string()))?;
+			let body = self
+				.client
+				.block_body(payload.block_hash)
+				.map_err(|e| ClientError(e.to_string()))?;
+			if let (Some(header), Some(body)) = (header, body) {
+				let extrinsics = body.iter().map(|extrinsic| extrinsic.encode()).collect();
+				index.write().record(proposal, header, extrinsics);
 			}
 		}
 
@@ -498,6 +2376,14 @@ where
 	}
 
 	pub async fn handle_vote(&mut self, vote: &Vote<B>) -> Result<(), HotstuffError> {
+		self.process_vote(vote, false).await
+	}
+
+	async fn process_vote(
+		&mut self,
+		vote: &Vote<B>,
+		signature_verified: bool,
+	) -> Result<(), HotstuffError> {
 		debug!(target: "Hotstuff","~~ handle_vote. self.view {}, vote.view {}, vote.author {}, vote.hash {}",
 			self.state.view(),
 			vote.view,
@@ -505,72 +2391,252 @@ where
 			vote.proposal_hash,
 		);
 
+		// A nil vote is the timeout of a node which saw no proposal of the view.
+		if let Some(timeout) = vote.as_timeout() {
+			trace!(target: "Hotstuff", "~~ handle_vote. nil vote of {} at view {}", vote.voter, vote.view);
+			return self.handle_timeout(&timeout).await;
+		}
+
 		// TODO check proposal is in local. If not exist, sync from network.
-		self.state.verify_vote(vote)?;
+		if signature_verified {
+			self.state.verify_vote_view(vote)?;
+		} else {
+			self.state.verify_vote(vote)?;
+		}
+		self.observe_authority(&vote.voter);
 
 		if let Some(qc) = self.state.add_vote(vote)? {
-			debug!(target: "Hotstuff","~~ handle_vote. get QC. view:{}, proposal_hash:{}, self.view {}", qc.view, qc.proposal_hash, self.state.view());
+			debug!(target: "Hotstuff","~~ handle_vote. get QC. view:{}, proposal_hash:{}, signers:{}, self.view {}",
+				qc.view,
+				qc.proposal_hash,
+				bitfield_hex(&signer_bitfield(&qc, self.state.authorities())),
+				self.state.view());
+			self.events
+				.emit(ConsensusEvent::QcFormed { view: qc.view, proposal_hash: qc.proposal_hash });
 			self.handle_qc(&qc);
 
 			debug!(target: "Hotstuff","~~ handle_vote. get QC. after handle qc, self view {}", self.state.view());
-			let current_leader = self.state.view_leader(self.state.view());
-			if self.state.local_authority_id().map_or(false, |id| id == current_leader) {
-				if let Some(payload) = self.get_proposal_payload() {
-					debug!(target: "Hotstuff","~~ handle_vote. make proposal. payload {}", payload);
-					debug!(target: "Hotstuff", "&-& proposal_hash_queue {:#?}", self.proposal_hash_queue);
-
-					let mut count = 0;
-					for item in self.proposal_hash_queue.iter().rev() {
-						if !item.eq(&Self::empty_payload_hash()) {
-							break;
-						}
-						count += 1;
-						if count == 2 && payload.block_hash.eq(&Self::empty_payload_hash()) {
-							debug!(target:"Hotstuff", "^^ already has 3 empty proposal, this empty not gossip");
-							return Ok(());
-						}
-					}
+			if self.is_proposal_due() {
+				self.propose_after_qc().await?;
+			}
+		}
 
-					if self.proposal_hash_queue.len() > 3 {
-						self.proposal_hash_queue.clear()
+		Ok(())
+	}
+
+	// The leader of the view a QC started proposes, unless it's paused or can't reach a quorum.
+	async fn propose_after_qc(&mut self) -> Result<(), HotstuffError> {
+		let current_leader = self.state.view_leader(self.state.view());
+		if self.state.local_authority_id().map_or(false, |id| id == current_leader)
+			&& !self.quorum_monitor.is_unreachable()
+			&& !self.pause.is_paused()
+		{
+			if let Some(payload) = self.get_proposal_payload() {
+				debug!(target: "Hotstuff","~~ handle_vote. make proposal. payload {}", payload);
+				debug!(target: "Hotstuff", "&-& proposal_hash_queue {:#?}", self.proposal_hash_queue);
+
+				// Enough empty proposals follow the last block to commit it.
+				let mut count = 0;
+				for item in self.proposal_hash_queue.iter().rev() {
+					if !item.eq(&Self::empty_payload_hash()) {
+						break;
+					}
+					count += 1;
+					if count == self.variant.chain_length()
+						&& payload.block_hash.eq(&Self::empty_payload_hash())
+					{
+						debug!(target:"Hotstuff", "^^ already has 3 empty proposal, this empty not gossip");
+						return Ok(());
 					}
+				}
 
-					self.proposal_hash_queue.push(payload.block_hash);
-					debug!(target: "Hotstuff", "&*& proposal_hash_queue {:#?}", self.proposal_hash_queue);
+				if self.proposal_hash_queue.len() > self.variant.chain_length() + 1 {
+					self.proposal_hash_queue.clear()
+				}
 
-					let proposal = self.state.make_proposal(payload, None)?;
-					let proposal_message = ConsensusMessage::Propose(proposal.clone());
+				self.proposal_hash_queue.push(payload.block_hash);
+				debug!(target: "Hotstuff", "&*& proposal_hash_queue {:#?}", self.proposal_hash_queue);
 
-					self.network.gossip_engine.lock().gossip_message(
-						ConsensusMessage::<B>::gossip_topic(),
-						proposal_message.encode(),
-						false,
-					);
+				let proposal = self.state.make_proposal(payload, None)?;
+				self.note_own_proposal(&proposal);
+				self.gossip_signed(ConsensusMessage::Propose(proposal.clone()), false);
 
-					// Inform oneself to handle the proposal.
-					// self.consensus_msg_tx
-					// .send(proposal_message)
-					// .await
-					// .map_err(|e| Other(e.to_string()))?;
-					self.handle_proposal(&proposal).await?;
-				}
+				// Inform oneself to handle the proposal.
+				// self.consensus_msg_tx
+				// .send(proposal_message)
+				// .await
+				// .map_err(|e| Other(e.to_string()))?;
+				self.handle_proposal(&proposal).await?;
 			}
 		}
 
 		Ok(())
 	}
 
+	// Whether the proposal after a QC is due, by how full the pool is when proposals are paced.
+	// Otherwise the pool is checked again shortly, so a pool filling meanwhile brings the
+	// proposal forward.
+	fn is_proposal_due(&mut self) -> bool {
+		self.pacing_deadline = None;
+		let Some(pacing) = self.proposal_pacing.as_ref().filter(|_| self.state.is_leader()) else {
+			return true;
+		};
+
+		let ready = self.pool_status.as_ref().and_then(PoolStatus::ready);
+		let remaining = pacing.remaining(self.view_start.elapsed(), ready);
+		if remaining.is_zero() {
+			return true;
+		}
+		let poll = remaining.min(PACING_POLL_INTERVAL);
+		self.pacing_deadline = Some((self.state.view(), Box::pin(sleep(poll))));
+		false
+	}
+
+	// The leader waiting for the pool at `view` checks it again.
+	pub async fn handle_proposal_pacing(&mut self, view: ViewNumber) -> Result<(), HotstuffError> {
+		if view != self.state.view() {
+			self.pacing_deadline = None;
+			return Ok(());
+		}
+		if self.is_proposal_due() {
+			self.propose_after_qc().await?;
+		}
+		Ok(())
+	}
+
 	pub fn handle_qc(&mut self, qc: &QC<B>) {
+		if *qc != QC::default() {
+			self.traces.note_qc(qc.view);
+			self.check_qc_conflict(qc);
+		}
+		self.block_tree.write().insert_qc(qc, self.state.authorities());
+		self.state.record_qc_participation(qc);
+		self.network.note_certified(qc);
+		if let Some(metrics) = self.metrics.as_mut() {
+			metrics.observe_qc(qc);
+		}
+
+		let unknown = unknown_signers(qc, self.state.authorities());
+		if !unknown.is_empty() {
+			warn!(target: "Hotstuff", "QC of view {} is signed by unknown authorities {:?}", qc.view, unknown);
+		}
+
+		if let Some((view, start)) = self.round_start {
+			if qc.view == view {
+				self.local_timer.record_round_latency(start.elapsed());
+				self.round_start = None;
+			}
+		}
+		if self.vote_deadline.as_ref().map_or(false, |(view, _)| qc.view >= *view) {
+			self.vote_deadline = None;
+		}
+
 		if qc.view >= self.state.view() {
 			self.advance_view(qc.view);
 			self.state.update_high_qc(qc);
 			self.local_timer.reset();
+			self.consecutive_timeouts = 0;
+		}
+	}
+
+	// Check `qc` against the QC known at its view, and raise a safety alarm if they certify
+	// distinct proposals.
+	fn check_qc_conflict(&mut self, qc: &QC<B>) {
+		let first = match self.recent_qcs.get(&qc.view) {
+			Some(first) => first,
+			None => {
+				if qc.view >= self.state.view().saturating_sub(DEDUP_VIEW_WINDOW) {
+					self.recent_qcs.insert(qc.view, qc.clone());
+				}
+				return;
+			},
+		};
+		if qcs_conflict(first, qc) {
+			error!(target: "Hotstuff", "!!! conflicting QCs at view {} certify proposals {} and {}, safety is violated",
+				qc.view, first.proposal_hash, qc.proposal_hash);
+
+			self.events.emit(ConsensusEvent::ConflictingQcs {
+				view: qc.view,
+				first: first.proposal_hash,
+				second: qc.proposal_hash,
+			});
+		}
+	}
+
+	// Handle a QC as if it was received from the network, for tests and administrative recovery.
+	// An invalid QC is rejected before any state changes.
+	#[cfg(any(test, feature = "injection"))]
+	pub fn inject_qc(&mut self, qc: QC<B>) -> Result<(), HotstuffError> {
+		self.state.verify_qc(&qc)?;
+		self.handle_qc(&qc);
+		Ok(())
+	}
+
+	// Handle a TC as if it was received from the network, see `inject_qc`.
+	#[cfg(any(test, feature = "injection"))]
+	pub async fn inject_tc(&mut self, tc: TC<B>) -> Result<(), HotstuffError> {
+		self.handle_tc(&tc).await
+	}
+
+	// Time out the current view and move to the next one without waiting for a TC, for an
+	// operator to recover a stuck node. Only the usual timeout of the view is signed, the view
+	// left isn't voted in anymore and the high QC stays, so nothing conflicting can be signed.
+	#[cfg(any(test, feature = "injection"))]
+	pub async fn force_view_change(&mut self, reason: String) -> Result<(), HotstuffError> {
+		let view = self.state.view();
+		warn!(target: "Hotstuff", "force a view change from view {}: {}", view, reason);
+		self.events.emit(ConsensusEvent::ViewForced { view, reason });
+
+		self.handle_local_timer().await?;
+		// The timeouts of the other nodes may have formed a TC already.
+		if self.state.view() == view {
+			self.advance_view(view);
+			self.local_timer.reset();
+		}
+		Ok(())
+	}
+
+	fn observe_authority(&mut self, authority: &AuthorityId) {
+		self.quorum_monitor.observe(authority.clone(), Instant::now());
+		if self.quorum_monitor.is_unreachable() {
+			self.check_quorum();
+		}
+	}
+
+	fn check_quorum(&mut self) {
+		self.quorum_monitor.set_window(self.local_timer.timeout() * 2);
+
+		let local = self.state.local_authority_id();
+		let view = self.state.view();
+		match self
+			.quorum_monitor
+			.check(self.state.authorities(), local.as_ref(), Instant::now())
+		{
+			Some(QuorumTransition::Unreachable { reachable }) => {
+				error!(target: "Hotstuff", "!!! quorum unreachable at view {}, only {} of {} authorities are reachable, stop proposing",
+					view, reachable, self.state.authorities().len());
+				self.events.emit(ConsensusEvent::QuorumUnreachable { view, reachable });
+			},
+			Some(QuorumTransition::Recovered { reachable }) => {
+				info!(target: "Hotstuff", "quorum recovered at view {}, {} of {} authorities are reachable",
+					view, reachable, self.state.authorities().len());
+				self.events.emit(ConsensusEvent::QuorumRecovered { view, reachable });
+			},
+			None => {},
 		}
 	}
 
 	pub async fn handle_tc(&mut self, tc: &TC<B>) -> Result<(), HotstuffError> {
 		debug!(target: "Hotstuff","~~ handle_tc. from network, self.view {}, tc.view {}",self.state.view(), tc.view);
 		self.state.verify_tc(tc)?;
+		self.state.update_high_tc(tc);
+		self.traces.note_tc(tc.view);
+
+		let elapsed = self.view_start.elapsed();
+		if tc.view == self.state.view() && self.local_timer.skew_suspected(elapsed) {
+			warn!(target: "Hotstuff", "clock skew suspected, peers timed out view {} after {:?} while the local timeout is {:?}",
+				tc.view, elapsed, self.local_timer.timeout());
+		}
 
 		self.advance_view(tc.view);
 		self.local_timer.reset();
@@ -585,7 +2651,19 @@ where
 	}
 
 	pub async fn generate_proposal(&mut self, tc: Option<TC<B>>) -> Result<(), HotstuffError> {
-		match self.get_proposal_payload() {
+		if self.pause.is_paused() {
+			debug!(target: "Hotstuff","~~ generate_proposal. paused, self.view {}", self.state.view());
+			return Ok(());
+		}
+
+		if self.quorum_monitor.is_unreachable() {
+			debug!(target: "Hotstuff","~~ generate_proposal. quorum unreachable, self.view {}", self.state.view());
+			return Ok(());
+		}
+
+		// Proposals are generated here after a view change.
+		let payload = self.reproposal_payload().or_else(|| self.get_proposal_payload());
+		match payload {
 			Some(payload) => {
 				debug!(target: "Hotstuff","~~ generate_proposal. payload :{}, self.view:{}",
 					payload,
@@ -593,13 +2671,8 @@ where
 				);
 
 				let proposal = self.state.make_proposal(payload, tc)?;
-				let proposal_message = ConsensusMessage::Propose(proposal.clone());
-
-				self.network.gossip_engine.lock().gossip_message(
-					ConsensusMessage::<B>::gossip_topic(),
-					proposal_message.encode(),
-					false,
-				);
+				self.note_own_proposal(&proposal);
+				self.gossip_signed(ConsensusMessage::Propose(proposal.clone()), false);
 
 				// TODO Inform oneself to handle the proposal by channel?
 				self.handle_proposal(&proposal).await?;
@@ -612,6 +2685,49 @@ where
 		Ok(())
 	}
 
+	// Count the proposals of the node which carry no block.
+	fn note_own_proposal(&mut self, proposal: &Proposal<B>) {
+		let empty = proposal.payload.block_hash == Self::empty_payload_hash();
+		if empty {
+			if let Some(metrics) = self.metrics.as_ref() {
+				metrics.observe_empty_proposal();
+			}
+		}
+		self.empty_proposals.note_proposal(empty);
+	}
+
+	// The block of the highest proposal which timed out uncertified, if it still extends the
+	// finalized chain. Otherwise the payload is rebuilt from the pending blocks.
+	pub(crate) fn reproposal_payload(&mut self) -> Option<Payload<B>> {
+		if !self.repropose_uncommitted {
+			return None;
+		}
+
+		let info = self.client.info();
+		let candidates = self.block_tree.read().uncertified_proposals(self.state.high_qc_view());
+		let payload = candidates
+			.into_iter()
+			.filter(|summary| summary.block_hash != Self::empty_payload_hash())
+			.find(|summary| {
+				summary.block_number > info.finalized_number
+					&& sp_blockchain::lowest_common_ancestor(
+						&*self.client,
+						info.finalized_hash,
+						summary.block_hash,
+					)
+					.map_or(false, |ancestor| ancestor.hash == info.finalized_hash)
+			})
+			.map(|summary| Payload::<B> {
+				block_hash: summary.block_hash,
+				block_number: summary.block_number,
+			})?;
+
+		debug!(target: "Hotstuff", "~~ reproposal_payload. propose {} again, self.view {}", payload, self.state.view());
+		self.processing_block =
+			Some(BlockInfo { hash: Some(payload.block_hash), number: payload.block_number });
+		Some(payload)
+	}
+
 	pub(crate) fn get_proposal_payload(&mut self) -> Option<Payload<B>> {
 		if let Ok(queue) = self.pending_finalize_queue.lock() {
 			if let Some(processing) = self.processing_block.as_ref() {
@@ -669,16 +2785,104 @@ where
 		None
 	}
 
+	pub fn events(&self) -> &EventSink<B> {
+		&self.events
+	}
+
+	/// Whether the node signs, and what it signed.
+	pub fn signing_status(&self) -> SigningStatus {
+		self.state.signing_status()
+	}
+
+	/// The QC the node is locked on and its highest TC.
+	pub fn certificates_state(&self) -> Certificates<B::Hash> {
+		Certificates {
+			locked_qc: self.state.high_qc().into(),
+			high_tc: self.state.high_tc().map(Into::into),
+		}
+	}
+
+	/// The view, timeout and deadline of the pacemaker, and how often in a row it timed out.
+	pub fn pacemaker_state(&self) -> PacemakerState {
+		PacemakerState {
+			view: self.state.view(),
+			timeout: self.local_timer.timeout().as_millis() as u64,
+			consecutive_timeouts: self.consecutive_timeouts,
+			remaining: self.local_timer.remaining().as_millis() as u64,
+		}
+	}
+
+	/// The share of the recent views each authority could vote in which it voted in.
+	pub fn participation(&self) -> Vec<(AuthorityId, f64)> {
+		self.state.participation()
+	}
+
+	/// The QC which committed the proposal of `view`, while it's kept.
+	pub fn qc_for_view(&self, view: ViewNumber) -> Option<QC<B>> {
+		self.synchronizer.qc_for_view(view).unwrap_or_else(|e| {
+			debug!(target: "Hotstuff", "~~ qc_for_view. has error {:#?}", e);
+			None
+		})
+	}
+
+	/// What the node decided at `view`, none for views too old or not seen.
+	pub fn view_trace(&self, view: ViewNumber) -> Option<ViewTrace<B>> {
+		self.traces.get(view).cloned()
+	}
+
+	/// The known proposals as a tree rooted at the last committed proposal.
+	pub fn block_tree(&self) -> BlockTreeView<B::Hash, NumberFor<B>> {
+		self.block_tree.read().view()
+	}
+
 	fn advance_view(&mut self, view: ViewNumber) {
+		let previous = self.state.view();
 		self.state.advance_view_from_target(view);
+		if self.state.view() != previous {
+			self.events.emit(ConsensusEvent::ViewChanged { view: self.state.view() });
+			self.view_start = Instant::now();
+			self.schedule_fallback_turn();
+		}
 		self.network.set_view(self.state.view());
+		self.seen_proposals.prune(self.state.view());
+		self.proposal_budget.prune(self.state.view());
+
+		let oldest = self.state.view().saturating_sub(DEDUP_VIEW_WINDOW);
+		self.recent_qcs = self.recent_qcs.split_off(&oldest);
+	}
+
+	// Wait for the proposal of the new view as long as the proposers before this node in its
+	// fallback chain may take to propose.
+	fn schedule_fallback_turn(&mut self) {
+		let view = self.state.view();
+		let rank = self.state.local_authority_id().and_then(|id| {
+			self.state.fallback_chain(view).iter().position(|proposer| *proposer == id)
+		});
+
+		self.fallback_deadline = rank
+			.filter(|rank| *rank > 0)
+			.map(|rank| (view, Box::pin(sleep(self.fallback_proposer_delay * rank as u32))));
 	}
 
 	pub(crate) fn empty_payload_hash() -> B::Hash {
-		<<B::Header as HeaderT>::Hashing as HashT>::hash(EMPTY_PAYLOAD)
+		Hasher::<B>::hash(EMPTY_PAYLOAD)
+	}
+}
+
+// Resolves to the view of the deadline once it passes, never without a deadline.
+async fn deadline_expired(deadline: &mut Option<(ViewNumber, Pin<Box<Sleep>>)>) -> ViewNumber {
+	match deadline {
+		Some((view, sleep)) => {
+			sleep.as_mut().await;
+			*view
+		},
+		None => future::pending().await,
 	}
 }
 
+type ReserveFuture<B> =
+	Pin<Box<dyn Future<Output = Result<OwnedPermit<GossipMessage<B>>, SendError<()>>> + Send>>;
+
 pub struct ConsensusNetwork<
 	B: BlockT,
 	N: NetworkT<B> + Sync + 'static,
@@ -686,8 +2890,14 @@ pub struct ConsensusNetwork<
 > {
 	network: HotstuffNetworkBridge<B, N, S>,
 	message_recv: Recv<TopicNotification>,
-	consensus_msg_tx: Sender<ConsensusMessage<B>>,
+	consensus_msg_tx: Sender<GossipMessage<B>>,
 	pending_queue: PendingFinalizeBlockQueue<B>,
+	// A message the worker has no room for yet. No more gossip is read until it is delivered.
+	blocked: Option<GossipMessage<B>>,
+	reserve: Option<ReserveFuture<B>>,
+	// Holds the messages back for a simulated network delay in tests.
+	#[cfg(test)]
+	delivery: Option<SimulatedDelivery<GossipMessage<B>>>,
 }
 
 impl<B, N, S> Future for ConsensusNetwork<B, N, S>
@@ -700,6 +2910,20 @@ where
 
 	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
 		loop {
+			if !self.poll_deliver_blocked(cx) {
+				break;
+			}
+
+			#[cfg(test)]
+			if let Some(Poll::Ready(message)) =
+				self.delivery.as_mut().map(|delivery| delivery.poll_release(cx))
+			{
+				if let Err(e) = self.deliver(message) {
+					error!("process incoming message error: {:#?}", e)
+				}
+				continue;
+			}
+
 			match StreamExt::poll_next_unpin(&mut self.message_recv, cx) {
 				Poll::Ready(None) => break,
 				Poll::Ready(Some(notification)) => {
@@ -733,7 +2957,7 @@ where
 {
 	fn new(
 		network: HotstuffNetworkBridge<B, N, S>,
-		consensus_msg_tx: Sender<ConsensusMessage<B>>,
+		consensus_msg_tx: Sender<GossipMessage<B>>,
 		pending_queue: PendingFinalizeBlockQueue<B>,
 	) -> Self {
 		let message_recv = network
@@ -742,20 +2966,88 @@ where
 			.lock()
 			.messages_for(ConsensusMessage::<B>::gossip_topic());
 
-		Self { network, consensus_msg_tx, message_recv, pending_queue }
+		Self {
+			network,
+			consensus_msg_tx,
+			message_recv,
+			pending_queue,
+			blocked: None,
+			reserve: None,
+			#[cfg(test)]
+			delivery: None,
+		}
+	}
+
+	#[cfg(test)]
+	pub(crate) fn with_delivery(mut self, delivery: SimulatedDelivery<GossipMessage<B>>) -> Self {
+		self.delivery = Some(delivery);
+		self
+	}
+
+	// Deliver the blocked message once the worker has room, returns whether there is none left.
+	fn poll_deliver_blocked(&mut self, cx: &mut Context<'_>) -> bool {
+		let Some(message) = self.blocked.take() else {
+			return true;
+		};
+
+		let consensus_msg_tx = self.consensus_msg_tx.clone();
+		let reserve =
+			self.reserve.get_or_insert_with(|| Box::pin(consensus_msg_tx.reserve_owned()));
+		match reserve.as_mut().poll(cx) {
+			Poll::Ready(Ok(permit)) => {
+				self.reserve = None;
+				permit.send(message);
+				true
+			},
+			Poll::Ready(Err(e)) => {
+				self.reserve = None;
+				error!("process incoming message error: {:#?}", e);
+				true
+			},
+			Poll::Pending => {
+				self.blocked = Some(message);
+				false
+			},
+		}
 	}
 
 	pub fn incoming_message_handler(
 		&mut self,
 		notification: TopicNotification,
 	) -> Result<(), HotstuffError> {
-		let message: ConsensusMessage<B> =
+		let message: GossipMessage<B> =
 			Decode::decode(&mut &notification.message[..]).map_err(|e| Other(e.to_string()))?;
 
-		self.consensus_msg_tx.try_send(message).map_err(|e| Other(e.to_string()))
+		#[cfg(test)]
+		if let Some(delivery) = self.delivery.as_mut() {
+			delivery.hold(message, notification.sender.as_ref(), Instant::now());
+			return Ok(());
+		}
+
+		self.deliver(message)
+	}
+
+	fn deliver(&mut self, message: GossipMessage<B>) -> Result<(), HotstuffError> {
+		match self.consensus_msg_tx.try_send(message) {
+			Ok(()) => Ok(()),
+			// Wait for the worker rather than dropping the message.
+			Err(TrySendError::Full(message)) => {
+				self.blocked = Some(message);
+				Ok(())
+			},
+			Err(e) => Err(Other(e.to_string())),
+		}
 	}
 }
 
+impl<B, N, S> Unpin for ConsensusNetwork<B, N, S>
+where
+	B: BlockT,
+	N: NetworkT<B> + Sync + 'static,
+	S: SyncingT<B> + Sync + 'static,
+{
+}
+
 impl<B, BE, C, N, S> Unpin for ConsensusWorker<B, BE, C, N, S>
 where
 	B: BlockT,
@@ -772,6 +3064,7 @@ pub fn start_hotstuff<B, BE, C, N, S, SC>(
 	sync: S,
 	hotstuff_protocol_name: ProtocolName,
 	keystore: KeystorePtr,
+	mut config: Config,
 ) -> sp_blockchain::Result<(impl Future<Output = ()> + Send, impl Future<Output = ()> + Send)>
 where
 	B: BlockT,
@@ -781,35 +3074,116 @@ where
 	C: ClientForHotstuff<B, BE> + 'static,
 	C::Api: hotstuff_primitives::HotstuffApi<B, AuthorityId>,
 {
-	let LinkHalf { client, .. } = link;
-	let authorities = get_genesis_authorities_from_client::<B, BE, C>(client.clone());
-
-	let network = HotstuffNetworkBridge::new(network.clone(), sync.clone(), hotstuff_protocol_name);
-	let synchronizer = Synchronizer::<B, BE, C>::new(client.clone());
-	let consensus_state = ConsensusState::<B>::new(keystore, authorities);
+	let LinkHalf {
+		client,
+		block_tree,
+		events,
+		inclusion_index,
+		pause,
+		signing_status,
+		participation,
+		pacemaker,
+		certificates,
+		committed_blocks,
+		..
+	} = link;
+	let authorities = load_authority_set(
+		get_genesis_authorities_from_client::<B, BE, C>(client.clone()),
+		config.duplicate_authorities,
+	)
+	.map_err(|e| sp_blockchain::Error::Backend(format!("hotstuff authority set: {:?}", e)))?;
+
+	let network = HotstuffNetworkBridge::new(
+		network.clone(),
+		sync.clone(),
+		hotstuff_protocol_name,
+		config.reputation.clone(),
+		config.signature_schemes.clone(),
+	);
+	let synchronizer = Synchronizer::<B, BE, C>::new(client.clone())
+		.with_qc_retention(config.committed_qc_retention);
+	let genesis_hash = client.info().genesis_hash;
+	let mut consensus_state = ConsensusState::<B>::new(keystore, authorities)
+		.with_signing_context(config.signing_context(genesis_hash.as_ref()))
+		.with_signing_status(signing_status)
+		.with_vote_participation(participation.clone());
+	participation.write().set_window(config.participation_window);
+	if let Some(sampler) = config.committee_sampler(genesis_hash.as_ref()) {
+		consensus_state = consensus_state.with_committee(sampler);
+	}
+	consensus_state = consensus_state
+		.with_fallback_proposers(config.fallback_proposers)
+		.with_leader_election(config.leader_election)
+		.with_proposal_self_vote(config.proposal_self_vote)
+		.with_proposal_time_window(config.proposal_time_window)
+		.with_vote_time_source(config.vote_time_source.clone())
+		.with_transition_window(config.set_transition_window)
+		.with_authority_id(config.authority_id.clone());
+	consensus_state.verify_self_authority().map_err(|e| {
+		sp_blockchain::Error::Backend(format!("hotstuff authority misconfigured: {:?}", e))
+	})?;
+	match consensus_state.keystore_selftest() {
+		Ok(()) | Err(NotAuthority) => {},
+		Err(e) => {
+			warn!(target: "Hotstuff", "keystore self-test failed, the node may not sign: {:?}", e)
+		},
+	}
+	if let Some(metadata) = config.proposal_metadata.clone() {
+		consensus_state = consensus_state.with_proposal_metadata(metadata).map_err(|e| {
+			sp_blockchain::Error::Backend(format!("hotstuff proposal metadata: {:?}", e))
+		})?;
+	}
+	if let Some(path) = config.trusted_checkpoint.as_ref() {
+		let checkpoint_error =
+			|e| sp_blockchain::Error::Backend(format!("hotstuff checkpoint: {:?}", e));
+		let checkpoint = TrustedCheckpoint::<B>::load(path).map_err(checkpoint_error)?;
+		consensus_state = consensus_state.with_checkpoint(&checkpoint).map_err(checkpoint_error)?;
+		info!(target: "Hotstuff", "voter starts from the checkpoint of block {}, view {}",
+			checkpoint.hash, checkpoint.justification.qc.view);
+	}
+	if let Some(path) = config.audit_log_path.as_ref() {
+		let audit_log = AuditLog::open_with(path, config.audit_rotation.clone())
+			.map_err(|e| sp_blockchain::Error::Backend(format!("hotstuff audit log: {}", e)))?;
+		consensus_state = consensus_state.with_audit_log(audit_log);
+	}
 
-	let (consensus_msg_tx, consensus_msg_rx) = channel::<ConsensusMessage<B>>(1000);
+	let (consensus_msg_tx, consensus_msg_rx) = channel::<GossipMessage<B>>(1000);
 
 	let queue = PendingFinalizeBlockQueue::<B>::new(client.clone()).expect("error");
 
-	let mut local_timer_duration = 3000;
 	if let Ok(value) = env::var("HOTSTUFF_DURATION") {
 		if let Ok(duration) = value.parse::<u64>() {
-			local_timer_duration = duration;
+			config.local_timer_duration = duration;
 		}
 	}
 
-	let consensus_worker = ConsensusWorker::<B, BE, C, N, S>::new(
+	let journal = config
+		.journal_path
+		.as_deref()
+		.map(Journal::open)
+		.transpose()
+		.map_err(|e| sp_blockchain::Error::Backend(format!("hotstuff journal: {}", e)))?;
+	let mut consensus_worker = ConsensusWorker::<B, BE, C, N, S>::new(
 		consensus_state,
 		client,
 		sync,
 		network.clone(),
 		synchronizer,
-		local_timer_duration,
+		config,
 		consensus_msg_tx.clone(),
 		consensus_msg_rx,
 		queue.queue(),
+		block_tree,
+		events,
+		inclusion_index,
+		pause,
+		pacemaker,
+		certificates,
+		committed_blocks,
 	);
+	if let Some(journal) = journal {
+		consensus_worker = consensus_worker.with_journal(journal);
+	}
 
 	let consensus_network = ConsensusNetwork::<B, N, S>::new(network, consensus_msg_tx, queue);
 
@@ -835,5 +3209,6 @@ pub fn get_genesis_authorities_from_client<
 
 	let authorities: Vec<AuthorityId> = Decode::decode(&mut &authorities_data[..]).expect("");
 
-	authorities.iter().map(|id| (id.clone(), 0)).collect::<AuthorityList>()
+	// The runtime doesn't weigh its authorities, so each one counts the same.
+	authorities.iter().map(|id| (id.clone(), 1)).collect::<AuthorityList>()
 }
@@ -0,0 +1,62 @@
+// Application checks of the extrinsics of a proposed block, run by a replica which has the block
+// before it votes, apart from the consensus verification of the proposal.
+use parity_scale_codec::Encode;
+use sp_runtime::{
+	traits::{Block as BlockT, Hash as HashT, Header as HeaderT},
+	StateVersion,
+};
+
+use crate::{
+	message::{Hasher, Proposal},
+	primitives::{HotstuffError, HotstuffError::*},
+};
+
+#[cfg(test)]
+#[path = "tests/content_tests.rs"]
+pub mod content_tests;
+
+/// The rules the extrinsics of a proposed block must follow.
+pub trait ContentRules<B: BlockT> {
+	/// Check the extrinsic at `index` of the block, the reason it's rejected otherwise.
+	fn check_extrinsic(&self, index: usize, extrinsic: &B::Extrinsic) -> Result<(), String>;
+}
+
+impl<B: BlockT> Proposal<B> {
+	/// Verify `block` is the proposed block, and that each of its extrinsics follows `rules`.
+	pub fn validate_block_contents(
+		&self,
+		block: &B,
+		rules: &dyn ContentRules<B>,
+	) -> Result<(), HotstuffError> {
+		let header = block.header();
+		if header.hash() != self.payload.block_hash || *header.number() != self.payload.block_number
+		{
+			return Err(InvalidBlockContents(format!(
+				"block {} isn't the proposed block {}",
+				header.hash(),
+				self.payload.block_hash
+			)));
+		}
+
+		// The extrinsics root is built with the trie layout of the runtime state version.
+		let extrinsics = block.extrinsics().iter().map(Encode::encode).collect::<Vec<_>>();
+		let root = header.extrinsics_root();
+		if [StateVersion::V1, StateVersion::V0]
+			.into_iter()
+			.all(|version| Hasher::<B>::ordered_trie_root(extrinsics.clone(), version) != *root)
+		{
+			return Err(InvalidBlockContents(format!(
+				"extrinsics of block {} don't match its extrinsics root",
+				header.hash()
+			)));
+		}
+
+		for (index, extrinsic) in block.extrinsics().iter().enumerate() {
+			rules.check_extrinsic(index, extrinsic).map_err(|reason| {
+				InvalidBlockContents(format!("extrinsic {} rejected: {}", index, reason))
+			})?;
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,171 @@
+// Detects proposals received more than once, and conflicting proposals of the same leader, and
+// bounds the proposals verified in each view.
+use std::collections::BTreeMap;
+
+use hotstuff_primitives::AuthorityId;
+
+use crate::primitives::ViewNumber;
+
+#[cfg(test)]
+#[path = "tests/dedup_tests.rs"]
+pub mod dedup_tests;
+
+// Proposals of views this far below the current view are forgotten.
+pub const DEDUP_VIEW_WINDOW: ViewNumber = 32;
+// Most proposals remembered, the lowest views are forgotten first.
+pub const DEDUP_CAPACITY: usize = 1024;
+// Distinct proposals of an author verified in a view by default, with room for an equivocation
+// to be noticed.
+pub const PROPOSAL_VERIFICATION_BUDGET: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seen<H> {
+	// The first proposal of its author in its view.
+	New,
+	// The same proposal was seen before.
+	Duplicate,
+	// Its author proposed `first` in the same view before.
+	Equivocation { first: H },
+}
+
+// What a node does once the leader of a view equivocated. The equivocation is reported under
+// every policy. By default the node keeps to the proposal seen first, so a single byzantine
+// leader can't stop the honest nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EquivocationPolicy {
+	// Stop voting in the view, as no proposal of the view is trusted. It gives up the view.
+	Halt,
+	// Keep to the proposal seen first.
+	#[default]
+	FirstSeen,
+	// Continue with the proposal of the lowest digest, the same at every node which saw both.
+	LowestHash,
+}
+
+impl EquivocationPolicy {
+	// Whether to continue with the proposal `digest` conflicting with `first`.
+	pub fn continues_with<H: Ord>(&self, first: &H, digest: &H) -> bool {
+		*self == Self::LowestHash && digest < first
+	}
+}
+
+// The digests of the proposals seen recently, keyed by view and author.
+pub struct ProposalDedup<H> {
+	seen: BTreeMap<(ViewNumber, AuthorityId), H>,
+	window: ViewNumber,
+	capacity: usize,
+}
+
+impl<H> Default for ProposalDedup<H> {
+	fn default() -> Self {
+		Self::new(DEDUP_VIEW_WINDOW, DEDUP_CAPACITY)
+	}
+}
+
+impl<H> ProposalDedup<H> {
+	pub fn new(window: ViewNumber, capacity: usize) -> Self {
+		Self { seen: BTreeMap::new(), window, capacity }
+	}
+
+	pub fn len(&self) -> usize {
+		self.seen.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.seen.is_empty()
+	}
+
+	// Forget the proposals of views more than the window below `view`.
+	pub fn prune(&mut self, view: ViewNumber) {
+		let oldest = view.saturating_sub(self.window);
+		while self.seen.first_key_value().map_or(false, |((v, _), _)| *v < oldest) {
+			self.seen.pop_first();
+		}
+	}
+}
+
+impl<H: Copy + Eq> ProposalDedup<H> {
+	// Whether exactly this proposal was seen before.
+	pub fn contains(&self, view: ViewNumber, author: &AuthorityId, digest: &H) -> bool {
+		self.seen.get(&(view, author.clone())) == Some(digest)
+	}
+
+	// Record a proposal. Only the first proposal of an author in a view is kept, so every
+	// conflicting one is reported against it.
+	pub fn insert(&mut self, view: ViewNumber, author: AuthorityId, digest: H) -> Seen<H> {
+		match self.seen.get(&(view, author.clone())) {
+			Some(first) if *first == digest => return Seen::Duplicate,
+			Some(first) => return Seen::Equivocation { first: *first },
+			None => {},
+		}
+
+		self.seen.insert((view, author), digest);
+		while self.seen.len() > self.capacity {
+			self.seen.pop_first();
+		}
+
+		Seen::New
+	}
+}
+
+// The proposals verified in each view by each author, so that a leader flooding distinct
+// proposals for a view can't make the node spend more than the budget verifying them, nor
+// forgeries in the name of others use up their budget.
+pub struct VerificationBudget {
+	budget: usize,
+	spent: BTreeMap<(ViewNumber, AuthorityId), usize>,
+	// Proposals dropped for being over the budget of their view.
+	dropped: u64,
+}
+
+impl Default for VerificationBudget {
+	fn default() -> Self {
+		Self::new(PROPOSAL_VERIFICATION_BUDGET)
+	}
+}
+
+impl VerificationBudget {
+	pub fn new(budget: usize) -> Self {
+		Self { budget, spent: BTreeMap::new(), dropped: 0 }
+	}
+
+	// Spend a verification on a proposal of `author` for `view`, false once the budget of the
+	// author in the view is spent and the proposal is to be dropped.
+	pub fn spend(&mut self, view: ViewNumber, author: &AuthorityId) -> bool {
+		let key = (view, author.clone());
+		// The lowest views are forgotten first, before the new entry so it's never the one.
+		if !self.spent.contains_key(&key) {
+			while self.spent.len() >= DEDUP_CAPACITY {
+				self.spent.pop_first();
+			}
+		}
+
+		let spent = self.spent.entry(key).or_default();
+		if *spent >= self.budget {
+			self.dropped += 1;
+			return false;
+		}
+		*spent += 1;
+		true
+	}
+
+	// Give back the verification spent on a proposal of `author` for `view` whose signature
+	// turned out forged.
+	pub fn refund(&mut self, view: ViewNumber, author: &AuthorityId) {
+		if let Some(spent) = self.spent.get_mut(&(view, author.clone())) {
+			*spent = spent.saturating_sub(1);
+		}
+	}
+
+	pub fn dropped(&self) -> u64 {
+		self.dropped
+	}
+
+	// Forget the views more than the dedup window below `view`.
+	pub fn prune(&mut self, view: ViewNumber) {
+		let oldest = view.saturating_sub(DEDUP_VIEW_WINDOW);
+		while self.spent.first_key_value().map_or(false, |((v, _), _)| *v < oldest) {
+			self.spent.pop_first();
+		}
+	}
+}
@@ -0,0 +1,130 @@
+// The election of the leader of each view from the authority set. It only uses integer
+// arithmetic, so every node elects the same leader whatever its platform.
+use hotstuff_primitives::{AuthorityId, AuthorityList};
+
+use crate::primitives::ViewNumber;
+
+#[cfg(test)]
+#[path = "tests/election_tests.rs"]
+pub mod election_tests;
+
+// Most views after a view scanned for its backup proposers.
+const MAX_FALLBACK_SCAN: ViewNumber = 1024;
+
+/// How the turns of authorities due at the same point of a weighted period are ordered. Every
+/// authority must break ties the same way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TieBreak {
+	/// The authority listed first leads first.
+	#[default]
+	AuthorityOrder,
+	/// The authority of the lowest id leads first, whatever the order of the set.
+	LowestId,
+}
+
+/// How the leader of a view is elected. Authorities of weight zero never lead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LeaderElection {
+	/// The authorities take turns in the order of the set, whatever their weight.
+	#[default]
+	RoundRobin,
+	/// Stride scheduling by weight. Over each period of as many views as the total weight, an
+	/// authority of weight `w` leads `w` views, its `k`-th turn due `k / w` of the way through
+	/// the period. Turns due at the same point are ordered by the tie break, so authorities of
+	/// equal weight rotate in that order.
+	Weighted(TieBreak),
+}
+
+impl LeaderElection {
+	/// The leader of `view`, none when no authority has weight.
+	pub fn leader(&self, authorities: &AuthorityList, view: ViewNumber) -> Option<AuthorityId> {
+		let leaders = leaders(authorities);
+		if leaders.is_empty() {
+			return None;
+		}
+
+		let index = match self {
+			Self::RoundRobin => (view % leaders.len() as ViewNumber) as usize,
+			Self::Weighted(tie_break) => weighted_turn(&leaders, view, *tie_break),
+		};
+		Some(leaders[index].0.clone())
+	}
+
+	/// The authorities which may propose at `view` in the order they take over: the leader,
+	/// then up to `backups` distinct leaders of the following views.
+	pub fn fallback_chain(
+		&self,
+		authorities: &AuthorityList,
+		view: ViewNumber,
+		backups: usize,
+	) -> Vec<AuthorityId> {
+		let length = leaders(authorities).len().min(backups + 1);
+		let scan = MAX_FALLBACK_SCAN.max(length as ViewNumber);
+
+		let mut chain = Vec::with_capacity(length);
+		for turn in 0..scan {
+			if chain.len() >= length {
+				break;
+			}
+			match self.leader(authorities, view.saturating_add(turn)) {
+				Some(leader) if !chain.contains(&leader) => chain.push(leader),
+				Some(_) => {},
+				None => break,
+			}
+		}
+		chain
+	}
+}
+
+// The authorities which may lead and their weights, scaled down by a power of two when they
+// total more than `i64::MAX` so the products of the election fit in a `u128`. Nonzero weights
+// stay nonzero.
+fn leaders(authorities: &AuthorityList) -> Vec<(&AuthorityId, u128)> {
+	let leaders = authorities
+		.iter()
+		.map(|(id, weight)| (id, *weight as u128))
+		.filter(|(_, weight)| *weight > 0)
+		.collect::<Vec<_>>();
+
+	let total: u128 = leaders.iter().map(|(_, weight)| weight).sum();
+	let shift = (u128::BITS - total.leading_zeros()).saturating_sub(u64::BITS - 1);
+	leaders.into_iter().map(|(id, weight)| (id, (weight >> shift).max(1))).collect()
+}
+
+// The index of the leader of `view` among weighted `leaders`. The turns due by `t / total` of a
+// period are `t * w / total` for an authority of weight `w`, so the point the turn of the view
+// is due at is searched for, and the turns due there are ordered exactly.
+fn weighted_turn(leaders: &[(&AuthorityId, u128)], view: ViewNumber, tie_break: TieBreak) -> usize {
+	let total: u128 = leaders.iter().map(|(_, weight)| weight).sum();
+	let turn = view as u128 % total;
+	let due_by = |t: u128| -> u128 { leaders.iter().map(|(_, weight)| t * weight / total).sum() };
+
+	// The first point by which more turns than `turn` are due.
+	let (mut low, mut high) = (1, total);
+	while low < high {
+		let middle = low + (high - low) / 2;
+		if due_by(middle) > turn {
+			high = middle;
+		} else {
+			low = middle + 1;
+		}
+	}
+
+	// No weight is above the total, so each authority has at most one turn due there.
+	let mut due = leaders
+		.iter()
+		.enumerate()
+		.filter_map(|(index, (id, weight))| {
+			let k = low * weight / total;
+			(k > (low - 1) * weight / total).then_some((index, *id, k, *weight))
+		})
+		.collect::<Vec<_>>();
+	due.sort_by(|(index_a, id_a, k_a, weight_a), (index_b, id_b, k_b, weight_b)| {
+		(k_a * weight_b).cmp(&(k_b * weight_a)).then_with(|| match tie_break {
+			TieBreak::AuthorityOrder => index_a.cmp(index_b),
+			TieBreak::LowestId => id_a.cmp(id_b),
+		})
+	});
+
+	due[(turn - due_by(low - 1)) as usize].0
+}
@@ -0,0 +1,86 @@
+// Notable events of the consensus worker, for operators and tests to observe.
+use serde::Serialize;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use tokio::sync::broadcast;
+
+use hotstuff_primitives::AuthorityId;
+
+use crate::{clock::CommitTime, primitives::ViewNumber};
+
+// Events not received by a lagging subscriber are dropped.
+const EVENT_CHANNEL_CAPACITY: usize = 128;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase", rename_all_fields = "camelCase", bound = "")]
+pub enum ConsensusEvent<B: BlockT> {
+	/// The node entered `view`.
+	ViewChanged { view: ViewNumber },
+	/// The block of a proposal committed at `view` was finalized.
+	Committed {
+		view: ViewNumber,
+		block_hash: B::Hash,
+		block_number: NumberFor<B>,
+		time: CommitTime,
+	},
+	/// The local timer of `view` expired before it made progress.
+	TimedOut { view: ViewNumber },
+	/// The votes of `view` for the proposal with `proposal_hash` formed a QC.
+	QcFormed { view: ViewNumber, proposal_hash: B::Hash },
+	/// A committed block is not in the local chain, so it can't be finalized. This should be
+	/// impossible with an honest majority and is a critical safety alarm.
+	UncommittableBlock { view: ViewNumber, block_hash: B::Hash, block_number: NumberFor<B> },
+	/// A committed block conflicts with `depth` finalized blocks, more than the configured
+	/// maximum reorg depth, so it isn't finalized. This is a critical safety alarm.
+	ReorgTooDeep { view: ViewNumber, block_hash: B::Hash, block_number: NumberFor<B>, depth: u32 },
+	/// The block committed at `view` couldn't be finalized in `attempts`, so consensus is ahead
+	/// of finality. This is a critical alarm.
+	FinalizationFailed {
+		view: ViewNumber,
+		block_hash: B::Hash,
+		block_number: NumberFor<B>,
+		attempts: u32,
+	},
+	/// Too few authorities were reachable to form a quorum for a while, so the node stopped
+	/// proposing until they come back.
+	QuorumUnreachable { view: ViewNumber, reachable: usize },
+	/// Enough authorities are reachable again to form a quorum.
+	QuorumRecovered { view: ViewNumber, reachable: usize },
+	/// The leader of `view` signed the proposals with the digests `first` and `second`.
+	ProposalEquivocation { view: ViewNumber, author: AuthorityId, first: B::Hash, second: B::Hash },
+	/// Two QCs of `view` certify the distinct proposals `first` and `second`. This should be
+	/// impossible with an honest majority and is a critical safety alarm.
+	ConflictingQcs { view: ViewNumber, first: B::Hash, second: B::Hash },
+	/// An operator forced the node out of `view` for `reason`.
+	ViewForced { view: ViewNumber, reason: String },
+	/// The voter panicked at `view` while handling `last_message`, and halted. This is a critical
+	/// alarm, the node votes no more until it's restarted.
+	WorkerHalted { view: ViewNumber, last_message: Option<String>, panic: String },
+}
+
+/// The sending side of the consensus events, cheap to clone.
+#[derive(Clone)]
+pub struct EventSink<B: BlockT> {
+	tx: broadcast::Sender<ConsensusEvent<B>>,
+}
+
+impl<B: BlockT> Default for EventSink<B> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<B: BlockT> EventSink<B> {
+	pub fn new() -> Self {
+		let (tx, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+		Self { tx }
+	}
+
+	pub fn subscribe(&self) -> broadcast::Receiver<ConsensusEvent<B>> {
+		self.tx.subscribe()
+	}
+
+	pub fn emit(&self, event: ConsensusEvent<B>) {
+		// It's fine that nobody is listening.
+		let _ = self.tx.send(event);
+	}
+}
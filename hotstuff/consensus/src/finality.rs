@@ -0,0 +1,146 @@
+// Retrying the finalization of committed blocks, so a transient failure of the client, such as
+// lock contention, doesn't leave consensus ahead of finality, and the blocks a committed proposal
+// stands for.
+use std::{sync::Arc, time::Duration};
+
+use sc_client_api::{Backend, Finalizer, LockImportRun};
+use sp_blockchain::{lowest_common_ancestor, HeaderBackend, HeaderMetadata};
+use sp_runtime::{
+	traits::{Block as BlockT, Header as HeaderT},
+	Justification,
+};
+use tokio::time::sleep;
+
+use crate::{
+	message::Proposal,
+	primitives::{HotstuffError, HotstuffError::*},
+};
+
+#[cfg(test)]
+#[path = "tests/finality_tests.rs"]
+pub mod finality_tests;
+
+/// How the finalization of a committed block is retried.
+#[derive(Debug, Clone)]
+pub struct FinalizeRetry {
+	/// Attempts before giving up, the first one included.
+	pub max_attempts: u32,
+	/// Wait before the first retry, doubled for each retry after it.
+	pub initial_backoff: Duration,
+	pub max_backoff: Duration,
+}
+
+impl Default for FinalizeRetry {
+	fn default() -> Self {
+		Self {
+			max_attempts: 5,
+			initial_backoff: Duration::from_millis(100),
+			max_backoff: Duration::from_secs(2),
+		}
+	}
+}
+
+impl FinalizeRetry {
+	fn backoff(&self, retry: u32) -> Duration {
+		self.initial_backoff.saturating_mul(1 << retry.min(16)).min(self.max_backoff)
+	}
+}
+
+/// Run `finalize` until it succeeds or `policy` gives up, the attempts made on success, and with
+/// the last error otherwise. `is_finalized` is checked before each retry, so an attempt which
+/// finalized the block but still failed counts as a success rather than being repeated.
+pub async fn finalize_with_retry<E>(
+	policy: &FinalizeRetry,
+	mut finalize: impl FnMut() -> Result<(), E>,
+	is_finalized: impl Fn() -> bool,
+) -> Result<u32, (u32, E)> {
+	let mut attempts = 0;
+	loop {
+		attempts += 1;
+		let error = match finalize() {
+			Ok(()) => return Ok(attempts),
+			Err(e) => e,
+		};
+		if attempts >= policy.max_attempts {
+			return Err((attempts, error));
+		}
+
+		sleep(policy.backoff(attempts - 1)).await;
+		if is_finalized() {
+			return Ok(attempts);
+		}
+	}
+}
+
+/// Blocks to finalize, each with its justification if any.
+pub type FinalityBlocks<B> = Vec<(<B as BlockT>::Hash, Option<Justification>)>;
+
+/// Finalize the committed `blocks`, each an ancestor of the next, in one backend transaction. The
+/// batch is all-or-nothing: on a failure the transaction is dropped, so no block of it is
+/// finalized nor notified. Only the justification of the last block is kept, which proves its
+/// ancestors final.
+pub fn finalize_batch<B, BE, C>(
+	client: &C,
+	blocks: &[(B::Hash, Option<Justification>)],
+) -> sp_blockchain::Result<()>
+where
+	B: BlockT,
+	BE: Backend<B>,
+	C: LockImportRun<B, BE> + Finalizer<B, BE> + HeaderMetadata<B, Error = sp_blockchain::Error>,
+{
+	let Some((last, justification)) = blocks.last() else { return Ok(()) };
+
+	client.lock_import_and_run(|operation| {
+		for pair in blocks.windows(2) {
+			let (ancestor, descendant) = (pair[0].0, pair[1].0);
+			if lowest_common_ancestor(client, ancestor, descendant)?.hash != ancestor {
+				return Err(sp_blockchain::Error::NonSequentialFinalization(format!(
+					"batched block {} isn't an ancestor of {}",
+					ancestor, descendant
+				)));
+			}
+		}
+		client.apply_finality(operation, *last, justification.clone(), true)
+	})
+}
+
+/// The blocks a committed proposal stands for, for chains which batch several blocks under one
+/// proposal. Each proposal stands for its own block only without it.
+pub trait CommittedBlocks<B: BlockT>: Send + Sync {
+	/// The blocks finalized by the commit of `proposal` whose block is `block`, each the parent
+	/// of the next.
+	fn committed_blocks(&self, proposal: &Proposal<B>, block: &B) -> Vec<B::Hash>;
+}
+
+impl<B: BlockT, F> CommittedBlocks<B> for F
+where
+	F: Fn(&Proposal<B>, &B) -> Vec<B::Hash> + Send + Sync,
+{
+	fn committed_blocks(&self, proposal: &Proposal<B>, block: &B) -> Vec<B::Hash> {
+		self(proposal, block)
+	}
+}
+
+/// The mapping of the committed proposals to their blocks, cheap to clone.
+pub type SharedCommittedBlocks<B> = Arc<dyn CommittedBlocks<B>>;
+
+/// Check each of `blocks` is the parent of the next.
+pub fn check_contiguous<B, C>(client: &C, blocks: &[B::Hash]) -> Result<(), HotstuffError>
+where
+	B: BlockT,
+	C: HeaderBackend<B>,
+{
+	for pair in blocks.windows(2) {
+		let header = client
+			.header(pair[1])
+			.map_err(|e| ClientError(e.to_string()))?
+			.ok_or_else(|| NonContiguousBlocks(format!("block {} is unknown", pair[1])))?;
+		if *header.parent_hash() != pair[0] {
+			return Err(NonContiguousBlocks(format!(
+				"block {} isn't the parent of {}",
+				pair[0], pair[1]
+			)));
+		}
+	}
+	Ok(())
+}
@@ -24,14 +24,25 @@ use sp_runtime::{
 	Justification,
 };
 
+use hotstuff_primitives::HOTSTUFF_ENGINE_ID;
+
 use crate::{
+	authorities::SharedAuthoritySet,
 	client::ClientForHotstuff,
+	committee::CommitteeSampler,
+	config::Config,
+	justification::HotstuffJustification,
+	message::SigningContext,
 	primitives::{HotstuffError, HotstuffError::*},
 };
 
 // const LOG_TARGET: &str  = "hotstuff";
 pub struct HotstuffBlockImport<Backend, Block: BlockT, Client> {
 	inner: Arc<Client>,
+	// The authorities whose commits the justifications must prove.
+	authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
+	signing_context: SigningContext,
+	committee: Option<CommitteeSampler>,
 	backend: PhantomData<Backend>,
 	_phantom: PhantomData<Block>,
 }
@@ -40,6 +51,9 @@ impl<Backend, Block: BlockT, Client> Clone for HotstuffBlockImport<Backend, Bloc
 	fn clone(&self) -> Self {
 		HotstuffBlockImport {
 			inner: self.inner.clone(),
+			authority_set: self.authority_set.clone(),
+			signing_context: self.signing_context.clone(),
+			committee: self.committee.clone(),
 			backend: PhantomData,
 			_phantom: PhantomData,
 		}
@@ -47,8 +61,33 @@ impl<Backend, Block: BlockT, Client> Clone for HotstuffBlockImport<Backend, Bloc
 }
 
 impl<Backend, Block: BlockT, Client> HotstuffBlockImport<Backend, Block, Client> {
-	pub fn new(inner: Arc<Client>) -> HotstuffBlockImport<Backend, Block, Client> {
-		HotstuffBlockImport { inner, backend: PhantomData, _phantom: PhantomData }
+	pub fn new(
+		inner: Arc<Client>,
+		authority_set: SharedAuthoritySet<Block::Hash, NumberFor<Block>>,
+		signing_context: SigningContext,
+	) -> HotstuffBlockImport<Backend, Block, Client> {
+		HotstuffBlockImport {
+			inner,
+			authority_set,
+			signing_context,
+			committee: None,
+			backend: PhantomData,
+			_phantom: PhantomData,
+		}
+	}
+}
+
+impl<BE, Block: BlockT, Client> HotstuffBlockImport<BE, Block, Client>
+where
+	BE: Backend<Block>,
+	Client: ClientForHotstuff<Block, BE>,
+{
+	/// Verify justifications signed in the context and committees of the voter `config`.
+	pub fn with_config(mut self, config: &Config) -> Self {
+		let genesis_hash = self.inner.info().genesis_hash;
+		self.signing_context = config.signing_context(genesis_hash.as_ref());
+		self.committee = config.committee_sampler(genesis_hash.as_ref());
+		self
 	}
 }
 
@@ -73,6 +112,7 @@ where
 		mut block: BlockImportParams<Block>,
 	) -> Result<ImportResult, Self::Error> {
 		let hash = block.post_hash();
+		let number = *block.header.number();
 
 		match self.inner.status(hash) {
 			Ok(BlockStatus::InChain) => {
@@ -88,8 +128,16 @@ where
 		// 	return self.import_state(block).await
 		// }
 
+		// The block is finalized with its hotstuff justification once imported, so the
+		// justification is verified against a known block.
+		let justification = block.justifications.as_mut().and_then(|justifications| {
+			let justification = justifications.get(HOTSTUFF_ENGINE_ID).cloned();
+			justifications.remove(HOTSTUFF_ENGINE_ID);
+			justification.map(|justification| (HOTSTUFF_ENGINE_ID, justification))
+		});
+
 		let import_result = (&*self.inner).import_block(block).await;
-		let imported_aux = {
+		let mut imported_aux = {
 			match import_result {
 				Ok(ImportResult::Imported(aux)) => aux,
 				Ok(r) => return Ok(r),
@@ -97,7 +145,13 @@ where
 			}
 		};
 
-		// TODO
+		if let Some(justification) = justification {
+			if let Err(e) = self.import_justification(hash, number, justification, false, false) {
+				log::warn!(target: "Hotstuff", "import justification of block {} failed: {}", hash, e);
+				imported_aux.bad_justification = true;
+			}
+		}
+
 		Ok(ImportResult::Imported(imported_aux))
 	}
 }
@@ -114,25 +168,27 @@ where
 	fn import_justification(
 		&mut self,
 		hash: Block::Hash,
-		_number: NumberFor<Block>,
-		_justification: Justification,
+		number: NumberFor<Block>,
+		justification: Justification,
 		_enacts_change: bool,
 		_initial_sync: bool,
 	) -> Result<(), ConsensusError> {
-		let client = self.inner.clone();
-		let _status = client.info();
-
-		let _res: Result<(), sp_blockchain::Error> = self.inner.finalize_block(hash, None, true);
-		match _res {
-			Ok(()) => {
-				println!("🔥💃🏻 success finalize_block");
-			},
-			Err(err) => {
-				println!("🔥💃🏻 finalize_block error: {:?}", err);
-			},
-		}
-
-		Ok(())
+		let authorities = self.authority_set.inner().current_authorities.clone();
+		HotstuffJustification::<Block>::decode_justification(&justification)
+			.and_then(|decoded| {
+				decoded.verify(
+					hash,
+					number,
+					&authorities,
+					self.committee.as_ref(),
+					&self.signing_context,
+				)
+			})
+			.map_err(|e| ConsensusError::ClientImport(format!("{:?}", e)))?;
+
+		self.inner
+			.finalize_block(hash, Some(justification), true)
+			.map_err(|e| ConsensusError::ClientImport(e.to_string()))
 	}
 }
 
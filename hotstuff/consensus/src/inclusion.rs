@@ -0,0 +1,191 @@
+// Proofs that a transaction is in a committed block, which light clients check against the QC
+// certifying the proposal of that block. The QC signs the proposal digest, the digest covers the
+// block hash, and the block header carries the root of the extrinsics trie.
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::Arc,
+};
+
+use parity_scale_codec::{Compact, Decode, Encode};
+use parking_lot::RwLock;
+use sp_runtime::traits::{Block as BlockT, Hash as HashT, Header as HeaderT};
+use sp_trie::{
+	generate_trie_proof, verify_trie_proof, LayoutV0, LayoutV1, MemoryDB, TrieConfiguration,
+	TrieDBMutBuilder, TrieMut,
+};
+
+use hotstuff_primitives::AuthorityId;
+
+use crate::{
+	message::{Payload, Proposal, VoteTimestamp, QC},
+	primitives::ViewNumber,
+};
+
+#[cfg(test)]
+#[path = "tests/inclusion_tests.rs"]
+pub mod inclusion_tests;
+
+// Committed blocks whose transactions can be proven.
+const MAX_INDEXED_BLOCKS: usize = 64;
+
+type Hashing<B> = <<B as BlockT>::Header as HeaderT>::Hashing;
+
+/// An index of the transactions of the latest committed blocks, shared between the voter and
+/// its users.
+pub type SharedInclusionIndex<B> = Arc<RwLock<InclusionIndex<B>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct InclusionProof<B: BlockT> {
+	// The fields of the committed proposal covered by its digest, besides the header hash.
+	pub author: AuthorityId,
+	pub view: ViewNumber,
+	pub parent_hash: B::Hash,
+	pub metadata: Option<Vec<u8>>,
+	pub timestamp: Option<u64>,
+	pub qc_timestamps: Vec<VoteTimestamp>,
+	pub header: B::Header,
+	/// Position of the transaction in the block.
+	pub index: u32,
+	/// The encoded transaction.
+	pub extrinsic: Vec<u8>,
+	/// Nodes of the extrinsics trie proving the transaction.
+	pub trie_proof: Vec<Vec<u8>>,
+}
+
+struct CommittedBlock<B: BlockT> {
+	author: AuthorityId,
+	view: ViewNumber,
+	parent_hash: B::Hash,
+	metadata: Option<Vec<u8>>,
+	timestamp: Option<u64>,
+	qc_timestamps: Vec<VoteTimestamp>,
+	header: B::Header,
+	extrinsics: Vec<Vec<u8>>,
+}
+
+pub struct InclusionIndex<B: BlockT> {
+	blocks: HashMap<B::Hash, CommittedBlock<B>>,
+	// Indexed blocks from the oldest.
+	order: VecDeque<B::Hash>,
+	// Block and position of each indexed transaction.
+	transactions: HashMap<B::Hash, (B::Hash, u32)>,
+}
+
+impl<B: BlockT> Default for InclusionIndex<B> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<B: BlockT> InclusionIndex<B> {
+	pub fn new() -> Self {
+		Self { blocks: HashMap::new(), order: VecDeque::new(), transactions: HashMap::new() }
+	}
+
+	pub fn shared() -> SharedInclusionIndex<B> {
+		Arc::new(RwLock::new(Self::new()))
+	}
+
+	/// Index the transactions of the block committed by `proposal`, forgetting the oldest block
+	/// beyond the capacity.
+	pub fn record(&mut self, proposal: &Proposal<B>, header: B::Header, extrinsics: Vec<Vec<u8>>) {
+		let block_hash = header.hash();
+		if block_hash != proposal.payload.block_hash || self.blocks.contains_key(&block_hash) {
+			return;
+		}
+
+		for (index, extrinsic) in extrinsics.iter().enumerate() {
+			self.transactions
+				.insert(Hashing::<B>::hash(extrinsic), (block_hash, index as u32));
+		}
+		self.blocks.insert(
+			block_hash,
+			CommittedBlock {
+				author: proposal.author.clone(),
+				view: proposal.view,
+				parent_hash: proposal.parent_hash(),
+				metadata: proposal.metadata.clone(),
+				timestamp: proposal.timestamp,
+				qc_timestamps: proposal.qc_timestamps.clone(),
+				header,
+				extrinsics,
+			},
+		);
+		self.order.push_back(block_hash);
+
+		while self.order.len() > MAX_INDEXED_BLOCKS {
+			if let Some(oldest) = self.order.pop_front() {
+				self.blocks.remove(&oldest);
+				self.transactions.retain(|_, (block, _)| *block != oldest);
+			}
+		}
+	}
+
+	/// Prove that the transaction hashed `tx_hash` is in a committed block, if indexed.
+	pub fn inclusion_proof(&self, tx_hash: B::Hash) -> Option<InclusionProof<B>> {
+		let (block_hash, index) = self.transactions.get(&tx_hash)?;
+		let block = self.blocks.get(block_hash)?;
+
+		// The extrinsics root is built with the trie layout of the runtime state version.
+		let trie_proof = trie_proof::<LayoutV1<Hashing<B>>>(&block.extrinsics, *index)
+			.filter(|(root, _)| root == block.header.extrinsics_root())
+			.or_else(|| {
+				trie_proof::<LayoutV0<Hashing<B>>>(&block.extrinsics, *index)
+					.filter(|(root, _)| root == block.header.extrinsics_root())
+			})
+			.map(|(_, proof)| proof)?;
+
+		Some(InclusionProof {
+			author: block.author.clone(),
+			view: block.view,
+			parent_hash: block.parent_hash,
+			metadata: block.metadata.clone(),
+			timestamp: block.timestamp,
+			qc_timestamps: block.qc_timestamps.clone(),
+			header: block.header.clone(),
+			index: *index,
+			extrinsic: block.extrinsics[*index as usize].clone(),
+			trie_proof,
+		})
+	}
+}
+
+// Build the extrinsics trie, returning its root and the proof of the extrinsic at `index`.
+fn trie_proof<L: TrieConfiguration>(
+	extrinsics: &[Vec<u8>],
+	index: u32,
+) -> Option<(sp_trie::TrieHash<L>, Vec<Vec<u8>>)> {
+	let mut db = MemoryDB::<L::Hash>::default();
+	let mut root = Default::default();
+	{
+		let mut trie = TrieDBMutBuilder::<L>::new(&mut db, &mut root).build();
+		for (position, extrinsic) in extrinsics.iter().enumerate() {
+			trie.insert(&Compact(position as u32).encode(), extrinsic).ok()?;
+		}
+	}
+
+	let proof = generate_trie_proof::<L, _, _, _>(&db, root, &[Compact(index).encode()]).ok()?;
+	Some((root, proof))
+}
+
+/// Check the transaction of `proof` is in the block of the proposal certified by `committed_qc`.
+/// The signatures of the QC are checked separately, against the authorities of its view.
+pub fn verify_inclusion<B: BlockT>(proof: &InclusionProof<B>, committed_qc: &QC<B>) -> bool {
+	let payload =
+		Payload::<B> { block_hash: proof.header.hash(), block_number: *proof.header.number() };
+	let parent = QC::<B> { proposal_hash: proof.parent_hash, ..Default::default() };
+	let mut proposal =
+		Proposal::<B>::new(parent, None, payload, proof.view, proof.author.clone(), None)
+			.with_qc_timestamps(proof.qc_timestamps.clone());
+	proposal.metadata = proof.metadata.clone();
+	proposal.timestamp = proof.timestamp;
+	if proposal.digest() != committed_qc.proposal_hash {
+		return false;
+	}
+
+	let root = proof.header.extrinsics_root();
+	let items = [(Compact(proof.index).encode(), Some(&proof.extrinsic))];
+	verify_trie_proof::<LayoutV1<Hashing<B>>, _, _, _>(root, &proof.trie_proof, &items).is_ok()
+		|| verify_trie_proof::<LayoutV0<Hashing<B>>, _, _, _>(root, &proof.trie_proof, &items)
+			.is_ok()
+}
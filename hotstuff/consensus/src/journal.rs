@@ -0,0 +1,89 @@
+// A journal of what the voter saw: every message it handled and every timer event, timestamped
+// with its local clock, so an incident can be replayed into a fresh worker which reproduces its
+// decisions. It's stored as length prefixed SCALE records, like the audit log.
+use std::{
+	fs::{self, File, OpenOptions},
+	io::{self, Write},
+	path::Path,
+};
+
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{
+	message::ConsensusMessage,
+	primitives::{HotstuffError, ViewNumber},
+};
+
+#[cfg(test)]
+#[path = "tests/journal_tests.rs"]
+pub mod journal_tests;
+
+/// An input of the voter, in the order it was handled.
+#[derive(Debug, Clone, Encode, Decode)]
+pub enum JournalEvent<B: BlockT> {
+	/// A message reaching its handler, after the checks of the authority set, duplicates and
+	/// load, either read from the network or back from the verification pool.
+	Message(Box<ConsensusMessage<B>>),
+	LocalTimer,
+	VoteCollectionTimeout(ViewNumber),
+	FallbackTurn(ViewNumber),
+	ProposalPacing(ViewNumber),
+	VoteBroadcast(ViewNumber),
+	SyncDeadline,
+	/// The voter was paused, or resumed.
+	Paused(bool),
+}
+
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct JournalEntry<B: BlockT> {
+	/// Milliseconds since the unix epoch on the local clock of the voter when it was handled.
+	pub timestamp: u64,
+	pub event: JournalEvent<B>,
+}
+
+pub struct Journal {
+	file: File,
+}
+
+impl Journal {
+	/// Open the journal at `path` for appending, creating it and its directory when missing.
+	pub fn open(path: &Path) -> io::Result<Self> {
+		if let Some(dir) = path.parent() {
+			fs::create_dir_all(dir)?;
+		}
+
+		let file = OpenOptions::new().create(true).append(true).open(path)?;
+		Ok(Self { file })
+	}
+
+	/// Append an entry. Unlike the audit log it isn't synced, an entry lost with the machine
+	/// only makes the replay stop short.
+	pub fn record<B: BlockT>(&mut self, entry: &JournalEntry<B>) -> Result<(), HotstuffError> {
+		let record = entry.encode();
+		let mut data = (record.len() as u32).to_le_bytes().to_vec();
+		data.extend(record);
+		self.file.write_all(&data).map_err(|e| HotstuffError::Journal(e.to_string()))
+	}
+
+	/// Read the entries of the journal at `path` in the order they were recorded. A record cut
+	/// short by a crash while it was written is dropped.
+	pub fn read<B: BlockT>(path: &Path) -> io::Result<Vec<JournalEntry<B>>> {
+		let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+		let data = fs::read(path)?;
+
+		let mut entries = Vec::new();
+		let mut rest = &data[..];
+		while rest.len() >= 4 {
+			let len = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+			let Some(mut record) = rest.get(4..4 + len) else {
+				break;
+			};
+			let entry =
+				JournalEntry::<B>::decode(&mut record).map_err(|e| invalid(e.to_string()))?;
+			entries.push(entry);
+			rest = &rest[4 + len..];
+		}
+		Ok(entries)
+	}
+}
@@ -0,0 +1,117 @@
+// Hotstuff commits as `Justification`s, so the finality of a block can be reported to and
+// checked by the block import pipeline like a GRANDPA commit.
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::{
+	traits::{Block as BlockT, NumberFor},
+	Justification,
+};
+
+use hotstuff_primitives::{AuthorityList, HOTSTUFF_ENGINE_ID};
+
+use crate::{
+	committee::{voting_committee, CommitteeSampler},
+	message::{Proposal, SigningContext, QC},
+	primitives::{HotstuffError, HotstuffError::*},
+	variant::ConsensusVariant,
+};
+
+#[cfg(test)]
+#[path = "tests/justification_tests.rs"]
+pub mod justification_tests;
+
+/// Proof that the block of `committed` was committed: its child in the next view carries the QC
+/// of `committed`, and `qc` certifies the child.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct HotstuffJustification<B: BlockT> {
+	pub committed: Proposal<B>,
+	pub child: Proposal<B>,
+	pub qc: QC<B>,
+}
+
+impl<B: BlockT> HotstuffJustification<B> {
+	pub fn new(committed: Proposal<B>, child: Proposal<B>, qc: QC<B>) -> Self {
+		Self { committed, child, qc }
+	}
+
+	pub fn into_justification(self) -> Justification {
+		(HOTSTUFF_ENGINE_ID, self.encode())
+	}
+
+	pub fn decode_justification(justification: &Justification) -> Result<Self, HotstuffError> {
+		if justification.0 != HOTSTUFF_ENGINE_ID {
+			return Err(InvalidJustification("not a hotstuff justification".into()));
+		}
+
+		Decode::decode(&mut &justification.1[..]).map_err(|e| InvalidJustification(e.to_string()))
+	}
+
+	/// Verify that block `hash` at `number` was committed by `authorities`, with only the
+	/// signing context and committee sampler of the chain. A justification carries the two-chain
+	/// of the [`Chained`](ConsensusVariant::Chained) commit rule whatever the variant: the voting
+	/// rules of both variants are those of the chained form, under which a certified child in
+	/// the next view already makes a proposal final. The three-chain of a
+	/// [`ThreePhase`](ConsensusVariant::ThreePhase) commit is checked by
+	/// [`verify_commit_certificate`].
+	pub fn verify(
+		&self,
+		hash: B::Hash,
+		number: NumberFor<B>,
+		authorities: &AuthorityList,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
+		if self.committed.payload.block_number != number {
+			return Err(InvalidJustification(format!(
+				"justifies block {}",
+				self.committed.payload
+			)));
+		}
+
+		verify_commit_certificate(
+			hash,
+			&[self.committed.clone(), self.child.clone()],
+			&self.qc,
+			authorities,
+			sampler,
+			ConsensusVariant::Chained,
+			context,
+		)
+	}
+}
+
+/// Verify that block `hash` was committed by `authorities` under the commit rule of `variant`,
+/// without any node state. `chain` starts with the proposal of the block, each following
+/// proposal carries the QC of the one before it and `qc` certifies the last. The first
+/// [`chain_length`](ConsensusVariant::chain_length) proposals must be in consecutive views: two
+/// for the two-chain of [`Chained`](ConsensusVariant::Chained), three for the three-chain of
+/// [`ThreePhase`](ConsensusVariant::ThreePhase).
+pub fn verify_commit_certificate<B: BlockT>(
+	hash: B::Hash,
+	chain: &[Proposal<B>],
+	qc: &QC<B>,
+	authorities: &AuthorityList,
+	sampler: Option<&CommitteeSampler>,
+	variant: ConsensusVariant,
+	context: &SigningContext,
+) -> Result<(), HotstuffError> {
+	let committing = match chain.get(..variant.chain_length()) {
+		Some(committing) => committing,
+		None => return Err(InvalidJustification("certificate chain is too short".into())),
+	};
+	if committing[0].payload.block_hash != hash {
+		return Err(InvalidJustification(format!("justifies block {}", committing[0].payload)));
+	}
+	if !committing.windows(2).all(|pair| pair[1].view == pair[0].view + 1) {
+		return Err(InvalidJustification("certificate chain skips a view".into()));
+	}
+
+	let certificates = chain.iter().skip(1).map(|proposal| &proposal.qc).chain([qc]);
+	for (qc, proposal) in certificates.zip(chain) {
+		if qc.proposal_hash != proposal.digest() || qc.view != proposal.view {
+			return Err(InvalidJustification(format!("QC of view {} is misplaced", qc.view)));
+		}
+		qc.verify_with(&voting_committee(authorities, sampler, qc.view), context)?;
+	}
+
+	Ok(())
+}
@@ -1,16 +1,46 @@
 pub mod aux_schema;
 pub mod import;
+pub mod inclusion;
+pub mod journal;
+pub mod justification;
+pub mod liveness;
 pub mod message;
+pub mod metrics;
 pub use import::HotstuffBlockImport;
 pub mod aggregator;
+pub mod audit;
 pub mod authorities;
+pub mod block_tree;
+pub mod certificates;
+pub mod checkpoint;
 pub mod client;
+pub mod clock;
+pub mod commit;
+pub mod committee;
 pub mod config;
 pub mod consensus;
+pub mod content;
+pub mod dedup;
+pub mod election;
+pub mod events;
+pub mod finality;
 pub mod network;
+pub mod pacemaker;
+pub mod participation;
+pub mod pause;
+pub mod pool;
 pub mod primitives;
+pub mod reputation;
+pub mod schema;
+pub mod scheme;
+pub mod signing;
 pub mod store;
 pub mod synchronizer;
+#[cfg(test)]
+pub mod testing;
+pub mod trace;
+pub mod variant;
+pub mod verifier;
 
 pub use client::{block_import, LinkHalf};
 
@@ -0,0 +1,94 @@
+// Detects when too few authorities are reachable to ever form a quorum.
+use std::{collections::HashMap, time::Duration};
+
+use tokio::time::Instant;
+
+use hotstuff_primitives::{AuthorityId, AuthorityList};
+
+#[cfg(test)]
+#[path = "tests/liveness_tests.rs"]
+pub mod liveness_tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuorumTransition {
+	/// The reachable authorities stayed below a quorum for the grace period.
+	Unreachable { reachable: usize },
+	/// The reachable authorities form a quorum again.
+	Recovered { reachable: usize },
+}
+
+pub struct QuorumMonitor {
+	// An authority is reachable when a message from it was received within this window.
+	window: Duration,
+	// How long the reachable authorities may stay below a quorum before it is unreachable.
+	grace: Duration,
+	last_seen: HashMap<AuthorityId, Instant>,
+	below_since: Option<Instant>,
+	unreachable: bool,
+}
+
+impl QuorumMonitor {
+	pub fn new(window: Duration, grace: Duration) -> Self {
+		Self { window, grace, last_seen: HashMap::new(), below_since: None, unreachable: false }
+	}
+
+	// Authorities send a timeout at least every local timeout, which may change over time.
+	pub fn set_window(&mut self, window: Duration) {
+		self.window = window;
+	}
+
+	pub fn is_unreachable(&self) -> bool {
+		self.unreachable
+	}
+
+	/// Record a message from `authority` at `now`.
+	pub fn observe(&mut self, authority: AuthorityId, now: Instant) {
+		self.last_seen.insert(authority, now);
+	}
+
+	/// The authorities heard from within the window, counting the local authority.
+	pub fn reachable(
+		&self,
+		authorities: &AuthorityList,
+		local: Option<&AuthorityId>,
+		now: Instant,
+	) -> usize {
+		authorities
+			.iter()
+			.filter(|(id, _)| {
+				Some(id) == local
+					|| self
+						.last_seen
+						.get(id)
+						.map_or(false, |seen| now.saturating_duration_since(*seen) <= self.window)
+			})
+			.count()
+	}
+
+	/// Update the state at `now`, returning the transition if it changed.
+	pub fn check(
+		&mut self,
+		authorities: &AuthorityList,
+		local: Option<&AuthorityId>,
+		now: Instant,
+	) -> Option<QuorumTransition> {
+		let reachable = self.reachable(authorities, local, now);
+
+		if reachable > authorities.len() * 2 / 3 {
+			self.below_since = None;
+			if self.unreachable {
+				self.unreachable = false;
+				return Some(QuorumTransition::Recovered { reachable });
+			}
+			return None;
+		}
+
+		let below_since = *self.below_since.get_or_insert(now);
+		if !self.unreachable && now.saturating_duration_since(below_since) >= self.grace {
+			self.unreachable = true;
+			return Some(QuorumTransition::Unreachable { reachable });
+		}
+
+		None
+	}
+}
@@ -1,19 +1,100 @@
-use std::{collections::HashSet, fmt, marker::PhantomData};
+use std::{borrow::Cow, cmp::Ordering, collections::HashSet, fmt, marker::PhantomData};
 
 use parity_scale_codec::{Decode, Encode};
+use scale_info::TypeInfo;
 use sp_core::Pair;
 use sp_runtime::traits::{Block as BlockT, Hash as HashT, Header as HeaderT};
 
 use hotstuff_primitives::{AuthorityId, AuthorityList, AuthorityPair, AuthoritySignature};
 
-use crate::primitives::{HotstuffError, HotstuffError::*, ViewNumber};
+use crate::{
+	committee::{voting_committee, CommitteeSampler},
+	primitives::{HotstuffError, HotstuffError::*, SetId, ViewNumber},
+	scheme::SignatureScheme,
+};
 
 #[cfg(test)]
 #[path = "tests/message_tests.rs"]
 pub mod message_tests;
 
+/// The hash of the digests of every message, chosen at the type level by the hashing of the
+/// block headers. Substrate chains mostly use blake2-256, others keccak-256.
+pub type Hasher<Block> = <<Block as BlockT>::Header as HeaderT>::Hashing;
+
+// The order of the sr25519 scalar group, little endian.
+const GROUP_ORDER: [u8; 32] = [
+	0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+	0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10,
+];
+
+/// Whether the scalar of a signature is reduced. Adding the group order to it gives another
+/// encoding of the same signature, which must not count as a second signature of its signer.
+pub fn is_canonical(signature: &AuthoritySignature) -> bool {
+	let bytes: &[u8] = signature.as_ref();
+	let mut scalar = [0u8; 32];
+	scalar.copy_from_slice(&bytes[32..64]);
+	// The top bit marks schnorrkel signatures and isn't part of the scalar.
+	scalar[31] &= 0x7f;
+
+	scalar.iter().rev().cmp(GROUP_ORDER.iter().rev()) == Ordering::Less
+}
+
+/// The most bytes of metadata a proposer may attach to its proposals.
+pub const MAX_PROPOSAL_METADATA: usize = 256;
+
+// Hashed into the proposal hash of nil votes, which no proposal digest equals.
+pub(crate) const NIL_PAYLOAD: &[u8] = b"hotstuff/nil_payload";
+
+pub(crate) fn check_metadata(metadata: &[u8]) -> Result<(), HotstuffError> {
+	if metadata.len() > MAX_PROPOSAL_METADATA {
+		return Err(OversizedMetadata(metadata.len()));
+	}
+	Ok(())
+}
+
+fn verify_signature<M: AsRef<[u8]>>(
+	signature: &AuthoritySignature,
+	message: M,
+	signer: &AuthorityId,
+) -> Result<(), HotstuffError> {
+	if !is_canonical(signature) {
+		return Err(NonCanonicalSignature(signer.clone()));
+	}
+	if !AuthorityPair::verify(signature, message, signer) {
+		return Err(InvalidSignature(signer.clone()));
+	}
+	Ok(())
+}
+
+/// Chain data mixed into every signed digest, so that a signature made on one chain can't be
+/// replayed on another chain whose authorities share keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct SigningContext {
+	genesis_hash: Vec<u8>,
+	fork_id: Option<Vec<u8>>,
+}
+
+impl SigningContext {
+	pub fn new(genesis_hash: &[u8], fork_id: Option<&str>) -> Self {
+		Self {
+			genesis_hash: genesis_hash.to_vec(),
+			fork_id: fork_id.map(|id| id.as_bytes().to_vec()),
+		}
+	}
+
+	/// The digest signed for a message `digest`. The default context signs the digest itself.
+	pub fn signing_digest<Block: BlockT>(&self, digest: Block::Hash) -> Block::Hash {
+		if *self == Self::default() {
+			return digest;
+		}
+
+		Hasher::<Block>::hash_of(&(self, digest))
+	}
+}
+
 /// Quorum certificate for a block.
-#[derive(Debug, Eq, Clone, Encode, Decode)]
+#[derive(Debug, Eq, Clone, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(Block))]
 pub struct QC<Block: BlockT> {
 	/// Hotstuff proposal hash.
 	pub proposal_hash: Block::Hash,
@@ -37,7 +118,7 @@ impl<Block: BlockT> QC<Block> {
 		let mut data = self.proposal_hash.encode();
 		data.append(&mut self.view.encode());
 
-		<<Block::Header as HeaderT>::Hashing as HashT>::hash_of(&data)
+		Hasher::<Block>::hash_of(&data)
 	}
 
 	// Add votes to QC.
@@ -46,33 +127,113 @@ impl<Block: BlockT> QC<Block> {
 		self.votes.push((authority_id, signature));
 	}
 
-	// Verify if the number of votes in the QC has exceeded (2/3 + 1) of the total authorities.
-	// We are currently not considering the weight of authorities.
-	pub fn verify(&self, authorities: &AuthorityList) -> Result<(), HotstuffError> {
+	// Verify if the number of votes in the QC has exceeded (2/3 + 1) of the voting committee of
+	// its view, which is every authority unless the committee is sub-sampled.
+	pub fn verify(&self, committee: &AuthorityList) -> Result<(), HotstuffError> {
+		self.verify_with(committee, &SigningContext::default())
+	}
+
+	// Verify the QC, its votes signed in `context`.
+	pub fn verify_with(
+		&self,
+		committee: &AuthorityList,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
 		let mut used = HashSet::<AuthorityId>::new();
-		let mut grant_votes = 0;
 
 		for (authority_id, _) in self.votes.iter() {
 			if used.contains(authority_id) {
 				return Err(AuthorityReuse(authority_id.clone()));
 			}
+			if !committee.iter().any(|(member, _)| member == authority_id) {
+				return Err(UnknownAuthority(authority_id.clone()));
+			}
 			used.insert(authority_id.clone());
-			grant_votes += 1;
 		}
 
-		if grant_votes <= (authorities.len() * 2 / 3) {
+		if !is_quorum(used.iter(), committee) {
 			return Err(InsufficientQuorum);
 		}
+		if self.proposal_hash == Vote::<Block>::nil_hash() {
+			return Err(NilCertificate);
+		}
 
-		let digest = self.digest();
+		let digest = context.signing_digest::<Block>(self.digest());
 
 		for (voter, signature) in self.votes.iter() {
-			if !AuthorityPair::verify(signature, digest, voter) {
-				return Err(InvalidSignature(voter.clone()));
-			}
+			verify_signature(signature, digest, voter)?;
 		}
 		Ok(())
 	}
+
+	// Verify the QC against the authorities `resolve` returns for its view, so a QC formed
+	// before an authority set change is checked against the set which formed it.
+	pub fn verify_with_resolver<F>(
+		&self,
+		resolve: F,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(ViewNumber) -> AuthorityList,
+	{
+		let authorities = resolve(self.view);
+		self.verify_with(&voting_committee(&authorities, sampler, self.view), context)
+	}
+}
+
+/// Whether `signers` hold more than two thirds of the weight of `authorities`. Signers which are
+/// not in `authorities` or have a zero weight, such as one joining before it is staked, weigh
+/// nothing, and every signer is counted once.
+pub fn is_quorum<'a>(
+	signers: impl IntoIterator<Item = &'a AuthorityId>,
+	authorities: &AuthorityList,
+) -> bool {
+	let weight_of = |id: &AuthorityId| {
+		authorities
+			.iter()
+			.find(|(authority_id, _)| authority_id == id)
+			.map_or(0, |(_, weight)| *weight)
+	};
+	let total: u64 = authorities.iter().map(|(_, weight)| *weight).sum();
+	let signed: u64 = signers.into_iter().collect::<HashSet<_>>().into_iter().map(weight_of).sum();
+
+	signed as u128 * 3 > total as u128 * 2
+}
+
+/// Positional bitmap of the QC signers over the canonical authority order. Signers which are not
+/// in `authorities` can't be placed and are left out, see [`unknown_signers`].
+pub fn signer_bitfield<B: BlockT>(qc: &QC<B>, authorities: &AuthorityList) -> Vec<bool> {
+	authorities
+		.iter()
+		.map(|(authority_id, _)| qc.votes.iter().any(|(voter, _)| voter == authority_id))
+		.collect()
+}
+
+/// The QC signers which are not in `authorities`.
+pub fn unknown_signers<B: BlockT>(qc: &QC<B>, authorities: &AuthorityList) -> Vec<AuthorityId> {
+	qc.votes
+		.iter()
+		.filter(|(voter, _)| !authorities.iter().any(|(authority_id, _)| authority_id == voter))
+		.map(|(voter, _)| voter.clone())
+		.collect()
+}
+
+/// Whether `a` and `b` certify different proposals at the same view. A quorum only forms for
+/// one proposal of a view while more than two thirds of the authorities are honest, so
+/// conflicting QCs are proof of a safety violation.
+pub fn qcs_conflict<B: BlockT>(a: &QC<B>, b: &QC<B>) -> bool {
+	a.view == b.view && a.proposal_hash != b.proposal_hash
+}
+
+/// Hex encode a bitfield, the first bit is the most significant bit of the first byte.
+pub fn bitfield_hex(bits: &[bool]) -> String {
+	let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+	for (index, _) in bits.iter().enumerate().filter(|(_, bit)| **bit) {
+		bytes[index / 8] |= 0x80 >> (index % 8);
+	}
+
+	array_bytes::bytes2hex("0x", bytes)
 }
 
 impl<Block: BlockT> PartialEq for QC<Block> {
@@ -81,7 +242,8 @@ impl<Block: BlockT> PartialEq for QC<Block> {
 	}
 }
 
-#[derive(Debug, Copy, Clone, Encode, Decode)]
+#[derive(Debug, Copy, Clone, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(Block))]
 pub struct Payload<Block: BlockT> {
 	pub block_hash: Block::Hash,
 	pub block_number: <Block::Header as HeaderT>::Number,
@@ -95,7 +257,8 @@ impl<Block: BlockT> fmt::Display for Payload<Block> {
 }
 
 // Hotstuff Proposal
-#[derive(Debug, Clone, Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(Block))]
 pub struct Proposal<Block: BlockT> {
 	// QC of parent proposal.
 	pub qc: QC<Block>,
@@ -108,6 +271,16 @@ pub struct Proposal<Block: BlockT> {
 	pub author: AuthorityId,
 	// Signature of current block digest.
 	pub signature: Option<AuthoritySignature>,
+	// Opaque data of the proposer, such as its client version, covered by the signature.
+	pub metadata: Option<Vec<u8>>,
+	// When the proposer made the proposal, in milliseconds since the unix epoch, covered by the
+	// signature.
+	pub timestamp: Option<u64>,
+	// The vote of the author for this proposal, seeding the tally of the next leader. Not part
+	// of the digest, which it signs.
+	pub self_vote: Option<Box<Vote<Block>>>,
+	// The signed clocks of a quorum of the voters of the QC, or none, covered by the signature.
+	pub qc_timestamps: Vec<VoteTimestamp>,
 }
 
 impl<Block: BlockT> Proposal<Block> {
@@ -119,7 +292,61 @@ impl<Block: BlockT> Proposal<Block> {
 		author: AuthorityId,
 		signature: Option<AuthoritySignature>,
 	) -> Self {
-		Proposal { qc, tc, payload, view, author, signature }
+		Proposal {
+			qc,
+			tc,
+			payload,
+			view,
+			author,
+			signature,
+			metadata: None,
+			timestamp: None,
+			self_vote: None,
+			qc_timestamps: Vec::new(),
+		}
+	}
+
+	// Attach the proposer `metadata`, at most `MAX_PROPOSAL_METADATA` bytes.
+	pub fn with_metadata(mut self, metadata: Vec<u8>) -> Result<Self, HotstuffError> {
+		check_metadata(&metadata)?;
+		self.metadata = Some(metadata);
+		Ok(self)
+	}
+
+	// Stamp the proposal with the clock of the proposer, `timestamp` milliseconds since the unix
+	// epoch.
+	pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+		self.timestamp = Some(timestamp);
+		self
+	}
+
+	// Carry the signed clocks of the voters of the QC, which time the commit of its proposal.
+	pub fn with_qc_timestamps(mut self, timestamps: Vec<VoteTimestamp>) -> Self {
+		self.qc_timestamps = timestamps;
+		self
+	}
+
+	// The median of the clocks of the voters of the QC, the lower of the two middle clocks of an
+	// even quorum, so every node agrees on it. None without vote timestamps.
+	pub fn qc_median_timestamp(&self) -> Option<u64> {
+		let mut timestamps = self
+			.qc_timestamps
+			.iter()
+			.map(|timestamp| timestamp.timestamp)
+			.collect::<Vec<_>>();
+		timestamps.sort_unstable();
+		timestamps.get(timestamps.len().saturating_sub(1) / 2).copied()
+	}
+
+	// Verify the timestamp of the proposal is at most `max_skew` milliseconds from `now`, which
+	// limits the replay of old or future dated proposals. A proposal without a timestamp passes.
+	pub fn verify_timestamp(&self, now: u64, max_skew: u64) -> Result<(), HotstuffError> {
+		match self.timestamp {
+			Some(timestamp) if timestamp.abs_diff(now) > max_skew => {
+				Err(ProposalOutsideClockWindow(timestamp))
+			},
+			_ => Ok(()),
+		}
 	}
 
 	pub fn parent_hash(&self) -> Block::Hash {
@@ -131,69 +358,322 @@ impl<Block: BlockT> Proposal<Block> {
 		data.append(&mut self.payload.encode());
 		data.append(&mut self.view.encode());
 		data.append(&mut self.qc.proposal_hash.encode());
+		// The optional fields keep their tag, so one can't be read as another.
+		data.append(&mut self.metadata.encode());
+		data.append(&mut self.timestamp.encode());
+		data.append(&mut self.qc_timestamps.encode());
 
-		<<Block::Header as HeaderT>::Hashing as HashT>::hash_of(&data)
+		Hasher::<Block>::hash_of(&data)
 	}
 
 	pub fn verify(&self, authorities: &AuthorityList) -> Result<(), HotstuffError> {
+		self.verify_with(authorities, None, &SigningContext::default())
+	}
+
+	// Verify the proposal is for the view right after the one justified by its QC or TC, so a
+	// leader can't jump views with a stale justification. A proposal on the genesis QC without
+	// a TC is exempt, as a chain may start at any view.
+	pub fn verify_view(&self) -> Result<(), HotstuffError> {
+		if self.qc == QC::<Block>::default() && self.tc.is_none() {
+			return Ok(());
+		}
+
+		let justified = self.tc.as_ref().map_or(self.qc.view, |tc| tc.view.max(self.qc.view));
+		if self.view != justified + 1 {
+			return Err(MissingTimeoutJustification);
+		}
+		Ok(())
+	}
+
+	// Verify the chain of the proposal reaches `committed`, the committed proposal of
+	// `committed_view`, looking its ancestors up with `get`. An ancestor which isn't known yet
+	// ends the walk, as the check can't tell and the chain is synced before it's voted on.
+	pub fn verify_extends<F>(
+		&self,
+		committed: Block::Hash,
+		committed_view: ViewNumber,
+		get: F,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(Block::Hash) -> Result<Option<Proposal<Block>>, HotstuffError>,
+	{
+		if self.digest() == committed {
+			return Ok(());
+		}
+
+		// The view of a QC is the view of the proposal it certifies.
+		let (mut hash, mut view) = (self.parent_hash(), self.qc.view);
+		while hash != committed {
+			if view <= committed_view {
+				return Err(DoesNotExtendCommitted(self.digest().to_string()));
+			}
+			match get(hash)? {
+				Some(ancestor) => (hash, view) = (ancestor.parent_hash(), ancestor.qc.view),
+				None => break,
+			}
+		}
+		Ok(())
+	}
+
+	// Verify the proposal signed in `context`, its QC against the committee drawn by `sampler`
+	// for the QC view.
+	pub fn verify_with(
+		&self,
+		authorities: &AuthorityList,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
+		self.verify_resolved(|_| Cow::Borrowed(authorities), sampler, context)
+	}
+
+	// Verify the proposal against the authorities `resolve` returns for its view and for the
+	// views of its certificates, so a proposal made across an authority set change is checked
+	// against the set of each view.
+	pub fn verify_with_resolver<F>(
+		&self,
+		resolve: F,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(ViewNumber) -> AuthorityList,
+	{
+		self.verify_resolved(|view| Cow::Owned(resolve(view)), sampler, context)
+	}
+
+	fn verify_resolved<'a, F>(
+		&self,
+		resolve: F,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(ViewNumber) -> Cow<'a, AuthorityList>,
+	{
+		let authorities = resolve(self.view);
 		authorities
 			.iter()
 			.find(|authority| authority.0 == self.author)
 			.ok_or(HotstuffError::UnknownAuthority(self.author.to_owned()))?;
 
+		if let Some(metadata) = self.metadata.as_ref() {
+			check_metadata(metadata)?;
+		}
+		self.verify_view()?;
+
 		self.signature.as_ref().ok_or(NullSignature).and_then(|signature| {
-			if !AuthorityPair::verify(signature, self.digest(), &self.author) {
-				return Err(InvalidSignature(self.author.to_owned()));
-			}
+			let digest = context.signing_digest::<Block>(self.digest());
+			verify_signature(signature, digest, &self.author)?;
 			Ok(())
 		})?;
 
+		let committee = voting_committee(&authorities, sampler, self.view);
+		if let Some(vote) = self.committee_self_vote(&committee) {
+			if vote.voter != self.author
+				|| vote.view != self.view
+				|| vote.proposal_hash != self.digest()
+			{
+				return Err(InvalidSelfVote);
+			}
+			vote.verify_with(&committee, context)?;
+		}
+
 		if self.qc != QC::<Block>::default() {
-			self.qc.verify(authorities)?;
+			let authorities = resolve(self.qc.view);
+			let committee = voting_committee(&authorities, sampler, self.qc.view);
+			self.qc.verify_with(&committee, context)?;
+			self.verify_qc_timestamps(&committee, context)?;
+		} else if !self.qc_timestamps.is_empty() {
+			return Err(InsufficientQuorum);
+		}
+
+		if let Some(tc) = self.tc.as_ref() {
+			tc.verify_with(&resolve(tc.view), sampler, context)?;
 		}
 
 		Ok(())
 	}
+
+	// The vote of the author, when it's a member of the voting `committee` of the view. The vote
+	// of an author outside the committee isn't needed, it's neither verified nor counted.
+	pub fn committee_self_vote(&self, committee: &AuthorityList) -> Option<&Vote<Block>> {
+		if !committee.iter().any(|(id, _)| *id == self.author) {
+			return None;
+		}
+		self.self_vote.as_deref()
+	}
+
+	// Verify the vote timestamps are signed by distinct voters of the QC making up a quorum of
+	// its `committee`, so the leader can't pick a few clocks to skew their median.
+	fn verify_qc_timestamps(
+		&self,
+		committee: &AuthorityList,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
+		if self.qc_timestamps.is_empty() {
+			return Ok(());
+		}
+
+		let mut used = HashSet::<&AuthorityId>::new();
+		for timestamp in self.qc_timestamps.iter() {
+			if !used.insert(&timestamp.voter) {
+				return Err(AuthorityReuse(timestamp.voter.clone()));
+			}
+			if !self.qc.votes.iter().any(|(voter, _)| *voter == timestamp.voter) {
+				return Err(UnknownAuthority(timestamp.voter.clone()));
+			}
+		}
+		if !is_quorum(used.into_iter(), committee) {
+			return Err(InsufficientQuorum);
+		}
+
+		let digest = self.qc.digest();
+		self.qc_timestamps
+			.iter()
+			.try_for_each(|timestamp| timestamp.verify_with::<Block>(digest, context))
+	}
+}
+
+/// The clock of a voter when it voted, in milliseconds since the unix epoch. It's signed apart
+/// from the vote, so the votes of a QC keep signing the same digest.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, TypeInfo)]
+pub struct VoteTimestamp {
+	pub voter: AuthorityId,
+	pub timestamp: u64,
+	pub signature: AuthoritySignature,
+}
+
+impl VoteTimestamp {
+	// The digest signed for the `timestamp` of a vote signing `vote_digest`.
+	pub fn digest<Block: BlockT>(vote_digest: Block::Hash, timestamp: u64) -> Block::Hash {
+		let mut data = vote_digest.encode();
+		data.append(&mut timestamp.encode());
+
+		Hasher::<Block>::hash_of(&data)
+	}
+
+	// Verify the timestamp of a vote signing `vote_digest` was signed in `context`.
+	pub fn verify_with<Block: BlockT>(
+		&self,
+		vote_digest: Block::Hash,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
+		let digest =
+			context.signing_digest::<Block>(Self::digest::<Block>(vote_digest, self.timestamp));
+		verify_signature(&self.signature, digest, &self.voter)
+	}
 }
 
 // Vote for a Proposal
-#[derive(Debug, Clone, Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(Block))]
 pub struct Vote<Block: BlockT> {
 	pub proposal_hash: Block::Hash,
 	pub view: ViewNumber,
 	pub voter: AuthorityId,
 	pub signature: Option<AuthoritySignature>,
+	// The clock of the voter and its signature, when votes are timestamped.
+	pub timestamp: Option<(u64, AuthoritySignature)>,
+	// The highest QC of the voter, on a nil vote only.
+	pub high_qc: Option<QC<Block>>,
 }
 
 impl<Block: BlockT> Vote<Block> {
 	pub fn new(proposal_hash: Block::Hash, proposal_view: ViewNumber, voter: AuthorityId) -> Self {
-		Self { proposal_hash, view: proposal_view, voter, signature: None }
+		Self {
+			proposal_hash,
+			view: proposal_view,
+			voter,
+			signature: None,
+			timestamp: None,
+			high_qc: None,
+		}
+	}
+
+	/// The proposal hash of nil votes.
+	pub fn nil_hash() -> Block::Hash {
+		Hasher::<Block>::hash(NIL_PAYLOAD)
+	}
+
+	/// A vote for no block at the view of `timeout`, cast by a replica which saw no proposal of
+	/// the view before it timed out. It signs the digest of the timeout, so nil votes form a TC
+	/// along with the timeouts of the view and never a QC, and nil is never committed.
+	pub fn nil(timeout: Timeout<Block>) -> Self {
+		Self {
+			proposal_hash: Self::nil_hash(),
+			view: timeout.view,
+			voter: timeout.voter,
+			signature: timeout.signature,
+			timestamp: None,
+			high_qc: Some(timeout.high_qc),
+		}
+	}
+
+	pub fn is_nil(&self) -> bool {
+		self.proposal_hash == Self::nil_hash()
+	}
+
+	/// The timeout a nil vote stands for, none for a vote of a proposal.
+	pub fn as_timeout(&self) -> Option<Timeout<Block>> {
+		self.is_nil().then(|| Timeout {
+			high_qc: self.high_qc.clone().unwrap_or_default(),
+			view: self.view,
+			voter: self.voter.clone(),
+			signature: self.signature.clone(),
+		})
+	}
+
+	pub fn vote_timestamp(&self) -> Option<VoteTimestamp> {
+		self.timestamp.as_ref().map(|(timestamp, signature)| VoteTimestamp {
+			voter: self.voter.clone(),
+			timestamp: *timestamp,
+			signature: signature.clone(),
+		})
 	}
 
 	pub fn digest(&self) -> Block::Hash {
+		if let Some(timeout) = self.as_timeout() {
+			return timeout.digest();
+		}
+
 		let mut data = self.proposal_hash.encode();
 		data.append(&mut self.view.encode());
 
-		<<Block::Header as HeaderT>::Hashing as HashT>::hash_of(&data)
+		Hasher::<Block>::hash_of(&data)
 	}
 
 	pub fn verify(&self, authorities: &AuthorityList) -> Result<(), HotstuffError> {
+		self.verify_with(authorities, &SigningContext::default())
+	}
+
+	// Verify the vote signed in `context`. The high QC of a nil vote is verified with its
+	// timeout.
+	pub fn verify_with(
+		&self,
+		authorities: &AuthorityList,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
 		authorities
 			.iter()
 			.find(|authority| authority.0 == self.voter)
 			.ok_or(HotstuffError::UnknownAuthority(self.voter.to_owned()))?;
 
 		self.signature.as_ref().ok_or(NullSignature).and_then(|signature| {
-			if !AuthorityPair::verify(signature, self.digest(), &self.voter) {
-				return Err(InvalidSignature(self.voter.to_owned()));
-			}
+			let digest = context.signing_digest::<Block>(self.digest());
+			verify_signature(signature, digest, &self.voter)?;
 			Ok(())
-		})
+		})?;
+
+		match self.vote_timestamp() {
+			Some(timestamp) => timestamp.verify_with::<Block>(self.digest(), context),
+			None => Ok(()),
+		}
 	}
 }
 
 // Timeout notification
-#[derive(Debug, Clone, Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(Block))]
 pub struct Timeout<Block: BlockT> {
 	// The hightest QC of local node.
 	pub high_qc: QC<Block>,
@@ -208,69 +688,131 @@ impl<Block: BlockT> Timeout<Block> {
 		let mut data = self.view.encode();
 		data.append(&mut self.high_qc.view.encode());
 
-		<<Block::Header as HeaderT>::Hashing as HashT>::hash_of(&data)
+		Hasher::<Block>::hash_of(&data)
 	}
 
 	pub fn verify(&self, authorities: &AuthorityList) -> Result<(), HotstuffError> {
-		authorities
+		self.verify_with(authorities, None, &SigningContext::default())
+	}
+
+	// Verify the timeout signed in `context`, its high QC against the committee drawn by
+	// `sampler` for the QC view.
+	pub fn verify_with(
+		&self,
+		authorities: &AuthorityList,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
+		self.verify_with_resolver(|_| authorities.clone(), sampler, context)
+	}
+
+	// Verify the timeout against the authorities `resolve` returns for its view, and its high QC
+	// against those of the QC view, so a QC formed before an authority set change still
+	// justifies a timeout after it.
+	pub fn verify_with_resolver<F>(
+		&self,
+		resolve: F,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(ViewNumber) -> AuthorityList,
+	{
+		resolve(self.view)
 			.iter()
 			.find(|authority| authority.0 == self.voter)
 			.ok_or(HotstuffError::UnknownAuthority(self.voter.to_owned()))?;
 
 		self.signature.as_ref().ok_or(NullSignature).and_then(|signature| {
-			if !AuthorityPair::verify(signature, self.digest(), &self.voter) {
-				return Err(InvalidSignature(self.voter.to_owned()));
-			}
+			let digest = context.signing_digest::<Block>(self.digest());
+			verify_signature(signature, digest, &self.voter)?;
 			Ok(())
 		})?;
 
 		if self.high_qc != QC::<Block>::default() {
-			self.high_qc.verify(authorities)?;
+			self.high_qc.verify_with_resolver(resolve, sampler, context)?;
 		}
 		Ok(())
 	}
 }
 
-#[derive(Debug, Clone, Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(Block))]
 pub struct TC<Block: BlockT> {
 	pub view: ViewNumber,
+	// The signers, their signatures and the views of their high QCs.
 	pub votes: Vec<(AuthorityId, AuthoritySignature, ViewNumber)>,
+	// The highest high QC of the timeouts, which a proposal justified by the TC extends.
+	pub high_qc: QC<Block>,
 	pub _phantom: PhantomData<Block>,
 }
 
 impl<Block: BlockT> TC<Block> {
 	pub fn verify(&self, authorities: &AuthorityList) -> Result<(), HotstuffError> {
+		self.verify_with(authorities, None, &SigningContext::default())
+	}
+
+	// Verify the TC, its timeouts signed in `context` and its high QC against the committee drawn
+	// by `sampler` for the QC view.
+	pub fn verify_with(
+		&self,
+		authorities: &AuthorityList,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError> {
+		self.verify_with_resolver(|_| authorities.clone(), sampler, context)
+	}
+
+	// Verify the TC against the authorities `resolve` returns for its view, and its high QC
+	// against those of the QC view.
+	pub fn verify_with_resolver<F>(
+		&self,
+		resolve: F,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(ViewNumber) -> AuthorityList,
+	{
+		let authorities = &resolve(self.view);
 		let mut used = HashSet::<AuthorityId>::new();
-		let mut grant_votes = 0;
 
 		for (authority_id, _, _) in self.votes.iter() {
 			if used.contains(authority_id) {
 				return Err(AuthorityReuse(authority_id.clone()));
 			}
 			used.insert(authority_id.clone());
-			grant_votes += 1;
 		}
 
-		if grant_votes <= (authorities.len() * 2 / 3) {
-			return Err(InsufficientQuorum);
+		// The same quorum as a QC, or a TC could form without the stake to form a QC.
+		if !is_quorum(used.iter(), authorities) {
+			return Err(InsufficientTimeoutWeight);
 		}
 
 		for (voter, signature, view) in self.votes.iter() {
 			// TODO a better way to construct `Timeout`, then call `Timeout::digest()`
 			let mut data = self.view.encode();
 			data.append(&mut view.encode());
-			let digest = <<Block::Header as HeaderT>::Hashing as HashT>::hash_of(&data);
+			let digest = context.signing_digest::<Block>(Hasher::<Block>::hash_of(&data));
 
-			if !AuthorityPair::verify(signature, digest, voter) {
-				return Err(InvalidSignature(voter.clone()));
-			}
+			verify_signature(signature, digest, voter)?;
+		}
+
+		// An aggregator embedding a lower QC would rewind the chain of the next proposal.
+		let highest = self.votes.iter().map(|(_, _, view)| *view).max().unwrap_or_default();
+		if self.high_qc.view != highest {
+			return Err(TcHighQcMismatch);
+		}
+		if self.high_qc != QC::<Block>::default() {
+			self.high_qc.verify_with_resolver(resolve, sampler, context)?;
 		}
 
 		Ok(())
 	}
 }
 
-#[derive(Debug, Encode, Decode)]
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(B))]
 pub enum ConsensusMessage<B: BlockT> {
 	Propose(Proposal<B>),
 	Vote(Vote<B>),
@@ -278,11 +820,66 @@ pub enum ConsensusMessage<B: BlockT> {
 	TC(TC<B>),
 	SyncRequest(B::Hash, AuthorityId),
 	Phantom(PhantomData<B>),
+	// The signature schemes the sender can verify, sent to each peer as it connects.
+	Handshake(Vec<SignatureScheme>),
+}
+
+/// The gossip wire format, a consensus message tagged with the authority set it was sent for.
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[scale_info(skip_type_params(B))]
+pub struct GossipMessage<B: BlockT> {
+	pub set_id: SetId,
+	pub message: ConsensusMessage<B>,
 }
 
 impl<Block: BlockT> ConsensusMessage<Block> {
+	/// The kind, view and sender of the message, to log it.
+	pub fn summary(&self) -> String {
+		match self {
+			Self::Propose(proposal) => format!(
+				"proposal {} of view {} by {}",
+				proposal.digest(),
+				proposal.view,
+				proposal.author
+			),
+			Self::Vote(vote) => format!("vote of view {} by {}", vote.view, vote.voter),
+			Self::Timeout(timeout) => {
+				format!("timeout of view {} by {}", timeout.view, timeout.voter)
+			},
+			Self::TC(tc) => format!("TC of view {}", tc.view),
+			Self::SyncRequest(digest, requester) => {
+				format!("sync request of {} by {}", digest, requester)
+			},
+			Self::Phantom(_) => "phantom message".to_string(),
+			Self::Handshake(schemes) => format!("handshake of schemes {:?}", schemes),
+		}
+	}
+
+	/// The kind of the message, to label it in metrics.
+	pub fn kind(&self) -> &'static str {
+		match self {
+			Self::Propose(_) => "proposal",
+			Self::Vote(_) => "vote",
+			Self::Timeout(_) => "timeout",
+			Self::TC(_) => "tc",
+			Self::SyncRequest(..) => "sync_request",
+			Self::Phantom(_) => "phantom",
+			Self::Handshake(_) => "handshake",
+		}
+	}
+
+	/// The digest signed by the sender of a proposal, vote or timeout.
+	pub fn signed_digest(&self) -> Option<Block::Hash> {
+		match self {
+			Self::Propose(proposal) => Some(proposal.digest()),
+			Self::Vote(vote) => Some(vote.digest()),
+			Self::Timeout(timeout) => Some(timeout.digest()),
+			_ => None,
+		}
+	}
+
 	pub fn gossip_topic() -> Block::Hash {
 		// TODO maybe use Lazy then just call hash once.
-		<<Block::Header as HeaderT>::Hashing as HashT>::hash(b"hotstuff/consensus")
+		Hasher::<Block>::hash(b"hotstuff/consensus")
 	}
 }
@@ -0,0 +1,116 @@
+// Prometheus metrics of the voter.
+use prometheus_endpoint::{
+	exponential_buckets, register, Counter, CounterVec, Gauge, Histogram, HistogramOpts,
+	HistogramVec, Opts, PrometheusError, Registry, U64,
+};
+use sp_runtime::traits::Block as BlockT;
+
+use crate::{message::QC, primitives::ViewNumber};
+
+#[cfg(test)]
+#[path = "tests/metrics_tests.rs"]
+pub mod metrics_tests;
+
+pub struct Metrics {
+	// The votes of each QC, above the quorum when some were redundant.
+	qc_votes: Histogram,
+	// The view of the last QC observed, as the QC of a view is seen again in each proposal.
+	last_qc_view: ViewNumber,
+	// The proposals of the node which carried no block.
+	empty_proposals: Counter<U64>,
+	// The size of each message sent by kind, encoded and on the wire, and whether it was
+	// compressed. An uncompressed message is as large on the wire as encoded.
+	message_bytes: HistogramVec,
+	// Whether the node sheds messages as its verification pool is overloaded, and the messages
+	// shed by kind.
+	overloaded: Gauge<U64>,
+	shed_messages: CounterVec<U64>,
+}
+
+impl Metrics {
+	pub fn register(registry: &Registry) -> Result<Self, PrometheusError> {
+		Ok(Self {
+			qc_votes: register(
+				Histogram::with_opts(
+					HistogramOpts::new(
+						"substrate_hotstuff_qc_votes",
+						"Number of votes each QC contained",
+					)
+					.buckets(exponential_buckets(1.0, 2.0, 10)?),
+				)?,
+				registry,
+			)?,
+			last_qc_view: 0,
+			empty_proposals: register(
+				Counter::new(
+					"substrate_hotstuff_empty_proposals",
+					"Number of proposals of the node which carried no block",
+				)?,
+				registry,
+			)?,
+			message_bytes: register(
+				HistogramVec::new(
+					HistogramOpts::new(
+						"substrate_hotstuff_message_bytes",
+						"Size in bytes of the messages sent, before and after compression",
+					)
+					.buckets(exponential_buckets(64.0, 2.0, 14)?),
+					&["kind", "stage", "compressed"],
+				)?,
+				registry,
+			)?,
+			overloaded: register(
+				Gauge::new(
+					"substrate_hotstuff_overloaded",
+					"Whether the node sheds messages as its verification pool is overloaded",
+				)?,
+				registry,
+			)?,
+			shed_messages: register(
+				CounterVec::new(
+					Opts::new(
+						"substrate_hotstuff_shed_messages",
+						"Number of messages shed while the verification pool was overloaded",
+					),
+					&["kind"],
+				)?,
+				registry,
+			)?,
+		})
+	}
+
+	// Observe the votes of `qc` if its view isn't observed yet.
+	pub fn observe_qc<B: BlockT>(&mut self, qc: &QC<B>) {
+		if qc.view > self.last_qc_view {
+			self.last_qc_view = qc.view;
+			self.qc_votes.observe(qc.votes.len() as f64);
+		}
+	}
+
+	pub fn observe_empty_proposal(&self) {
+		self.empty_proposals.inc();
+	}
+
+	// Observe a message of `kind` sent `encoded` bytes long, and `compressed` bytes long on the
+	// wire when it was compressed.
+	pub fn observe_message_size(&self, kind: &str, encoded: usize, compressed: Option<usize>) {
+		let (sent, marker) = match compressed {
+			Some(size) => (size, "true"),
+			None => (encoded, "false"),
+		};
+		self.message_bytes
+			.with_label_values(&[kind, "encoded", marker])
+			.observe(encoded as f64);
+		self.message_bytes
+			.with_label_values(&[kind, "sent", marker])
+			.observe(sent as f64);
+	}
+
+	pub fn set_overloaded(&self, overloaded: bool) {
+		self.overloaded.set(overloaded as u64);
+	}
+
+	pub fn observe_shed(&self, kind: &str) {
+		self.shed_messages.with_label_values(&[kind]).inc();
+	}
+}
@@ -1,9 +1,15 @@
+#[cfg(test)]
+use std::{collections::BTreeMap, time::Duration};
 use std::{
+	collections::{BTreeSet, HashMap},
 	marker::PhantomData,
 	pin::Pin,
 	sync::Arc,
 	task::{Context, Poll},
+	time::Instant,
 };
+#[cfg(test)]
+use tokio::time::Sleep;
 
 use futures::prelude::*;
 use parking_lot::Mutex;
@@ -14,13 +20,19 @@ use sc_network::{
 use sc_network_gossip::{
 	GossipEngine, MessageIntent, Network as GossipNetwork, ValidationResult, ValidatorContext,
 };
-use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver};
-use sp_core::Decode;
+use sc_utils::mpsc::{tracing_unbounded, TracingUnboundedReceiver, TracingUnboundedSender};
+use sp_core::{hashing::blake2_256, Decode, Encode};
 use sp_runtime::traits::{Block as BlockT, Hash as HashT, Header as HeaderT, NumberFor};
 
 use hotstuff_primitives::RoundNumber;
 
-use crate::{import::PeerReport, message::ConsensusMessage, primitives::ViewNumber};
+use crate::{
+	import::PeerReport,
+	message::{ConsensusMessage, GossipMessage, QC},
+	primitives::{SetId, ViewNumber},
+	reputation::{Misbehavior, PeerScores, ReputationConfig, SyncRequestLimiter},
+	scheme::{SchemeConfig, SchemeNegotiation},
+};
 
 /// A handle to the network.
 ///
@@ -62,82 +74,192 @@ where
 {
 }
 
+#[cfg(test)]
+#[path = "tests/network_tests.rs"]
+pub mod network_tests;
+
 pub(super) struct GossipValidator<Block: BlockT> {
 	view: parking_lot::RwLock<ViewNumber>,
+	// The views and proposal hashes of the QCs known locally, whose votes aren't forwarded.
+	certified: parking_lot::RwLock<BTreeSet<(ViewNumber, Block::Hash)>>,
+	// The proposals asked for by sync requests, with the view they were first asked at.
+	requested: parking_lot::RwLock<HashMap<Block::Hash, ViewNumber>>,
+	// The sync requests of the peers, to send the proposals back to their senders.
+	sync_requests: Mutex<SyncRequestLimiter<Block::Hash>>,
+	scores: Mutex<PeerScores>,
+	schemes: Mutex<SchemeNegotiation>,
+	report_sender: TracingUnboundedSender<PeerReport>,
 	_phantom: Option<PhantomData<Block>>,
 }
 
+// Identifies a consensus message, to find who sent it.
+pub(crate) fn message_key<B: BlockT>(message: &ConsensusMessage<B>) -> [u8; 32] {
+	blake2_256(&message.encode())
+}
+
 impl<Block: BlockT> GossipValidator<Block> {
 	/// Create a new gossip-validator. The current set is initialized to 0. If
 	/// `catch_up_enabled` is set to false then the validator will not issue any
 	/// catch up requests (useful e.g. when running just the hotstuff observer).
-	pub(super) fn new() -> (GossipValidator<Block>, TracingUnboundedReceiver<PeerReport>) {
-		let (_tx, rx) = tracing_unbounded("mpsc_hotstuff_gossip_validator", 100_000);
-		let val = GossipValidator { _phantom: None, view: parking_lot::RwLock::new(0) };
+	pub(super) fn new(
+		reputation: ReputationConfig,
+		schemes: SchemeConfig,
+	) -> (GossipValidator<Block>, TracingUnboundedReceiver<PeerReport>) {
+		let (tx, rx) = tracing_unbounded("mpsc_hotstuff_gossip_validator", 100_000);
+		let val = GossipValidator {
+			_phantom: None,
+			view: parking_lot::RwLock::new(0),
+			certified: parking_lot::RwLock::new(BTreeSet::new()),
+			requested: parking_lot::RwLock::new(HashMap::new()),
+			sync_requests: Mutex::new(SyncRequestLimiter::default()),
+			scores: Mutex::new(PeerScores::new(reputation)),
+			schemes: Mutex::new(SchemeNegotiation::new(schemes)),
+			report_sender: tx,
+		};
 
 		(val, rx)
 	}
 
+	fn report(&self, who: PeerId, misbehavior: Misbehavior) {
+		if let Some(cost_benefit) = self.scores.lock().report(who, misbehavior, Instant::now()) {
+			let _ = self.report_sender.unbounded_send(PeerReport { who, cost_benefit });
+		}
+	}
+
+	// Blame the peer which sent the message with `key`.
+	pub(super) fn report_sender(&self, key: &[u8; 32], misbehavior: Misbehavior) {
+		let report = self.scores.lock().report_sender(key, misbehavior, Instant::now());
+		if let Some((who, cost_benefit)) = report {
+			let _ = self.report_sender.unbounded_send(PeerReport { who, cost_benefit });
+		}
+	}
+
 	pub fn set_view(&self, new_view: ViewNumber) {
 		let mut view = self.view.write();
-		*view = new_view
+		*view = new_view;
+
+		// Votes of older views expire anyway.
+		let oldest = new_view.saturating_sub(1);
+		let mut certified = self.certified.write();
+		while certified.first().map_or(false, |(view, _)| *view < oldest) {
+			certified.pop_first();
+		}
+		self.requested.write().retain(|_, view| *view >= oldest);
+	}
+
+	pub fn note_certified(&self, view: ViewNumber, proposal_hash: Block::Hash) {
+		self.certified.write().insert((view, proposal_hash));
+	}
+
+	fn is_certified(&self, view: ViewNumber, proposal_hash: &Block::Hash) -> bool {
+		self.certified.read().contains(&(view, *proposal_hash))
 	}
 
 	pub fn get_view(&self) -> ViewNumber {
 		let view = self.view.read();
 		*view
 	}
+
+	// The peers which asked for the proposal `digest`, each returned once.
+	pub(super) fn take_sync_requesters(&self, digest: &Block::Hash) -> Vec<PeerId> {
+		self.sync_requests.lock().take_requesters(digest)
+	}
+
+	// Note a sync request for the proposal `digest`, at the current view unless it already was.
+	fn note_requested(&self, digest: Block::Hash) {
+		let view = self.get_view();
+		self.requested.write().entry(digest).or_insert(view);
+	}
+
+	// The view after which a message is stale. A sync request of the node, and the proposal sent
+	// back for it, are as old as the request, so a node catching up gets the old proposals it's
+	// missing.
+	fn message_view(&self, message: &ConsensusMessage<Block>) -> ViewNumber {
+		match message {
+			ConsensusMessage::Propose(proposal) => {
+				let requested = self.requested.read().get(&proposal.digest()).copied();
+				requested.map_or(proposal.view, |view| view.max(proposal.view))
+			},
+			ConsensusMessage::Vote(vote) => vote.view,
+			ConsensusMessage::Timeout(timeout) => timeout.view,
+			ConsensusMessage::TC(tc) => tc.view,
+			ConsensusMessage::SyncRequest(digest, _) => {
+				self.requested.read().get(digest).copied().unwrap_or_default()
+			},
+			_ => 0,
+		}
+	}
 }
 
 impl<B: BlockT> sc_network_gossip::Validator<B> for GossipValidator<B> {
-	/// New peer is connected.
-	fn new_peer(&self, _context: &mut dyn ValidatorContext<B>, _who: &PeerId, _role: ObservedRole) {
-		// println!("~~~~【GossipValidator】:: new_peer PeerId:{}", who);
-		// context.send_message(who, Vec::from("hotstuff send back"));
+	/// New peer is connected, it's told the signature schemes the node can verify.
+	fn new_peer(&self, context: &mut dyn ValidatorContext<B>, who: &PeerId, _role: ObservedRole) {
+		let schemes = self.schemes.lock().advertised().to_vec();
+		let handshake =
+			GossipMessage::<B> { set_id: 0, message: ConsensusMessage::Handshake(schemes) };
+		context.send_message(who, handshake.encode());
 	}
 
 	/// New connection is dropped.
-	fn peer_disconnected(&self, _context: &mut dyn ValidatorContext<B>, _who: &PeerId) {
-		println!("【GossipValidator】:: peer_disconnected PeerId:{}", _who);
+	fn peer_disconnected(&self, _context: &mut dyn ValidatorContext<B>, who: &PeerId) {
+		println!("【GossipValidator】:: peer_disconnected PeerId:{}", who);
+		self.schemes.lock().forget_peer(who);
+		self.sync_requests.lock().forget_peer(who);
 	}
 
 	/// Validate consensus message.
 	fn validate(
 		&self,
 		_context: &mut dyn ValidatorContext<B>,
-		_sender: &PeerId,
+		sender: &PeerId,
 		mut data: &[u8],
 	) -> ValidationResult<B::Hash> {
-		if let Ok(message) = ConsensusMessage::<B>::decode(&mut data) {
-			let message_vew = match message {
-				ConsensusMessage::Propose(proposal) => proposal.view,
-				ConsensusMessage::Vote(vote) => vote.view,
-				ConsensusMessage::Timeout(timeout) => timeout.view,
-				ConsensusMessage::TC(tc) => tc.view,
-				_ => 0,
-			};
+		if let Ok(GossipMessage { message, .. }) = GossipMessage::<B>::decode(&mut data) {
+			// The handshake is for the validator only.
+			if let ConsensusMessage::Handshake(schemes) = message {
+				self.schemes.lock().note_peer(*sender, schemes);
+				return ValidationResult::Discard;
+			}
+			if !self.schemes.lock().accepts(sender) {
+				return ValidationResult::Discard;
+			}
+
+			let topic = ConsensusMessage::<B>::gossip_topic();
+			// A sync request isn't signed, it's answered to its sender only and not propagated, so
+			// it can't make the network send the proposals around again.
+			if let ConsensusMessage::SyncRequest(digest, _) = &message {
+				if !self.sync_requests.lock().admit(*sender, *digest, Instant::now()) {
+					self.report(*sender, Misbehavior::ExcessiveSyncRequest);
+					return ValidationResult::Discard;
+				}
+				return ValidationResult::ProcessAndDiscard(topic);
+			}
+			let message_vew = self.message_view(&message);
 
 			let current_view = self.get_view();
 
 			if current_view > 1 && message_vew < current_view - 1 {
+				self.report(*sender, Misbehavior::StaleMessage);
 				return ValidationResult::Discard;
 			}
-			return ValidationResult::ProcessAndKeep(ConsensusMessage::<B>::gossip_topic());
+			self.scores.lock().record_sender(message_key(&message), *sender);
+			// A proposal sent back for a sync request of the node is of no use to the others.
+			if let ConsensusMessage::Propose(proposal) = &message {
+				if self.requested.read().contains_key(&proposal.digest()) {
+					return ValidationResult::ProcessAndDiscard(topic);
+				}
+			}
+			return ValidationResult::ProcessAndKeep(topic);
 		}
+		self.report(*sender, Misbehavior::MalformedMessage);
 		ValidationResult::Discard
 	}
 
 	/// Produce a closure for validating messages on a given topic.
 	fn message_expired<'a>(&'a self) -> Box<dyn FnMut(B::Hash, &[u8]) -> bool + 'a> {
 		Box::new(move |_topic, mut data| {
-			if let Ok(message) = ConsensusMessage::<B>::decode(&mut data) {
-				let message_vew = match message {
-					ConsensusMessage::Propose(proposal) => proposal.view,
-					ConsensusMessage::Vote(vote) => vote.view,
-					ConsensusMessage::Timeout(timeout) => timeout.view,
-					ConsensusMessage::TC(tc) => tc.view,
-					_ => 0,
-				};
+			if let Ok(GossipMessage { message, .. }) = GossipMessage::<B>::decode(&mut data) {
+				let message_vew = self.message_view(&message);
 
 				let current_view = self.get_view();
 				if current_view >= 1 && message_vew < current_view - 1 {
@@ -158,14 +280,19 @@ impl<B: BlockT> sc_network_gossip::Validator<B> for GossipValidator<B> {
 				return false;
 			}
 
-			if let Ok(message) = ConsensusMessage::<B>::decode(&mut data) {
-				let message_vew = match message {
-					ConsensusMessage::Propose(proposal) => proposal.view,
-					ConsensusMessage::Vote(vote) => vote.view,
-					ConsensusMessage::Timeout(timeout) => timeout.view,
-					ConsensusMessage::TC(tc) => tc.view,
-					_ => 0,
-				};
+			if let Ok(GossipMessage { message, .. }) = GossipMessage::<B>::decode(&mut data) {
+				match &message {
+					// Whoever needs the votes of a known QC gets the QC with the next proposal.
+					ConsensusMessage::Vote(vote)
+						if self.is_certified(vote.view, &vote.proposal_hash) =>
+					{
+						return false
+					},
+					// A request of the node itself, so the proposal sent back isn't discarded as stale.
+					ConsensusMessage::SyncRequest(digest, _) => self.note_requested(*digest),
+					_ => {},
+				}
+				let message_vew = self.message_view(&message);
 
 				let current_view = self.get_view();
 				if current_view >= 1 && message_vew < current_view - 1 {
@@ -185,10 +312,10 @@ pub struct HotstuffNetworkBridge<B: BlockT, N: Network<B>, S: Syncing<B>> {
 	pub sync: S,
 	pub gossip_engine: Arc<Mutex<GossipEngine<B>>>,
 	gossip_validator: Arc<GossipValidator<B>>,
+	// Peers to report to the network, from the gossip validator.
+	report_stream: Arc<Mutex<TracingUnboundedReceiver<PeerReport>>>,
 }
 
-pub type SetId = u64;
-
 /// Create a unique topic for a round and set-id combo.
 #[allow(unused)]
 pub(crate) fn round_topic<B: BlockT>(round: RoundNumber, set_id: SetId) -> B::Hash {
@@ -202,8 +329,14 @@ impl<B: BlockT, N: Network<B>, S: Syncing<B>> HotstuffNetworkBridge<B, N, S> {
 	/// handle.
 	/// On creation it will register previous rounds' votes with the gossip
 	/// service taken from the VoterSetState.
-	pub fn new(service: N, sync: S, protocol_name: ProtocolName) -> Self {
-		let (validator, _report_stream) = GossipValidator::new();
+	pub fn new(
+		service: N,
+		sync: S,
+		protocol_name: ProtocolName,
+		reputation: ReputationConfig,
+		schemes: SchemeConfig,
+	) -> Self {
+		let (validator, report_stream) = GossipValidator::new(reputation, schemes);
 
 		let validator = Arc::new(validator);
 		let gossip_engine = Arc::new(Mutex::new(GossipEngine::new(
@@ -214,22 +347,55 @@ impl<B: BlockT, N: Network<B>, S: Syncing<B>> HotstuffNetworkBridge<B, N, S> {
 			None,
 		)));
 
-		HotstuffNetworkBridge { service, sync, gossip_engine, gossip_validator: validator.clone() }
+		HotstuffNetworkBridge {
+			service,
+			sync,
+			gossip_engine,
+			gossip_validator: validator.clone(),
+			report_stream: Arc::new(Mutex::new(report_stream)),
+		}
 	}
 
 	pub fn set_view(&self, view: ViewNumber) {
 		self.gossip_validator.set_view(view)
 	}
 
+	/// Stop forwarding the votes certified by `qc`. They are still received.
+	pub fn note_certified(&self, qc: &QC<B>) {
+		self.gossip_validator.note_certified(qc.view, qc.proposal_hash)
+	}
+
+	/// Messages dropped for coming from peers without a signature scheme in common.
+	pub fn incompatible_messages(&self) -> u64 {
+		self.gossip_validator.schemes.lock().dropped()
+	}
+
 	pub fn local_peer_id(&self) -> PeerId {
 		self.service.local_peer_id()
 	}
+
+	/// The peers which asked for the proposal `digest` since it was last sent back.
+	pub fn take_sync_requesters(&self, digest: &B::Hash) -> Vec<PeerId> {
+		self.gossip_validator.take_sync_requesters(digest)
+	}
+
+	/// Lower the reputation of the peer which sent `message`, whose signature is invalid.
+	pub fn report_invalid_signature(&self, message: &ConsensusMessage<B>) {
+		self.gossip_validator
+			.report_sender(&message_key(message), Misbehavior::InvalidSignature)
+	}
 }
 
 impl<B: BlockT, N: Network<B>, S: Syncing<B>> Future for HotstuffNetworkBridge<B, N, S> {
 	type Output = ();
 
 	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		while let Poll::Ready(Some(PeerReport { who, cost_benefit })) =
+			self.report_stream.lock().poll_next_unpin(cx)
+		{
+			self.gossip_engine.lock().report(who, cost_benefit);
+		}
+
 		match self.gossip_engine.lock().poll_unpin(cx) {
 			Poll::Ready(()) => return Poll::Ready(()),
 			Poll::Pending => {},
@@ -238,3 +404,115 @@ impl<B: BlockT, N: Network<B>, S: Syncing<B>> Future for HotstuffNetworkBridge<B
 		Poll::Pending
 	}
 }
+
+// A splitmix64 generator, so the simulated network behaves the same under a seed on every run.
+#[cfg(test)]
+struct SeededRng(u64);
+
+#[cfg(test)]
+impl SeededRng {
+	fn next_u64(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		z ^ (z >> 31)
+	}
+
+	// A duration up to `max`, included.
+	fn duration_up_to(&mut self, max: Duration) -> Duration {
+		let nanos = max.as_nanos() as u64;
+		Duration::from_nanos(if nanos == 0 { 0 } else { self.next_u64() % (nanos + 1) })
+	}
+}
+
+/// Holds the messages read from gossip back from the worker for a simulated network delay, so
+/// tests can run the voters over a slow network. The jitter on top of the delay is drawn from a
+/// seeded generator: a seed delays the n-th message received by the same amount on every run.
+/// The messages relayed by a peer with a link of its own are delayed by the latency of the link.
+/// A link is keyed on the peer the gossip engine received a message from, not on its author: a
+/// message another peer relayed first takes the link of that peer, so a slow link only delays
+/// what reaches the voter straight from the slow peer.
+#[cfg(test)]
+pub(crate) struct SimulatedDelivery<M> {
+	delay: Duration,
+	jitter: Duration,
+	// The delay of the link from each peer, over the default delay.
+	links: HashMap<PeerId, Duration>,
+	rng: SeededRng,
+	// The messages held, by release time then arrival.
+	held: BTreeMap<(tokio::time::Instant, u64), M>,
+	arrivals: u64,
+	timer: Option<Pin<Box<Sleep>>>,
+}
+
+#[cfg(test)]
+impl<M> SimulatedDelivery<M> {
+	pub fn new(delay: Duration, seed: u64) -> Self {
+		Self {
+			delay,
+			jitter: Duration::ZERO,
+			links: HashMap::new(),
+			rng: SeededRng(seed),
+			held: BTreeMap::new(),
+			arrivals: 0,
+			timer: None,
+		}
+	}
+
+	/// Delay each message by up to `jitter` more.
+	pub fn with_jitter(mut self, jitter: Duration) -> Self {
+		self.jitter = jitter;
+		self
+	}
+
+	/// Delay the messages relayed by `peer` by `delay` rather than the default delay. Messages
+	/// authored by `peer` but relayed by another one keep the delay of the relay.
+	pub fn with_link_delay(mut self, peer: PeerId, delay: Duration) -> Self {
+		self.links.insert(peer, delay);
+		self
+	}
+
+	/// Release each message at a random time within `window` of its arrival, so the messages
+	/// arriving within a window of each other reach the worker in an order drawn from `seed`.
+	pub fn reordering(window: Duration, seed: u64) -> Self {
+		Self::new(Duration::ZERO, seed).with_jitter(window)
+	}
+
+	/// Hold `message`, relayed by `sender` if known, for the delay of its link.
+	pub fn hold(&mut self, message: M, sender: Option<&PeerId>, now: tokio::time::Instant) {
+		let delay = sender.and_then(|peer| self.links.get(peer)).copied().unwrap_or(self.delay);
+		let release = now + delay + self.rng.duration_up_to(self.jitter);
+		self.held.insert((release, self.arrivals), message);
+		self.arrivals += 1;
+	}
+
+	/// The first message due at `now`.
+	pub fn release(&mut self, now: tokio::time::Instant) -> Option<M> {
+		let (&key, _) = self.held.first_key_value().filter(|((release, _), _)| *release <= now)?;
+		self.held.remove(&key)
+	}
+
+	/// Resolves to the next message once it's due.
+	pub fn poll_release(&mut self, cx: &mut Context<'_>) -> Poll<M> {
+		loop {
+			if let Some(message) = self.release(tokio::time::Instant::now()) {
+				return Poll::Ready(message);
+			}
+			let Some(&(release, _)) = self.held.keys().next() else { return Poll::Pending };
+
+			let timer =
+				self.timer.get_or_insert_with(|| Box::pin(tokio::time::sleep_until(release)));
+			if timer.deadline() != release {
+				timer.as_mut().reset(release);
+			}
+			if timer.as_mut().poll(cx).is_pending() {
+				return Poll::Pending;
+			}
+		}
+	}
+
+	pub fn held(&self) -> usize {
+		self.held.len()
+	}
+}
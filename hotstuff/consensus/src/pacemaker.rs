@@ -0,0 +1,51 @@
+// What the pacemaker of the voter waits for, to diagnose why a node keeps timing out.
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::primitives::ViewNumber;
+
+/// The pacemaker of the voter.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PacemakerState {
+	pub view: ViewNumber,
+	/// Milliseconds the view waits before timing out.
+	pub timeout: u64,
+	/// Local timeouts in a row since a QC last advanced the view.
+	pub consecutive_timeouts: u64,
+	/// Milliseconds left until the view times out.
+	pub remaining: u64,
+}
+
+/// The pacemaker state published by the voter, cheap to clone.
+#[derive(Clone)]
+pub struct SharedPacemaker {
+	// The last published state and when its view times out.
+	inner: Arc<RwLock<(PacemakerState, Instant)>>,
+}
+
+impl Default for SharedPacemaker {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl SharedPacemaker {
+	pub fn new() -> Self {
+		Self { inner: Arc::new(RwLock::new((PacemakerState::default(), Instant::now()))) }
+	}
+
+	pub(crate) fn publish(&self, state: PacemakerState, deadline: Instant) {
+		*self.inner.write() = (state, deadline);
+	}
+
+	/// The last published state, with the time left until its deadline.
+	pub fn state(&self) -> PacemakerState {
+		let (state, deadline) = self.inner.read().clone();
+		let remaining = deadline.saturating_duration_since(Instant::now());
+		PacemakerState { remaining: remaining.as_millis() as u64, ..state }
+	}
+}
@@ -0,0 +1,93 @@
+// How often each authority votes in the views it may vote in, a reliability signal for governance.
+use std::{
+	collections::{BTreeMap, HashMap},
+	sync::Arc,
+};
+
+use parking_lot::RwLock;
+
+use hotstuff_primitives::{AuthorityId, AuthorityList};
+
+use crate::primitives::ViewNumber;
+
+pub type SharedVoteParticipation = Arc<RwLock<VoteParticipation>>;
+
+// Views measured by default.
+pub const DEFAULT_PARTICIPATION_WINDOW: usize = 100;
+
+/// The votes of each authority over the last views which received some.
+#[derive(Debug, Clone)]
+pub struct VoteParticipation {
+	window: usize,
+	// The authorities eligible to vote at each view, and whether they voted.
+	views: BTreeMap<ViewNumber, HashMap<AuthorityId, bool>>,
+}
+
+impl Default for VoteParticipation {
+	fn default() -> Self {
+		Self::new(DEFAULT_PARTICIPATION_WINDOW)
+	}
+}
+
+impl VoteParticipation {
+	pub fn new(window: usize) -> Self {
+		Self { window, views: BTreeMap::new() }
+	}
+
+	pub fn shared() -> SharedVoteParticipation {
+		Arc::new(RwLock::new(Self::default()))
+	}
+
+	/// Measure the last `window` views.
+	pub fn set_window(&mut self, window: usize) {
+		self.window = window;
+		self.prune();
+	}
+
+	/// Note the `voters` of `view`, at which the `eligible` authorities could vote. The
+	/// authorities eligible at a view are fixed by its first record, so an authority joining
+	/// the set is only measured from the views after it joined.
+	pub fn record<'a>(
+		&mut self,
+		view: ViewNumber,
+		eligible: &AuthorityList,
+		voters: impl IntoIterator<Item = &'a AuthorityId>,
+	) {
+		let full = self.views.len() >= self.window;
+		if full && self.views.keys().next().map_or(true, |oldest| view < *oldest) {
+			return;
+		}
+
+		let votes = self
+			.views
+			.entry(view)
+			.or_insert_with(|| eligible.iter().map(|(id, _)| (id.clone(), false)).collect());
+		for voter in voters {
+			if let Some(voted) = votes.get_mut(voter) {
+				*voted = true;
+			}
+		}
+		self.prune();
+	}
+
+	/// The share of the views each authority was eligible at which it voted in.
+	pub fn participation(&self) -> Vec<(AuthorityId, f64)> {
+		let mut counts = BTreeMap::<&AuthorityId, (u32, u32)>::new();
+		for (id, voted) in self.views.values().flatten() {
+			let (votes, eligible) = counts.entry(id).or_default();
+			*votes += u32::from(*voted);
+			*eligible += 1;
+		}
+
+		counts
+			.into_iter()
+			.map(|(id, (votes, eligible))| (id.clone(), f64::from(votes) / f64::from(eligible)))
+			.collect()
+	}
+
+	fn prune(&mut self) {
+		while self.views.len() > self.window {
+			self.views.pop_first();
+		}
+	}
+}
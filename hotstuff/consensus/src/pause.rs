@@ -0,0 +1,42 @@
+// Pausing the production of signed messages, e.g. for a coordinated upgrade.
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Pauses and resumes the voter without restarting it, cheap to clone. A paused voter keeps
+/// receiving and verifying messages to stay in sync, but signs no proposal, vote or timeout,
+/// and its timer is stopped so no view times out meanwhile.
+#[derive(Clone)]
+pub struct PauseHandle {
+	tx: Arc<watch::Sender<bool>>,
+}
+
+impl Default for PauseHandle {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl PauseHandle {
+	pub fn new() -> Self {
+		let (tx, _) = watch::channel(false);
+		Self { tx: Arc::new(tx) }
+	}
+
+	pub fn pause(&self) {
+		self.tx.send_replace(true);
+	}
+
+	/// Resume at the current view of the voter.
+	pub fn resume(&self) {
+		self.tx.send_replace(false);
+	}
+
+	pub fn is_paused(&self) -> bool {
+		*self.tx.borrow()
+	}
+
+	pub(crate) fn subscribe(&self) -> watch::Receiver<bool> {
+		self.tx.subscribe()
+	}
+}
@@ -0,0 +1,153 @@
+// The transaction pool as seen by the voter, to notice a leader which keeps proposing no block
+// while transactions wait, such as when the block authoring isn't built on the pool, and to pace
+// the proposals of the leader by how full the pool is.
+use std::{
+	fmt,
+	sync::{Arc, Weak},
+	time::Duration,
+};
+
+use log::{info, warn};
+
+#[cfg(test)]
+#[path = "tests/pool_tests.rs"]
+pub mod pool_tests;
+
+// Consecutive empty proposals with transactions waiting before the node warns.
+pub const EMPTY_PROPOSAL_THRESHOLD: u32 = 10;
+
+// How often a leader waiting for the pool to fill checks it again.
+pub const PACING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The number of transactions ready in the pool, such as `pool.status().ready`, none while the
+/// pool is unavailable.
+#[derive(Clone)]
+pub struct PoolStatus(Arc<dyn Fn() -> Option<usize> + Send + Sync>);
+
+impl PoolStatus {
+	pub fn new(ready: impl Fn() -> usize + Send + Sync + 'static) -> Self {
+		Self(Arc::new(move || Some(ready())))
+	}
+
+	/// The status of a pool which may be unavailable at times, such as while it restarts.
+	pub fn fallible(ready: impl Fn() -> Option<usize> + Send + Sync + 'static) -> Self {
+		Self(Arc::new(ready))
+	}
+
+	/// The status of a pool the voter doesn't keep alive, unavailable once it's dropped.
+	pub fn from_weak<P: Send + Sync + 'static>(
+		pool: Weak<P>,
+		ready: impl Fn(&P) -> usize + Send + Sync + 'static,
+	) -> Self {
+		Self::fallible(move || pool.upgrade().map(|pool| ready(&pool)))
+	}
+
+	pub fn ready(&self) -> Option<usize> {
+		(self.0)()
+	}
+}
+
+impl fmt::Debug for PoolStatus {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("PoolStatus").field(&self.ready()).finish()
+	}
+}
+
+/// Counts the empty proposals of the node, and warns when too many follow each other while the
+/// pool has transactions ready. An empty proposal while the pool is empty too is expected, and an
+/// unavailable pool is taken as empty.
+pub struct EmptyProposalMonitor {
+	pool: Option<PoolStatus>,
+	pool_unavailable: bool,
+	threshold: u32,
+	// Empty proposals since the last one with a block or with an empty pool.
+	consecutive: u32,
+	empty: u64,
+}
+
+impl EmptyProposalMonitor {
+	pub fn new(pool: Option<PoolStatus>, threshold: u32) -> Self {
+		Self { pool, pool_unavailable: false, threshold, consecutive: 0, empty: 0 }
+	}
+
+	/// Note a proposal of the node, whether the warning fired for it. It fires once each time
+	/// the threshold is reached.
+	pub fn note_proposal(&mut self, empty: bool) -> bool {
+		if !empty {
+			self.consecutive = 0;
+			return false;
+		}
+
+		self.empty += 1;
+		let ready = match self.pool.as_ref().map(PoolStatus::ready) {
+			Some(Some(ready)) => {
+				if std::mem::take(&mut self.pool_unavailable) {
+					info!(target: "Hotstuff", "the transaction pool is available again");
+				}
+				ready
+			},
+			Some(None) => {
+				if !std::mem::replace(&mut self.pool_unavailable, true) {
+					warn!(target: "Hotstuff", "the transaction pool is unavailable, taking it as empty");
+				}
+				0
+			},
+			None => 0,
+		};
+		if ready == 0 {
+			self.consecutive = 0;
+			return false;
+		}
+
+		self.consecutive += 1;
+		let warn = self.consecutive == self.threshold;
+		if warn {
+			warn!(target: "Hotstuff", "the last {} proposals carried no block while {} transactions are ready, is block authoring built on the transaction pool?",
+				self.consecutive, ready);
+		}
+		warn
+	}
+
+	pub fn is_pool_unavailable(&self) -> bool {
+		self.pool_unavailable
+	}
+
+	/// The empty proposals noted.
+	pub fn empty_proposals(&self) -> u64 {
+		self.empty
+	}
+}
+
+/// Paces the proposal of the leader after a QC by how full the pool is: it waits the longest
+/// with an empty pool, to batch more transactions in the block, and less the fuller the pool,
+/// down to the least wait with a full pool. A pool filling while the leader waits makes it
+/// propose as soon as the least wait is over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposalPacing {
+	/// The least the leader waits in a view before it proposes, even with a full pool.
+	pub min_interval: Duration,
+	/// The most the leader waits, with an empty or unavailable pool. It must stay well below
+	/// the view timeout.
+	pub max_interval: Duration,
+	/// Transactions ready at which the pool is taken as full.
+	pub pool_capacity: usize,
+}
+
+impl ProposalPacing {
+	/// The wait before proposing with `ready` transactions in the pool, scaling down linearly
+	/// from the most wait to the least as the pool fills.
+	pub fn interval(&self, ready: Option<usize>) -> Duration {
+		let span = self.max_interval.saturating_sub(self.min_interval).as_nanos();
+		let capacity = self.pool_capacity.max(1);
+		let ready = ready.unwrap_or(0).min(capacity) as u128;
+
+		let wait = span - span * ready / capacity as u128;
+		self.min_interval + Duration::from_nanos(u64::try_from(wait).unwrap_or(u64::MAX))
+	}
+
+	/// The wait left `elapsed` into the view with `ready` transactions in the pool, zero once
+	/// the leader is due to propose.
+	pub fn remaining(&self, elapsed: Duration, ready: Option<usize>) -> Duration {
+		self.interval(ready).saturating_sub(elapsed)
+	}
+}
@@ -3,6 +3,13 @@ use hotstuff_primitives::AuthorityId;
 // define some primitives used in hotstuff
 pub type ViewNumber = u64;
 
+// Identifier of an authority set, increased on every change of the authorities.
+pub type SetId = u64;
+
+#[cfg(test)]
+#[path = "tests/primitives_tests.rs"]
+pub mod primitives_tests;
+
 // TODO The `AuthorityId` in this context should be reference instead of value?
 #[derive(Debug, PartialEq, Eq)]
 pub enum HotstuffError {
@@ -12,9 +19,15 @@ pub enum HotstuffError {
 	// The QC without a quorum
 	InsufficientQuorum,
 
+	// The TC whose signers hold no more than two thirds of the authority weight.
+	InsufficientTimeoutWeight,
+
 	// Get invalid signature from a authority.
 	InvalidSignature(AuthorityId),
 
+	// A signature whose scalar isn't reduced, a malleated copy of a valid signature.
+	NonCanonicalSignature(AuthorityId),
+
 	NullSignature,
 
 	UnknownAuthority(AuthorityId),
@@ -24,6 +37,9 @@ pub enum HotstuffError {
 
 	WrongProposer,
 
+	// The leader of a view signed a second, different proposal for it.
+	ProposalEquivocation(AuthorityId),
+
 	// can't find the parent of this proposal.
 	// TODO add proposal info.
 	ProposalNoParent,
@@ -33,8 +49,89 @@ pub enum HotstuffError {
 
 	InvalidTC,
 
+	// The high QC embedded in a TC isn't the highest QC of its timeouts, or a proposal justified
+	// by the TC doesn't extend it.
+	TcHighQcMismatch,
+
+	// Message from an authority set older than the previous one.
+	AuthoritySetRegression(SetId),
+
+	// An authority set which is empty, has no weight or lists an authority twice.
+	InvalidAuthoritySet(String),
+
+	// An authority set change at a block which isn't committed yet.
+	UncommittedSetTransition(String),
+
+	// Signing at a view already signed with another key of the node, such as across a key
+	// rotation.
+	SigningKeyOverlap(ViewNumber),
+
+	// A trusted checkpoint which can't be read or whose commit doesn't verify.
+	InvalidCheckpoint(String),
+
+	// Proposer metadata longer than `MAX_PROPOSAL_METADATA` bytes.
+	OversizedMetadata(usize),
+
+	// A proposal on a branch which doesn't descend from the last committed proposal.
+	DoesNotExtendCommitted(String),
+
+	// The vote a proposal carries isn't its author's vote for it.
+	InvalidSelfVote,
+
+	// A proposal whose timestamp is further from the local clock than the configured skew.
+	ProposalOutsideClockWindow(u64),
+
+	// A proposed block whose extrinsics break the content rules of the replica.
+	InvalidBlockContents(String),
+
+	// The keystore holds no key of the authority the node is configured as.
+	MissingSigningKey(AuthorityId),
+
+	// The authority the node is configured as isn't in the authority set.
+	SelfNotInAuthoritySet(AuthorityId),
+
+	// A QC of a chain replayed to finalize its blocks which doesn't certify a known child of the
+	// proposal certified before it.
+	BrokenQcChain(String),
+
+	// An authority listed more than once in an authority set loaded from the runtime.
+	DuplicateAuthorityInSet(AuthorityId),
+
+	// The blocks a committed proposal stands for which aren't each the parent of the next.
+	NonContiguousBlocks(String),
+
+	// A signature of the keystore self-test which doesn't verify against the authority it was
+	// made with.
+	KeystoreSelfTestFailed(AuthorityId),
+
+	// A proposal sent for an authority set which is neither set around the transition its view
+	// is in, nor the current set outside such a window.
+	SetIdOutsideTransition(SetId),
+
+	// A proposal which skips the previous view doesn't carry the TC of that view, or isn't for
+	// the view right after its QC or TC.
+	MissingTimeoutJustification,
+
+	// A QC of the nil payload, or a nil vote counted towards a QC. Nil votes only form TCs.
+	NilCertificate,
+
 	FinalizeBlock(String),
 
+	// A justification which doesn't prove the commit of its block.
+	InvalidJustification(String),
+
+	// A committed block is not in the chain, which should be impossible with an honest majority.
+	UncommittableBlock(String),
+
+	// Committing the block would revert more finalized blocks than allowed.
+	ReorgTooDeep(String),
+
+	// Failed to record a signed message in the audit log.
+	AuditLog(String),
+
+	// Failed to record an input of the voter in its journal.
+	Journal(String),
+
 	SaveProposal(String),
 
 	// Error generate by node client.
@@ -42,3 +139,56 @@ pub enum HotstuffError {
 
 	Other(String),
 }
+
+impl HotstuffError {
+	/// A numeric code of the error for clients, such as RPC callers. A code is never changed or
+	/// reused once released, new variants take the next free code.
+	pub fn error_code(&self) -> i32 {
+		use HotstuffError::*;
+
+		match self {
+			AuthorityReuse(_) => 1,
+			InsufficientQuorum => 2,
+			InsufficientTimeoutWeight => 3,
+			InvalidSignature(_) => 4,
+			NonCanonicalSignature(_) => 5,
+			NullSignature => 6,
+			UnknownAuthority(_) => 7,
+			NotAuthority => 8,
+			WrongProposer => 9,
+			ProposalEquivocation(_) => 10,
+			ProposalNoParent => 11,
+			ExpiredVote => 12,
+			InvalidTC => 13,
+			AuthoritySetRegression(_) => 14,
+			InvalidAuthoritySet(_) => 15,
+			UncommittedSetTransition(_) => 16,
+			MissingTimeoutJustification => 17,
+			FinalizeBlock(_) => 18,
+			InvalidJustification(_) => 19,
+			UncommittableBlock(_) => 20,
+			ReorgTooDeep(_) => 21,
+			AuditLog(_) => 22,
+			SaveProposal(_) => 23,
+			ClientError(_) => 24,
+			Other(_) => 25,
+			TcHighQcMismatch => 26,
+			SigningKeyOverlap(_) => 27,
+			InvalidCheckpoint(_) => 28,
+			OversizedMetadata(_) => 29,
+			DoesNotExtendCommitted(_) => 30,
+			InvalidSelfVote => 31,
+			ProposalOutsideClockWindow(_) => 32,
+			InvalidBlockContents(_) => 33,
+			MissingSigningKey(_) => 34,
+			SelfNotInAuthoritySet(_) => 35,
+			BrokenQcChain(_) => 36,
+			DuplicateAuthorityInSet(_) => 37,
+			NonContiguousBlocks(_) => 38,
+			KeystoreSelfTestFailed(_) => 39,
+			SetIdOutsideTransition(_) => 40,
+			NilCertificate => 41,
+			Journal(_) => 42,
+		}
+	}
+}
@@ -0,0 +1,192 @@
+// Scores the peers gossiping consensus messages. Every bad message lowers the score of its
+// sender, and the score decays back to zero, so only a sustained stream of bad messages gets a
+// peer banned.
+use std::{
+	collections::{HashMap, VecDeque},
+	hash::Hash,
+	time::{Duration, Instant},
+};
+
+use sc_network::{PeerId, ReputationChange};
+
+#[cfg(test)]
+#[path = "tests/reputation_tests.rs"]
+pub mod reputation_tests;
+
+// Most messages whose sender is remembered, to blame it for signatures found invalid later.
+pub const SENDER_CAPACITY: usize = 1024;
+// Sync requests of a peer answered within a window, those over it are dropped.
+pub const SYNC_REQUEST_LIMIT: usize = 32;
+// The window the sync requests of a peer are counted over, a proposal is sent back to a peer
+// once within it.
+pub const SYNC_REQUEST_WINDOW: Duration = Duration::from_secs(10);
+
+const BAN: ReputationChange = ReputationChange::new_fatal("Hotstuff: misbehaving peer");
+
+/// A reason to lower the score of a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Misbehavior {
+	/// A message with an invalid signature.
+	InvalidSignature,
+	/// A message of a view long gone.
+	StaleMessage,
+	/// A message which doesn't decode.
+	MalformedMessage,
+	/// A sync request repeated within its window, or over the limit of the peer.
+	ExcessiveSyncRequest,
+}
+
+/// The cost of each misbehavior and when a peer gets banned.
+#[derive(Debug, Clone)]
+pub struct ReputationConfig {
+	pub invalid_signature_cost: f64,
+	pub stale_message_cost: f64,
+	pub malformed_message_cost: f64,
+	pub excessive_sync_request_cost: f64,
+	/// A peer whose score drops below this is reported to the network for banning.
+	pub ban_threshold: f64,
+	/// Time for a score to recover half way to zero.
+	pub half_life: Duration,
+}
+
+impl Default for ReputationConfig {
+	fn default() -> Self {
+		Self {
+			invalid_signature_cost: 100.0,
+			stale_message_cost: 5.0,
+			malformed_message_cost: 50.0,
+			excessive_sync_request_cost: 10.0,
+			ban_threshold: -500.0,
+			half_life: Duration::from_secs(60),
+		}
+	}
+}
+
+impl ReputationConfig {
+	fn cost(&self, misbehavior: Misbehavior) -> f64 {
+		match misbehavior {
+			Misbehavior::InvalidSignature => self.invalid_signature_cost,
+			Misbehavior::StaleMessage => self.stale_message_cost,
+			Misbehavior::MalformedMessage => self.malformed_message_cost,
+			Misbehavior::ExcessiveSyncRequest => self.excessive_sync_request_cost,
+		}
+	}
+}
+
+/// The decaying scores of the peers, and the senders of the recent messages.
+pub struct PeerScores {
+	config: ReputationConfig,
+	scores: HashMap<PeerId, (f64, Instant)>,
+	senders: HashMap<[u8; 32], PeerId>,
+	// Keys of `senders` in arrival order, the oldest are forgotten first.
+	arrivals: VecDeque<[u8; 32]>,
+}
+
+impl PeerScores {
+	pub fn new(config: ReputationConfig) -> Self {
+		Self { config, scores: HashMap::new(), senders: HashMap::new(), arrivals: VecDeque::new() }
+	}
+
+	/// The score of `who` at `now`, zero for a peer which never misbehaved.
+	pub fn score(&self, who: &PeerId, now: Instant) -> f64 {
+		self.scores.get(who).map_or(0.0, |(score, at)| self.decayed(*score, *at, now))
+	}
+
+	fn decayed(&self, score: f64, at: Instant, now: Instant) -> f64 {
+		let half_lives = now.saturating_duration_since(at).as_secs_f64()
+			/ self.config.half_life.as_secs_f64().max(f64::MIN_POSITIVE);
+		score * 0.5f64.powf(half_lives)
+	}
+
+	/// Lower the score of `who`, returns the report banning it once it's below the threshold.
+	pub fn report(
+		&mut self,
+		who: PeerId,
+		misbehavior: Misbehavior,
+		now: Instant,
+	) -> Option<ReputationChange> {
+		let score = self.score(&who, now) - self.config.cost(misbehavior);
+		self.scores.insert(who, (score, now));
+
+		(score < self.config.ban_threshold).then_some(BAN)
+	}
+
+	/// Remember `who` sent the message with `key`.
+	pub fn record_sender(&mut self, key: [u8; 32], who: PeerId) {
+		if self.senders.insert(key, who).is_none() {
+			self.arrivals.push_back(key);
+		}
+		while self.arrivals.len() > SENDER_CAPACITY {
+			if let Some(oldest) = self.arrivals.pop_front() {
+				self.senders.remove(&oldest);
+			}
+		}
+	}
+
+	/// Lower the score of the peer which sent the message with `key`, if it's remembered.
+	pub fn report_sender(
+		&mut self,
+		key: &[u8; 32],
+		misbehavior: Misbehavior,
+		now: Instant,
+	) -> Option<(PeerId, ReputationChange)> {
+		let who = *self.senders.get(key)?;
+		self.report(who, misbehavior, now).map(|change| (who, change))
+	}
+}
+
+/// The sync requests of the peers, so each is answered to its sender only, once, and a peer
+/// can't make the node send back more than the limit of proposals in a window.
+pub struct SyncRequestLimiter<H> {
+	// The requests of each peer within the window, with when they were received.
+	requests: HashMap<PeerId, VecDeque<(H, Instant)>>,
+	// The peers waiting for each proposal to be sent back.
+	pending: HashMap<H, Vec<(PeerId, Instant)>>,
+}
+
+impl<H> Default for SyncRequestLimiter<H> {
+	fn default() -> Self {
+		Self { requests: HashMap::new(), pending: HashMap::new() }
+	}
+}
+
+impl<H: Hash + Eq + Copy> SyncRequestLimiter<H> {
+	/// Admit the request of `who` for the proposal `digest`, false when it asked for it within
+	/// the window already, or is over its limit.
+	pub fn admit(&mut self, who: PeerId, digest: H, now: Instant) -> bool {
+		let expired = |at: &Instant| now.saturating_duration_since(*at) >= SYNC_REQUEST_WINDOW;
+		self.pending.retain(|_, waiting| {
+			waiting.retain(|(_, at)| !expired(at));
+			!waiting.is_empty()
+		});
+
+		let requests = self.requests.entry(who).or_default();
+		while requests.front().map_or(false, |(_, at)| expired(at)) {
+			requests.pop_front();
+		}
+		if requests.len() >= SYNC_REQUEST_LIMIT || requests.iter().any(|(d, _)| *d == digest) {
+			return false;
+		}
+		requests.push_back((digest, now));
+		self.pending.entry(digest).or_default().push((who, now));
+		true
+	}
+
+	/// The peers to send the proposal `digest` back to, each answered once.
+	pub fn take_requesters(&mut self, digest: &H) -> Vec<PeerId> {
+		self.pending
+			.remove(digest)
+			.unwrap_or_default()
+			.into_iter()
+			.map(|(who, _)| who)
+			.collect()
+	}
+
+	pub fn forget_peer(&mut self, who: &PeerId) {
+		self.requests.remove(who);
+		self.pending.retain(|_, waiting| {
+			waiting.retain(|(peer, _)| peer != who);
+			!waiting.is_empty()
+		});
+	}
+}
@@ -0,0 +1,98 @@
+// A machine readable description of the SCALE layout of the consensus messages, generated from
+// their type metadata, so that decoders outside of this crate keep in sync with the wire format.
+use scale_info::{
+	form::PortableForm, Field, MetaType, PortableRegistry, Registry, Type, TypeDef, TypeInfo,
+};
+use serde_json::{json, Map, Value};
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+
+use crate::message::{GossipMessage, Proposal, Timeout, Vote, QC, TC};
+
+#[cfg(test)]
+#[path = "tests/schema_tests.rs"]
+pub mod schema_tests;
+
+/// The layout of the messages of a chain of blocks `B`. `messages` maps the name of each message
+/// type to its fields in encoding order, and `types` describes every type they refer to by id.
+/// `GossipMessage` is what goes on the wire.
+pub fn message_schema<B>() -> Value
+where
+	B: BlockT,
+	B::Hash: TypeInfo,
+	NumberFor<B>: TypeInfo,
+{
+	let mut registry = Registry::new();
+	let roots = [
+		("Proposal", MetaType::new::<Proposal<B>>()),
+		("Vote", MetaType::new::<Vote<B>>()),
+		("QC", MetaType::new::<QC<B>>()),
+		("TC", MetaType::new::<TC<B>>()),
+		("Timeout", MetaType::new::<Timeout<B>>()),
+		("GossipMessage", MetaType::new::<GossipMessage<B>>()),
+	]
+	.map(|(name, ty)| (name, registry.register_type(&ty).id));
+	let registry = PortableRegistry::from(registry);
+
+	let mut messages = Map::new();
+	for (name, id) in roots {
+		let ty = registry.resolve(id).expect("registered above; qed");
+		let fields = match &ty.type_def {
+			TypeDef::Composite(composite) => fields(&composite.fields),
+			_ => Value::Null,
+		};
+		messages.insert(name.to_string(), json!({ "type": id, "fields": fields }));
+	}
+
+	let types = registry.types.iter().map(|ty| type_schema(ty.id, &ty.ty)).collect::<Vec<_>>();
+	json!({ "encoding": "SCALE", "messages": messages, "types": types })
+}
+
+fn type_schema(id: u32, ty: &Type<PortableForm>) -> Value {
+	let def = match &ty.type_def {
+		TypeDef::Composite(composite) => json!({ "composite": fields(&composite.fields) }),
+		TypeDef::Variant(variant) => {
+			let variants = variant
+				.variants
+				.iter()
+				.map(|variant| {
+					json!({
+						"name": variant.name,
+						"index": variant.index,
+						"fields": fields(&variant.fields),
+					})
+				})
+				.collect::<Vec<_>>();
+			json!({ "variant": variants })
+		},
+		TypeDef::Sequence(sequence) => json!({ "sequence": sequence.type_param.id }),
+		TypeDef::Array(array) => {
+			json!({ "array": { "len": array.len, "type": array.type_param.id } })
+		},
+		TypeDef::Tuple(tuple) => {
+			json!({ "tuple": tuple.fields.iter().map(|field| field.id).collect::<Vec<_>>() })
+		},
+		TypeDef::Primitive(primitive) => {
+			json!({ "primitive": format!("{:?}", primitive).to_lowercase() })
+		},
+		TypeDef::Compact(compact) => json!({ "compact": compact.type_param.id }),
+		TypeDef::BitSequence(bits) => json!({
+			"bitSequence": { "store": bits.bit_store_type.id, "order": bits.bit_order_type.id }
+		}),
+	};
+	let params = ty
+		.type_params
+		.iter()
+		.map(|param| json!({ "name": param.name, "type": param.ty.map(|ty| ty.id) }))
+		.collect::<Vec<_>>();
+
+	json!({ "id": id, "path": ty.path.segments, "params": params, "def": def })
+}
+
+fn fields(fields: &[Field<PortableForm>]) -> Value {
+	fields
+		.iter()
+		.map(
+			|field| json!({ "name": field.name, "type": field.ty.id, "typeName": field.type_name }),
+		)
+		.collect()
+}
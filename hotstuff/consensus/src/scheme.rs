@@ -0,0 +1,107 @@
+// The signature schemes each peer can verify, advertised when it connects, so that an authority
+// set can migrate to another key type while its nodes upgrade one at a time.
+use std::collections::HashMap;
+
+use log::warn;
+use parity_scale_codec::{Decode, Encode};
+use sc_network::PeerId;
+use scale_info::TypeInfo;
+use sp_application_crypto::AppCrypto;
+use sp_core::{crypto::CryptoTypeId, ecdsa, ed25519, sr25519};
+
+use hotstuff_primitives::AuthorityId;
+
+#[cfg(test)]
+#[path = "tests/scheme_tests.rs"]
+pub mod scheme_tests;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode, TypeInfo)]
+pub enum SignatureScheme {
+	Sr25519,
+	Ed25519,
+	Ecdsa,
+}
+
+impl SignatureScheme {
+	/// The scheme of the keys of `crypto_id`.
+	pub fn of(crypto_id: CryptoTypeId) -> Option<Self> {
+		match crypto_id {
+			sr25519::CRYPTO_ID => Some(Self::Sr25519),
+			ed25519::CRYPTO_ID => Some(Self::Ed25519),
+			ecdsa::CRYPTO_ID => Some(Self::Ecdsa),
+			_ => None,
+		}
+	}
+
+	/// The scheme the authority set signs with, that of the authority keys.
+	pub fn active() -> Self {
+		Self::of(AuthorityId::CRYPTO_ID).expect("authority keys are of a known scheme; qed")
+	}
+}
+
+/// Every scheme the node can verify.
+#[derive(Debug, Clone)]
+pub struct SchemeConfig {
+	pub supported: Vec<SignatureScheme>,
+}
+
+impl Default for SchemeConfig {
+	fn default() -> Self {
+		Self { supported: vec![SignatureScheme::active()] }
+	}
+}
+
+/// The schemes advertised by the connected peers.
+#[derive(Debug)]
+pub struct SchemeNegotiation {
+	config: SchemeConfig,
+	peers: HashMap<PeerId, Vec<SignatureScheme>>,
+	// Messages dropped for coming from a peer without a scheme in common.
+	dropped: u64,
+}
+
+impl SchemeNegotiation {
+	pub fn new(config: SchemeConfig) -> Self {
+		Self { config, peers: HashMap::new(), dropped: 0 }
+	}
+
+	/// The schemes the node advertises to its peers.
+	pub fn advertised(&self) -> &[SignatureScheme] {
+		&self.config.supported
+	}
+
+	/// Note the `schemes` advertised by `who`. A peer which can't verify the active scheme is
+	/// warned about, and the messages of one without a scheme in common are dropped.
+	pub fn note_peer(&mut self, who: PeerId, schemes: Vec<SignatureScheme>) {
+		if !self.has_common_scheme(&schemes) {
+			warn!(target: "Hotstuff", "peer {} advertises no signature scheme in common {:?}, its messages are dropped",
+				who, schemes);
+		} else if !schemes.contains(&SignatureScheme::active()) {
+			warn!(target: "Hotstuff", "peer {} advertises {:?}, not the active signature scheme {:?}",
+				who, schemes, SignatureScheme::active());
+		}
+		self.peers.insert(who, schemes);
+	}
+
+	pub fn forget_peer(&mut self, who: &PeerId) {
+		self.peers.remove(who);
+	}
+
+	/// Whether to take a message from `who`, counting it dropped otherwise. A peer which didn't
+	/// advertise its schemes, such as one running an older node, is assumed compatible.
+	pub fn accepts(&mut self, who: &PeerId) -> bool {
+		let accepted = self.peers.get(who).map_or(true, |schemes| self.has_common_scheme(schemes));
+		if !accepted {
+			self.dropped += 1;
+		}
+		accepted
+	}
+
+	pub fn dropped(&self) -> u64 {
+		self.dropped
+	}
+
+	fn has_common_scheme(&self, schemes: &[SignatureScheme]) -> bool {
+		schemes.iter().any(|scheme| self.config.supported.contains(scheme))
+	}
+}
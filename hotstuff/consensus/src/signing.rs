@@ -0,0 +1,125 @@
+// What the local node signs, so operators can confirm it takes part in consensus, and a
+// self-test of its keystore.
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use sp_application_crypto::AppCrypto;
+use sp_core::{crypto::ByteArray, Pair};
+use sp_keystore::KeystorePtr;
+
+use hotstuff_primitives::{AuthorityId, AuthorityPair, AuthoritySignature};
+
+use crate::{
+	audit::SignedKind,
+	primitives::{HotstuffError, HotstuffError::*, ViewNumber},
+};
+
+#[cfg(test)]
+#[path = "tests/signing_tests.rs"]
+pub mod signing_tests;
+
+// Signed by the keystore self-test. It's signed as is, never as a digest of the signing context,
+// so its signature can't pass for a consensus message.
+const SELFTEST_MESSAGE: &[u8] = b"hotstuff keystore self-test";
+
+pub type SharedSigningStatus = Arc<RwLock<SigningStatus>>;
+
+/// Whether the local node signs consensus messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Participation {
+	/// The node doesn't run the voter of an authority.
+	#[default]
+	NotAuthority,
+	/// The node runs the voter of an authority, but its keystore holds the key of no authority
+	/// of the current set, so it signs nothing.
+	KeyMissing,
+	/// The node signs with the key of an authority of the current set.
+	Signing,
+}
+
+/// Whether the local node signs, and what it signed since it started.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningStatus {
+	pub participation: Participation,
+	/// The authority of the current set the node signs for.
+	pub authority: Option<AuthorityId>,
+	pub last_proposal_view: Option<ViewNumber>,
+	pub last_vote_view: Option<ViewNumber>,
+	pub proposals_signed: u64,
+	pub votes_signed: u64,
+	pub timeouts_signed: u64,
+}
+
+impl SigningStatus {
+	pub fn shared() -> SharedSigningStatus {
+		Arc::new(RwLock::new(Self::default()))
+	}
+
+	/// Note the authority the node signs for, if its key is in the keystore.
+	pub fn set_authority(&mut self, authority: Option<AuthorityId>) {
+		self.participation = match authority {
+			Some(_) => Participation::Signing,
+			None => Participation::KeyMissing,
+		};
+		self.authority = authority;
+	}
+
+	/// Count a message signed at `view`.
+	pub fn record(&mut self, kind: SignedKind, view: ViewNumber) {
+		match kind {
+			SignedKind::Proposal => {
+				self.proposals_signed += 1;
+				self.last_proposal_view = Some(view);
+			},
+			SignedKind::Vote => {
+				self.votes_signed += 1;
+				self.last_vote_view = Some(view);
+			},
+			SignedKind::Timeout => self.timeouts_signed += 1,
+		}
+	}
+}
+
+/// Sign a known message with the key of `authority` and verify the signature against it, to
+/// catch a keystore which can't sign with the key, such as a misconfigured HSM, before the node
+/// joins consensus.
+pub fn keystore_selftest(
+	keystore: &KeystorePtr,
+	authority: &AuthorityId,
+) -> Result<(), HotstuffError> {
+	let signature = keystore
+		.sign_with(AuthorityId::ID, AuthorityId::CRYPTO_ID, authority.as_slice(), SELFTEST_MESSAGE)
+		.map_err(|e| Other(e.to_string()))?
+		.ok_or_else(|| MissingSigningKey(authority.clone()))?;
+	let signature = AuthoritySignature::try_from(signature)
+		.map_err(|_| KeystoreSelfTestFailed(authority.clone()))?;
+
+	if !AuthorityPair::verify(&signature, SELFTEST_MESSAGE, authority) {
+		return Err(KeystoreSelfTestFailed(authority.clone()));
+	}
+	Ok(())
+}
+
+/// Runs the keystore self-test on demand, such as from an admin RPC, cheap to clone.
+#[derive(Clone)]
+pub struct KeystoreSelfTest {
+	keystore: KeystorePtr,
+	signing_status: SharedSigningStatus,
+}
+
+impl KeystoreSelfTest {
+	pub fn new(keystore: KeystorePtr, signing_status: SharedSigningStatus) -> Self {
+		Self { keystore, signing_status }
+	}
+
+	/// Test the key of `authority`, by default that of the authority the node signs for.
+	pub fn run(&self, authority: Option<AuthorityId>) -> Result<(), HotstuffError> {
+		let authority = authority
+			.or_else(|| self.signing_status.read().authority.clone())
+			.ok_or(NotAuthority)?;
+		keystore_selftest(&self.keystore, &authority)
+	}
+}
@@ -25,6 +25,11 @@ impl<Block: BlockT, BE: Backend<Block>, C: ClientForHotstuff<Block, BE>> Store<B
 	pub fn set(&mut self, key: &[u8], value: &[u8]) -> Result<(), Error> {
 		self.backend.insert_aux(&[(key, value)], &[])
 	}
+
+	// Insert and delete keys in one atomic write.
+	pub fn apply(&mut self, insert: &[(&[u8], &[u8])], delete: &[&[u8]]) -> Result<(), Error> {
+		self.backend.insert_aux(insert, delete)
+	}
 }
 
 #[cfg(test)]
@@ -1,5 +1,8 @@
 use std::{
+	collections::{HashMap, VecDeque},
+	fmt,
 	future::Future,
+	hash::Hash,
 	pin::Pin,
 	sync::Arc,
 	task::{Context, Poll},
@@ -7,27 +10,125 @@ use std::{
 };
 
 use log::debug;
-use tokio::time::{interval, Instant, Interval};
+use tokio::time::{interval, interval_at, Instant, Interval};
 
 use sc_client_api::Backend;
 use sp_core::{Decode, Encode};
 use sp_runtime::traits::Block as BlockT;
 
 use crate::{
-	client::ClientForHotstuff, message::Proposal, primitives::HotstuffError, store::Store,
+	client::ClientForHotstuff,
+	message::{Proposal, QC},
+	primitives::{HotstuffError, ViewNumber},
+	store::Store,
 };
 
+#[cfg(test)]
+#[path = "tests/synchronizer_tests.rs"]
+pub mod synchronizer_tests;
+
+// The adaptive timeout is this multiple of the average round latency.
+const ADAPTIVE_TIMEOUT_FACTOR: u32 = 4;
+// The adaptive timeout stays within this factor of the configured duration either way.
+const ADAPTIVE_TIMEOUT_RANGE: u32 = 4;
+// A round latency counts at most this multiple of the current average, so a single outlier
+// can't blow up the timeout.
+const MAX_LATENCY_SAMPLE_FACTOR: u32 = 4;
+// Weight of a new round latency in the average, as 1/N.
+const LATENCY_EMA_WEIGHT: u32 = 8;
+// Peers timing out a view this factor sooner or later than the local timeout hint at clock skew.
+const SKEW_INDICATOR_FACTOR: u32 = 3;
+// Most proposals waiting for the proposal their QC certifies.
+pub const MAX_ORPHAN_PROPOSALS: usize = 64;
+// The views of the stored QCs of committed proposals, oldest first.
+const COMMITTED_QC_VIEWS_KEY: &[u8] = b"hotstuff_committed_qc_views";
+const COMMITTED_QC_PREFIX: &[u8] = b"hotstuff_committed_qc";
+
+fn committed_qc_key(view: ViewNumber) -> Vec<u8> {
+	(COMMITTED_QC_PREFIX, view).encode()
+}
+
 pub struct Timer {
 	delay: Interval,
+	base: Duration,
+	// Exponential moving average of the round latencies, only when the timeout is adaptive.
+	ema_latency: Option<Duration>,
+	// Added to every timeout to absorb the clock drift between nodes.
+	skew_tolerance: Duration,
+	// When the timer fires next.
+	deadline: Instant,
 }
 
 impl Timer {
 	pub fn new(duration: u64) -> Self {
-		Self { delay: interval(Duration::from_millis(duration)) }
+		let base = Duration::from_millis(duration);
+		Self {
+			delay: interval(base),
+			base,
+			ema_latency: None,
+			skew_tolerance: Duration::ZERO,
+			deadline: Instant::now(),
+		}
+	}
+
+	// Wait `tolerance` milliseconds longer on every timeout, at most the configured duration.
+	pub fn with_skew_tolerance(mut self, tolerance: u64) -> Self {
+		self.skew_tolerance = Duration::from_millis(tolerance).min(self.base);
+		self.delay = interval(self.timeout());
+		self.deadline = Instant::now();
+		self
+	}
+
+	// The time left until the timer fires.
+	pub fn remaining(&self) -> Duration {
+		self.deadline.saturating_duration_since(Instant::now())
+	}
+
+	pub fn deadline(&self) -> Instant {
+		self.deadline
+	}
+
+	// A timer learning its timeout from the round latencies, starting at `duration`.
+	pub fn adaptive(duration: u64) -> Self {
+		let mut timer = Self::new(duration);
+		timer.ema_latency = Some(timer.base / ADAPTIVE_TIMEOUT_FACTOR);
+		timer
+	}
+
+	pub fn timeout(&self) -> Duration {
+		let timeout = match self.ema_latency {
+			Some(ema) => (ema * ADAPTIVE_TIMEOUT_FACTOR)
+				.clamp(self.base / ADAPTIVE_TIMEOUT_RANGE, self.base * ADAPTIVE_TIMEOUT_RANGE),
+			None => self.base,
+		};
+		timeout + self.skew_tolerance
+	}
+
+	// Whether peers timing out a view `elapsed` after this node entered it differ so much from
+	// the local timeout that the clocks likely drift apart.
+	pub fn skew_suspected(&self, elapsed: Duration) -> bool {
+		let timeout = self.timeout();
+		elapsed * SKEW_INDICATOR_FACTOR < timeout || elapsed > timeout * SKEW_INDICATOR_FACTOR
+	}
+
+	// Record the latency from a proposal to its QC. The new timeout applies from the next reset.
+	pub fn record_round_latency(&mut self, latency: Duration) {
+		if let Some(ema) = self.ema_latency {
+			let sample = latency.min(ema * MAX_LATENCY_SAMPLE_FACTOR);
+			self.ema_latency = Some(
+				ema * (LATENCY_EMA_WEIGHT - 1) / LATENCY_EMA_WEIGHT + sample / LATENCY_EMA_WEIGHT,
+			);
+		}
 	}
 
 	pub fn reset(&mut self) {
-		self.delay.reset();
+		let timeout = self.timeout();
+		if self.delay.period() == timeout {
+			self.delay.reset();
+		} else {
+			self.delay = interval_at(Instant::now() + timeout, timeout);
+		}
+		self.deadline = Instant::now() + timeout;
 	}
 }
 
@@ -35,13 +136,22 @@ impl Future for Timer {
 	type Output = Instant;
 
 	fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-		self.delay.poll_tick(cx)
+		let tick = self.delay.poll_tick(cx);
+		if let Poll::Ready(instant) = tick {
+			self.deadline = instant + self.delay.period();
+		}
+		tick
 	}
 }
 
 // Synchronizer synchronizes replicas to the same view.
 pub struct Synchronizer<B: BlockT, BE: Backend<B>, C: ClientForHotstuff<B, BE>> {
 	store: Store<B, BE, C>,
+	// Proposals whose certified parent is being fetched, by the digest of the parent, with the
+	// time they are dropped at.
+	orphans: HashMap<B::Hash, Vec<(Proposal<B>, Instant)>>,
+	// Most QCs of committed proposals kept.
+	qc_retention: usize,
 }
 
 impl<B, BE, C> Synchronizer<B, BE, C>
@@ -51,7 +161,13 @@ where
 	C: ClientForHotstuff<B, BE>,
 {
 	pub fn new(client: Arc<C>) -> Self {
-		Self { store: Store::new(client) }
+		Self { store: Store::new(client), orphans: HashMap::new(), qc_retention: usize::MAX }
+	}
+
+	// Keep the QCs of the last `retention` committed proposals.
+	pub fn with_qc_retention(mut self, retention: usize) -> Self {
+		self.qc_retention = retention;
+		self
 	}
 
 	pub fn save_proposal(&mut self, proposal: &Proposal<B>) -> Result<(), HotstuffError> {
@@ -65,7 +181,93 @@ where
 			.map_err(|e| HotstuffError::SaveProposal(e.to_string()))
 	}
 
-	// pub fn get_proposal(&mut self, hash: B::Hash) {}
+	pub fn get_proposal(&self, digest: B::Hash) -> Result<Option<Proposal<B>>, HotstuffError> {
+		self.decode(digest.as_ref())
+	}
+
+	// Persist the QC of a committed proposal. Only the QCs of committed proposals are stored, and
+	// the oldest are pruned once more than the retention are kept.
+	pub fn save_committed_qc(&mut self, qc: &QC<B>) -> Result<(), HotstuffError> {
+		let mut views = self.committed_qc_views()?;
+		if views.last().map_or(false, |last| *last >= qc.view) {
+			return Ok(());
+		}
+
+		views.push(qc.view);
+		let pruned = views.len().saturating_sub(self.qc_retention);
+		let deleted = views.drain(..pruned).map(committed_qc_key).collect::<Vec<_>>();
+		let deleted = deleted.iter().map(Vec::as_slice).collect::<Vec<_>>();
+
+		debug!("~~ save committed QC, view {}", qc.view);
+		self.store
+			.apply(
+				&[
+					(&committed_qc_key(qc.view), &qc.encode()),
+					(COMMITTED_QC_VIEWS_KEY, &views.encode()),
+				],
+				&deleted,
+			)
+			.map_err(|e| HotstuffError::SaveProposal(e.to_string()))
+	}
+
+	// The QC of the committed proposal of `view`, if it's still kept.
+	pub fn qc_for_view(&self, view: ViewNumber) -> Result<Option<QC<B>>, HotstuffError> {
+		self.decode(&committed_qc_key(view))
+	}
+
+	fn committed_qc_views(&self) -> Result<Vec<ViewNumber>, HotstuffError> {
+		Ok(self.decode(COMMITTED_QC_VIEWS_KEY)?.unwrap_or_default())
+	}
+
+	fn decode<T: Decode>(&self, key: &[u8]) -> Result<Option<T>, HotstuffError> {
+		let res = self.store.get(key).map_err(|e| HotstuffError::Other(e.to_string()))?;
+
+		res.map(|data| {
+			Decode::decode(&mut &data[..]).map_err(|e| HotstuffError::Other(e.to_string()))
+		})
+		.transpose()
+	}
+
+	// Keep a proposal until its parent is fetched or `deadline`, returns false when the buffer
+	// is full and the proposal is dropped.
+	pub fn buffer_orphan(&mut self, proposal: Proposal<B>, deadline: Instant) -> bool {
+		if self.orphan_count() >= MAX_ORPHAN_PROPOSALS {
+			return false;
+		}
+
+		self.orphans
+			.entry(proposal.parent_hash())
+			.or_default()
+			.push((proposal, deadline));
+		true
+	}
+
+	// The proposals waiting for the proposal `digest`.
+	pub fn take_orphans(&mut self, digest: &B::Hash) -> Vec<Proposal<B>> {
+		self.orphans
+			.remove(digest)
+			.map(|orphans| orphans.into_iter().map(|(proposal, _)| proposal).collect())
+			.unwrap_or_default()
+	}
+
+	// Drop the proposals whose parent couldn't be fetched by their deadline.
+	pub fn prune_orphans(&mut self, now: Instant) -> usize {
+		let before = self.orphan_count();
+		self.orphans.retain(|_, orphans| {
+			orphans.retain(|(_, deadline)| *deadline > now);
+			!orphans.is_empty()
+		});
+		before - self.orphan_count()
+	}
+
+	pub fn orphan_count(&self) -> usize {
+		self.orphans.values().map(Vec::len).sum()
+	}
+
+	// Whether a proposal waits for the proposal `digest`.
+	pub fn is_awaited(&self, digest: &B::Hash) -> bool {
+		self.orphans.contains_key(digest)
+	}
 
 	pub fn get_proposal_ancestors(
 		&self,
@@ -77,23 +279,154 @@ where
 		Ok((parent, grandpa))
 	}
 
+	// The proposal followed by `ancestors` of its ancestors.
+	pub fn get_proposal_chain(
+		&self,
+		proposal: &Proposal<B>,
+		ancestors: usize,
+	) -> Result<Vec<Proposal<B>>, HotstuffError> {
+		let mut chain = vec![proposal.clone()];
+		while chain.len() <= ancestors {
+			let parent = self.get_proposal_parent(&chain[chain.len() - 1])?;
+			chain.push(parent);
+		}
+
+		Ok(chain)
+	}
+
 	pub fn get_proposal_parent(
 		&self,
 		proposal: &Proposal<B>,
 	) -> Result<Proposal<B>, HotstuffError> {
-		let res = self
-			.store
-			.get(proposal.parent_hash().as_ref())
-			.map_err(|e| HotstuffError::Other(e.to_string()))?;
-
-		if let Some(data) = res {
-			let proposal: Proposal<B> =
-				Decode::decode(&mut &data[..]).map_err(|e| HotstuffError::Other(e.to_string()))?;
-			return Ok(proposal);
+		self.get_proposal(proposal.parent_hash())?
+			.ok_or(HotstuffError::ProposalNoParent)
+	}
+}
+
+/// How the fetches of unknown proposals from the peers are limited and retried, so a large gap
+/// is synced at a controlled rate.
+#[derive(Debug, Clone)]
+pub struct SyncRequestLimit {
+	/// Most fetches awaiting their proposal, the others wait in a queue.
+	pub max_in_flight: usize,
+	/// How long a fetch awaits its proposal before it's taken as failed and frees its slot.
+	pub timeout: Duration,
+	/// Wait before a failed fetch is sent again, doubled for each retry after it.
+	pub initial_backoff: Duration,
+	pub max_backoff: Duration,
+	/// Attempts before a fetch is given up, the first one included.
+	pub max_attempts: u32,
+}
+
+impl Default for SyncRequestLimit {
+	fn default() -> Self {
+		Self {
+			max_in_flight: 16,
+			timeout: Duration::from_secs(1),
+			initial_backoff: Duration::from_millis(200),
+			max_backoff: Duration::from_secs(5),
+			max_attempts: 5,
 		}
+	}
+}
 
-		// TODO request from network, wait result here?
+impl SyncRequestLimit {
+	fn backoff(&self, retry: u32) -> Duration {
+		self.initial_backoff.saturating_mul(1 << retry.min(16)).min(self.max_backoff)
+	}
+}
+
+/// The fetches of unknown proposals by digest, at most `max_in_flight` of them awaiting their
+/// proposal at a time.
+pub struct SyncRequests<H> {
+	limit: SyncRequestLimit,
+	// The fetches sent, with when they fail and the attempts made.
+	in_flight: HashMap<H, (Instant, u32)>,
+	// The fetches waiting for a slot in request order, with when they may be sent and the
+	// attempts made.
+	queued: VecDeque<(H, Instant, u32)>,
+}
+
+impl<H: Copy + Eq + Hash + fmt::Debug> SyncRequests<H> {
+	pub fn new(limit: SyncRequestLimit) -> Self {
+		Self { limit, in_flight: HashMap::new(), queued: VecDeque::new() }
+	}
+
+	/// Queue the fetch of `digest` at `now`, unless it's already fetched.
+	pub fn request(&mut self, digest: H, now: Instant) {
+		if !self.in_flight.contains_key(&digest)
+			&& !self.queued.iter().any(|(queued, _, _)| *queued == digest)
+		{
+			self.queued.push_back((digest, now, 0));
+		}
+	}
+
+	/// The proposal `digest` was received, which frees the slot of its fetch. Whether it was
+	/// fetched.
+	pub fn received(&mut self, digest: &H) -> bool {
+		let queued = self.queued.len();
+		self.queued.retain(|(queued, _, _)| queued != digest);
+		self.in_flight.remove(digest).is_some() || self.queued.len() < queued
+	}
+
+	/// Stop the fetches of the digests `keep` rejects, such as once no proposal awaits them.
+	pub fn retain(&mut self, keep: impl Fn(&H) -> bool) {
+		self.in_flight.retain(|digest, _| keep(digest));
+		self.queued.retain(|(digest, _, _)| keep(digest));
+	}
+
+	/// The fetches to send at `now`. The fetches which failed free their slot, and are queued
+	/// again after a backoff unless they made their last attempt. The free slots then go to the
+	/// queued fetches which are due, in request order.
+	pub fn poll(&mut self, now: Instant) -> Vec<H> {
+		let failed = self
+			.in_flight
+			.iter()
+			.filter(|(_, (deadline, _))| *deadline <= now)
+			.map(|(digest, (_, attempts))| (*digest, *attempts))
+			.collect::<Vec<_>>();
+		for (digest, attempts) in failed {
+			self.in_flight.remove(&digest);
+			if attempts < self.limit.max_attempts {
+				self.queued
+					.push_back((digest, now + self.limit.backoff(attempts - 1), attempts));
+			} else {
+				debug!("~~ give up the fetch of {:?} after {} attempts", digest, attempts);
+			}
+		}
+
+		let mut sent = Vec::new();
+		let mut index = 0;
+		while self.in_flight.len() < self.limit.max_in_flight.max(1) && index < self.queued.len() {
+			if self.queued[index].1 > now {
+				index += 1;
+				continue;
+			}
+			let (digest, _, attempts) =
+				self.queued.remove(index).expect("index is in the queue; qed");
+			self.in_flight.insert(digest, (now + self.limit.timeout, attempts + 1));
+			sent.push(digest);
+		}
+		sent
+	}
+
+	/// When [`Self::poll`] has something to do next: a fetch fails, or a queued fetch is due
+	/// while a slot is free.
+	pub fn next_deadline(&self) -> Option<Instant> {
+		let failing = self.in_flight.values().map(|(deadline, _)| *deadline).min();
+		let due = (self.in_flight.len() < self.limit.max_in_flight.max(1))
+			.then(|| self.queued.iter().map(|(_, due, _)| *due).min())
+			.flatten();
+		failing.into_iter().chain(due).min()
+	}
+
+	/// The fetches awaiting their proposal.
+	pub fn in_flight(&self) -> usize {
+		self.in_flight.len()
+	}
 
-		Err(HotstuffError::ProposalNoParent)
+	/// The fetches waiting for a slot.
+	pub fn queued(&self) -> usize {
+		self.queued.len()
 	}
 }
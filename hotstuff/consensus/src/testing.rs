@@ -0,0 +1,55 @@
+// Helpers shared by the tests of the crate.
+use std::{fmt, time::Duration};
+
+use sp_keystore::{testing::MemoryKeystore, Keystore, KeystorePtr};
+
+use hotstuff_primitives::{AuthorityId, HOTSTUFF_KEY_TYPE};
+
+#[path = "tests/testing_tests.rs"]
+pub mod testing_tests;
+
+/// A keystore holding the sr25519 keys derived from `authority_seeds`, such as `//Alice`, with
+/// the authority ids in the order of the seeds. The same seeds give the same authorities on
+/// every run, so digests and signatures can be compared against known values.
+pub fn deterministic_keystore(authority_seeds: &[&str]) -> (KeystorePtr, Vec<AuthorityId>) {
+	let keystore = MemoryKeystore::new();
+	let authorities = authority_seeds
+		.iter()
+		.map(|seed| {
+			keystore
+				.sr25519_generate_new(HOTSTUFF_KEY_TYPE, Some(seed))
+				.expect("Creates authority pair")
+				.into()
+		})
+		.collect();
+
+	(keystore.into(), authorities)
+}
+
+/// The 50th, 95th and 99th percentiles of latency samples, by the nearest rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LatencySummary {
+	pub p50: Duration,
+	pub p95: Duration,
+	pub p99: Duration,
+}
+
+impl LatencySummary {
+	/// Panics without samples.
+	pub fn new(mut samples: Vec<Duration>) -> Self {
+		assert!(!samples.is_empty(), "no latency samples");
+		samples.sort();
+		let rank = |percentile: usize| {
+			let rank = (samples.len() * percentile + 99) / 100;
+			samples[rank.max(1) - 1]
+		};
+
+		Self { p50: rank(50), p95: rank(95), p99: rank(99) }
+	}
+}
+
+impl fmt::Display for LatencySummary {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "p50 {:?}, p95 {:?}, p99 {:?}", self.p50, self.p95, self.p99)
+	}
+}
@@ -0,0 +1,95 @@
+use super::*;
+
+fn digest(view: ViewNumber) -> [u8; 32] {
+	[view as u8; 32]
+}
+
+// The size of a record of a 32 bytes digest.
+fn record_bytes() -> u64 {
+	let record = AuditRecord { timestamp: 0, kind: SignedKind::Vote, view: 0, digest: vec![0; 32] };
+	4 + record.encode().len() as u64
+}
+
+#[test]
+fn entries_should_be_read_back_across_rotated_segments() {
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("audit.log");
+	// Three records per segment.
+	let rotation = AuditRotation {
+		max_segment_bytes: 3 * record_bytes(),
+		max_segment_age: None,
+		retained_segments: 8,
+	};
+
+	let mut log = AuditLog::open_with(&path, rotation.clone()).expect("open audit log");
+	for view in 1..=5 {
+		log.record(SignedKind::Vote, view, &digest(view)).unwrap();
+	}
+	assert!(segment_path(&path, 1).exists());
+	assert_eq!(read_segment(&segment_path(&path, 1)).unwrap().len(), 3);
+	assert_eq!(read_segment(&path).unwrap().len(), 2);
+
+	// Reopened, the log keeps filling its current segment.
+	drop(log);
+	let mut log = AuditLog::open_with(&path, rotation).expect("reopen audit log");
+	log.record(SignedKind::Timeout, 5, &digest(5)).unwrap();
+	log.record(SignedKind::Vote, 6, &digest(6)).unwrap();
+	assert!(segment_path(&path, 2).exists());
+
+	let entries = AuditLog::read_entries(&path).expect("read audit log");
+	assert_eq!(
+		entries.iter().map(|entry| (entry.kind, entry.view)).collect::<Vec<_>>(),
+		vec![
+			(SignedKind::Vote, 1),
+			(SignedKind::Vote, 2),
+			(SignedKind::Vote, 3),
+			(SignedKind::Vote, 4),
+			(SignedKind::Vote, 5),
+			(SignedKind::Timeout, 5),
+			(SignedKind::Vote, 6),
+		]
+	);
+	assert_eq!(entries[0].digest, array_bytes::bytes2hex("0x", digest(1)));
+
+	// The messages of view 5 span the end of the first segment and the start of the second.
+	let at_view = AuditLog::entries_at_view(&path, 5).expect("scan audit log");
+	assert_eq!(
+		at_view.iter().map(|entry| entry.kind).collect::<Vec<_>>(),
+		vec![SignedKind::Vote, SignedKind::Timeout]
+	);
+}
+
+#[test]
+fn oldest_segments_should_be_deleted_beyond_retention() {
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("audit.log");
+	let rotation =
+		AuditRotation { max_segment_bytes: 1, max_segment_age: None, retained_segments: 2 };
+
+	let mut log = AuditLog::open_with(&path, rotation).expect("open audit log");
+	for view in 1..=4 {
+		log.record(SignedKind::Proposal, view, &digest(view)).unwrap();
+	}
+
+	assert!(!segment_path(&path, 1).exists());
+	assert!(!segment_path(&path, 2).exists());
+	let entries = AuditLog::read_entries(&path).expect("read audit log");
+	assert_eq!(entries.iter().map(|entry| entry.view).collect::<Vec<_>>(), vec![3, 4]);
+}
+
+#[test]
+fn torn_record_should_be_dropped() {
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("audit.log");
+
+	let mut log = AuditLog::open(&path).expect("open audit log");
+	log.record(SignedKind::Vote, 1, &digest(1)).unwrap();
+	log.record(SignedKind::Vote, 2, &digest(2)).unwrap();
+	drop(log);
+
+	// A crash cut the last record short.
+	let data = fs::read(&path).unwrap();
+	fs::write(&path, &data[..data.len() - 5]).unwrap();
+	let entries = AuditLog::read_entries(&path).expect("read audit log");
+	assert_eq!(entries.iter().map(|entry| entry.view).collect::<Vec<_>>(), vec![1]);
+}
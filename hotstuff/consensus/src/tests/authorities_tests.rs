@@ -0,0 +1,144 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+
+fn make_ids(keys: &[Sr25519Keyring]) -> AuthorityList {
+	keys.iter().map(|key| (key.public().into(), 1)).collect()
+}
+
+fn old_set() -> AuthorityList {
+	make_ids(&[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	])
+}
+
+#[test]
+fn fully_overlapping_transition_should_be_accepted() {
+	let new = make_ids(&[
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Alice,
+	]);
+
+	assert_eq!(verify_set_transition(&old_set(), &new), Ok(()));
+	assert_eq!(shared_members(&old_set(), &new), 4);
+}
+
+#[test]
+fn partially_overlapping_transition_should_be_accepted() {
+	let new = make_ids(&[
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Eve,
+		Sr25519Keyring::Ferdie,
+	]);
+
+	assert_eq!(verify_set_transition(&old_set(), &new), Ok(()));
+	assert_eq!(shared_members(&old_set(), &new), 2);
+}
+
+#[test]
+fn canonical_encoding_should_not_depend_on_the_order() {
+	let set = old_set();
+	let reordered = make_ids(&[
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Bob,
+	]);
+	assert_ne!(set.encode(), reordered.encode());
+	assert_eq!(canonical_authorities_encoding(&set), canonical_authorities_encoding(&reordered));
+
+	// A fixed width id and weight per authority, and the weights count.
+	assert_eq!(canonical_authorities_encoding(&set).len(), set.len() * 40);
+	let mut reweighted = reordered;
+	reweighted[0].1 = 2;
+	assert_ne!(canonical_authorities_encoding(&set), canonical_authorities_encoding(&reweighted));
+}
+
+#[test]
+fn disjoint_transition_should_only_warn() {
+	let new = make_ids(&[Sr25519Keyring::Eve, Sr25519Keyring::Ferdie, Sr25519Keyring::One]);
+
+	assert_eq!(verify_set_transition(&old_set(), &new), Ok(()));
+	assert_eq!(shared_members(&old_set(), &new), 0);
+
+	// A malformed set is rejected whatever the overlap.
+	assert!(matches!(verify_set_transition(&old_set(), &vec![]), Err(InvalidAuthoritySet(_))));
+	let twice = make_ids(&[Sr25519Keyring::Eve, Sr25519Keyring::Eve]);
+	assert!(matches!(verify_set_transition(&old_set(), &twice), Err(InvalidAuthoritySet(_))));
+}
+
+#[test]
+fn zero_weight_authority_should_be_listed() {
+	let mut new = old_set();
+	new[3].1 = 0;
+	assert_eq!(verify_set_transition(&old_set(), &new), Ok(()));
+
+	// A set needs some weight to ever form a quorum.
+	let unstaked = new.iter().map(|(id, _)| (id.clone(), 0)).collect();
+	assert!(matches!(verify_set_transition(&old_set(), &unstaked), Err(InvalidAuthoritySet(_))));
+}
+
+#[test]
+fn transition_should_happen_at_a_committed_block() {
+	let mut set = AuthoritySet::<u64, u64>::genesis(old_set()).expect("genesis set is valid");
+	let new = make_ids(&[Sr25519Keyring::Alice, Sr25519Keyring::Eve]);
+
+	assert_eq!(
+		set.apply_transition(new.clone(), 10, 9),
+		Err(UncommittedSetTransition("10".into()))
+	);
+	assert_eq!(set.current_authorities, old_set());
+
+	assert_eq!(set.apply_transition(new.clone(), 10, 10), Ok(()));
+	assert_eq!(set.current_authorities, new);
+	assert_eq!(set.authority_set_changes, AuthoritySetChanges(vec![(0, 10)]));
+}
+
+#[test]
+fn duplicate_authority_should_be_rejected_by_default() {
+	let listed = make_ids(&[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Alice]);
+
+	assert_eq!(
+		load_authority_set(listed, DuplicateAuthorities::default()),
+		Err(DuplicateAuthorityInSet(Sr25519Keyring::Alice.public().into()))
+	);
+	assert_eq!(load_authority_set(old_set(), DuplicateAuthorities::Reject), Ok(old_set()));
+}
+
+#[test]
+fn duplicate_authority_should_be_merged_when_allowed() {
+	let mut listed = make_ids(&[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Alice]);
+	listed[2].1 = 3;
+
+	// The first entry stays in place, with the weight of both.
+	let loaded = load_authority_set(listed, DuplicateAuthorities::Merge).expect("set is merged");
+	assert_eq!(
+		loaded,
+		vec![(Sr25519Keyring::Alice.public().into(), 4), (Sr25519Keyring::Bob.public().into(), 1)]
+	);
+	assert_eq!(verify_set_transition(&old_set(), &loaded), Ok(()));
+}
+
+#[test]
+fn authorities_announced_in_the_digest_should_be_read() {
+	use sp_runtime::{generic::DigestItem, testing::Header, traits::Header as _, Digest};
+
+	let ids = old_set().into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+	let log =
+		|log: ConsensusLog<AuthorityId>| DigestItem::Consensus(HOTSTUFF_ENGINE_ID, log.encode());
+	let header = |logs| {
+		Header::new(1, Default::default(), Default::default(), Default::default(), Digest { logs })
+	};
+
+	// An unrelated log of the engine comes before the announcement.
+	let announcing =
+		header(vec![log(ConsensusLog::OnDisabled(2)), log(ConsensusLog::AuthoritiesChange(ids))]);
+	assert_eq!(announced_authorities(&announcing), Some(old_set()));
+	assert_eq!(announced_authorities(&header(vec![log(ConsensusLog::OnDisabled(2))])), None);
+}
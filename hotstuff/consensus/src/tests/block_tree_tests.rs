@@ -0,0 +1,322 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+use hotstuff_primitives::{AuthorityId, AuthorityList};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+fn authorities() -> AuthorityList {
+	vec![(Sr25519Keyring::Alice.public().into(), 1)]
+}
+
+fn make_proposal(parent: Option<&Proposal<TestBlock>>, view: ViewNumber) -> Proposal<TestBlock> {
+	let qc = match parent {
+		Some(parent) => {
+			QC::<TestBlock> { proposal_hash: parent.digest(), view: parent.view, votes: Vec::new() }
+		},
+		None => QC::<TestBlock>::default(),
+	};
+
+	make_proposal_with_qc(qc, view)
+}
+
+fn make_proposal_with_qc(qc: QC<TestBlock>, view: ViewNumber) -> Proposal<TestBlock> {
+	let author: AuthorityId = Sr25519Keyring::Alice.public().into();
+	let payload =
+		Payload::<TestBlock> { block_hash: H256::from_low_u64_be(view), block_number: view };
+
+	Proposal::<TestBlock>::new(qc, None, payload, view, author, None)
+}
+
+#[test]
+fn block_tree_should_show_forks_and_pending_proposals() {
+	let mut tree = BlockTree::<TestBlock>::new();
+
+	let p1 = make_proposal(None, 1);
+	let p2 = make_proposal(Some(&p1), 2);
+	let p3 = make_proposal(Some(&p1), 3);
+	let p4 = make_proposal(Some(&p3), 4);
+	// The parent of this proposal was never received.
+	let orphan = make_proposal_with_qc(
+		QC::<TestBlock> { proposal_hash: H256::repeat_byte(9), view: 5, votes: Vec::new() },
+		6,
+	);
+
+	for proposal in [&p4, &p1, &orphan, &p3, &p2] {
+		tree.insert(proposal, &authorities());
+	}
+
+	let view = tree.view();
+	assert_eq!(view.roots.len(), 1);
+
+	let root = &view.roots[0];
+	assert_eq!(root.hash, p1.digest());
+	assert_eq!(root.qc_signers.as_ref().map(|signers| signers.bitfield.as_str()), Some("0x00"));
+	assert!(!root.committed);
+	assert_eq!(
+		root.children.iter().map(|node| node.hash).collect::<Vec<_>>(),
+		vec![p2.digest(), p3.digest()]
+	);
+	assert!(root.children[0].qc_signers.is_none());
+	assert!(root.children[1].qc_signers.is_some());
+	assert_eq!(root.children[1].children[0].hash, p4.digest());
+
+	assert_eq!(view.pending.len(), 1);
+	assert_eq!(view.pending[0].hash, orphan.digest());
+}
+
+#[test]
+fn block_tree_commit_should_reroot_and_prune() {
+	let mut tree = BlockTree::<TestBlock>::new();
+
+	let p1 = make_proposal(None, 1);
+	let p2 = make_proposal(Some(&p1), 2);
+	let p3 = make_proposal(Some(&p2), 3);
+	// A fork from p1 which can't be committed once p2 is committed.
+	let fork = make_proposal(Some(&p1), 4);
+	let stale_orphan = make_proposal_with_qc(
+		QC::<TestBlock> { proposal_hash: H256::repeat_byte(9), view: 0, votes: Vec::new() },
+		1,
+	);
+
+	for proposal in [&p1, &p2, &p3, &fork, &stale_orphan] {
+		tree.insert(proposal, &authorities());
+	}
+
+	tree.commit(p2.digest());
+	assert_eq!(tree.committed_hash(), p2.digest());
+
+	let view = tree.view();
+	assert_eq!(view.roots.len(), 1);
+	assert_eq!(view.roots[0].hash, p2.digest());
+	assert!(view.roots[0].committed);
+	assert_eq!(view.roots[0].children.len(), 1);
+	assert_eq!(view.roots[0].children[0].hash, p3.digest());
+	assert!(!view.roots[0].children[0].committed);
+	assert!(view.pending.is_empty());
+}
+
+#[test]
+fn block_tree_commit_should_drop_the_qcs_of_unknown_proposals_below_it() {
+	let mut tree = BlockTree::<TestBlock>::new();
+
+	let p1 = make_proposal(None, 1);
+	let p2 = make_proposal(Some(&p1), 2);
+	let p3 = make_proposal(Some(&p2), 3);
+	for proposal in [&p1, &p2, &p3] {
+		tree.insert(proposal, &authorities());
+	}
+
+	// The high QCs of timeouts, certifying proposals which were never received.
+	let stale = QC::<TestBlock> { proposal_hash: H256::repeat_byte(7), view: 2, votes: Vec::new() };
+	let ahead = QC::<TestBlock> { proposal_hash: H256::repeat_byte(8), view: 5, votes: Vec::new() };
+	tree.insert_qc(&stale, &authorities());
+	tree.insert_qc(&ahead, &authorities());
+	assert_eq!(tree.certified.len(), 4);
+
+	// Only the QCs of p2 and of the proposal above the committed view are left.
+	tree.commit(p2.digest());
+	assert_eq!(tree.certified.len(), 2);
+	assert!(tree.certified.contains_key(&p2.digest()));
+	assert!(tree.certified.contains_key(&ahead.proposal_hash));
+}
+
+#[test]
+fn pending_proposals_should_shrink_as_proposals_commit() {
+	let mut tree = BlockTree::<TestBlock>::new();
+
+	let p1 = make_proposal(None, 1);
+	let mut qc = QC::<TestBlock> { proposal_hash: p1.digest(), view: p1.view, votes: Vec::new() };
+	let key = Sr25519Keyring::Alice;
+	qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	let p2 = make_proposal_with_qc(qc, 2);
+	let p3 = make_proposal(Some(&p2), 3);
+	// A sibling of p3, superseded once p3 commits.
+	let fork = make_proposal(Some(&p2), 4);
+	let p5 = make_proposal(Some(&p3), 5);
+
+	for proposal in [&p1, &p2, &p3, &fork, &p5] {
+		tree.insert(proposal, &authorities());
+	}
+
+	let pending = tree.pending_proposals();
+	assert_eq!(
+		pending.iter().map(|summary| summary.hash).collect::<Vec<_>>(),
+		vec![p1.digest(), p2.digest(), p3.digest(), fork.digest(), p5.digest()]
+	);
+	assert_eq!(pending[0].votes, 1);
+	assert_eq!(pending[0].block_number, 1);
+	assert_eq!(pending[1].votes, 0);
+
+	tree.commit(p1.digest());
+	assert_eq!(tree.pending_proposals().len(), 4);
+
+	tree.commit(p3.digest());
+	assert_eq!(
+		tree.pending_proposals().iter().map(|summary| summary.hash).collect::<Vec<_>>(),
+		vec![p5.digest()]
+	);
+}
+
+#[test]
+fn uncertified_proposals_should_survive_a_view_change() {
+	let mut tree = BlockTree::<TestBlock>::new();
+
+	let p1 = make_proposal(None, 1);
+	let p2 = make_proposal(Some(&p1), 2);
+	// Certifies p2, then view 3 times out and so does the next leader at view 4.
+	let p3 = make_proposal(Some(&p2), 3);
+	let p4 = make_proposal(Some(&p2), 4);
+
+	for proposal in [&p1, &p2, &p3, &p4] {
+		tree.insert(proposal, &authorities());
+	}
+
+	// The next leader extends the high QC of view 2, the blocks of views 3 and 4 are left out.
+	assert_eq!(
+		tree.uncertified_proposals(2)
+			.iter()
+			.map(|summary| summary.hash)
+			.collect::<Vec<_>>(),
+		vec![p4.digest(), p3.digest()]
+	);
+	assert_eq!(tree.uncertified_proposals(2)[0].block_hash, p4.payload.block_hash);
+
+	// Once certified, a proposal is in the chain and isn't proposed again.
+	let qc = QC::<TestBlock> { proposal_hash: p4.digest(), view: p4.view, votes: Vec::new() };
+	tree.insert_qc(&qc, &authorities());
+	assert_eq!(
+		tree.uncertified_proposals(2)
+			.iter()
+			.map(|summary| summary.hash)
+			.collect::<Vec<_>>(),
+		vec![p3.digest()]
+	);
+	assert!(tree.uncertified_proposals(4).is_empty());
+}
+
+#[test]
+fn block_tree_should_show_proposer_metadata() {
+	let mut tree = BlockTree::<TestBlock>::new();
+	let p1 = make_proposal(None, 1).with_metadata(vec![0xab, 0xcd]).unwrap();
+	let p2 = make_proposal(Some(&p1), 2);
+	tree.insert(&p1, &authorities());
+	tree.insert(&p2, &authorities());
+
+	let view = tree.view();
+	assert_eq!(view.roots[0].metadata.as_deref(), Some("0xabcd"));
+	assert!(view.roots[0].children[0].metadata.is_none());
+}
+
+#[test]
+fn fork_choice_should_extend_the_highest_certified_branch() {
+	let mut tree = BlockTree::<TestBlock>::new();
+	assert_eq!(fork_choice(&tree.view()), H256::default());
+
+	let p1 = make_proposal(None, 1);
+	// Two branches from p1, the one of p3 certified at a higher view than the one of p2.
+	let p2 = make_proposal(Some(&p1), 2);
+	let p3 = make_proposal(Some(&p1), 3);
+	let p4 = make_proposal(Some(&p3), 4);
+	let p5 = make_proposal(Some(&p2), 5);
+	// A certified proposal whose parent is unknown isn't extended.
+	let orphan = make_proposal_with_qc(
+		QC::<TestBlock> { proposal_hash: H256::repeat_byte(9), view: 8, votes: Vec::new() },
+		9,
+	);
+
+	for proposal in [&p1, &p2, &p3, &p4, &p5, &orphan] {
+		tree.insert(proposal, &authorities());
+	}
+	tree.insert_qc(
+		&QC::<TestBlock> { proposal_hash: orphan.digest(), view: orphan.view, votes: Vec::new() },
+		&authorities(),
+	);
+	// The QCs of p1, p2 and p3 are carried by their children.
+	assert_eq!(fork_choice(&tree.view()), p3.digest());
+
+	tree.insert_qc(
+		&QC::<TestBlock> { proposal_hash: p5.digest(), view: p5.view, votes: Vec::new() },
+		&authorities(),
+	);
+	assert_eq!(fork_choice(&tree.view()), p5.digest());
+
+	// Nothing above the committed proposal is certified once p5 commits.
+	tree.commit(p5.digest());
+	assert_eq!(fork_choice(&tree.view()), p5.digest());
+}
+
+#[test]
+fn fork_choice_should_break_ties_by_digest() {
+	let mut tree = BlockTree::<TestBlock>::new();
+
+	let p1 = make_proposal(None, 1);
+	// Two certified proposals of the same view, as proposed by an equivocating leader.
+	let fork_a = make_proposal(Some(&p1), 2);
+	let mut fork_b = make_proposal(Some(&p1), 2);
+	fork_b.payload.block_hash = H256::repeat_byte(2);
+
+	for proposal in [&p1, &fork_a, &fork_b] {
+		tree.insert(proposal, &authorities());
+		tree.insert_qc(
+			&QC::<TestBlock> {
+				proposal_hash: proposal.digest(),
+				view: proposal.view,
+				votes: vec![],
+			},
+			&authorities(),
+		);
+	}
+
+	assert_ne!(fork_a.digest(), fork_b.digest());
+	assert_eq!(fork_choice(&tree.view()), fork_a.digest().min(fork_b.digest()));
+}
+
+#[test]
+fn active_forks_should_list_every_branch_above_the_committed_proposal() {
+	let mut tree = BlockTree::<TestBlock>::new();
+	assert!(tree.active_forks().is_empty());
+
+	let p1 = make_proposal(None, 1);
+	let p2 = make_proposal(Some(&p1), 2);
+	let p3 = make_proposal(Some(&p2), 3);
+	// A branch from p1 beside the one of p2.
+	let p4 = make_proposal(Some(&p1), 4);
+	let p5 = make_proposal(Some(&p4), 5);
+	let p6 = make_proposal(Some(&p5), 6);
+	for proposal in [&p1, &p2, &p3] {
+		tree.insert(proposal, &authorities());
+	}
+
+	// A single chain is a single branch, the QCs of p1 and p2 carried by their children.
+	let forks = tree.active_forks();
+	assert_eq!(forks.len(), 1);
+	assert_eq!((forks[0].tip, forks[0].high_qc_view, forks[0].depth), (p3.digest(), Some(2), 3));
+
+	for proposal in [&p4, &p5, &p6] {
+		tree.insert(proposal, &authorities());
+	}
+	let forks = tree
+		.active_forks()
+		.into_iter()
+		.map(|fork| (fork.tip, fork.tip_view, fork.high_qc_view, fork.depth))
+		.collect::<Vec<_>>();
+	assert_eq!(forks, vec![(p6.digest(), 6, Some(5), 4), (p3.digest(), 3, Some(2), 3)]);
+
+	// The depths count from the committed proposal.
+	tree.commit(p1.digest());
+	let depths = tree
+		.active_forks()
+		.iter()
+		.map(|fork| (fork.tip, fork.depth))
+		.collect::<Vec<_>>();
+	assert_eq!(depths, vec![(p6.digest(), 3), (p3.digest(), 2)]);
+
+	// Nothing is above a committed tip.
+	tree.commit(p3.digest());
+	assert!(tree.active_forks().is_empty());
+}
@@ -0,0 +1,97 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_keystore::testing::MemoryKeystore;
+use sp_runtime::testing::TestXt;
+
+use crate::{
+	consensus::ConsensusState,
+	message::{Payload, Proposal, QC},
+	primitives::ViewNumber,
+};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+const KEYS: [Sr25519Keyring; 4] =
+	[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie, Sr25519Keyring::Dave];
+
+fn authorities(keys: &[Sr25519Keyring]) -> AuthorityList {
+	keys.iter().map(|key| (key.public().into(), 1)).collect()
+}
+
+// A QC of `proposal` signed by a quorum of `KEYS`.
+fn certify(proposal: &Proposal<TestBlock>) -> QC<TestBlock> {
+	let mut qc = QC::<TestBlock> {
+		proposal_hash: proposal.digest(),
+		view: proposal.view,
+		votes: Vec::new(),
+	};
+	for key in KEYS[..3].iter() {
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	}
+	qc
+}
+
+fn make_proposal(qc: QC<TestBlock>, view: ViewNumber) -> Proposal<TestBlock> {
+	let author = KEYS[view as usize % KEYS.len()];
+	let payload =
+		Payload::<TestBlock> { block_hash: H256::from_low_u64_be(view), block_number: view };
+	Proposal::<TestBlock>::new(qc, None, payload, view, author.public().into(), None)
+}
+
+// The checkpoint of the block of view 7, committed by `KEYS`.
+fn make_checkpoint(authorities: AuthorityList) -> TrustedCheckpoint<TestBlock> {
+	let committed = make_proposal(QC::default(), 7);
+	let child = make_proposal(certify(&committed), 8);
+	let qc = certify(&child);
+	TrustedCheckpoint {
+		hash: H256::from_low_u64_be(7),
+		number: 7,
+		justification: HotstuffJustification::new(committed, child, qc),
+		authorities,
+	}
+}
+
+#[test]
+fn valid_checkpoint_should_be_adopted() {
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("checkpoint");
+	fs::write(&path, make_checkpoint(authorities(&KEYS)).encode()).unwrap();
+
+	let checkpoint = TrustedCheckpoint::<TestBlock>::load(&path).expect("checkpoint decodes");
+	assert_eq!(checkpoint.verify(None, &Default::default()), Ok(()));
+
+	// A node starting from genesis with an outdated set.
+	let state = ConsensusState::<TestBlock>::new(
+		MemoryKeystore::new().into(),
+		authorities(&[Sr25519Keyring::Eve]),
+	)
+	.with_checkpoint(&checkpoint)
+	.expect("checkpoint verifies");
+	assert_eq!(state.authorities(), &authorities(&KEYS));
+	assert_eq!(state.high_qc_view(), 8);
+	assert_eq!(state.view(), 9);
+}
+
+#[test]
+fn invalid_checkpoint_should_be_rejected() {
+	// The QC isn't signed by a quorum of the provided set.
+	let others = authorities(&[Sr25519Keyring::Eve, Sr25519Keyring::Ferdie, Sr25519Keyring::One]);
+	let checkpoint = make_checkpoint(others);
+	assert!(matches!(checkpoint.verify(None, &Default::default()), Err(InvalidCheckpoint(_))));
+
+	let state = ConsensusState::<TestBlock>::new(MemoryKeystore::new().into(), authorities(&KEYS));
+	assert!(matches!(state.with_checkpoint(&checkpoint), Err(InvalidCheckpoint(_))));
+
+	// Another block than the one committed.
+	let mut checkpoint = make_checkpoint(authorities(&KEYS));
+	checkpoint.hash = H256::from_low_u64_be(8);
+	assert!(matches!(checkpoint.verify(None, &Default::default()), Err(InvalidCheckpoint(_))));
+
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("checkpoint");
+	fs::write(&path, b"garbage").unwrap();
+	assert!(matches!(TrustedCheckpoint::<TestBlock>::load(&path), Err(InvalidCheckpoint(_))));
+}
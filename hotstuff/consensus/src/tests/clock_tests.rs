@@ -0,0 +1,122 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+use hotstuff_primitives::AuthorityList;
+
+use crate::{
+	message::{Payload, Vote, VoteTimestamp, QC},
+	primitives::{HotstuffError::*, ViewNumber},
+};
+
+type TestBlock = sp_runtime::testing::Block<TestXt<(), ()>>;
+
+const KEYS: [Sr25519Keyring; 4] =
+	[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie, Sr25519Keyring::Dave];
+
+fn authorities() -> AuthorityList {
+	KEYS.iter().map(|key| (key.public().into(), 1)).collect()
+}
+
+// A vote of `key` for the proposal of `view`, stamped with `timestamp`.
+fn make_vote(key: Sr25519Keyring, view: ViewNumber, timestamp: u64) -> Vote<TestBlock> {
+	let mut vote = Vote::<TestBlock>::new(H256::from_low_u64_be(view), view, key.public().into());
+	vote.signature = Some(key.sign(vote.digest().as_ref()).into());
+	let digest = VoteTimestamp::digest::<TestBlock>(vote.digest(), timestamp);
+	vote.timestamp = Some((timestamp, key.sign(digest.as_ref()).into()));
+	vote
+}
+
+// The child of the proposal of `view` carrying the votes of `voters` at their `clocks`.
+fn make_child(voters: &[Sr25519Keyring], clocks: &[u64], view: ViewNumber) -> Proposal<TestBlock> {
+	let votes = voters
+		.iter()
+		.zip(clocks)
+		.map(|(key, clock)| make_vote(*key, view, *clock))
+		.collect::<Vec<_>>();
+	let qc = QC::<TestBlock> {
+		proposal_hash: H256::from_low_u64_be(view),
+		view,
+		votes: votes
+			.iter()
+			.map(|vote| (vote.voter.clone(), vote.signature.clone().unwrap()))
+			.collect(),
+	};
+
+	let author = KEYS[(view + 1) as usize % KEYS.len()];
+	let payload = Payload::<TestBlock> {
+		block_hash: H256::from_low_u64_be(view + 1),
+		block_number: view + 1,
+	};
+	let mut child =
+		Proposal::<TestBlock>::new(qc, None, payload, view + 1, author.public().into(), None)
+			.with_qc_timestamps(votes.iter().filter_map(Vote::vote_timestamp).collect());
+	child.signature = Some(author.sign(child.digest().as_ref()).into());
+	child
+}
+
+#[test]
+fn commit_time_should_be_the_median_of_the_quorum_vote_timestamps() {
+	let clocks = [1_700_000_003_000, 1_700_000_001_000, 1_700_000_002_000, 1_700_000_009_000];
+	for voters in [&KEYS[..3], &KEYS[..]] {
+		let child = make_child(voters, &clocks, 4);
+		assert_eq!(child.verify(&authorities()), Ok(()));
+
+		// The lower middle clock of the four, the middle one of the three.
+		let time = CommitTime::certified_by(&child);
+		assert_eq!(time, CommitTime { timestamp: 1_700_000_002_000, local: false });
+	}
+
+	// Without vote timestamps the local clock times the commit, flagged as such.
+	let mut child = make_child(&KEYS[..3], &clocks, 4);
+	child.qc_timestamps.clear();
+	let before = unix_millis();
+	let time = CommitTime::certified_by(&child);
+	assert!(time.local && time.timestamp >= before);
+
+	// A vote verifies along with its timestamp.
+	let vote = make_vote(Sr25519Keyring::Alice, 4, clocks[0]);
+	assert_eq!(vote.verify(&authorities()), Ok(()));
+}
+
+#[test]
+fn tampered_vote_timestamps_should_not_verify() {
+	let clocks = [1_000, 2_000, 3_000, 4_000];
+	let authorities = authorities();
+
+	// A vote whose clock was changed after signing.
+	let mut vote = make_vote(Sr25519Keyring::Alice, 4, clocks[0]);
+	vote.timestamp.as_mut().unwrap().0 += 1;
+	assert_eq!(vote.verify(&authorities), Err(InvalidSignature(vote.voter.clone())));
+
+	// The clocks of too few voters could be picked to skew the median.
+	let mut child = make_child(&KEYS, &clocks, 4);
+	child.qc_timestamps.truncate(2);
+	child.signature = Some(KEYS[1].sign(child.digest().as_ref()).into());
+	assert_eq!(child.verify(&authorities), Err(InsufficientQuorum));
+
+	// The clock of an authority which isn't a voter of the QC.
+	let mut child = make_child(&KEYS[..3], &clocks, 4);
+	let outsider = make_vote(Sr25519Keyring::Dave, 4, clocks[3]);
+	child.qc_timestamps.push(outsider.vote_timestamp().unwrap());
+	child.signature = Some(KEYS[1].sign(child.digest().as_ref()).into());
+	assert_eq!(child.verify(&authorities), Err(UnknownAuthority(outsider.voter)));
+
+	// The vote timestamps are covered by the signature of the proposer.
+	let mut child = make_child(&KEYS, &clocks, 4);
+	child.qc_timestamps.pop();
+	assert_eq!(child.verify(&authorities), Err(InvalidSignature(child.author.clone())));
+}
+
+#[test]
+fn mock_clock_should_only_move_when_set() {
+	let clock = MockClock::new(1_000);
+	let source = clock.time_source();
+	assert_eq!(source.now(), 1_000);
+
+	// Its clones and time sources read the time it's set to.
+	clock.clone().set(2_500);
+	assert_eq!((clock.now(), source.now()), (2_500, 2_500));
+}
@@ -0,0 +1,86 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+use crate::{message::QC, primitives::HotstuffError::*};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+const KEYS: [Sr25519Keyring; 6] = [
+	Sr25519Keyring::Alice,
+	Sr25519Keyring::Bob,
+	Sr25519Keyring::Charlie,
+	Sr25519Keyring::Dave,
+	Sr25519Keyring::Eve,
+	Sr25519Keyring::Ferdie,
+];
+
+fn authorities() -> AuthorityList {
+	KEYS.iter().map(|key| (key.public().into(), 1)).collect()
+}
+
+fn sampler() -> CommitteeSampler {
+	CommitteeSampler::new(3, b"hotstuff/committee_tests".to_vec())
+}
+
+fn signed_qc(signers: &[Sr25519Keyring], view: ViewNumber) -> QC<TestBlock> {
+	let mut qc =
+		QC::<TestBlock> { proposal_hash: H256::from_low_u64_be(view), view, votes: Vec::new() };
+	for key in signers {
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	}
+	qc
+}
+
+fn keys_of(committee: &AuthorityList) -> Vec<Sr25519Keyring> {
+	KEYS.iter()
+		.copied()
+		.filter(|key| committee.iter().any(|(id, _)| *id == key.public().into()))
+		.collect()
+}
+
+#[test]
+fn committee_should_be_deterministic_per_view() {
+	let authorities = authorities();
+
+	let committees =
+		(1..=8).map(|view| sampler().committee(&authorities, view)).collect::<Vec<_>>();
+	for (view, committee) in (1..=8).zip(committees.iter()) {
+		assert_eq!(committee.len(), 3);
+		assert_eq!(*committee, sampler().committee(&authorities, view));
+		// In the canonical order of the authorities.
+		let positions = committee
+			.iter()
+			.map(|member| authorities.iter().position(|authority| authority == member))
+			.collect::<Vec<_>>();
+		assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+	}
+	// The committee rotates between views.
+	assert!(committees.iter().any(|committee| *committee != committees[0]));
+
+	// A small set is never sub-sampled.
+	let small = authorities[..3].to_vec();
+	assert_eq!(sampler().committee(&small, 1), small);
+	assert_eq!(voting_committee(&authorities, None, 1).into_owned(), authorities);
+}
+
+#[test]
+fn qc_should_verify_against_committee_of_its_view() {
+	let authorities = authorities();
+	let view = 1;
+	let committee = voting_committee(&authorities, Some(&sampler()), view);
+
+	let members = keys_of(&committee);
+	assert_eq!(signed_qc(&members, view).verify(&committee), Ok(()));
+
+	// A quorum of the committee is not a quorum of every authority.
+	assert_eq!(signed_qc(&members, view).verify(&authorities), Err(InsufficientQuorum));
+
+	// A subset with an authority outside the committee.
+	let outsider = KEYS.iter().copied().find(|key| !members.contains(key)).expect("3 of 6 sampled");
+	let wrong = signed_qc(&[members[0], members[1], outsider], view);
+	assert_eq!(wrong.verify(&committee), Err(UnknownAuthority(outsider.public().into())));
+}
@@ -3,20 +3,37 @@ use super::*;
 
 use futures::{future, stream, FutureExt};
 use parking_lot::{Mutex, RwLock};
-use tokio::runtime::Handle;
+use tokio::{runtime::Handle, sync::broadcast};
 
-use sc_consensus::{BoxJustificationImport, LongestChain};
+use sc_client_api::BlockBackend;
+use sc_consensus::{
+	BlockImport, BlockImportParams, BoxJustificationImport, ForkChoiceStrategy, ImportResult,
+	LongestChain,
+};
 use sc_network_test::{
 	Block, BlockImportAdapter, FullPeerConfig, PassThroughVerifier, Peer, PeersClient,
 	PeersFullClient, TestNetFactory,
 };
 use sp_api::{ApiRef, ProvideRuntimeApi};
+use sp_blockchain::HeaderBackend;
+use sp_consensus::BlockOrigin;
+use sp_core::{Pair, H256};
 use sp_keyring::Sr25519Keyring;
 use sp_keystore::{testing::MemoryKeystore, Keystore, KeystorePtr};
-use sp_runtime::traits::Header as HeaderT;
+use sp_runtime::{traits::Header as HeaderT, Justifications};
 
-use crate::client::GenesisAuthoritySetProvider;
-use hotstuff_primitives::HotstuffApi;
+use crate::{
+	aggregator::VOTE_RETENTION_SLACK,
+	audit::{AuditLog, SignedKind},
+	client::GenesisAuthoritySetProvider,
+	commit::{CommitWatcher, CommittedRange},
+	dedup::{EquivocationPolicy, VerificationBudget},
+	journal::{Journal, JournalEvent},
+	justification::HotstuffJustification,
+	signing::Participation,
+	testing::{deterministic_keystore, LatencySummary},
+};
+use hotstuff_primitives::{HotstuffApi, HOTSTUFF_ENGINE_ID};
 
 type TestLinkHalf =
 	LinkHalf<Block, PeersFullClient, LongestChain<substrate_test_runtime_client::Backend, Block>>;
@@ -52,8 +69,8 @@ impl ProvideRuntimeApi<Block> for TestApi {
 }
 
 impl GenesisAuthoritySetProvider<Block> for TestApi {
-	fn get(&self) -> sp_blockchain::Result<Vec<AuthorityId>> {
-		Ok(self.genesis_authorities.iter().map(|a| a.0.clone()).collect())
+	fn get(&self) -> sp_blockchain::Result<AuthorityList> {
+		Ok(self.genesis_authorities.clone())
 	}
 }
 
@@ -166,6 +183,8 @@ fn start_hotstuff<B, BE, C, N, S, SC>(
 	hotstuff_protocol_name: ProtocolName,
 	keystore: KeystorePtr,
 	authorities: AuthorityList,
+	variant: ConsensusVariant,
+	delivery: Option<SimulatedDelivery<GossipMessage<B>>>,
 ) -> sp_blockchain::Result<(impl Future<Output = ()> + Send, impl Future<Output = ()> + Send)>
 where
 	B: BlockT,
@@ -174,7 +193,7 @@ where
 	S: SyncingT<B> + Sync + 'static,
 	C: ClientForHotstuff<B, BE> + 'static,
 {
-	let (worker, net) = build_hotstuff_components(
+	let (mut worker, net) = build_hotstuff_components(
 		network,
 		link,
 		sync,
@@ -182,6 +201,11 @@ where
 		keystore,
 		authorities,
 	)?;
+	worker.variant = variant;
+	let net = match delivery {
+		Some(delivery) => net.with_delivery(delivery),
+		None => net,
+	};
 	Ok((async { worker.run().await }, net))
 }
 
@@ -200,13 +224,31 @@ where
 	S: SyncingT<B> + Sync + 'static,
 	C: ClientForHotstuff<B, BE> + 'static,
 {
-	let LinkHalf { client, .. } = link;
+	let LinkHalf {
+		client,
+		block_tree,
+		events,
+		inclusion_index,
+		pause,
+		signing_status,
+		pacemaker,
+		certificates,
+		committed_blocks,
+		..
+	} = link;
 
-	let network = HotstuffNetworkBridge::new(network.clone(), sync.clone(), hotstuff_protocol_name);
+	let network = HotstuffNetworkBridge::new(
+		network.clone(),
+		sync.clone(),
+		hotstuff_protocol_name,
+		Default::default(),
+		Default::default(),
+	);
 	let synchronizer = Synchronizer::<B, BE, C>::new(client.clone());
-	let consensus_state = ConsensusState::<B>::new(keystore, authorities);
+	let consensus_state =
+		ConsensusState::<B>::new(keystore, authorities).with_signing_status(signing_status);
 
-	let (consensus_msg_tx, consensus_msg_rx) = channel::<ConsensusMessage<B>>(1000);
+	let (consensus_msg_tx, consensus_msg_rx) = channel::<GossipMessage<B>>(1000);
 
 	let queue = PendingFinalizeBlockQueue::<B>::new(client.clone()).expect("error");
 
@@ -216,10 +258,17 @@ where
 		sync,
 		network.clone(),
 		synchronizer,
-		2000,
+		Config { local_timer_duration: 2000, ..Default::default() },
 		consensus_msg_tx.clone(),
 		consensus_msg_rx,
 		queue.queue(),
+		block_tree,
+		events,
+		inclusion_index,
+		pause,
+		pacemaker,
+		certificates,
+		committed_blocks,
 	);
 
 	let consensus_network = ConsensusNetwork::<B, N, S>::new(network, consensus_msg_tx, queue);
@@ -228,11 +277,31 @@ where
 }
 
 fn instantiate_hotstuff(net: &mut TestNet, peers: &[Sr25519Keyring]) -> impl Future<Output = ()> {
-	let voters = stream::FuturesUnordered::new();
-	let authority_list = make_ids(peers);
-	for (peer_id, key) in peers.iter().enumerate() {
-		let keystore = create_keystore(*key);
+	instantiate_hotstuff_with(net, peers, make_ids(peers), ConsensusVariant::Chained)
+}
+
+// Start a voter for each of `peers` running `variant`, with the authorities of `authority_list`.
+fn instantiate_hotstuff_with(
+	net: &mut TestNet,
+	peers: &[Sr25519Keyring],
+	authority_list: AuthorityList,
+	variant: ConsensusVariant,
+) -> impl Future<Output = ()> {
+	let keystores = peers.iter().map(|key| create_keystore(*key)).collect();
+	instantiate_voters(net, keystores, authority_list, variant, |_| None)
+}
 
+// Start a voter with each of `keystores`, reading gossip through the simulated network
+// `delivery` gives the voter of each index, none for a direct one.
+fn instantiate_voters(
+	net: &mut TestNet,
+	keystores: Vec<KeystorePtr>,
+	authority_list: AuthorityList,
+	variant: ConsensusVariant,
+	delivery: impl Fn(usize) -> Option<SimulatedDelivery<GossipMessage<Block>>>,
+) -> impl Future<Output = ()> {
+	let voters = stream::FuturesUnordered::new();
+	for (peer_id, keystore) in keystores.into_iter().enumerate() {
 		let (net_service, link) = {
 			// temporary needed for some reason
 			let link =
@@ -248,6 +317,8 @@ fn instantiate_hotstuff(net: &mut TestNet, peers: &[Sr25519Keyring]) -> impl Fut
 			crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
 			keystore,
 			authority_list.clone(),
+			variant,
+			delivery(peer_id),
 		)
 		.expect("");
 
@@ -342,6 +413,384 @@ async fn finalize_three_voters() {
 	}
 }
 
+// Test the voters keep finalizing while each of them receives the messages out of order, under
+// several seeds. The seeds which stalled finalization are reported.
+#[tokio::test]
+async fn reordered_messages_should_still_finalize() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+	let window = Duration::from_millis(200);
+
+	let mut stalled = Vec::new();
+	for seed in [1, 2, 3, 4, 5] {
+		let mut net = TestNet::new(TestApi::new(voters.clone()), peers.len(), 0);
+		let keystores = peers.iter().map(|key| create_keystore(*key)).collect();
+		tokio::spawn(instantiate_voters(
+			&mut net,
+			keystores,
+			voters.clone(),
+			ConsensusVariant::Chained,
+			|index| Some(SimulatedDelivery::reordering(window, seed * 100 + index as u64)),
+		));
+
+		net.peer(0).push_blocks(5, false);
+		net.run_until_sync().await;
+
+		let net = Arc::new(Mutex::new(net));
+		let finalized = run_to_completion(5, net.clone(), peers);
+		if tokio::time::timeout(Duration::from_secs(60), finalized).await.is_err() {
+			stalled.push(seed);
+		}
+	}
+
+	assert!(stalled.is_empty(), "finalization stalled under the seeds {:?}", stalled);
+}
+
+// Resolves once each of the first `voters` peers finalized the block of `number`.
+async fn finalized_by_all(number: u64, net: &Arc<Mutex<TestNet>>, voters: usize) {
+	let finalized = (0..voters)
+		.map(|peer_id| {
+			let client = net.lock().peers[peer_id].client().clone();
+			let finalized = client.info().finalized_number >= number;
+			async move {
+				if !finalized {
+					let mut stream = client.finality_notification_stream();
+					while let Some(notification) = stream.next().await {
+						if *notification.header.number() >= number {
+							break;
+						}
+					}
+				}
+			}
+		})
+		.collect::<Vec<_>>();
+
+	run_until_complete(Box::pin(future::join_all(finalized)), net).await;
+}
+
+// The latency from the submission of each of `blocks` transactions to the finalization of its
+// block by every one of `voters` voters, with gossip reaching each voter after `delay` plus a
+// jitter of up to `jitter` drawn under `seed`. It's measured on the clock of the runtime, which
+// the benchmark pauses, so the delays and timers advance in virtual time.
+async fn commit_latency(
+	voters: usize,
+	blocks: u64,
+	delay: Duration,
+	jitter: Duration,
+	seed: u64,
+) -> LatencySummary {
+	let (keystores, ids): (Vec<_>, Vec<_>) = (0..voters)
+		.map(|index| {
+			let (keystore, ids) = deterministic_keystore(&[&format!("//Bench{}", index)]);
+			(keystore, ids[0].clone())
+		})
+		.unzip();
+	let authorities = ids.into_iter().map(|id| (id, 1)).collect::<AuthorityList>();
+
+	let mut net = TestNet::new(TestApi::new(authorities.clone()), voters, 0);
+	tokio::spawn(instantiate_voters(
+		&mut net,
+		keystores,
+		authorities,
+		ConsensusVariant::Chained,
+		|index| Some(SimulatedDelivery::new(delay, seed + index as u64).with_jitter(jitter)),
+	));
+	net.run_until_sync().await;
+	let net = Arc::new(Mutex::new(net));
+
+	let mut samples = Vec::new();
+	for number in 1..=blocks {
+		let submitted = Instant::now();
+		net.lock().peer(0).push_blocks(1, true);
+		finalized_by_all(number, &net, voters).await;
+		samples.push(submitted.elapsed());
+	}
+	LatencySummary::new(samples)
+}
+
+// Benchmark the commit latency over 4, 7 and 10 voters, in virtual time. Gossip reaches each
+// voter after HOTSTUFF_BENCH_DELAY_MS (20 by default) plus a jitter of up to
+// HOTSTUFF_BENCH_JITTER_MS (10), drawn under HOTSTUFF_BENCH_SEED (0). The latency doesn't
+// depend on the load of the machine, so the percentiles of two revisions are comparable. Run with
+// `RUST_LOG=hotstuff_bench=info cargo test -p hotstuff-consensus commit_latency_benchmark -- --ignored`.
+#[tokio::test(start_paused = true)]
+#[ignore]
+async fn commit_latency_benchmark() {
+	const BLOCKS: u64 = 20;
+	sp_tracing::try_init_simple();
+
+	let setting = |name: &str, default: u64| {
+		env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+	};
+	let delay = Duration::from_millis(setting("HOTSTUFF_BENCH_DELAY_MS", 20));
+	let jitter = Duration::from_millis(setting("HOTSTUFF_BENCH_JITTER_MS", 10));
+	let seed = setting("HOTSTUFF_BENCH_SEED", 0);
+
+	for voters in [4, 7, 10] {
+		let summary = commit_latency(voters, BLOCKS, delay, jitter, seed).await;
+		assert!(summary.p50 <= summary.p95 && summary.p95 <= summary.p99);
+		assert!(summary.p50 >= delay, "a commit takes at least a network delay");
+
+		info!(
+			target: "hotstuff_bench",
+			"{} voters, {:?} delay, {:?} jitter, seed {}: {}",
+			voters,
+			delay,
+			jitter,
+			seed,
+			summary
+		);
+	}
+}
+
+// The views voter 0 goes through on average from the submission of a block until every voter
+// finalized it, with every link delayed by 20ms and those from Dave by `slow_link`. The delays
+// and the pacemaker run on the clock of the runtime, so under a paused clock the rounds don't
+// depend on the load of the machine. The links are keyed on the relaying peer, and a message of
+// Dave's relayed by a fast voter skips the slow link, but the voters form a full mesh: a
+// proposal of Dave's still reaches each voter straight from Dave, and its views time out unless
+// a relayed copy arrives first.
+async fn rounds_to_commit(slow_link: Duration) -> f64 {
+	use sc_network::NetworkStateInfo;
+	const BLOCKS: u64 = 6;
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), peers.len(), 0);
+	let pacemaker = net.peers[0].data.lock().as_ref().expect("link initialized").shared_pacemaker();
+	let slow = net.peers[3].network_service().local_peer_id();
+	let keystores = peers.iter().map(|key| create_keystore(*key)).collect();
+	tokio::spawn(instantiate_voters(
+		&mut net,
+		keystores,
+		voters,
+		ConsensusVariant::Chained,
+		move |index| {
+			let delivery = SimulatedDelivery::new(Duration::from_millis(20), index as u64)
+				.with_jitter(Duration::from_millis(10));
+			Some(if index == 3 { delivery } else { delivery.with_link_delay(slow, slow_link) })
+		},
+	));
+	net.run_until_sync().await;
+	let net = Arc::new(Mutex::new(net));
+
+	let mut rounds = 0;
+	for number in 1..=BLOCKS {
+		let submitted = pacemaker.state().view;
+		net.lock().peer(0).push_blocks(1, true);
+		finalized_by_all(number, &net, peers.len()).await;
+		rounds += pacemaker.state().view - submitted;
+	}
+	rounds as f64 / BLOCKS as f64
+}
+
+// Test the voters keep committing over links with latency, and that a voter whose links are
+// slower than the local timer costs the others rounds: its proposals arrive after its views
+// timed out.
+#[tokio::test(start_paused = true)]
+async fn slow_links_should_take_more_rounds_to_commit() {
+	sp_tracing::try_init_simple();
+
+	let fast = rounds_to_commit(Duration::from_millis(20)).await;
+	let slow = rounds_to_commit(Duration::from_millis(2_500)).await;
+	assert!(slow > fast, "{} rounds to commit over slow links, {} over fast ones", slow, fast);
+}
+
+// Test a flood of distinct proposals for a view is verified only up to the budget of the view.
+#[tokio::test]
+async fn proposal_flood_should_be_verified_within_the_budget() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.proposal_budget = VerificationBudget::new(2);
+
+	// Bob leads view 1, and floods proposals of distinct blocks.
+	let author: AuthorityId = peers[1].public().into();
+	for byte in 0..10 {
+		let payload = Payload::<Block> { block_hash: H256::repeat_byte(byte), block_number: 1 };
+		let proposal =
+			Proposal::<Block>::new(QC::default(), None, payload, 1, author.clone(), None);
+		let message = GossipMessage { set_id: 0, message: ConsensusMessage::Propose(proposal) };
+		worker._consensus_msg_tx.send(message).await.expect("worker is alive");
+	}
+	for _ in 0..10 {
+		worker.step().await;
+	}
+
+	assert_eq!(worker.proposals_over_budget(), 8);
+}
+
+// Test proposals forged in the name of the leader, or sent by others, before its own don't use up
+// the verification budget of the leader.
+#[tokio::test]
+async fn forged_proposals_should_not_use_up_the_budget_of_the_leader() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.proposal_budget = VerificationBudget::new(2);
+
+	// Bob leads view 1, Charlie signs proposals in the name of Bob and in its own.
+	let (leader, forger) = (peers[1], peers[2]);
+	let propose = |author: Sr25519Keyring, signer: Sr25519Keyring, byte| {
+		let payload =
+			Payload::<Block> { block_hash: Hasher::<Block>::hash(EMPTY_PAYLOAD), block_number: 1 };
+		let mut proposal =
+			Proposal::<Block>::new(QC::default(), None, payload, 1, author.public().into(), None)
+				.with_metadata(vec![byte])
+				.expect("metadata within bounds");
+		proposal.signature = Some(signer.sign(proposal.digest().as_ref()).into());
+		proposal
+	};
+	let mut proposals = (0..8)
+		.flat_map(|byte| [propose(leader, forger, byte), propose(forger, forger, byte)])
+		.collect::<Vec<_>>();
+	let honest = propose(leader, leader, 8);
+	proposals.push(honest.clone());
+
+	for proposal in proposals.iter().cloned() {
+		let message = GossipMessage { set_id: 0, message: ConsensusMessage::Propose(proposal) };
+		worker._consensus_msg_tx.send(message).await.expect("worker is alive");
+	}
+	for _ in 0..proposals.len() {
+		worker.step().await;
+	}
+
+	assert_eq!(worker.proposals_over_budget(), 0);
+	assert!(worker.is_duplicate(&honest));
+}
+
+// Test voters running the three-phase variant finalize blocks too.
+#[tokio::test]
+async fn finalize_three_voters_in_three_phases() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 3, 0);
+	tokio::spawn(instantiate_hotstuff_with(&mut net, peers, voters, ConsensusVariant::ThreePhase));
+
+	net.peer(0).push_blocks(10, false);
+	net.run_until_sync().await;
+
+	let net = Arc::new(Mutex::new(net));
+	run_to_completion(10, net.clone(), peers).await;
+
+	for i in 0..3 {
+		assert_eq!(net.lock().peer(i).client().info().finalized_number as u64, 10);
+	}
+}
+
+// Test a subscriber of the consensus events sees the rounds finalizing blocks.
+#[tokio::test]
+async fn finalized_blocks_should_emit_commit_events() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters), 3, 0);
+	let mut events = net.peers[0].data.lock().as_ref().expect("link").event_sink().subscribe();
+	tokio::spawn(instantiate_hotstuff(&mut net, peers));
+
+	net.peer(0).push_blocks(2, false);
+	net.run_until_sync().await;
+
+	let net = Arc::new(Mutex::new(net));
+	run_to_completion(2, net.clone(), peers).await;
+
+	// Peer 0 emits the event right after finalizing the block.
+	let committed = async {
+		loop {
+			match events.recv().await {
+				Ok(ConsensusEvent::Committed { block_number: 2, .. }) => break,
+				Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {},
+				Err(broadcast::error::RecvError::Closed) => panic!("block 2 is never committed"),
+			}
+		}
+	};
+	tokio::time::timeout(Duration::from_secs(10), committed)
+		.await
+		.expect("commit event arrives");
+}
+
+// Test voters with uneven weights finalize blocks while an authority holding just under a third
+// of the weight is offline.
+#[tokio::test]
+async fn finalize_weighted_voters_without_a_third_of_the_weight() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Alice,
+	];
+	let voters: AuthorityList = make_ids(peers)
+		.into_iter()
+		.zip([33, 33, 1, 33])
+		.map(|((authority_id, _), weight)| (authority_id, weight))
+		.collect();
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 4, 0);
+	// Alice never starts, so each of her views times out.
+	tokio::spawn(instantiate_hotstuff_with(
+		&mut net,
+		&peers[..3],
+		voters,
+		ConsensusVariant::Chained,
+	));
+
+	net.peer(0).push_blocks(10, false);
+	net.run_until_sync().await;
+
+	let net = Arc::new(Mutex::new(net));
+	run_to_completion(10, net.clone(), &peers[..3]).await;
+
+	for i in 0..3 {
+		assert_eq!(net.lock().peer(i).client().info().finalized_number as u64, 10);
+	}
+}
+
 // Test when there are three voter and a full node, they can finalize block.
 #[tokio::test]
 async fn finalize_3_voters_with_1_full() {
@@ -368,3 +817,2169 @@ async fn finalize_3_voters_with_1_full() {
 		assert_eq!(net.lock().peer(i).client().info().finalized_number as u64, 10);
 	}
 }
+
+// Test a committed block which failed to import is not finalized and raises a safety alarm.
+#[tokio::test]
+async fn uncommittable_block_should_not_finalize() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters.clone(),
+	)
+	.expect("");
+	let mut alarms = worker.events().subscribe();
+
+	// The client never stored this block, as its import failed.
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(0x42), block_number: 1 };
+	let proposal =
+		Proposal::<Block>::new(QC::default(), None, payload.clone(), 3, voters[0].0.clone(), None);
+
+	assert_eq!(
+		worker.finalize_committed(&proposal, None, CommitTime::local()).await,
+		Err(UncommittableBlock(payload.block_hash.to_string()))
+	);
+	assert_eq!(net.peer(0).client().info().finalized_number, 0);
+	assert_eq!(
+		alarms.try_recv(),
+		Ok(ConsensusEvent::UncommittableBlock {
+			view: 3,
+			block_hash: payload.block_hash,
+			block_number: 1
+		})
+	);
+
+	// The imported block can still be finalized.
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+	let proposal =
+		Proposal::<Block>::new(QC::default(), None, payload, 4, voters[0].0.clone(), None);
+
+	assert_eq!(worker.finalize_committed(&proposal, None, CommitTime::local()).await, Ok(()));
+	assert_eq!(net.peer(0).client().info().finalized_number, 1);
+	assert!(matches!(alarms.try_recv(), Ok(ConsensusEvent::Committed { view: 4, .. })));
+}
+
+// Test a committed block conflicting with more finalized blocks than allowed is not finalized and
+// raises a safety alarm.
+#[tokio::test]
+async fn deep_reorg_should_not_finalize() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	// A fork of one block, then a longer chain of two blocks on genesis.
+	net.peer(0).push_blocks_at(BlockId::Number(0), 1, true);
+	let fork_hash = net.peer(0).client().info().best_hash;
+	net.peer(0).push_blocks_at(BlockId::Number(0), 2, false);
+	let best = net.peer(0).client().info();
+	assert_ne!(best.best_hash, fork_hash);
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters.clone(),
+	)
+	.expect("");
+
+	let payload = Payload::<Block> { block_hash: best.best_hash, block_number: 2 };
+	let proposal =
+		Proposal::<Block>::new(QC::default(), None, payload, 3, voters[0].0.clone(), None);
+	assert_eq!(worker.finalize_committed(&proposal, None, CommitTime::local()).await, Ok(()));
+	assert_eq!(net.peer(0).client().info().finalized_number, 2);
+	let mut alarms = worker.events().subscribe();
+
+	// Committing the fork would revert both finalized blocks.
+	let payload = Payload::<Block> { block_hash: fork_hash, block_number: 1 };
+	let proposal =
+		Proposal::<Block>::new(QC::default(), None, payload, 4, voters[0].0.clone(), None);
+
+	assert_eq!(
+		worker.finalize_committed(&proposal, None, CommitTime::local()).await,
+		Err(ReorgTooDeep(fork_hash.to_string()))
+	);
+	assert_eq!(net.peer(0).client().info().finalized_hash, best.best_hash);
+	assert_eq!(
+		alarms.try_recv(),
+		Ok(ConsensusEvent::ReorgTooDeep {
+			view: 4,
+			block_hash: fork_hash,
+			block_number: 1,
+			depth: 2
+		})
+	);
+}
+
+// Test the leader collecting the votes of a proposal times out the view when the votes are
+// withheld, long before the local timer.
+#[tokio::test]
+async fn withheld_votes_should_time_out_vote_collection() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	// Charlie leads view 2, so collects the votes of the proposal of view 1.
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[2]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+	worker.vote_collection_timeout = Some(Duration::from_millis(100));
+	let mut events = worker.events().subscribe();
+
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("Bob proposes");
+	assert_eq!(worker.handle_proposal(&proposal).await, Ok(()));
+	assert!(worker.vote_deadline.is_some());
+
+	// Only Charlie's own vote arrives.
+	let view = tokio::time::timeout(
+		Duration::from_millis(1000),
+		deadline_expired(&mut worker.vote_deadline),
+	)
+	.await
+	.expect("vote collection times out before the local timer");
+	assert_eq!(view, 1);
+	assert_eq!(worker.handle_vote_collection_timeout(view).await, Ok(()));
+
+	assert!(worker.vote_deadline.is_none());
+	let mut timed_out = false;
+	while let Ok(event) = events.try_recv() {
+		timed_out |= event == ConsensusEvent::TimedOut { view: 1 };
+	}
+	assert!(timed_out);
+}
+
+// Test an own vote is sent to the next leader after the configured delay, in time for its QC to
+// form within the view.
+#[tokio::test]
+async fn delayed_vote_should_still_form_a_qc() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Eve,
+		Sr25519Keyring::Ferdie,
+		Sr25519Keyring::One,
+		Sr25519Keyring::Two,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	// Alice votes for the proposal of Bob, Charlie collects the votes.
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+	worker.ordered_signing = true;
+	let delay = Duration::from_millis(50);
+	worker.vote_broadcast_delay = Some(delay);
+
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("Bob proposes");
+	let start = Instant::now();
+	assert_eq!(worker.handle_proposal(&proposal).await, Ok(()));
+	assert!(worker.take_outbox().is_empty());
+
+	let view = tokio::time::timeout(
+		Duration::from_millis(1000),
+		deadline_expired(&mut worker.vote_broadcast_deadline),
+	)
+	.await
+	.expect("the vote is sent before the view times out");
+	assert!(start.elapsed() >= delay);
+	assert_eq!(worker.handle_vote_broadcast(view), Ok(()));
+	let vote = match worker.take_outbox().as_slice() {
+		[ConsensusMessage::Vote(vote)] => vote.clone(),
+		sent => panic!("expected the vote of Alice, sent {:?}", sent),
+	};
+	assert_eq!(worker.state.view(), 1);
+
+	let mut leader = make_state(peers, 2, 1);
+	assert_eq!(leader.add_vote(&vote), Ok(None));
+	let mut qc = None;
+	for index in 1..peers.len() {
+		let vote = make_state(peers, index, 1).make_vote(&proposal).expect("authority votes");
+		qc = qc.or(leader.add_vote(&vote).unwrap());
+	}
+	assert!(qc.map_or(false, |qc| qc.votes.iter().any(|(id, _)| *id == vote.voter)));
+}
+
+// Test the next leader counts the vote a proposal carries as soon as it handles the proposal.
+#[tokio::test]
+async fn next_leader_should_count_the_proposal_self_vote() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	// Charlie leads view 2, so collects the votes of the proposal of view 1.
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[2]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+
+	let proposal = make_state(peers, 1, 1)
+		.with_proposal_self_vote(true)
+		.make_proposal(payload, None)
+		.expect("Bob proposes");
+	assert_eq!(worker.handle_proposal(&proposal).await, Ok(()));
+
+	// The votes of Bob and Charlie, one short of a quorum.
+	assert_eq!(worker.state.aggregator.buffered_messages(), 2);
+	let vote = make_state(peers, 0, 1).make_vote(&proposal).expect("authority votes");
+	assert_eq!(worker.handle_vote(&vote).await, Ok(()));
+	assert_eq!(worker.state.high_qc_view(), 1);
+}
+
+// Test the certificates published for the RPC are those of the worker after a round.
+#[tokio::test]
+async fn published_certificates_should_match_the_worker() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	// Charlie leads view 2, so collects the votes of the proposal of view 1.
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let certificates = link.shared_certificates();
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[2]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("Bob proposes");
+	assert_eq!(worker.handle_proposal(&proposal).await, Ok(()));
+	for index in [0, 1] {
+		let vote = make_state(peers, index, 1).make_vote(&proposal).expect("authority votes");
+		assert_eq!(worker.handle_vote(&vote).await, Ok(()));
+	}
+	assert_eq!(worker.state.high_qc_view(), 1);
+
+	// What the worker publishes at the end of each step.
+	*worker.certificates.write() = Some(worker.certificates_state());
+	let published = certificates.read().clone().expect("published");
+	assert_eq!(published.locked_qc, worker.state.high_qc().into());
+	assert_eq!(published.locked_qc.view, 1);
+	assert_eq!(published.locked_qc.proposal_hash, proposal.digest());
+	assert_eq!(published.high_tc, None);
+}
+
+// Test the trace of a view records the proposal seen and why the node withheld its vote.
+#[tokio::test]
+async fn view_trace_should_record_the_withheld_vote() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("proposes");
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+	worker.ordered_signing = true;
+	worker.pause.pause();
+
+	assert_eq!(worker.handle_proposal(&proposal).await, Ok(()));
+	assert!(worker.take_outbox().is_empty());
+
+	let trace = worker.view_trace(1).expect("view 1 is traced");
+	assert_eq!(trace.proposal, Some((proposal.digest(), peers[1].public().into())));
+	assert_eq!(trace.vote, Some(VoteDecision::Withheld(VoteWithheld::Paused)));
+	assert!(!trace.qc_formed && !trace.tc_formed);
+	assert_eq!(trace.outcome, None);
+	assert!(worker.view_trace(2).is_none());
+}
+
+// Test each equivocation policy when the leader of view 1 sends two conflicting proposals, the
+// one of the higher digest first while the vote for it waits to be sent.
+#[tokio::test]
+async fn equivocating_proposals_should_follow_the_policy() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+		Sr25519Keyring::Eve,
+		Sr25519Keyring::Ferdie,
+		Sr25519Keyring::One,
+		Sr25519Keyring::Two,
+	];
+	let voters = make_ids(peers);
+
+	for policy in
+		[EquivocationPolicy::Halt, EquivocationPolicy::FirstSeen, EquivocationPolicy::LowestHash]
+	{
+		let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+		let mut proposals = Vec::new();
+		for number in 1..=2 {
+			net.peer(0).push_blocks(1, false);
+			let payload = Payload::<Block> {
+				block_hash: net.peer(0).client().info().best_hash,
+				block_number: number,
+			};
+			proposals.push(make_state(peers, 1, 1).make_proposal(payload, None).expect("proposes"));
+		}
+		proposals.sort_by_key(|proposal| proposal.digest());
+		let (low, high) = (&proposals[0], &proposals[1]);
+
+		let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+		let (mut worker, _) = build_hotstuff_components(
+			net.peers[0].network_service().clone(),
+			link,
+			net.peers[0].sync_service().clone(),
+			crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+			create_keystore(peers[0]),
+			voters.clone(),
+		)
+		.expect("");
+		worker.state.view = 1;
+		worker.ordered_signing = true;
+		worker.vote_broadcast_delay = Some(Duration::from_millis(50));
+		worker.equivocation_policy = policy;
+
+		assert_eq!(worker.handle_proposal(high).await, Ok(()));
+		assert!(worker.delayed_vote.is_some());
+		let result = worker.handle_proposal(low).await;
+
+		if let Some(view) = worker.vote_broadcast_deadline.as_ref().map(|(view, _)| *view) {
+			assert_eq!(worker.handle_vote_broadcast(view), Ok(()));
+		}
+		let votes = worker
+			.take_outbox()
+			.into_iter()
+			.filter_map(|message| match message {
+				ConsensusMessage::Vote(vote) => Some(vote.proposal_hash),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		let author = ProposalEquivocation(peers[1].public().into());
+		match policy {
+			// The vote waiting to be sent is withdrawn.
+			EquivocationPolicy::Halt => {
+				assert_eq!(result, Err(author));
+				assert!(votes.is_empty());
+				assert_eq!(worker.halted_view, Some(1));
+			},
+			EquivocationPolicy::FirstSeen => {
+				assert_eq!(result, Err(author));
+				assert_eq!(votes, vec![high.digest()]);
+				assert!(worker.synchronizer.get_proposal(low.digest()).unwrap().is_none());
+			},
+			// The chain of the lowest proposal is followed, without a second vote in the view.
+			EquivocationPolicy::LowestHash => {
+				assert_eq!(result, Ok(()));
+				assert_eq!(votes, vec![high.digest()]);
+				assert!(worker.synchronizer.get_proposal(low.digest()).unwrap().is_some());
+			},
+		}
+	}
+}
+
+// Test a backup proposes in place of a silent leader, and a late proposal of the leader gets no
+// second vote of the view.
+#[tokio::test]
+async fn silent_leader_should_be_replaced_by_a_backup() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	// Charlie leads view 2 but is offline, Dave backs it up.
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[3]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+	worker.state.fallback_proposers = 1;
+	worker.fallback_proposer_delay = Duration::from_millis(100);
+
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("Bob proposes");
+	assert_eq!(worker.handle_proposal(&proposal).await, Ok(()));
+	let mut qc = QC::<Block> { proposal_hash: proposal.digest(), view: 1, votes: Vec::new() };
+	for index in 0..3 {
+		let vote = make_state(peers, index, 1).make_vote(&proposal).expect("authority votes");
+		qc.add_votes(vote.voter.clone(), vote.signature.clone().expect("vote is signed"));
+		assert_eq!(worker.handle_vote(&vote).await, Ok(()));
+	}
+	assert_eq!(worker.state.view(), 2);
+
+	let view = tokio::time::timeout(
+		Duration::from_millis(1000),
+		deadline_expired(&mut worker.fallback_deadline),
+	)
+	.await
+	.expect("backup proposes before the local timer");
+	assert_eq!(view, 2);
+	assert_eq!(worker.handle_fallback_turn(view).await, Ok(()));
+	assert!(worker.fallback_deadline.is_none());
+	// Dave voted for its own proposal.
+	assert_eq!(worker.state.last_voted_view, 2);
+
+	// The leader wakes up and proposes the view the backup drove.
+	let mut leader = make_state(peers, 2, 1);
+	leader.update_high_qc(&qc);
+	leader.advance_view_from_target(qc.view);
+	let late = leader.make_proposal(proposal.payload.clone(), None).expect("Charlie proposes");
+	assert_eq!(worker.handle_proposal(&late).await, Ok(()));
+	assert!(worker.state.make_vote(&late).is_none());
+}
+
+// Test a panic of the voter loop halts the voter with an alarm, instead of ending its task.
+#[tokio::test]
+async fn panicking_voter_should_halt_with_an_alarm() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.catch_panics = true;
+	let view = worker.state.view();
+	let mut alarms = worker.events().subscribe();
+
+	fn panicking<W>(_: &mut W) -> BoxFuture<'_, ()> {
+		Box::pin(async { panic!("injected handler panic") })
+	}
+	let voter = tokio::spawn(worker.run_with(panicking));
+
+	let alarm = tokio::time::timeout(Duration::from_secs(5), alarms.recv())
+		.await
+		.expect("alarm is raised");
+	assert_eq!(
+		alarm,
+		Ok(ConsensusEvent::WorkerHalted {
+			view,
+			last_message: None,
+			panic: "injected handler panic".to_string(),
+		})
+	);
+
+	// The halted voter is still alive.
+	tokio::time::sleep(Duration::from_millis(100)).await;
+	assert!(!voter.is_finished());
+	voter.abort();
+}
+
+fn make_state(peers: &[Sr25519Keyring], index: usize, view: ViewNumber) -> ConsensusState<Block> {
+	let mut state = ConsensusState::<Block>::new(create_keystore(peers[index]), make_ids(peers));
+	state.view = view;
+	state
+}
+
+// Test the authority a node is configured as must have its key and be in the authority set.
+#[test]
+fn configured_authority_should_be_verified() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let configured = |keystore, authority: Sr25519Keyring| {
+		ConsensusState::<Block>::new(keystore, make_ids(peers))
+			.with_authority_id(Some(authority.public().into()))
+	};
+
+	// Nothing is checked unless an authority is configured.
+	let state = ConsensusState::<Block>::new(MemoryKeystore::new().into(), make_ids(peers));
+	assert_eq!(state.verify_self_authority(), Ok(()));
+
+	let state = configured(create_keystore(Sr25519Keyring::Bob), Sr25519Keyring::Bob);
+	assert_eq!(state.verify_self_authority(), Ok(()));
+
+	let state = configured(create_keystore(Sr25519Keyring::Alice), Sr25519Keyring::Bob);
+	assert_eq!(
+		state.verify_self_authority(),
+		Err(MissingSigningKey(Sr25519Keyring::Bob.public().into()))
+	);
+
+	let state = configured(create_keystore(Sr25519Keyring::Dave), Sr25519Keyring::Dave);
+	assert_eq!(
+		state.verify_self_authority(),
+		Err(SelfNotInAuthoritySet(Sr25519Keyring::Dave.public().into()))
+	);
+}
+
+// Test an authority of the current set shows as missing its key until the key is inserted, after
+// which its signed messages are counted.
+#[test]
+fn signing_status_should_report_missing_key() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let keystore: KeystorePtr = MemoryKeystore::new().into();
+	let mut state = ConsensusState::<Block>::new(keystore.clone(), make_ids(peers));
+	state.view = 1;
+	assert_eq!(state.signing_status().participation, Participation::KeyMissing);
+
+	keystore
+		.sr25519_generate_new(AuthorityId::ID, Some(&Sr25519Keyring::Alice.to_seed()))
+		.unwrap();
+	let status = state.signing_status();
+	assert_eq!(status.participation, Participation::Signing);
+	assert_eq!(status.authority, Some(Sr25519Keyring::Alice.public().into()));
+
+	// Bob leads view 1.
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("leader proposes");
+	state.make_vote(&proposal).expect("authority votes");
+	let status = state.signing_status();
+	assert_eq!((status.votes_signed, status.last_vote_view), (1, Some(1)));
+	assert_eq!((status.proposals_signed, status.last_proposal_view), (0, None));
+}
+
+// Test a key rotated at an authority set change signs every view with a single key, the old key
+// before the activation view and the new key from it.
+#[test]
+fn rotated_key_should_never_sign_a_view_twice() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let rotated = &[Sr25519Keyring::Ferdie, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let keystore = create_keystore(Sr25519Keyring::Alice);
+	keystore
+		.sr25519_generate_new(AuthorityId::ID, Some(&Sr25519Keyring::Ferdie.to_seed()))
+		.unwrap();
+	let mut state = ConsensusState::<Block>::new(keystore, make_ids(peers));
+	state.view = 3;
+	state.schedule_set_change(make_ids(rotated), 5).unwrap();
+
+	let old_key: AuthorityId = Sr25519Keyring::Alice.public().into();
+	let new_key: AuthorityId = Sr25519Keyring::Ferdie.public().into();
+	assert_eq!(state.make_timeout().unwrap().voter, old_key);
+	state.advance_view_from_target(3);
+	assert_eq!(state.make_timeout().unwrap().voter, old_key);
+	assert_eq!(state.set_id(), 0);
+
+	// The epoch boundary.
+	state.advance_view_from_target(4);
+	assert_eq!(state.set_id(), 1);
+	let timeout = state.make_timeout().unwrap();
+	assert_eq!(timeout.voter, new_key);
+	assert_eq!(state.verify_timeout(&timeout), Ok(()));
+
+	// A late proposal of a view the old key signed gets no vote from the new key.
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(4), block_number: 4 };
+	let proposal =
+		Proposal::<Block>::new(QC::default(), None, payload, 4, peers[1].public().into(), None);
+	assert!(state.make_vote(&proposal).is_none());
+
+	state.view = 4;
+	assert_eq!(state.make_timeout().err(), Some(SigningKeyOverlap(4)));
+}
+
+// Test a QC formed before an authority set change still verifies after it, against the set of
+// its view.
+#[test]
+fn qc_of_the_previous_set_should_verify_after_the_change() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let joined = &[Sr25519Keyring::Bob, Sr25519Keyring::Charlie, Sr25519Keyring::Dave];
+	let mut state = make_state(peers, 1, 4);
+	state.schedule_set_change(make_ids(joined), 5).unwrap();
+	state.advance_view_from_target(4);
+	assert_eq!(state.set_id(), 1);
+
+	let signed_qc = |view| {
+		let mut qc = QC::<Block> { proposal_hash: H256::repeat_byte(4), view, votes: Vec::new() };
+		for key in [Sr25519Keyring::Alice, Sr25519Keyring::Charlie, Sr25519Keyring::Bob] {
+			qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+		}
+		qc
+	};
+
+	assert_eq!(state.verify_qc(&signed_qc(4)), Ok(()));
+	assert_eq!(
+		state.verify_qc(&signed_qc(5)),
+		Err(UnknownAuthority(Sr25519Keyring::Alice.public().into()))
+	);
+}
+
+// Test a proposal at the activation view of an authority set change verifies against the new set
+// within the transition window, even before the node switched, and that one sent for a set out of
+// the window is rejected.
+#[test]
+fn proposal_at_the_activation_view_should_verify_against_the_new_set() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let joined = &[Sr25519Keyring::Bob, Sr25519Keyring::Charlie, Sr25519Keyring::Dave];
+	let mut state = make_state(peers, 1, 4);
+	state.schedule_set_change(make_ids(joined), 5).unwrap();
+	assert_eq!(state.set_id(), 0);
+
+	// The QC of the last view of the old set, and proposals of the activation view extending it.
+	let mut qc = QC::<Block> { proposal_hash: H256::repeat_byte(4), view: 4, votes: Vec::new() };
+	for key in [Sr25519Keyring::Alice, Sr25519Keyring::Charlie, Sr25519Keyring::Bob] {
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	}
+	let propose = |author: Sr25519Keyring, view| {
+		let payload = Payload::<Block> { block_hash: H256::repeat_byte(5), block_number: 5 };
+		let mut proposal =
+			Proposal::<Block>::new(qc.clone(), None, payload, view, author.public().into(), None);
+		proposal.signature = Some(author.sign(proposal.digest().as_ref()).into());
+		proposal
+	};
+	// Dave leads the activation view in the new set, Charlie would in the old one.
+	let (new_leader, old_leader) =
+		(propose(Sr25519Keyring::Dave, 5), propose(Sr25519Keyring::Charlie, 5));
+
+	// Checked against the current set, the new leader is rejected.
+	assert_eq!(state.verify_proposal(&new_leader), Err(WrongProposer));
+	assert_eq!(state.verify_proposal(&old_leader), Ok(()));
+
+	let mut state = state.with_transition_window(Some(2));
+	assert_eq!(state.verify_proposal(&new_leader), Ok(()));
+	assert_eq!(state.verify_proposal(&old_leader), Err(WrongProposer));
+
+	// Within the window either set may send it, and only the current set out of it.
+	assert_eq!(state.check_proposal_set_id(&new_leader, 0), Ok(()));
+	assert_eq!(state.check_proposal_set_id(&new_leader, 1), Ok(()));
+	assert_eq!(state.check_proposal_set_id(&new_leader, 2), Err(SetIdOutsideTransition(2)));
+	let late = propose(Sr25519Keyring::Alice, 8);
+	assert!(!state.in_transition_window(8));
+	assert_eq!(state.check_proposal_set_id(&late, 1), Err(SetIdOutsideTransition(1)));
+	assert_eq!(state.check_proposal_set_id(&late, 0), Ok(()));
+
+	// Once the node switched, the same proposal still verifies.
+	state.advance_view_from_target(4);
+	assert_eq!(state.set_id(), 1);
+	assert_eq!(state.verify_proposal(&new_leader), Ok(()));
+	assert_eq!(state.check_proposal_set_id(&new_leader, 0), Ok(()));
+}
+
+// Test a node with a proposal time window timestamps its proposals and rejects those dated out of
+// the window, while a node without it ignores the timestamps.
+#[test]
+fn proposal_out_of_the_time_window_should_be_rejected() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let mut leader = make_state(peers, 1, 1).with_proposal_time_window(Some(10_000));
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal = leader.make_proposal(payload, None).unwrap();
+	assert!(proposal.timestamp.is_some());
+
+	let state = make_state(peers, 0, 1).with_proposal_time_window(Some(10_000));
+	assert_eq!(state.verify_proposal(&proposal), Ok(()));
+
+	let mut stale = proposal.clone();
+	let timestamp = proposal.timestamp.unwrap() - 60_000;
+	stale.timestamp = Some(timestamp);
+	assert_eq!(state.verify_proposer(&stale), Err(ProposalOutsideClockWindow(timestamp)));
+
+	let state = make_state(peers, 0, 1);
+	assert_eq!(state.verify_proposer(&stale), Ok(()));
+}
+
+// Test the buffered votes and timeouts of a stalled past view are dropped once they expire, while
+// those of the current view are kept however old.
+#[test]
+fn expired_buffered_messages_should_be_dropped() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let mut state = make_state(peers, 0, 2);
+
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal =
+		Proposal::<Block>::new(QC::default(), None, payload, 1, peers[0].public().into(), None);
+	let vote = make_state(peers, 1, 1).make_vote(&proposal).expect("authority votes");
+	let timeout = make_state(peers, 1, 2).make_timeout().expect("authority times out");
+	assert_eq!(state.add_vote(&vote), Ok(None));
+	assert!(state.add_timeout(&timeout).unwrap().is_none());
+	assert_eq!(state.aggregator.buffered_messages(), 2);
+
+	let ttl = Duration::from_secs(60);
+	assert_eq!(state.prune_expired_messages(Instant::now(), ttl), 0);
+	assert_eq!(state.prune_expired_messages(Instant::now() + ttl, ttl), 1);
+	assert_eq!(state.aggregator.buffered_messages(), 1);
+
+	// Once the view passes, its timeout expires too.
+	state.view = 3;
+	assert_eq!(state.prune_expired_messages(Instant::now() + ttl, ttl), 1);
+	assert_eq!(state.aggregator.buffered_messages(), 0);
+}
+
+// Test a proposal flooded with votes retains a bounded number of them and still forms its QC.
+#[test]
+fn vote_retention_should_be_bounded() {
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let mut state = make_state(peers, 0, 1);
+
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal =
+		Proposal::<Block>::new(QC::default(), None, payload, 1, peers[0].public().into(), None);
+	let votes = (0..peers.len())
+		.map(|index| make_state(peers, index, 1).make_vote(&proposal).expect("authority votes"))
+		.collect::<Vec<_>>();
+
+	// Signed votes of keys outside the set, and replays of a legitimate vote.
+	for index in 0..100 {
+		let pair = sp_core::sr25519::Pair::from_string(&format!("//Spurious{}", index), None)
+			.expect("derives key");
+		let mut spurious = Vote::<Block>::new(proposal.digest(), 1, pair.public().into());
+		spurious.signature = Some(pair.sign(spurious.digest().as_ref()).into());
+		assert_eq!(state.add_vote(&spurious), Ok(None));
+		assert_eq!(state.add_vote(&votes[0]), Ok(None));
+	}
+	assert_eq!(state.aggregator.buffered_messages(), 1);
+	assert_eq!(state.dropped_votes(), 100);
+
+	assert_eq!(state.add_vote(&votes[1]), Ok(None));
+	let qc = state.add_vote(&votes[2]).unwrap().expect("quorum forms a QC");
+	assert_eq!(qc.votes.len(), 3);
+	assert!(state.add_vote(&votes[3]).unwrap().is_some());
+	assert!(state.aggregator.buffered_messages() <= peers.len() + VOTE_RETENTION_SLACK);
+}
+
+// Test the vote a proposal carries counts toward its QC, and a vote which isn't its author's vote
+// for it has the proposal rejected.
+#[test]
+fn proposal_self_vote_should_seed_the_tally() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+
+	// Bob leads view 1.
+	let mut leader = make_state(peers, 1, 1).with_proposal_self_vote(true);
+	let proposal = leader.make_proposal(payload.clone(), None).expect("leader makes proposal");
+	let self_vote = proposal.self_vote.clone().expect("proposal carries the vote of its leader");
+	assert!(leader.make_vote(&proposal).is_none());
+
+	let mut next_leader = make_state(peers, 2, 1);
+	assert_eq!(next_leader.verify_proposal(&proposal), Ok(()));
+	assert_eq!(next_leader.add_vote(&self_vote), Ok(None));
+	let vote = make_state(peers, 0, 1).make_vote(&proposal).expect("authority votes");
+	assert_eq!(next_leader.add_vote(&vote), Ok(None));
+	let vote = next_leader.make_vote(&proposal).expect("authority votes");
+	let qc = next_leader.add_vote(&vote).unwrap().expect("quorum forms a QC");
+	assert!(qc.votes.iter().any(|(id, _)| *id == proposal.author));
+
+	// Another authority's vote, and the leader's vote for another proposal.
+	let mut forged = proposal.clone();
+	forged.self_vote = make_state(peers, 0, 1).make_vote(&proposal).map(Box::new);
+	assert_eq!(next_leader.verify_proposal(&forged), Err(InvalidSelfVote));
+
+	let other = Proposal::<Block>::new(
+		QC::default(),
+		None,
+		Payload::<Block> { block_hash: H256::repeat_byte(2), block_number: 1 },
+		1,
+		proposal.author.clone(),
+		None,
+	);
+	let mut mismatched = proposal.clone();
+	mismatched.self_vote = make_state(peers, 1, 1).make_vote(&other).map(Box::new);
+	assert_eq!(next_leader.verify_proposal(&mismatched), Err(InvalidSelfVote));
+
+	let mut unsigned = proposal;
+	unsigned.self_vote.as_mut().unwrap().signature = None;
+	assert_eq!(next_leader.verify_proposal(&unsigned), Err(NullSignature));
+}
+
+// Test the votes of nodes with a vote time source carry their clocks to the next leader, whose
+// proposal times the commit of their proposal by the median clock, and by the local clock when
+// too few votes are timestamped.
+#[test]
+fn commit_time_should_be_the_median_of_the_vote_timestamps() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let clocks = [1_700_000_005_000, 1_700_000_001_000, 1_700_000_003_000];
+	let voter = |index: usize, clock: Option<u64>| {
+		make_state(peers, index, 1)
+			.with_vote_time_source(clock.map(|clock| TimeSource::new(move || clock)))
+	};
+
+	// Bob leads view 1 and Charlie view 2.
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).unwrap();
+	let child = |timestamped: usize| {
+		let mut next_leader = voter(2, Some(clocks[2]));
+		let mut qc = None;
+		for index in 0..peers.len() {
+			let clock = (index < timestamped || index == 2).then_some(clocks[index]);
+			let vote = voter(index, clock).make_vote(&proposal).expect("authority votes");
+			assert_eq!(vote.timestamp.is_some(), clock.is_some());
+			qc = next_leader.add_vote(&vote).unwrap();
+		}
+		next_leader.update_high_qc(&qc.expect("quorum forms a QC"));
+		next_leader.view = 2;
+		let payload = Payload::<Block> { block_hash: H256::repeat_byte(2), block_number: 2 };
+		next_leader.make_proposal(payload, None).unwrap()
+	};
+
+	let timed = child(peers.len());
+	assert_eq!(timed.qc_timestamps.len(), 3);
+	assert_eq!(make_state(peers, 0, 2).verify_proposal(&timed), Ok(()));
+	let time = CommitTime::certified_by(&timed);
+	assert_eq!(time, CommitTime { timestamp: 1_700_000_003_000, local: false });
+
+	// Only two clocks of the three votes of the quorum.
+	let untimed = child(1);
+	assert!(untimed.qc_timestamps.is_empty());
+	assert_eq!(make_state(peers, 0, 2).verify_proposal(&untimed), Ok(()));
+	assert!(CommitTime::certified_by(&untimed).local);
+}
+
+// Test an authority often absent has a low vote participation, and an authority joining the set
+// is only measured from the views after it joined.
+#[test]
+fn absent_authority_should_have_low_participation() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let joined = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let mut state = make_state(peers, 0, 1);
+	state.schedule_set_change(make_ids(joined), 6).unwrap();
+
+	for view in 1..=10 {
+		let payload =
+			Payload::<Block> { block_hash: H256::repeat_byte(view as u8), block_number: view };
+		let proposal = Proposal::<Block>::new(
+			QC::default(),
+			None,
+			payload,
+			view,
+			peers[0].public().into(),
+			None,
+		);
+
+		// Charlie only votes every fourth view, Dave every view since it joined.
+		let mut voters = vec![0, 1];
+		if view % 4 == 0 {
+			voters.push(2);
+		}
+		if view >= 6 {
+			voters.push(3);
+		}
+		for index in voters {
+			let mut voter = ConsensusState::<Block>::new(
+				create_keystore(joined[index]),
+				make_ids(if view >= 6 { joined } else { peers }),
+			);
+			voter.view = view;
+			let vote = voter.make_vote(&proposal).expect("authority votes");
+			state.add_vote(&vote).unwrap();
+		}
+		state.advance_view_from_target(view);
+	}
+
+	let participation = state.participation();
+	let rate = |peer: Sr25519Keyring| {
+		let id = AuthorityId::from(peer.public());
+		participation
+			.iter()
+			.find(|(authority, _)| *authority == id)
+			.expect("measured")
+			.1
+	};
+	assert_eq!(participation.len(), 4);
+	assert_eq!(rate(Sr25519Keyring::Alice), 1.0);
+	assert_eq!(rate(Sr25519Keyring::Charlie), 0.2);
+	assert_eq!(rate(Sr25519Keyring::Dave), 1.0);
+}
+
+// Test the leaders take turns among the authorities with a weight, skipping one of weight zero.
+#[test]
+fn zero_weight_authority_should_never_lead() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let mut authorities = make_ids(peers);
+	authorities[1].1 = 0;
+	let state = ConsensusState::<Block>::new(create_keystore(peers[1]), authorities.clone());
+
+	let leaders = (0..4).map(|view| state.view_leader(view)).collect::<Vec<_>>();
+	assert_eq!(
+		leaders,
+		[&authorities[0], &authorities[2], &authorities[0], &authorities[2]]
+			.map(|(id, _)| id.clone())
+	);
+	assert!(!state.is_leader());
+}
+
+// Test every node derives the same fallback chain from the leader rotation, and proposals are
+// accepted from its members only.
+#[test]
+fn fallback_chain_should_follow_the_leader_rotation() {
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let ids = make_ids(peers).into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+	let state = |index| make_state(peers, index, 3).with_fallback_proposers(2);
+
+	for index in 0..peers.len() {
+		assert_eq!(
+			state(index).fallback_chain(3),
+			vec![ids[3].clone(), ids[0].clone(), ids[1].clone()]
+		);
+	}
+	assert_eq!(make_state(peers, 0, 3).fallback_chain(3), vec![ids[3].clone()]);
+
+	// The third backup is beyond the chain.
+	let qc = QC::<Block> { proposal_hash: H256::repeat_byte(2), view: 2, votes: Vec::new() };
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal = |author: &AuthorityId| {
+		Proposal::<Block>::new(qc.clone(), None, payload.clone(), 3, author.clone(), None)
+	};
+	assert_eq!(state(0).verify_proposer(&proposal(&ids[1])), Ok(()));
+	assert_eq!(state(0).verify_proposer(&proposal(&ids[2])), Err(WrongProposer));
+	assert_eq!(make_state(peers, 0, 3).verify_proposer(&proposal(&ids[1])), Err(WrongProposer));
+}
+
+// Test the high TC only moves to later views than it and the high QC, whatever the order the TCs
+// arrive in, and justifies a proposal only when no QC of the previous view is known.
+#[test]
+fn highest_tc_should_justify_the_view_jump() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let tc = |view| TC::<Block> {
+		view,
+		votes: Vec::new(),
+		high_qc: QC::default(),
+		_phantom: std::marker::PhantomData,
+	};
+	let mut state = make_state(peers, 0, 6);
+	state.update_high_qc(&QC::<Block> {
+		proposal_hash: H256::repeat_byte(1),
+		view: 2,
+		votes: Vec::new(),
+	});
+
+	assert!(!state.update_high_tc(&tc(2)));
+	assert!(state.update_high_tc(&tc(3)));
+	assert!(state.update_high_tc(&tc(5)));
+	assert!(!state.update_high_tc(&tc(4)));
+	assert!(!state.update_high_tc(&tc(5)));
+	assert_eq!(state.high_tc().map(|tc| tc.view), Some(5));
+
+	// The QC of view 4 is older than the TC of view 5, which justifies view 6.
+	state.update_high_qc(&QC::<Block> {
+		proposal_hash: H256::repeat_byte(4),
+		view: 4,
+		votes: Vec::new(),
+	});
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal = state.make_proposal(payload.clone(), None).expect("TC justifies the proposal");
+	assert_eq!((proposal.qc.view, proposal.tc.map(|tc| tc.view)), (4, Some(5)));
+
+	// A QC of view 5 makes the TC of the same view useless.
+	state.update_high_qc(&QC::<Block> {
+		proposal_hash: H256::repeat_byte(5),
+		view: 5,
+		votes: Vec::new(),
+	});
+	assert!(state.high_tc().is_none());
+	assert!(!state.update_high_tc(&tc(5)));
+	let proposal = state.make_proposal(payload, None).expect("QC justifies the proposal");
+	assert_eq!((proposal.qc.view, proposal.tc.map(|tc| tc.view)), (5, None));
+}
+
+// Test a proposal justified by a TC doesn't extend a QC older than the high QC of the TC.
+#[test]
+fn proposal_should_extend_the_tc_high_qc() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let qc = |view| QC::<Block> {
+		proposal_hash: H256::repeat_byte(view as u8),
+		view,
+		votes: Vec::new(),
+	};
+	let tc = TC::<Block> {
+		view: 5,
+		votes: Vec::new(),
+		high_qc: qc(4),
+		_phantom: std::marker::PhantomData,
+	};
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	// Alice leads view 6.
+	let author: AuthorityId = peers[0].public().into();
+	let proposal = |qc_view| {
+		Proposal::<Block>::new(
+			qc(qc_view),
+			Some(tc.clone()),
+			payload.clone(),
+			6,
+			author.clone(),
+			None,
+		)
+	};
+
+	let replica = make_state(peers, 1, 6);
+	assert_eq!(replica.verify_proposer(&proposal(4)), Ok(()));
+	assert_eq!(replica.verify_proposer(&proposal(3)), Err(TcHighQcMismatch));
+
+	// A leader which missed the QC of view 4 learns it from the TC.
+	let mut leader = make_state(peers, 0, 6);
+	leader.update_high_qc(&qc(3));
+	assert!(leader.update_high_tc(&tc));
+	let proposal = leader.make_proposal(payload, None).expect("TC justifies the proposal");
+	assert_eq!(proposal.qc.view, 4);
+	assert_eq!(replica.verify_proposer(&proposal), Ok(()));
+}
+
+// Test a leader holding both a QC and a TC of the previous view justifies its proposal with the QC.
+#[test]
+fn qc_should_be_preferred_over_tc_of_the_same_view() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let qc = |view| QC::<Block> {
+		proposal_hash: H256::repeat_byte(view as u8),
+		view,
+		votes: Vec::new(),
+	};
+	let tc = TC::<Block> {
+		view: 5,
+		votes: Vec::new(),
+		high_qc: qc(4),
+		_phantom: std::marker::PhantomData,
+	};
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+
+	// Alice leads view 6, whichever of the QC and the TC arrives first.
+	for qc_first in [true, false] {
+		let mut leader = make_state(peers, 0, 6);
+		if qc_first {
+			leader.update_high_qc(&qc(5));
+			leader.update_high_tc(&tc);
+		} else {
+			leader.update_high_tc(&tc);
+			leader.update_high_qc(&qc(5));
+		}
+
+		let proposal = leader.make_proposal(payload.clone(), Some(tc.clone())).unwrap();
+		assert_eq!(proposal.qc, qc(5));
+		assert!(proposal.tc.is_none());
+		assert_eq!(make_state(peers, 1, 6).verify_proposer(&proposal), Ok(()));
+	}
+}
+
+// Test a proposal which skips a timed out view must carry the TC of that view.
+#[test]
+fn proposal_after_timeout_should_carry_tc() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+
+	// View 1 times out, Charlie is the leader of view 2.
+	let mut leader = make_state(peers, 2, 1);
+	let mut tc = None;
+	for index in 0..peers.len() {
+		let timeout = make_state(peers, index, 1).make_timeout().expect("authority makes timeout");
+		tc = leader.add_timeout(&timeout).expect("timeout is valid").or(tc);
+	}
+	let tc = tc.expect("timeouts of all authorities form a TC");
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+
+	leader.advance_view_from_target(tc.view);
+	assert_eq!(leader.view(), 2);
+	assert_eq!(
+		leader.make_proposal(payload.clone(), None).err(),
+		Some(MissingTimeoutJustification)
+	);
+
+	leader.update_high_tc(&tc);
+	let proposal = leader.make_proposal(payload.clone(), None).expect("leader makes proposal");
+	assert_eq!(proposal.tc.as_ref().map(|tc| tc.view), Some(1));
+
+	let replica = make_state(peers, 0, 2);
+	assert_eq!(replica.verify_proposal(&proposal), Ok(()));
+
+	let mut unjustified = proposal.clone();
+	unjustified.tc = None;
+	assert_eq!(replica.verify_proposal(&unjustified), Err(MissingTimeoutJustification));
+
+	// A proposal extending the QC of the previous view needs no TC.
+	let mut qc = QC::<Block> { proposal_hash: proposal.digest(), view: 2, votes: Vec::new() };
+	for index in 0..peers.len() {
+		let vote = make_state(peers, index, 2).make_vote(&proposal).expect("authority votes");
+		qc.add_votes(vote.voter, vote.signature.expect("vote is signed"));
+	}
+	let mut next_leader = make_state(peers, 0, 2);
+	next_leader.update_high_qc(&qc);
+	next_leader.advance_view_from_target(qc.view);
+	let proposal = next_leader.make_proposal(payload, None).expect("leader makes proposal");
+	assert!(proposal.tc.is_none());
+	assert_eq!(make_state(peers, 1, 3).verify_proposal(&proposal), Ok(()));
+}
+
+// Test nil votes form the TC of their view, and a QC of nil is never formed nor accepted.
+#[test]
+fn nil_votes_should_form_a_tc_but_never_a_qc() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+
+	// No proposal of view 1 arrived, Charlie is the leader of view 2.
+	let mut leader = make_state(peers, 2, 1);
+	let mut tc = None;
+	for index in 0..peers.len() {
+		let timeout = make_state(peers, index, 1).make_timeout().expect("authority makes timeout");
+		let nil = Vote::nil(timeout);
+		assert_eq!(leader.verify_vote(&nil), Ok(()));
+		assert_eq!(leader.add_vote(&nil), Err(NilCertificate));
+
+		let timeout = nil.as_timeout().expect("nil vote stands for a timeout");
+		assert_eq!(leader.verify_timeout(&timeout), Ok(()));
+		tc = leader.add_timeout(&timeout).expect("timeout is valid").or(tc);
+	}
+	let tc = tc.expect("nil votes of all authorities form a TC");
+	assert_eq!(tc.view, 1);
+	assert_eq!(tc.verify(&make_ids(peers)), Ok(()));
+
+	// A vote for a proposal is no nil vote.
+	let vote = Vote::<Block>::new(H256::repeat_byte(1), 1, peers[0].public().into());
+	assert!(!vote.is_nil() && vote.as_timeout().is_none());
+
+	// Signatures of nil as a proposal make no QC, so nil is never committed.
+	let mut qc =
+		QC::<Block> { proposal_hash: Vote::<Block>::nil_hash(), view: 1, votes: Vec::new() };
+	for key in peers {
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	}
+	assert_eq!(leader.verify_qc(&qc), Err(NilCertificate));
+}
+
+// Test awaiting a commit height while the voters are driven.
+#[tokio::test]
+async fn commit_at_least_should_resolve_on_commit() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters), 3, 0);
+	tokio::spawn(instantiate_hotstuff(&mut net, peers));
+
+	net.peer(0).push_blocks(10, false);
+	net.run_until_sync().await;
+
+	let watcher = CommitWatcher::<Block, _>::new(net.peer(0).client().as_client());
+	// Subscribed before the voters commit anything.
+	let commit = watcher.commit_at_least(5);
+	assert_eq!(net.peer(0).client().info().finalized_number, 0);
+
+	let committed = Arc::new(Mutex::new(None));
+	let wait_for = {
+		let committed = committed.clone();
+		commit.map(move |block| *committed.lock() = block)
+	};
+
+	let net = Arc::new(Mutex::new(net));
+	run_until_complete(Box::pin(wait_for), &net).await;
+
+	let block = committed.lock().take().expect("voters commit block 5");
+	assert!(block.number >= 5);
+	assert!(net.lock().peer(0).client().info().finalized_number >= block.number);
+	assert_eq!(watcher.commit_at_least(block.number).await, Some(block));
+}
+
+// Test commits made in quick succession are delivered as one range covering every finalized
+// block, even those finalized implicitly along with a later one.
+#[tokio::test]
+async fn coalesced_commits_should_cover_every_block() {
+	let peers = &[Sr25519Keyring::Alice];
+	let mut net = TestNet::new(TestApi::new(make_ids(peers)), 1, 0);
+	net.peer(0).push_blocks(20, false);
+
+	let client = net.peer(0).client().clone();
+	let hashes = (1..=20)
+		.map(|number| client.as_client().hash(number).unwrap().expect("block is imported"))
+		.collect::<Vec<_>>();
+
+	// Subscribed before the commits.
+	let watcher = CommitWatcher::<Block, _>::new(client.as_client());
+	let mut each = Box::pin(watcher.commits());
+	let coalescing = watcher.with_coalescing_window(Duration::from_millis(200));
+	let mut coalesced = Box::pin(coalescing.commits());
+
+	// Blocks 1 to 10 one at a time, then blocks 15 and 20 finalizing the blocks before them.
+	for number in (1..=10).chain([15, 20]) {
+		client.finalize_block(hashes[number - 1], None, true).unwrap();
+	}
+
+	let range = coalesced.next().await.expect("client is running");
+	assert_eq!((range.first, range.last.number, range.last.hash), (1, 20, hashes[19]));
+	assert_eq!(range.hashes, hashes);
+
+	// Without coalescing, each commit is a range of its own.
+	let mut ranges: Vec<CommittedRange<Block>> = Vec::new();
+	while ranges.last().map_or(true, |range| range.last.number < 20) {
+		ranges.push(each.next().await.expect("client is running"));
+	}
+	assert_eq!(ranges.len(), 12);
+	assert_eq!((ranges[10].first, ranges[10].last.number), (11, 15));
+	assert_eq!(ranges.into_iter().flat_map(|range| range.hashes).collect::<Vec<_>>(), hashes);
+}
+
+// Test blocks finalized in a batch while catching up are finalized and notified together, and a
+// batch failing midway finalizes none of its blocks.
+#[tokio::test]
+async fn batched_finalization_should_be_all_or_nothing() {
+	let peers = &[Sr25519Keyring::Alice];
+	let mut net = TestNet::new(TestApi::new(make_ids(peers)), 1, 0);
+	net.peer(0).push_blocks(10, false);
+
+	let client = net.peer(0).client().as_client();
+	let hashes = (1..=10)
+		.map(|number| client.hash(number).unwrap().expect("block is imported"))
+		.collect::<Vec<_>>();
+	let mut finality = client.finality_notification_stream();
+
+	// A block of the batch is unknown, after blocks which could be finalized.
+	let batch = [hashes[0], hashes[1], H256::repeat_byte(7), hashes[3]].map(|hash| (hash, None));
+	assert!(finalize_batch(&*client, &batch).is_err());
+	// Blocks out of order.
+	let batch = [hashes[2], hashes[1]].map(|hash| (hash, None));
+	assert!(finalize_batch(&*client, &batch).is_err());
+	assert_eq!(client.info().finalized_number, 0);
+
+	// Catching up on blocks 1 to 5.
+	let batch = hashes[..5]
+		.iter()
+		.map(|hash| (*hash, Some((HOTSTUFF_ENGINE_ID, hash.encode()))))
+		.collect::<Vec<_>>();
+	finalize_batch(&*client, &batch).expect("blocks are in order");
+	assert_eq!(client.info().finalized_number, 5);
+	assert!(client.justifications(hashes[4]).unwrap().is_some());
+
+	// One notification for the whole batch, none for the failed ones.
+	let notification = finality.next().await.expect("client is running");
+	assert_eq!(notification.hash, hashes[4]);
+	assert_eq!(notification.tree_route.to_vec(), hashes[..4].to_vec());
+	assert!(finality.next().now_or_never().is_none());
+}
+
+// The proposals of Alice for `blocks` at the views of their numbers, each extending the one
+// before, and the QCs certifying them.
+fn certified_chain(blocks: &[H256]) -> Vec<(Proposal<Block>, QC<Block>)> {
+	let key = Sr25519Keyring::Alice;
+	let mut chain: Vec<(Proposal<Block>, QC<Block>)> = Vec::new();
+	for (index, hash) in blocks.iter().enumerate() {
+		let view = index as ViewNumber + 1;
+		let parent_qc = chain.last().map(|(_, qc)| qc.clone()).unwrap_or_default();
+		let payload = Payload::<Block> { block_hash: *hash, block_number: view };
+		let proposal =
+			Proposal::<Block>::new(parent_qc, None, payload, view, key.public().into(), None);
+
+		let mut qc = QC::<Block> { proposal_hash: proposal.digest(), view, votes: Vec::new() };
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+		chain.push((proposal, qc));
+	}
+	chain
+}
+
+// Test a chain of QCs finalizes the blocks it commits, and only those before a broken link.
+#[tokio::test]
+async fn qc_chain_should_finalize_up_to_its_first_broken_link() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(6, false);
+	let client = net.peer(0).client().as_client();
+	let hashes = (1..=6)
+		.map(|number| client.hash(number).unwrap().expect("block is imported"))
+		.collect::<Vec<_>>();
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+
+	let chain = certified_chain(&hashes);
+	for (proposal, _) in chain.iter() {
+		worker.synchronizer.save_proposal(proposal).expect("proposal is saved");
+	}
+	let qcs = chain.iter().map(|(_, qc)| qc.clone()).collect::<Vec<_>>();
+
+	// Each proposal of views 1 to 3 is committed by the QC of its child.
+	assert_eq!(worker.finalize_from_qc_chain(&qcs[..4]).await, Ok(3));
+	assert_eq!(client.info().finalized_number, 3);
+	assert_eq!(worker.state.last_committed.map(|(_, view)| view), Some(3));
+
+	// The QC of view 5 is missing, so the proposal of view 6 doesn't extend the one of view 4.
+	let gap = [qcs[3].clone(), qcs[5].clone()];
+	assert!(matches!(worker.finalize_from_qc_chain(&gap).await, Err(BrokenQcChain(_))));
+	assert_eq!(client.info().finalized_number, 3);
+
+	// The blocks committed before the gap are finalized.
+	let gap = [qcs[3].clone(), qcs[4].clone(), qcs[5].clone(), qcs[3].clone()];
+	assert!(matches!(worker.finalize_from_qc_chain(&gap).await, Err(BrokenQcChain(_))));
+	assert_eq!(client.info().finalized_number, 5);
+}
+
+// Test messages of the previous authority set are tolerated and older ones are rejected.
+#[test]
+fn authority_set_regression_should_be_rejected() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let mut state = make_state(peers, 0, 1);
+	state.set_id = 5;
+
+	assert_eq!(state.check_set_id(6), Ok(()));
+	assert_eq!(state.check_set_id(5), Ok(()));
+	// Lagging peers in the middle of the transition.
+	assert_eq!(state.check_set_id(4), Ok(()));
+	assert_eq!(state.regressed_messages(), 0);
+
+	assert_eq!(state.check_set_id(3), Err(AuthoritySetRegression(3)));
+	assert_eq!(state.check_set_id(0), Err(AuthoritySetRegression(0)));
+	assert_eq!(state.regressed_messages(), 2);
+}
+
+// Test each vote signed in a batch binds to its own proposal.
+#[test]
+fn batch_signed_votes_should_verify() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let mut state = make_state(peers, 0, 3);
+
+	let proposals = [3, 1, 2, 2]
+		.into_iter()
+		.map(|view| {
+			let payload =
+				Payload::<Block> { block_hash: H256::repeat_byte(view as u8), block_number: view };
+			Proposal::<Block>::new(
+				QC::default(),
+				None,
+				payload,
+				view,
+				peers[0].public().into(),
+				None,
+			)
+		})
+		.collect::<Vec<_>>();
+
+	let votes = state.make_votes(&proposals);
+
+	// In view order, without a second vote for view 2.
+	assert_eq!(votes.iter().map(|vote| vote.view).collect::<Vec<_>>(), vec![1, 2, 3]);
+	for (vote, proposal) in votes.iter().zip([&proposals[1], &proposals[2], &proposals[0]]) {
+		assert_eq!(vote.proposal_hash, proposal.digest());
+		assert_eq!(vote.verify(state.authorities()), Ok(()));
+	}
+
+	// Every view was voted.
+	assert!(state.make_vote(&proposals[0]).is_none());
+}
+
+// Test signed messages are in the audit log, in signing order, once they can be sent.
+#[test]
+fn signed_messages_should_be_audited_before_release() {
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("audit.log");
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+
+	// Bob leads view 1.
+	let mut state =
+		make_state(peers, 1, 1).with_audit_log(AuditLog::open(&path).expect("open audit log"));
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+
+	let timeout = state.make_timeout().expect("authority makes timeout");
+	let proposal = state.make_proposal(payload, None).expect("leader makes proposal");
+	let vote = state.make_vote(&proposal).expect("authority votes");
+
+	// The vote is returned, not yet gossiped, and already recorded.
+	let entries = AuditLog::read_entries(&path).expect("read audit log");
+	assert_eq!(
+		entries
+			.iter()
+			.map(|entry| (entry.kind, entry.view, entry.digest.clone()))
+			.collect::<Vec<_>>(),
+		vec![
+			(SignedKind::Timeout, 1, array_bytes::bytes2hex("0x", timeout.digest())),
+			(SignedKind::Proposal, 1, array_bytes::bytes2hex("0x", proposal.digest())),
+			(SignedKind::Vote, 1, array_bytes::bytes2hex("0x", vote.digest())),
+		]
+	);
+	assert!(entries.windows(2).all(|pair| pair[0].timestamp <= pair[1].timestamp));
+}
+
+// Test a QC certifying another proposal of a view than the QC known raises a safety alarm.
+#[tokio::test]
+async fn conflicting_qcs_should_raise_an_alarm() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 2;
+	let mut alarms = worker.events().subscribe();
+
+	let signed_qc = |byte| {
+		let key = Sr25519Keyring::Alice;
+		let mut qc =
+			QC::<Block> { proposal_hash: H256::repeat_byte(byte), view: 2, votes: Vec::new() };
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+		qc
+	};
+
+	assert_eq!(worker.inject_qc(signed_qc(1)), Ok(()));
+	assert_eq!(worker.inject_qc(signed_qc(1)), Ok(()));
+	assert_eq!(worker.inject_qc(signed_qc(2)), Ok(()));
+
+	let events = std::iter::from_fn(|| alarms.try_recv().ok()).collect::<Vec<_>>();
+	let conflicts = events
+		.into_iter()
+		.filter(|event| matches!(event, ConsensusEvent::ConflictingQcs { .. }))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		conflicts,
+		vec![ConsensusEvent::ConflictingQcs {
+			view: 2,
+			first: H256::repeat_byte(1),
+			second: H256::repeat_byte(2),
+		}]
+	);
+}
+
+// Test injected certificates drive the worker only when they are valid.
+#[tokio::test]
+async fn injected_certificates_should_advance_the_worker() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters.clone(),
+	)
+	.expect("");
+	worker.state.view = 2;
+
+	let signed_qc = |key: Sr25519Keyring, view| {
+		let mut qc = QC::<Block> { proposal_hash: H256::repeat_byte(1), view, votes: Vec::new() };
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+		qc
+	};
+
+	// Signed by an authority outside the set.
+	assert_eq!(
+		worker.inject_qc(signed_qc(Sr25519Keyring::Bob, 2)),
+		Err(UnknownAuthority(Sr25519Keyring::Bob.public().into()))
+	);
+	assert_eq!(worker.state.high_qc_view(), 0);
+	assert_eq!(worker.state.view(), 2);
+
+	assert_eq!(worker.inject_qc(signed_qc(Sr25519Keyring::Alice, 2)), Ok(()));
+	assert_eq!(worker.state.high_qc_view(), 2);
+	assert_eq!(worker.state.view(), 3);
+
+	let mut alice = make_state(peers, 0, 3);
+	let tc = alice
+		.add_timeout(&alice.make_timeout().expect("authority makes timeout"))
+		.expect("timeout is valid")
+		.expect("the only authority forms a TC");
+
+	let mut forged = tc.clone();
+	forged.view = 4;
+	assert!(worker.inject_tc(forged).await.is_err());
+	assert_eq!(worker.state.view(), 3);
+
+	assert_eq!(worker.inject_tc(tc).await, Ok(()));
+	assert_eq!(worker.state.view(), 4);
+}
+
+// Test forcing a stuck node out of its view moves it to the next view, without a vote in the view
+// it left or a change of its high QC.
+#[tokio::test]
+async fn forced_view_change_should_keep_safety() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+	let mut events = worker.events().subscribe();
+
+	// The other authorities are unreachable, so no TC of view 1 can form.
+	let reason = "partition healed".to_string();
+	assert_eq!(worker.force_view_change(reason.clone()).await, Ok(()));
+	assert_eq!(worker.state.view(), 2);
+	assert_eq!(worker.state.high_qc_view(), 0);
+
+	let mut seen = Vec::new();
+	while let Ok(event) = events.try_recv() {
+		seen.push(event);
+	}
+	assert!(seen.contains(&ConsensusEvent::ViewForced { view: 1, reason }));
+	assert!(seen.contains(&ConsensusEvent::TimedOut { view: 1 }));
+	assert!(seen.contains(&ConsensusEvent::ViewChanged { view: 2 }));
+
+	// A late proposal of the view left gets no vote.
+	let late = make_state(peers, 1, 1).make_proposal(payload, None).expect("Bob proposes");
+	assert!(worker.state.make_vote(&late).is_none());
+}
+
+// Test messages signed for one fork don't verify on another fork sharing the keys.
+#[test]
+fn messages_signed_for_another_fork_should_be_rejected() {
+	let peers = &[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie];
+	let genesis_hash = H256::repeat_byte(7);
+	let fork = |peer, fork_id| {
+		make_state(peers, peer, 1)
+			.with_signing_context(SigningContext::new(genesis_hash.as_ref(), Some(fork_id)))
+	};
+
+	// Bob leads view 1.
+	let payload = Payload::<Block> { block_hash: H256::repeat_byte(1), block_number: 1 };
+	let proposal = fork(1, "a").make_proposal(payload, None).expect("leader makes proposal");
+	let vote = fork(0, "a").make_vote(&proposal).expect("authority votes");
+
+	assert_eq!(fork(2, "a").verify_proposal(&proposal), Ok(()));
+	assert_eq!(fork(2, "a").verify_vote(&vote), Ok(()));
+
+	let other = fork(2, "b");
+	assert_eq!(other.verify_proposal(&proposal), Err(InvalidSignature(proposal.author.clone())));
+	assert_eq!(other.verify_vote(&vote), Err(InvalidSignature(vote.voter.clone())));
+}
+
+// Test a block imported with a hotstuff justification is final.
+#[tokio::test]
+async fn block_with_hotstuff_justification_should_import_as_final() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	// Peer 1 builds the block, peer 0 imports it with its justification.
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 2, 0);
+	let mut built = None;
+	net.peer(1).generate_blocks(1, BlockOrigin::File, |builder| {
+		let block = builder.build().expect("block builds").block;
+		built = Some(block.clone());
+		block
+	});
+	let block = built.expect("block is built");
+	let hash = block.hash();
+
+	let client = net.peer(0).client().as_client();
+	let context = SigningContext::new(client.info().genesis_hash.as_ref(), None);
+	let certify = |proposal: &Proposal<Block>| {
+		let mut qc = QC::<Block> {
+			proposal_hash: proposal.digest(),
+			view: proposal.view,
+			votes: Vec::new(),
+		};
+		let digest = context.signing_digest::<Block>(qc.digest());
+		qc.add_votes(peers[0].public().into(), peers[0].sign(digest.as_ref()).into());
+		qc
+	};
+
+	let payload = Payload::<Block> { block_hash: hash, block_number: 1 };
+	let committed =
+		Proposal::<Block>::new(QC::default(), None, payload.clone(), 2, voters[0].0.clone(), None);
+	let child =
+		Proposal::<Block>::new(certify(&committed), None, payload, 3, voters[0].0.clone(), None);
+	let qc = certify(&child);
+	let justification = HotstuffJustification::new(committed, child, qc).into_justification();
+
+	let (header, body) = block.deconstruct();
+	let mut params = BlockImportParams::new(BlockOrigin::NetworkBroadcast, header);
+	params.body = Some(body);
+	params.justifications = Some(Justifications::from(justification));
+	params.fork_choice = Some(ForkChoiceStrategy::LongestChain);
+
+	let (mut import, _): (HotstuffBlockImport, TestLinkHalf) =
+		crate::client::block_import(client.clone(), &TestApi::new(voters))
+			.expect("block import is created");
+	assert!(matches!(
+		import.import_block(params).await,
+		Ok(ImportResult::Imported(aux)) if !aux.bad_justification
+	));
+
+	assert_eq!(client.info().finalized_hash, hash);
+	let stored = client.justifications(hash).expect("justifications are readable");
+	assert!(stored.and_then(|j| j.into_justification(HOTSTUFF_ENGINE_ID)).is_some());
+}
+
+// Test a paused voter signs nothing but follows the QCs, and votes again once resumed.
+#[tokio::test]
+async fn paused_voter_should_track_qcs_and_resume_voting() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("audit.log");
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let pause = link.pause_handle();
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters.clone(),
+	)
+	.expect("");
+	worker.state.view = 1;
+	worker.state.audit_log = Some(AuditLog::open(&path).expect("open audit log"));
+
+	// Bob, Charlie and Dave lead views 1, 2 and 3 and certify each proposal.
+	let mut others = (1..4).map(|index| make_state(peers, index, 1)).collect::<Vec<_>>();
+	let mut certify = |proposal: &Proposal<Block>| {
+		let mut qc = QC::<Block> {
+			proposal_hash: proposal.digest(),
+			view: proposal.view,
+			votes: Vec::new(),
+		};
+		for state in others.iter_mut() {
+			let vote = state.make_vote(proposal).expect("authority votes");
+			qc.add_votes(vote.voter, vote.signature.expect("vote is signed"));
+		}
+		qc
+	};
+	let propose = |index: usize, view, qc: &QC<Block>| {
+		let mut leader = make_state(peers, index, view);
+		leader.update_high_qc(qc);
+		leader.make_proposal(payload.clone(), None).expect("leader makes proposal")
+	};
+
+	let first = propose(1, 1, &QC::default());
+	let second = propose(2, 2, &certify(&first));
+
+	pause.pause();
+	assert_eq!(worker.handle_proposal(&first).await, Ok(()));
+	assert_eq!(worker.handle_proposal(&second).await, Ok(()));
+	assert_eq!(worker.state.high_qc_view(), 1);
+	assert!(AuditLog::read_entries(&path).expect("read audit log").is_empty());
+
+	pause.resume();
+	let third = propose(3, 3, &certify(&second));
+	assert_eq!(worker.handle_proposal(&third).await, Ok(()));
+	assert_eq!(worker.state.high_qc_view(), 2);
+
+	let entries = AuditLog::read_entries(&path).expect("read audit log");
+	assert_eq!(
+		entries.iter().map(|entry| (entry.kind, entry.view)).collect::<Vec<_>>(),
+		vec![(SignedKind::Vote, 3)]
+	);
+}
+
+// Test the proposal and the vote a leader signs while handling one vote are sent in the order of
+// the audit log.
+#[tokio::test]
+async fn signed_messages_should_be_sent_in_audit_order() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("audit.log");
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	// Charlie collects the votes of view 1 and leads view 2.
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[2]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+	worker.state.audit_log = Some(AuditLog::open(&path).expect("open audit log"));
+	worker.ordered_signing = true;
+
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("Bob proposes");
+	assert_eq!(worker.handle_proposal(&proposal).await, Ok(()));
+	assert!(worker.take_outbox().is_empty());
+
+	// The last vote forms the QC, so Charlie proposes view 2 and votes for its proposal.
+	for index in 0..2 {
+		let vote = make_state(peers, index, 1).make_vote(&proposal).expect("authority votes");
+		assert_eq!(worker.handle_vote(&vote).await, Ok(()));
+	}
+	// Queued in any order, they are sent in signing order.
+	worker.outbox.reverse();
+	let sent = worker.take_outbox();
+
+	let entries = AuditLog::read_entries(&path).expect("read audit log");
+	assert_eq!(
+		entries.iter().map(|entry| (entry.kind, entry.view)).collect::<Vec<_>>(),
+		vec![(SignedKind::Vote, 1), (SignedKind::Proposal, 2), (SignedKind::Vote, 2)]
+	);
+	assert_eq!(
+		sent.iter()
+			.map(|message| {
+				array_bytes::bytes2hex("0x", message.signed_digest().expect("signed").as_ref())
+			})
+			.collect::<Vec<_>>(),
+		entries[1..].iter().map(|entry| entry.digest.clone()).collect::<Vec<_>>()
+	);
+	assert!(matches!(sent[..], [ConsensusMessage::Propose(_), ConsensusMessage::Vote(_)]));
+}
+
+// Test a proposal certifying a proposal which was missed waits for it to be fetched, and is
+// dropped if it never comes.
+#[tokio::test]
+async fn proposal_with_unknown_parent_should_wait_for_it() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters.clone(),
+	)
+	.expect("");
+	worker.state.view = 1;
+
+	let mut others = (1..4).map(|index| make_state(peers, index, 1)).collect::<Vec<_>>();
+	let mut certify = |proposal: &Proposal<Block>| {
+		let mut qc = QC::<Block> {
+			proposal_hash: proposal.digest(),
+			view: proposal.view,
+			votes: Vec::new(),
+		};
+		for state in others.iter_mut() {
+			let vote = state.make_vote(proposal).expect("authority votes");
+			qc.add_votes(vote.voter, vote.signature.expect("vote is signed"));
+		}
+		qc
+	};
+	let propose = |index: usize, view, qc: &QC<Block>| {
+		let mut leader = make_state(peers, index, view);
+		leader.update_high_qc(qc);
+		leader.make_proposal(payload.clone(), None).expect("leader makes proposal")
+	};
+
+	let first = propose(1, 1, &QC::default());
+	let second = propose(2, 2, &certify(&first));
+	let third = propose(3, 3, &certify(&second));
+	let fourth = propose(0, 4, &certify(&third));
+
+	// The first proposal was missed, so the second waits for it.
+	assert_eq!(worker.handle_proposal(&second).await, Ok(()));
+	assert_eq!(worker.state.high_qc_view(), 1);
+	assert_eq!(worker.synchronizer.orphan_count(), 1);
+	assert!(worker.synchronizer.get_proposal(second.digest()).unwrap().is_none());
+
+	// Once fetched, both are processed.
+	assert_eq!(worker.handle_proposal(&first).await, Ok(()));
+	assert_eq!(worker.synchronizer.orphan_count(), 0);
+	assert!(worker.synchronizer.get_proposal(second.digest()).unwrap().is_some());
+
+	// The third proposal is never fetched, so the fourth is dropped after the timeout.
+	assert_eq!(worker.handle_proposal(&fourth).await, Ok(()));
+	assert_eq!(worker.synchronizer.orphan_count(), 1);
+	let timeout = Duration::from_millis(Config::default().proposal_fetch_timeout);
+	assert_eq!(worker.synchronizer.prune_orphans(Instant::now() + timeout), 1);
+	assert!(worker.synchronizer.get_proposal(fourth.digest()).unwrap().is_none());
+}
+
+// Test a session recorded in the journal of a voter replays into a fresh worker, which makes
+// the same decisions and ends in the same committed state.
+#[tokio::test]
+async fn replayed_journal_should_reproduce_the_committed_state() {
+	sp_tracing::try_init_simple();
+
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	// Alice on the first peer records the session, the second peer replays it.
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 2, 0);
+	net.peer(0).push_blocks(1, false);
+	net.run_until_sync().await;
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	let mut workers = (0..2)
+		.map(|index| {
+			let link =
+				net.peers[index].data.lock().take().expect("link initialized at startup; qed");
+			let (mut worker, _) = build_hotstuff_components(
+				net.peers[index].network_service().clone(),
+				link,
+				net.peers[index].sync_service().clone(),
+				crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+				create_keystore(peers[0]),
+				voters.clone(),
+			)
+			.expect("");
+			worker.state.view = 1;
+			let audit_log = dir.path().join(format!("audit{}.log", index));
+			worker.state.audit_log = Some(AuditLog::open(&audit_log).expect("open audit log"));
+			worker
+		})
+		.collect::<Vec<_>>();
+	let journal = dir.path().join("journal");
+	let mut replayed = workers.pop().expect("two workers");
+	let mut recorded = workers
+		.pop()
+		.expect("two workers")
+		.with_journal(Journal::open(&journal).unwrap());
+
+	let mut others = (1..4).map(|index| make_state(peers, index, 1)).collect::<Vec<_>>();
+	let mut certify = |proposal: &Proposal<Block>| {
+		let mut qc = QC::<Block> {
+			proposal_hash: proposal.digest(),
+			view: proposal.view,
+			votes: Vec::new(),
+		};
+		for state in others.iter_mut() {
+			let vote = state.make_vote(proposal).expect("authority votes");
+			qc.add_votes(vote.voter, vote.signature.expect("vote is signed"));
+		}
+		qc
+	};
+	let propose = |index: usize, view, qc: &QC<Block>| {
+		let mut leader = make_state(peers, index, view);
+		leader.update_high_qc(qc);
+		leader.make_proposal(payload.clone(), None).expect("leader makes proposal")
+	};
+
+	// Bob, Charlie and Dave lead views 1 to 3, committing the first proposal, then view 3
+	// times out.
+	let first = propose(1, 1, &QC::default());
+	let second = propose(2, 2, &certify(&first));
+	let third = propose(3, 3, &certify(&second));
+	for proposal in [first, second, third] {
+		let message = GossipMessage { set_id: 0, message: ConsensusMessage::Propose(proposal) };
+		recorded._consensus_msg_tx.send(message).await.expect("worker is alive");
+		recorded.step().await;
+	}
+	assert_eq!(recorded.handle_event(JournalEvent::LocalTimer).await, Ok(()));
+	assert_eq!(net.peer(0).client().info().finalized_number, 1);
+
+	let entries = Journal::read::<Block>(&journal).expect("read journal");
+	assert_eq!(entries.len(), 4);
+	replayed.replay(entries).await;
+
+	assert_eq!(net.peer(1).client().info().finalized_number, 1);
+	assert_eq!(replayed.state.view(), recorded.state.view());
+	assert_eq!(replayed.state.high_qc_view(), recorded.state.high_qc_view());
+	assert_eq!(replayed.state.last_committed, recorded.state.last_committed);
+	assert_eq!(
+		replayed.block_tree.read().committed_hash(),
+		recorded.block_tree.read().committed_hash()
+	);
+
+	// The same votes and timeout were signed.
+	let signed = |index: usize| {
+		AuditLog::read_entries(&dir.path().join(format!("audit{}.log", index)))
+			.expect("read audit log")
+			.into_iter()
+			.map(|entry| (entry.kind, entry.view, entry.digest))
+			.collect::<Vec<_>>()
+	};
+	assert_eq!(signed(0).last().map(|(kind, ..)| *kind), Some(SignedKind::Timeout));
+	assert_eq!(signed(1), signed(0));
+}
+
+// Test the pacemaker state counts the local timeouts of a stalled view, and shows the reset
+// timeout once a QC advances the view.
+#[tokio::test]
+async fn pacemaker_state_should_follow_timeouts_and_recovery() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[
+		Sr25519Keyring::Alice,
+		Sr25519Keyring::Bob,
+		Sr25519Keyring::Charlie,
+		Sr25519Keyring::Dave,
+	];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(1, false);
+	let payload =
+		Payload::<Block> { block_hash: net.peer(0).client().info().best_hash, block_number: 1 };
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let pacemaker = link.shared_pacemaker();
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters,
+	)
+	.expect("");
+	worker.state.view = 1;
+
+	// Alone, Alice times out view 1 twice without forming a TC.
+	for _ in 0..2 {
+		assert_eq!(worker.handle_local_timer().await, Ok(()));
+	}
+	let state = worker.pacemaker_state();
+	assert_eq!((state.view, state.timeout, state.consecutive_timeouts), (1, 2000, 2));
+	assert!(state.remaining <= 2000);
+
+	// A QC of view 1 recovers the node.
+	let proposal = make_state(peers, 1, 1).make_proposal(payload, None).expect("Bob proposes");
+	let mut collector = make_state(peers, 2, 1);
+	let qc = (1..4)
+		.filter_map(|index| {
+			let vote = make_state(peers, index, 1).make_vote(&proposal).expect("authority votes");
+			collector.add_vote(&vote).expect("vote aggregates")
+		})
+		.last()
+		.expect("quorum of votes");
+	worker.handle_qc(&qc);
+
+	let state = worker.pacemaker_state();
+	assert_eq!((state.view, state.timeout, state.consecutive_timeouts), (2, 2000, 0));
+	assert!(state.remaining > 1000);
+
+	// The RPC sees what the voter publishes.
+	worker
+		.pacemaker
+		.publish(worker.pacemaker_state(), worker.local_timer.deadline());
+	assert_eq!(pacemaker.state().consecutive_timeouts, 0);
+	assert_eq!(pacemaker.state().view, 2);
+}
+
+// Test a proposal standing for several blocks finalizes them all, and a mapping to blocks which
+// don't chain finalizes none.
+#[tokio::test]
+async fn proposal_should_finalize_every_block_it_stands_for() {
+	sp_tracing::try_init_simple();
+
+	let peers = &[Sr25519Keyring::Alice];
+	let voters = make_ids(peers);
+
+	let mut net = TestNet::new(TestApi::new(voters.clone()), 1, 0);
+	net.peer(0).push_blocks(6, false);
+	let client = net.peer(0).client().as_client();
+	let hashes = (1..=6)
+		.map(|number| client.hash(number).unwrap().expect("block is imported"))
+		.collect::<Vec<_>>();
+
+	let link = net.peers[0].data.lock().take().expect("link initialized at startup; qed");
+	let (mut worker, _) = build_hotstuff_components(
+		net.peers[0].network_service().clone(),
+		link,
+		net.peers[0].sync_service().clone(),
+		crate::config::HOTSTUFF_PROTOCOL_NAME.into(),
+		create_keystore(peers[0]),
+		voters.clone(),
+	)
+	.expect("");
+	let proposal = |number: u64, view: ViewNumber| {
+		let payload =
+			Payload::<Block> { block_hash: hashes[number as usize - 1], block_number: number };
+		Proposal::<Block>::new(QC::default(), None, payload, view, voters[0].0.clone(), None)
+	};
+
+	// Block `n` stands for itself and the two blocks after it.
+	let mapped = hashes.clone();
+	let mapping = move |_: &Proposal<Block>, block: &Block| {
+		let index = *block.header().number() as usize - 1;
+		mapped[index..index + 3].to_vec()
+	};
+	worker.committed_blocks = Some(Arc::new(mapping));
+	assert_eq!(worker.finalize_committed(&proposal(1, 3), None, CommitTime::local()).await, Ok(()));
+	assert_eq!(client.info().finalized_number, 3);
+
+	let mapped = hashes.clone();
+	let mapping = move |_: &Proposal<Block>, _: &Block| vec![mapped[3], mapped[5]];
+	worker.committed_blocks = Some(Arc::new(mapping));
+	assert_eq!(
+		worker.finalize_committed(&proposal(4, 4), None, CommitTime::local()).await,
+		Err(NonContiguousBlocks(format!("block {} isn't the parent of {}", hashes[3], hashes[5])))
+	);
+	assert_eq!(client.info().finalized_number, 3);
+
+	// Without a mapping, each proposal stands for its own block.
+	worker.committed_blocks = None;
+	assert_eq!(worker.finalize_committed(&proposal(4, 5), None, CommitTime::local()).await, Ok(()));
+	assert_eq!(client.info().finalized_number, 4);
+}
@@ -0,0 +1,66 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::{Header as TestHeader, TestXt};
+
+use crate::message::{Payload, QC};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+// Rejects the extrinsics signed by a banned account.
+struct BannedSigner(u64);
+
+impl ContentRules<TestBlock> for BannedSigner {
+	fn check_extrinsic(&self, _index: usize, extrinsic: &TestExtrinsic) -> Result<(), String> {
+		match extrinsic.signature {
+			Some((signer, _)) if signer == self.0 => Err(format!("signed by {}", signer)),
+			_ => Ok(()),
+		}
+	}
+}
+
+fn make_block(extrinsics: Vec<TestExtrinsic>) -> TestBlock {
+	let mut header = TestHeader::new_from_number(3);
+	header.extrinsics_root = Hasher::<TestBlock>::ordered_trie_root(
+		extrinsics.iter().map(Encode::encode).collect(),
+		StateVersion::V0,
+	);
+	TestBlock { header, extrinsics }
+}
+
+fn propose(block: &TestBlock) -> Proposal<TestBlock> {
+	let payload =
+		Payload::<TestBlock> { block_hash: block.header.hash(), block_number: block.header.number };
+	let author = Sr25519Keyring::Alice.public().into();
+	Proposal::<TestBlock>::new(QC::default(), None, payload, 3, author, None)
+}
+
+#[test]
+fn block_with_disallowed_extrinsic_should_be_rejected() {
+	let rules = BannedSigner(7);
+
+	let allowed = make_block(vec![TestXt::new((), None), TestXt::new((), Some((1, ())))]);
+	assert_eq!(propose(&allowed).validate_block_contents(&allowed, &rules), Ok(()));
+
+	let disallowed =
+		make_block(vec![TestXt::new((), Some((1, ()))), TestXt::new((), Some((7, ())))]);
+	assert!(matches!(
+		propose(&disallowed).validate_block_contents(&disallowed, &rules),
+		Err(InvalidBlockContents(reason)) if reason.contains("extrinsic 1")
+	));
+
+	// Another block than the proposed one.
+	assert!(matches!(
+		propose(&allowed).validate_block_contents(&disallowed, &rules),
+		Err(InvalidBlockContents(_))
+	));
+
+	// A body which doesn't match the header of the proposed block.
+	let mut tampered = allowed.clone();
+	tampered.extrinsics.pop();
+	assert!(matches!(
+		propose(&allowed).validate_block_contents(&tampered, &rules),
+		Err(InvalidBlockContents(_))
+	));
+}
@@ -0,0 +1,116 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+
+fn alice() -> AuthorityId {
+	Sr25519Keyring::Alice.public().into()
+}
+
+#[test]
+fn identical_proposal_should_be_a_duplicate() {
+	let mut dedup = ProposalDedup::<u64>::default();
+
+	assert!(!dedup.contains(3, &alice(), &7));
+	assert_eq!(dedup.insert(3, alice(), 7), Seen::New);
+
+	// Redundant gossip delivers the same proposal again.
+	assert!(dedup.contains(3, &alice(), &7));
+	assert_eq!(dedup.insert(3, alice(), 7), Seen::Duplicate);
+	assert_eq!(dedup.len(), 1);
+}
+
+#[test]
+fn conflicting_proposal_should_be_an_equivocation() {
+	let mut dedup = ProposalDedup::<u64>::default();
+	let bob: AuthorityId = Sr25519Keyring::Bob.public().into();
+
+	assert_eq!(dedup.insert(3, alice(), 7), Seen::New);
+
+	// Another proposal of the same leader for the same view is not a duplicate.
+	assert!(!dedup.contains(3, &alice(), &8));
+	assert_eq!(dedup.insert(3, alice(), 8), Seen::Equivocation { first: 7 });
+	assert_eq!(dedup.insert(3, alice(), 9), Seen::Equivocation { first: 7 });
+
+	// Other views and authors don't conflict.
+	assert_eq!(dedup.insert(4, alice(), 8), Seen::New);
+	assert_eq!(dedup.insert(3, bob, 8), Seen::New);
+}
+
+#[test]
+fn dedup_should_be_pruned_by_view_and_bounded() {
+	let mut dedup = ProposalDedup::<u64>::new(4, 8);
+
+	for view in 1..=6 {
+		dedup.insert(view, alice(), view);
+	}
+	dedup.prune(8);
+	assert_eq!(dedup.len(), 3);
+	assert!(!dedup.contains(3, &alice(), &3));
+	assert!(dedup.contains(4, &alice(), &4));
+
+	// Beyond the capacity the lowest views are forgotten.
+	for view in 7..=20 {
+		dedup.insert(view, alice(), view);
+	}
+	assert_eq!(dedup.len(), 8);
+	assert!(!dedup.contains(12, &alice(), &12));
+	assert!(dedup.contains(13, &alice(), &13));
+}
+
+#[test]
+fn equivocation_policy_should_pick_the_proposal_to_continue_with() {
+	assert_eq!(EquivocationPolicy::default(), EquivocationPolicy::FirstSeen);
+
+	for (first, second) in [(7, 8), (8, 7)] {
+		assert!(!EquivocationPolicy::Halt.continues_with(&first, &second));
+		assert!(!EquivocationPolicy::FirstSeen.continues_with(&first, &second));
+	}
+	// Every node ends on the lowest digest, whichever it saw first.
+	assert!(EquivocationPolicy::LowestHash.continues_with(&8, &7));
+	assert!(!EquivocationPolicy::LowestHash.continues_with(&7, &8));
+}
+
+#[test]
+fn flood_of_proposals_should_be_verified_within_the_budget() {
+	let mut budget = VerificationBudget::new(3);
+	let bob: AuthorityId = Sr25519Keyring::Bob.public().into();
+
+	// The leader of view 5 floods distinct proposals.
+	let verified = (0..100).filter(|_| budget.spend(5, &alice())).count();
+	assert_eq!(verified, 3);
+	assert_eq!(budget.dropped(), 97);
+
+	// Other views, and other authors, have their own budget.
+	assert!(budget.spend(6, &alice()));
+	assert!(budget.spend(5, &bob));
+
+	// A forged proposal gives its verification back.
+	budget.refund(5, &alice());
+	assert!(budget.spend(5, &alice()));
+
+	// The budget of a view is forgotten once the view is far behind.
+	budget.prune(5 + DEDUP_VIEW_WINDOW);
+	assert!(!budget.spend(5, &alice()));
+	budget.prune(6 + DEDUP_VIEW_WINDOW);
+	assert!(budget.spend(5, &alice()));
+	assert_eq!(budget.dropped(), 98);
+}
+
+#[test]
+fn budget_at_capacity_should_forget_the_lowest_views() {
+	let mut budget = VerificationBudget::new(1);
+	for view in 0..DEDUP_CAPACITY as ViewNumber {
+		assert!(budget.spend(view, &alice()));
+	}
+
+	// A flood at the newest view is held to its budget once the budget is full.
+	let newest = DEDUP_CAPACITY as ViewNumber;
+	assert!(budget.spend(newest, &alice()));
+	assert!(!budget.spend(newest, &alice()));
+	assert!(!budget.spend(newest, &alice()));
+
+	// The lowest view made room, the others are still spent.
+	assert!(budget.spend(0, &alice()));
+	assert!(!budget.spend(newest - 1, &alice()));
+	assert_eq!(budget.dropped(), 3);
+}
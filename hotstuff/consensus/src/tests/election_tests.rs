@@ -0,0 +1,116 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+
+const KEYS: [Sr25519Keyring; 6] = [
+	Sr25519Keyring::Alice,
+	Sr25519Keyring::Bob,
+	Sr25519Keyring::Charlie,
+	Sr25519Keyring::Dave,
+	Sr25519Keyring::Eve,
+	Sr25519Keyring::Ferdie,
+];
+
+fn weighted(weights: &[u64]) -> AuthorityList {
+	KEYS.iter()
+		.zip(weights)
+		.map(|(key, weight)| (key.public().into(), *weight))
+		.collect()
+}
+
+fn leader_indices(
+	election: LeaderElection,
+	authorities: &AuthorityList,
+	views: ViewNumber,
+) -> Vec<usize> {
+	(0..views)
+		.map(|view| {
+			let leader = election.leader(authorities, view).expect("the set has weight");
+			authorities
+				.iter()
+				.position(|(id, _)| *id == leader)
+				.expect("leader is an authority")
+		})
+		.collect()
+}
+
+// Step through the turns of each period one by one, the next due first.
+fn stride_schedule(weights: &[u64], views: usize) -> Vec<usize> {
+	let mut schedule = Vec::with_capacity(views);
+	let mut next = vec![1u64; weights.len()];
+	while schedule.len() < views {
+		let due =
+			(0..weights.len())
+				.filter(|index| next[*index] <= weights[*index])
+				.min_by(|a, b| {
+					(next[*a] as u128 * weights[*b] as u128)
+						.cmp(&(next[*b] as u128 * weights[*a] as u128))
+						.then(a.cmp(b))
+				});
+		match due {
+			Some(index) => {
+				schedule.push(index);
+				next[index] += 1;
+			},
+			None => next.iter_mut().for_each(|next| *next = 1),
+		}
+	}
+	schedule
+}
+
+#[test]
+fn weighted_election_should_follow_the_stride_schedule() {
+	let weights = [5, 3, 1, 3, 0, 2];
+	let authorities = weighted(&weights);
+	let election = LeaderElection::Weighted(TieBreak::AuthorityOrder);
+
+	// Worked out by hand: Alice's turns fall at fifths of the period, Bob's and Dave's at thirds,
+	// Ferdie's at halves, and Charlie's at its end. Eve has no weight.
+	let period = leader_indices(election, &authorities, 14);
+	assert_eq!(period, [0, 1, 3, 0, 5, 0, 1, 3, 0, 0, 1, 2, 3, 5]);
+
+	let leaders = leader_indices(election, &authorities, 1000);
+	assert_eq!(leaders, stride_schedule(&weights, 1000));
+	for (index, weight) in weights.iter().enumerate() {
+		let turns = leaders[..14].iter().filter(|leader| **leader == index).count();
+		assert_eq!(turns as u64, *weight);
+	}
+
+	// The backups are the distinct leaders of the following views.
+	let ids = |indices: &[usize]| {
+		indices.iter().map(|index| authorities[*index].0.clone()).collect::<Vec<_>>()
+	};
+	assert_eq!(election.fallback_chain(&authorities, 8, 2), ids(&[0, 1, 2]));
+	assert_eq!(election.fallback_chain(&authorities, 11, 9), ids(&[2, 3, 5, 0, 1]));
+}
+
+#[test]
+fn equal_weights_should_rotate_in_the_tie_break_order() {
+	let authorities = weighted(&[2; 6]);
+
+	// Authorities of equal weight are due together, in the order of the set.
+	let by_order = leader_indices(LeaderElection::Weighted(Default::default()), &authorities, 1000);
+	assert_eq!(by_order, leader_indices(LeaderElection::RoundRobin, &authorities, 1000));
+
+	// By id, the same rotation whatever the order of the set.
+	let election = LeaderElection::Weighted(TieBreak::LowestId);
+	let reversed = authorities.iter().rev().cloned().collect::<AuthorityList>();
+	let leaders = |authorities: &AuthorityList| {
+		(0..1000).map(|view| election.leader(authorities, view)).collect::<Vec<_>>()
+	};
+	assert_eq!(leaders(&authorities), leaders(&reversed));
+
+	let mut ids = authorities.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>();
+	ids.sort();
+	assert_eq!(leaders(&authorities)[..6], ids.into_iter().map(Some).collect::<Vec<_>>()[..]);
+}
+
+#[test]
+fn heavy_weights_should_not_overflow() {
+	let authorities = weighted(&[u64::MAX, u64::MAX, 1]);
+	let election = LeaderElection::Weighted(TieBreak::AuthorityOrder);
+
+	// Scaled down, the light authority keeps a turn at the end of each period.
+	assert_eq!(leader_indices(election, &authorities, 4), [0, 1, 0, 1]);
+	assert_eq!(election.leader(&weighted(&[0, 0]), 1), None);
+}
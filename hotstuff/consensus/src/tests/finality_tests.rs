@@ -0,0 +1,74 @@
+use super::*;
+
+use std::cell::Cell;
+
+// A client whose finalization fails a number of times before it goes through.
+struct FlakyClient {
+	failures: Cell<u32>,
+	attempts: Cell<u32>,
+	finalized: Cell<bool>,
+}
+
+impl FlakyClient {
+	fn new(failures: u32) -> Self {
+		Self { failures: Cell::new(failures), attempts: Cell::new(0), finalized: Cell::new(false) }
+	}
+
+	fn finalize_block(&self) -> Result<(), String> {
+		self.attempts.set(self.attempts.get() + 1);
+		if self.failures.get() > 0 {
+			self.failures.set(self.failures.get() - 1);
+			return Err("lock contention".to_string());
+		}
+		self.finalized.set(true);
+		Ok(())
+	}
+}
+
+fn policy(max_attempts: u32) -> FinalizeRetry {
+	FinalizeRetry {
+		max_attempts,
+		initial_backoff: Duration::from_millis(1),
+		max_backoff: Duration::from_millis(4),
+	}
+}
+
+#[tokio::test]
+async fn transient_failures_should_be_retried() {
+	let client = FlakyClient::new(3);
+	let result =
+		finalize_with_retry(&policy(5), || client.finalize_block(), || client.finalized.get())
+			.await;
+	assert_eq!(result, Ok(4));
+	assert!(client.finalized.get());
+
+	// The attempts run out.
+	let client = FlakyClient::new(5);
+	let result =
+		finalize_with_retry(&policy(3), || client.finalize_block(), || client.finalized.get())
+			.await;
+	assert_eq!(result, Err((3, "lock contention".to_string())));
+	assert_eq!(client.attempts.get(), 3);
+	assert!(!client.finalized.get());
+}
+
+#[tokio::test]
+async fn block_finalized_by_a_failed_attempt_should_not_be_finalized_again() {
+	// The first attempt finalizes the block but reports a failure.
+	let client = FlakyClient::new(0);
+	let result = finalize_with_retry(
+		&policy(5),
+		|| client.finalize_block().and(Err("notification failed".to_string())),
+		|| client.finalized.get(),
+	)
+	.await;
+	assert_eq!(result, Ok(1));
+	assert_eq!(client.attempts.get(), 1);
+}
+
+#[test]
+fn backoff_should_double_up_to_the_maximum() {
+	let policy = policy(10);
+	let backoffs = (0..4).map(|retry| policy.backoff(retry).as_millis()).collect::<Vec<_>>();
+	assert_eq!(backoffs, vec![1, 2, 4, 4]);
+}
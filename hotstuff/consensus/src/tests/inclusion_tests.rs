@@ -0,0 +1,124 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::{
+	testing::{Header as TestHeader, TestXt},
+	traits::BlakeTwo256,
+	Digest, StateVersion,
+};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+fn extrinsics() -> Vec<Vec<u8>> {
+	(0u64..5).map(|nonce| (b"transfer", nonce, [7u8; 40]).encode()).collect()
+}
+
+// A committed proposal of a block with `extrinsics`, and the QC certifying it.
+fn commit(
+	extrinsics: &[Vec<u8>],
+	state_version: StateVersion,
+) -> (Proposal<TestBlock>, TestHeader, QC<TestBlock>) {
+	let header = TestHeader::new(
+		1,
+		BlakeTwo256::ordered_trie_root(extrinsics.to_vec(), state_version),
+		Default::default(),
+		H256::repeat_byte(1),
+		Digest::default(),
+	);
+	let parent =
+		QC::<TestBlock> { proposal_hash: H256::repeat_byte(2), view: 4, votes: Vec::new() };
+	let payload = Payload::<TestBlock> { block_hash: header.hash(), block_number: 1 };
+	let proposal = Proposal::<TestBlock>::new(
+		parent,
+		None,
+		payload,
+		5,
+		Sr25519Keyring::Alice.public().into(),
+		None,
+	);
+	let qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view: 5, votes: Vec::new() };
+
+	(proposal, header, qc)
+}
+
+#[test]
+fn inclusion_proof_should_verify_against_committed_qc() {
+	for state_version in [StateVersion::V0, StateVersion::V1] {
+		let extrinsics = extrinsics();
+		let (proposal, header, qc) = commit(&extrinsics, state_version);
+
+		let mut index = InclusionIndex::<TestBlock>::new();
+		index.record(&proposal, header, extrinsics.clone());
+
+		let proof = index
+			.inclusion_proof(BlakeTwo256::hash(&extrinsics[3]))
+			.expect("committed transaction is indexed");
+		assert_eq!(proof.index, 3);
+		assert!(verify_inclusion(&proof, &qc));
+
+		// Another transaction at that position.
+		let mut forged = proof.clone();
+		forged.extrinsic = extrinsics[2].clone();
+		assert!(!verify_inclusion(&forged, &qc));
+
+		// The QC of another proposal.
+		let other = QC::<TestBlock> { proposal_hash: H256::repeat_byte(3), ..qc.clone() };
+		assert!(!verify_inclusion(&proof, &other));
+	}
+}
+
+#[test]
+fn inclusion_proof_should_cover_every_field_of_the_digest() {
+	let extrinsics = extrinsics();
+	let (proposal, header, _) = commit(&extrinsics, StateVersion::V1);
+	let voter = Sr25519Keyring::Bob;
+	let signature = voter.sign(b"clock").into();
+	let proposal = proposal
+		.with_metadata(b"hotstuff/v1".to_vec())
+		.expect("metadata fits")
+		.with_timestamp(1_700_000_000_000)
+		.with_qc_timestamps(vec![VoteTimestamp {
+			voter: voter.public().into(),
+			timestamp: 1_700_000_000_100,
+			signature,
+		}]);
+	let qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view: 5, votes: Vec::new() };
+
+	let mut index = InclusionIndex::<TestBlock>::new();
+	index.record(&proposal, header, extrinsics.clone());
+	let proof = index
+		.inclusion_proof(BlakeTwo256::hash(&extrinsics[1]))
+		.expect("committed transaction is indexed");
+	assert!(verify_inclusion(&proof, &qc));
+
+	// Each field signed with the proposal is bound by the QC.
+	let mut forged = proof.clone();
+	forged.metadata = None;
+	assert!(!verify_inclusion(&forged, &qc));
+	let mut forged = proof.clone();
+	forged.timestamp = Some(1_700_000_009_000);
+	assert!(!verify_inclusion(&forged, &qc));
+	let mut forged = proof.clone();
+	forged.qc_timestamps[0].timestamp += 1;
+	assert!(!verify_inclusion(&forged, &qc));
+}
+
+#[test]
+fn inclusion_index_should_forget_old_blocks() {
+	let mut index = InclusionIndex::<TestBlock>::new();
+	assert!(index.inclusion_proof(H256::repeat_byte(9)).is_none());
+
+	let first = vec![b"first".to_vec()];
+	let (proposal, header, _) = commit(&first, StateVersion::V1);
+	index.record(&proposal, header, first.clone());
+	assert!(index.inclusion_proof(BlakeTwo256::hash(&first[0])).is_some());
+
+	for nonce in 0..MAX_INDEXED_BLOCKS as u64 {
+		let extrinsics = vec![nonce.encode()];
+		let (proposal, header, _) = commit(&extrinsics, StateVersion::V1);
+		index.record(&proposal, header, extrinsics);
+	}
+	assert!(index.inclusion_proof(BlakeTwo256::hash(&first[0])).is_none());
+}
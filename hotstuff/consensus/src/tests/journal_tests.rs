@@ -0,0 +1,79 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+use crate::message::Vote;
+
+type TestBlock = sp_runtime::testing::Block<TestXt<(), ()>>;
+
+fn entries() -> Vec<JournalEntry<TestBlock>> {
+	let vote =
+		Vote::<TestBlock>::new(H256::from_low_u64_be(3), 3, Sr25519Keyring::Alice.public().into());
+	vec![
+		JournalEntry {
+			timestamp: 1_000,
+			event: JournalEvent::Message(Box::new(ConsensusMessage::Vote(vote))),
+		},
+		JournalEntry { timestamp: 1_200, event: JournalEvent::VoteCollectionTimeout(3) },
+		JournalEntry { timestamp: 1_500, event: JournalEvent::Paused(true) },
+		JournalEntry { timestamp: 2_000, event: JournalEvent::LocalTimer },
+	]
+}
+
+// The timestamps and kinds of `entries`, and the view of their vote if any.
+fn summary(entries: &[JournalEntry<TestBlock>]) -> Vec<(u64, String)> {
+	entries
+		.iter()
+		.map(|entry| {
+			let event = match &entry.event {
+				JournalEvent::Message(message) => match &**message {
+					ConsensusMessage::Vote(vote) => format!("vote {}", vote.view),
+					message => message.summary(),
+				},
+				event => format!("{:?}", event),
+			};
+			(entry.timestamp, event)
+		})
+		.collect()
+}
+
+#[test]
+fn entries_should_be_read_back_in_order() {
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("hotstuff").join("journal");
+
+	let mut journal = Journal::open(&path).expect("open journal");
+	for entry in entries().iter().take(2) {
+		journal.record(entry).unwrap();
+	}
+	// Reopened, the journal is appended to.
+	drop(journal);
+	let mut journal = Journal::open(&path).expect("reopen journal");
+	for entry in entries().iter().skip(2) {
+		journal.record(entry).unwrap();
+	}
+
+	let read = Journal::read::<TestBlock>(&path).expect("read journal");
+	assert_eq!(summary(&read), summary(&entries()));
+	assert_eq!(summary(&read)[0], (1_000, "vote 3".to_string()));
+}
+
+#[test]
+fn torn_entry_should_be_dropped() {
+	let dir = tempfile::tempdir().expect("create temporary directory");
+	let path = dir.path().join("journal");
+
+	let mut journal = Journal::open(&path).expect("open journal");
+	for entry in entries().iter() {
+		journal.record(entry).unwrap();
+	}
+	drop(journal);
+
+	// A crash cut the last entry short.
+	let data = fs::read(&path).unwrap();
+	fs::write(&path, &data[..data.len() - 1]).unwrap();
+	let read = Journal::read::<TestBlock>(&path).expect("read journal");
+	assert_eq!(summary(&read), summary(&entries()[..3]));
+}
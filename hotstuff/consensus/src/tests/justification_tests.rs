@@ -0,0 +1,139 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+use crate::{message::Payload, primitives::ViewNumber};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+const KEYS: [Sr25519Keyring; 4] =
+	[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie, Sr25519Keyring::Dave];
+
+fn authorities() -> AuthorityList {
+	KEYS.iter().map(|key| (key.public().into(), 1)).collect()
+}
+
+// A QC of `proposal` signed by a quorum of `KEYS`.
+fn certify(proposal: &Proposal<TestBlock>) -> QC<TestBlock> {
+	let mut qc = QC::<TestBlock> {
+		proposal_hash: proposal.digest(),
+		view: proposal.view,
+		votes: Vec::new(),
+	};
+	for key in KEYS[..3].iter() {
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	}
+	qc
+}
+
+fn make_proposal(qc: QC<TestBlock>, view: ViewNumber) -> Proposal<TestBlock> {
+	let author = KEYS[view as usize % KEYS.len()];
+	let payload =
+		Payload::<TestBlock> { block_hash: H256::from_low_u64_be(view), block_number: view };
+	Proposal::<TestBlock>::new(qc, None, payload, view, author.public().into(), None)
+}
+
+// The justification of the block of view 2.
+fn make_justification() -> HotstuffJustification<TestBlock> {
+	let committed = make_proposal(QC::default(), 2);
+	let child = make_proposal(certify(&committed), 3);
+	let qc = certify(&child);
+	HotstuffJustification::new(committed, child, qc)
+}
+
+#[test]
+fn justification_should_verify_standalone() {
+	let justification = make_justification().into_justification();
+	assert_eq!(justification.0, HOTSTUFF_ENGINE_ID);
+
+	let decoded = HotstuffJustification::<TestBlock>::decode_justification(&justification)
+		.expect("hotstuff justification decodes");
+	assert_eq!(
+		decoded.verify(H256::from_low_u64_be(2), 2, &authorities(), None, &Default::default()),
+		Ok(())
+	);
+}
+
+#[test]
+fn justification_should_only_prove_its_commit() {
+	let justification = make_justification();
+	let verify = |justification: &HotstuffJustification<TestBlock>, hash, number| {
+		justification.verify(hash, number, &authorities(), None, &Default::default())
+	};
+
+	// Another block.
+	assert!(matches!(
+		verify(&justification, H256::from_low_u64_be(3), 3),
+		Err(InvalidJustification(_))
+	));
+
+	// The child isn't in the next view, so the block is only certified.
+	let mut skipped = justification.clone();
+	skipped.child.view = 4;
+	assert!(matches!(verify(&skipped, H256::from_low_u64_be(2), 2), Err(InvalidJustification(_))));
+
+	// The QC certifies another proposal.
+	let mut misplaced = justification.clone();
+	misplaced.qc = misplaced.child.qc.clone();
+	assert!(matches!(
+		verify(&misplaced, H256::from_low_u64_be(2), 2),
+		Err(InvalidJustification(_))
+	));
+
+	// Too few votes.
+	let mut short = justification;
+	short.qc.votes.truncate(2);
+	assert_eq!(verify(&short, H256::from_low_u64_be(2), 2), Err(InsufficientQuorum));
+
+	// Another engine.
+	let grandpa = (*b"FRNK", make_justification().encode());
+	assert!(matches!(
+		HotstuffJustification::<TestBlock>::decode_justification(&grandpa),
+		Err(InvalidJustification(_))
+	));
+}
+
+#[test]
+fn commit_certificate_should_verify_standalone() {
+	let committed = make_proposal(QC::default(), 2);
+	let child = make_proposal(certify(&committed), 3);
+	let grandchild = make_proposal(certify(&child), 5);
+	let qc = certify(&grandchild);
+	let chain = [committed, child, grandchild];
+	let verify_as = |variant, chain: &[Proposal<TestBlock>], qc: &QC<TestBlock>| {
+		verify_commit_certificate(
+			H256::from_low_u64_be(2),
+			chain,
+			qc,
+			&authorities(),
+			None,
+			variant,
+			&Default::default(),
+		)
+	};
+	let verify = |chain: &[Proposal<TestBlock>], qc: &QC<TestBlock>| {
+		verify_as(ConsensusVariant::Chained, chain, qc)
+	};
+
+	assert_eq!(verify(&chain, &qc), Ok(()));
+	assert_eq!(verify(&chain[..2], &chain[2].qc), Ok(()));
+
+	// The grandchild doesn't extend the child.
+	let mut broken = chain.clone();
+	broken[2].qc = broken[1].qc.clone();
+	assert!(matches!(verify(&broken, &certify(&broken[2])), Err(InvalidJustification(_))));
+
+	// A single certified proposal isn't committed.
+	assert!(matches!(verify(&chain[..1], &chain[1].qc), Err(InvalidJustification(_))));
+
+	// Three phases commit with the grandchild in the view after the child only.
+	let three_phase = |chain, qc| verify_as(ConsensusVariant::ThreePhase, chain, qc);
+	assert!(matches!(three_phase(&chain, &qc), Err(InvalidJustification(_))));
+	assert!(matches!(three_phase(&chain[..2], &chain[2].qc), Err(InvalidJustification(_))));
+	let mut consecutive = chain.clone();
+	consecutive[2] = make_proposal(certify(&chain[1]), 4);
+	assert_eq!(three_phase(&consecutive, &certify(&consecutive[2])), Ok(()));
+}
@@ -0,0 +1,53 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+
+const KEYS: [Sr25519Keyring; 4] =
+	[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie, Sr25519Keyring::Dave];
+
+fn authorities() -> AuthorityList {
+	KEYS.iter().map(|key| (key.public().into(), 1)).collect()
+}
+
+#[test]
+fn quorum_unreachable_should_be_entered_and_recovered() {
+	let authorities = authorities();
+	let local: AuthorityId = Sr25519Keyring::Alice.public().into();
+	let mut monitor = QuorumMonitor::new(Duration::from_secs(6), Duration::from_secs(30));
+	let start = Instant::now();
+	let at = |secs| start + Duration::from_secs(secs);
+
+	// Everybody is online.
+	for key in &KEYS[1..] {
+		monitor.observe(key.public().into(), at(0));
+	}
+	assert_eq!(monitor.check(&authorities, Some(&local), at(1)), None);
+
+	// Only Bob stays connected, two of the four authorities are short of a quorum.
+	monitor.observe(Sr25519Keyring::Bob.public().into(), at(10));
+	assert_eq!(monitor.check(&authorities, Some(&local), at(10)), None);
+	assert_eq!(monitor.reachable(&authorities, Some(&local), at(10)), 2);
+
+	// Below the quorum within the grace period.
+	monitor.observe(Sr25519Keyring::Bob.public().into(), at(35));
+	assert_eq!(monitor.check(&authorities, Some(&local), at(35)), None);
+	assert!(!monitor.is_unreachable());
+
+	monitor.observe(Sr25519Keyring::Bob.public().into(), at(40));
+	assert_eq!(
+		monitor.check(&authorities, Some(&local), at(40)),
+		Some(QuorumTransition::Unreachable { reachable: 2 })
+	);
+	assert!(monitor.is_unreachable());
+	// Entered only once.
+	assert_eq!(monitor.check(&authorities, Some(&local), at(41)), None);
+
+	// Charlie reconnects.
+	monitor.observe(Sr25519Keyring::Bob.public().into(), at(42));
+	monitor.observe(Sr25519Keyring::Charlie.public().into(), at(42));
+	assert_eq!(
+		monitor.check(&authorities, Some(&local), at(42)),
+		Some(QuorumTransition::Recovered { reachable: 3 })
+	);
+	assert!(!monitor.is_unreachable());
+}
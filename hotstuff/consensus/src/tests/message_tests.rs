@@ -1,27 +1,20 @@
 use super::*;
 
-use sc_keystore::LocalKeystore;
+use parity_scale_codec::Compact;
+use sp_core::H256;
 use sp_keystore::KeystorePtr;
-use sp_runtime::testing::{Header as TestHeader, TestXt};
+use sp_runtime::{
+	testing::{Header as TestHeader, TestXt},
+	traits::Keccak256,
+};
 
 use hotstuff_primitives::{AuthorityId, AuthorityList, HOTSTUFF_KEY_TYPE};
 
+use crate::testing::deterministic_keystore;
+
 type TestExtrinsic = TestXt<(), ()>;
 type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
 
-fn generate_sr25519_authorities(num: usize, keystore: &KeystorePtr) -> Vec<AuthorityId> {
-	let mut authorities = Vec::new();
-	for i in 0..num {
-		let authority_id = keystore
-			.sr25519_generate_new(HOTSTUFF_KEY_TYPE, Some(format!("//User{}", i).as_str()))
-			.expect("Creates authority pair")
-			.into();
-		authorities.push(authority_id);
-	}
-
-	authorities
-}
-
 fn generate_proposal_with_block(
 	keystore: KeystorePtr,
 	signer: &AuthorityId,
@@ -62,6 +55,8 @@ fn generate_vote_with_proposal(
 		view,
 		voter: signer.clone(),
 		signature: None,
+		timestamp: None,
+		high_qc: None,
 	};
 
 	vote.signature = Some(
@@ -80,12 +75,17 @@ fn generate_timeout(
 	signer: &AuthorityId,
 	view: ViewNumber,
 ) -> Timeout<TestBlock> {
-	let mut timeout = Timeout::<TestBlock> {
-		high_qc: QC::<TestBlock>::default(),
-		view,
-		voter: signer.clone(),
-		signature: None,
-	};
+	generate_timeout_with_qc(keystore, signer, view, QC::<TestBlock>::default())
+}
+
+fn generate_timeout_with_qc(
+	keystore: KeystorePtr,
+	signer: &AuthorityId,
+	view: ViewNumber,
+	high_qc: QC<TestBlock>,
+) -> Timeout<TestBlock> {
+	let mut timeout =
+		Timeout::<TestBlock> { high_qc, view, voter: signer.clone(), signature: None };
 
 	timeout.signature = Some(
 		keystore
@@ -107,12 +107,7 @@ struct TestEnv {
 }
 
 fn create_test_env() -> TestEnv {
-	let keystore_path = tempfile::tempdir().expect("Creates keystore path");
-	let keystore: KeystorePtr = LocalKeystore::open(keystore_path.path(), None)
-		.expect("Creates keystore")
-		.into();
-
-	let pks = generate_sr25519_authorities(4, &keystore);
+	let (keystore, pks) = deterministic_keystore(&["//User0", "//User1", "//User2", "//User3"]);
 	let authorities = &pks[0..3];
 
 	let weighted_authorities =
@@ -206,6 +201,178 @@ fn test_proposal_verify() {
 	}
 }
 
+#[test]
+fn proposal_metadata_should_be_signed() {
+	let TestEnv { keystore, weighted_authorities, test_block, view, .. } = create_test_env();
+	let author = weighted_authorities[1].0.clone();
+
+	let payload = Payload::<TestBlock> {
+		block_hash: test_block.hash(),
+		block_number: test_block.header.number,
+	};
+	let unsigned =
+		Proposal::<TestBlock>::new(QC::default(), None, payload, view, author.clone(), None);
+	let mut proposal = unsigned.clone().with_metadata(b"node/1.0.0".to_vec()).unwrap();
+	assert_ne!(proposal.digest(), unsigned.digest());
+
+	proposal.signature = Some(
+		keystore
+			.sr25519_sign(HOTSTUFF_KEY_TYPE, author.as_ref(), proposal.digest().as_bytes())
+			.unwrap()
+			.unwrap()
+			.into(),
+	);
+	assert_eq!(proposal.verify(&weighted_authorities), Ok(()));
+
+	let mut tampered = proposal.clone();
+	tampered.metadata = Some(b"node/6.6.6".to_vec());
+	assert_eq!(tampered.verify(&weighted_authorities), Err(InvalidSignature(author.clone())));
+
+	let mut stripped = proposal.clone();
+	stripped.metadata = None;
+	assert_eq!(stripped.verify(&weighted_authorities), Err(InvalidSignature(author)));
+
+	let oversized = vec![0u8; MAX_PROPOSAL_METADATA + 1];
+	assert_eq!(
+		unsigned.with_metadata(oversized.clone()).err(),
+		Some(OversizedMetadata(MAX_PROPOSAL_METADATA + 1))
+	);
+	proposal.metadata = Some(oversized);
+	assert_eq!(
+		proposal.verify(&weighted_authorities),
+		Err(OversizedMetadata(MAX_PROPOSAL_METADATA + 1))
+	);
+}
+
+// Test proposals whose optional fields encode to the same bytes end to end have distinct digests.
+#[test]
+fn proposal_digest_fields_should_not_collide() {
+	let TestEnv { weighted_authorities, test_block, view, .. } = create_test_env();
+	let author = weighted_authorities[1].0.clone();
+
+	let payload = Payload::<TestBlock> {
+		block_hash: test_block.hash(),
+		block_number: test_block.header.number,
+	};
+	let unsigned = Proposal::<TestBlock>::new(QC::default(), None, payload, view, author, None);
+
+	// The timestamp reads as the length of seven bytes of metadata, then those bytes.
+	let metadata = vec![1, 2, 3, 4, 5, 6, 7];
+	let mut encoded = Compact(metadata.len() as u32).encode();
+	encoded.extend(&metadata);
+	let timestamp = u64::from_le_bytes(encoded.try_into().unwrap());
+
+	let stamped = unsigned.clone().with_timestamp(timestamp);
+	let described = unsigned.clone().with_metadata(metadata).unwrap();
+	assert_ne!(stamped.digest(), described.digest());
+
+	// Each optional field is digested with its tag, absent or not, and the vote timestamps with
+	// their length, none or not.
+	let digest = |proposal: &Proposal<TestBlock>| {
+		let mut data = proposal.author.encode();
+		data.append(&mut proposal.payload.encode());
+		data.append(&mut proposal.view.encode());
+		data.append(&mut proposal.qc.proposal_hash.encode());
+		data.append(&mut proposal.metadata.encode());
+		data.append(&mut proposal.timestamp.encode());
+		data.append(&mut proposal.qc_timestamps.encode());
+		Hasher::<TestBlock>::hash_of(&data)
+	};
+	for proposal in [&unsigned, &stamped, &described] {
+		assert_eq!(proposal.digest(), digest(proposal));
+	}
+}
+
+#[test]
+fn proposal_timestamp_should_be_within_the_window() {
+	let TestEnv { keystore, weighted_authorities, test_block, view, .. } = create_test_env();
+	let author = weighted_authorities[1].0.clone();
+
+	let payload = Payload::<TestBlock> {
+		block_hash: test_block.hash(),
+		block_number: test_block.header.number,
+	};
+	let unsigned =
+		Proposal::<TestBlock>::new(QC::default(), None, payload, view, author.clone(), None);
+	let now = 1_700_000_000_000;
+	let mut proposal = unsigned.clone().with_timestamp(now);
+	assert_ne!(proposal.digest(), unsigned.digest());
+	proposal.signature = Some(
+		keystore
+			.sr25519_sign(HOTSTUFF_KEY_TYPE, author.as_ref(), proposal.digest().as_bytes())
+			.unwrap()
+			.unwrap()
+			.into(),
+	);
+	assert_eq!(proposal.verify(&weighted_authorities), Ok(()));
+
+	// In the window on either side of the local clock.
+	assert_eq!(proposal.verify_timestamp(now, 500), Ok(()));
+	assert_eq!(proposal.verify_timestamp(now + 500, 500), Ok(()));
+	assert_eq!(proposal.verify_timestamp(now - 500, 500), Ok(()));
+
+	// Replayed long after, or dated ahead of the local clock.
+	assert_eq!(proposal.verify_timestamp(now + 501, 500), Err(ProposalOutsideClockWindow(now)));
+	assert_eq!(proposal.verify_timestamp(now - 501, 500), Err(ProposalOutsideClockWindow(now)));
+
+	// The timestamp is signed.
+	let mut redated = proposal.clone();
+	redated.timestamp = Some(now + 60_000);
+	assert_eq!(redated.verify(&weighted_authorities), Err(InvalidSignature(author)));
+
+	assert_eq!(unsigned.verify_timestamp(now + 60_000, 500), Ok(()));
+}
+
+#[test]
+fn proposal_should_extend_the_committed_proposal() {
+	let author: AuthorityId = sp_keyring::Sr25519Keyring::Alice.public().into();
+	let make_proposal = |parent: Option<&Proposal<TestBlock>>, view: ViewNumber| {
+		let qc = parent.map_or(QC::default(), |parent| QC::<TestBlock> {
+			proposal_hash: parent.digest(),
+			view: parent.view,
+			votes: Vec::new(),
+		});
+		let payload =
+			Payload::<TestBlock> { block_hash: H256::from_low_u64_be(view), block_number: view };
+		Proposal::<TestBlock>::new(qc, None, payload, view, author.clone(), None)
+	};
+
+	// p1 <- p2 <- p3 <- p4, with p2 committed, and a stale fork p1 <- p5.
+	let p1 = make_proposal(None, 1);
+	let p2 = make_proposal(Some(&p1), 2);
+	let p3 = make_proposal(Some(&p2), 3);
+	let p4 = make_proposal(Some(&p3), 4);
+	let p5 = make_proposal(Some(&p1), 5);
+	let store = [&p1, &p2, &p3, &p5]
+		.into_iter()
+		.map(|proposal| (proposal.digest(), proposal.clone()))
+		.collect::<std::collections::HashMap<_, _>>();
+	let get = |hash| Ok(store.get(&hash).cloned());
+
+	assert_eq!(p3.verify_extends(p2.digest(), p2.view, get), Ok(()));
+	assert_eq!(p4.verify_extends(p2.digest(), p2.view, get), Ok(()));
+	assert_eq!(p2.verify_extends(p2.digest(), p2.view, get), Ok(()));
+	assert_eq!(
+		p5.verify_extends(p2.digest(), p2.view, get),
+		Err(DoesNotExtendCommitted(p5.digest().to_string()))
+	);
+	let p6 = make_proposal(Some(&p5), 6);
+	assert_eq!(
+		p6.verify_extends(p2.digest(), p2.view, get),
+		Err(DoesNotExtendCommitted(p6.digest().to_string()))
+	);
+	// The genesis QC only extends genesis.
+	assert_eq!(
+		p1.verify_extends(p2.digest(), p2.view, get),
+		Err(DoesNotExtendCommitted(p1.digest().to_string()))
+	);
+	assert_eq!(p4.verify_extends(Default::default(), 0, get), Ok(()));
+
+	// An unknown ancestor can't be told apart.
+	let orphan = make_proposal(Some(&make_proposal(Some(&p4), 7)), 8);
+	assert_eq!(orphan.verify_extends(p2.digest(), p2.view, get), Ok(()));
+}
+
 #[test]
 fn test_vote_verify() {
 	let TestEnv { keystore, pks, weighted_authorities, test_block, view } = create_test_env();
@@ -489,7 +656,12 @@ fn test_tc_verify() {
 		TestCase {
 			describe: "Case: Normal tc".to_string(),
 			tc: || -> TC<TestBlock> {
-				let mut tc = TC::<TestBlock> { view, votes: Vec::new(), _phantom: PhantomData };
+				let mut tc = TC::<TestBlock> {
+					view,
+					votes: Vec::new(),
+					high_qc: QC::default(),
+					_phantom: PhantomData,
+				};
 				for authority_id in authorities.iter() {
 					let timeout = generate_timeout(keystore.clone(), &authority_id, view);
 					tc.votes.push((
@@ -506,7 +678,12 @@ fn test_tc_verify() {
 		TestCase {
 			describe: "Case: Insufficient quorum".to_string(),
 			tc: || -> TC<TestBlock> {
-				let mut tc = TC::<TestBlock> { view, votes: Vec::new(), _phantom: PhantomData };
+				let mut tc = TC::<TestBlock> {
+					view,
+					votes: Vec::new(),
+					high_qc: QC::default(),
+					_phantom: PhantomData,
+				};
 				let mut count = 0;
 				for authority_id in authorities.iter() {
 					if count == 2 {
@@ -524,12 +701,17 @@ fn test_tc_verify() {
 
 				tc
 			}(),
-			result: Err(InsufficientQuorum),
+			result: Err(InsufficientTimeoutWeight),
 		},
 		TestCase {
 			describe: "Case: Reuse quorum".to_string(),
 			tc: || -> TC<TestBlock> {
-				let mut tc = TC::<TestBlock> { view, votes: Vec::new(), _phantom: PhantomData };
+				let mut tc = TC::<TestBlock> {
+					view,
+					votes: Vec::new(),
+					high_qc: QC::default(),
+					_phantom: PhantomData,
+				};
 
 				for authority_id in authorities.iter() {
 					let timeout = generate_timeout(keystore.clone(), &authority_id, view);
@@ -548,7 +730,12 @@ fn test_tc_verify() {
 		TestCase {
 			describe: "Case: Invalid signature".to_string(),
 			tc: || -> TC<TestBlock> {
-				let mut tc = TC::<TestBlock> { view, votes: Vec::new(), _phantom: PhantomData };
+				let mut tc = TC::<TestBlock> {
+					view,
+					votes: Vec::new(),
+					high_qc: QC::default(),
+					_phantom: PhantomData,
+				};
 
 				for authority_id in authorities.iter() {
 					let ban_signature = keystore
@@ -578,12 +765,7 @@ fn test_tc_verify() {
 
 #[test]
 fn qc_from_votes_should_work() {
-	let keystore_path = tempfile::tempdir().expect("Creates keystore path");
-	let keystore: KeystorePtr = LocalKeystore::open(keystore_path.path(), None)
-		.expect("Creates keystore")
-		.into();
-
-	let authorities = generate_sr25519_authorities(3, &keystore);
+	let (_, authorities) = deterministic_keystore(&["//User0", "//User1", "//User2"]);
 
 	let test_block = TestBlock { header: TestHeader::new_from_number(3), extrinsics: Vec::new() };
 
@@ -599,6 +781,10 @@ fn qc_from_votes_should_work() {
 		view: view_number,
 		author: authorities[0].clone(),
 		signature: None,
+		metadata: None,
+		timestamp: None,
+		self_vote: None,
+		qc_timestamps: Vec::new(),
 	};
 
 	let proposal_digest = proposal.digest();
@@ -608,6 +794,8 @@ fn qc_from_votes_should_work() {
 		view: view_number,
 		voter: authorities[0].clone(),
 		signature: None,
+		timestamp: None,
+		high_qc: None,
 	};
 
 	let qc =
@@ -615,3 +803,407 @@ fn qc_from_votes_should_work() {
 
 	assert_eq!(vote.digest(), qc.digest());
 }
+
+#[test]
+fn signer_bitfield_should_follow_authority_order() {
+	let TestEnv { keystore, pks, weighted_authorities, test_block, view } = create_test_env();
+
+	let proposal = generate_proposal_with_block(keystore.clone(), &pks[0], &test_block, view);
+
+	// Signed by the third and the first authority, and by a key outside the authority list.
+	let mut qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view, votes: Vec::new() };
+	for signer in [&pks[2], &pks[0], &pks[3]] {
+		let vote = generate_vote_with_proposal(keystore.clone(), signer, &proposal, view);
+		qc.add_votes(signer.clone(), vote.signature.unwrap());
+	}
+
+	let bitfield = signer_bitfield(&qc, &weighted_authorities);
+	assert_eq!(bitfield, vec![true, false, true]);
+	assert_eq!(bitfield_hex(&bitfield), "0xa0");
+	assert_eq!(unknown_signers(&qc, &weighted_authorities), vec![pks[3].clone()]);
+
+	assert_eq!(
+		bitfield_hex(&signer_bitfield(&QC::<TestBlock>::default(), &weighted_authorities)),
+		"0x00"
+	);
+}
+
+#[test]
+fn qcs_certifying_different_proposals_of_a_view_should_conflict() {
+	let TestEnv { keystore, pks, weighted_authorities, test_block, view } = create_test_env();
+
+	let other_block = TestBlock { header: TestHeader::new_from_number(11), extrinsics: Vec::new() };
+	let qc_of = |block: &TestBlock, view: ViewNumber| {
+		let proposal = generate_proposal_with_block(keystore.clone(), &pks[0], block, view);
+		let mut qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view, votes: Vec::new() };
+		for signer in &pks[0..3] {
+			let vote = generate_vote_with_proposal(keystore.clone(), signer, &proposal, view);
+			qc.add_votes(signer.clone(), vote.signature.unwrap());
+		}
+		assert_eq!(qc.verify(&weighted_authorities), Ok(()));
+		qc
+	};
+
+	let qc = qc_of(&test_block, view);
+	let conflicting = qc_of(&other_block, view);
+	assert!(qcs_conflict(&qc, &conflicting));
+	assert!(qcs_conflict(&conflicting, &qc));
+
+	// The same proposal, or proposals of distinct views, don't conflict.
+	assert!(!qcs_conflict(&qc, &qc.clone()));
+	assert!(!qcs_conflict(&qc, &qc_of(&other_block, view + 1)));
+}
+
+// Add the group order to the scalar of a signature, which keeps it valid for a lenient verifier.
+fn malleate(signature: &AuthoritySignature) -> AuthoritySignature {
+	let mut bytes = [0u8; 64];
+	bytes.copy_from_slice(signature.as_ref());
+	bytes[63] &= 0x7f;
+
+	let mut carry = 0u16;
+	for (byte, order) in bytes[32..].iter_mut().zip(GROUP_ORDER.iter()) {
+		let sum = *byte as u16 + *order as u16 + carry;
+		*byte = sum as u8;
+		carry = sum >> 8;
+	}
+	bytes[63] |= 0x80;
+
+	sp_core::sr25519::Signature::from_raw(bytes).into()
+}
+
+#[test]
+fn non_canonical_signatures_should_be_rejected() {
+	let TestEnv { keystore, weighted_authorities, test_block, view, .. } = create_test_env();
+	let author = weighted_authorities[0].0.clone();
+
+	let mut proposal = generate_proposal_with_block(keystore.clone(), &author, &test_block, view);
+	let mut vote = generate_vote_with_proposal(keystore, &author, &proposal, view);
+	assert!(is_canonical(proposal.signature.as_ref().unwrap()));
+	assert_eq!(proposal.verify(&weighted_authorities), Ok(()));
+	assert_eq!(vote.verify(&weighted_authorities), Ok(()));
+
+	proposal.signature = proposal.signature.as_ref().map(malleate);
+	vote.signature = vote.signature.as_ref().map(malleate);
+	assert!(!is_canonical(proposal.signature.as_ref().unwrap()));
+	assert_eq!(proposal.verify(&weighted_authorities), Err(NonCanonicalSignature(author.clone())));
+	assert_eq!(vote.verify(&weighted_authorities), Err(NonCanonicalSignature(author)));
+}
+
+#[test]
+fn tc_should_need_a_weighted_quorum() {
+	let TestEnv { keystore, pks, view, .. } = create_test_env();
+	let authorities: AuthorityList = pks
+		.iter()
+		.zip([70, 10, 10, 10])
+		.map(|(id, weight)| (id.clone(), weight))
+		.collect();
+	let make_tc = |signers: &[AuthorityId]| {
+		let mut tc = TC::<TestBlock> {
+			view,
+			votes: Vec::new(),
+			high_qc: QC::default(),
+			_phantom: PhantomData,
+		};
+		for signer in signers {
+			let timeout = generate_timeout(keystore.clone(), signer, view);
+			tc.votes
+				.push((signer.clone(), timeout.signature.unwrap(), timeout.high_qc.view));
+		}
+		tc
+	};
+
+	// Three of the four authorities, but only 30% of the weight.
+	assert_eq!(make_tc(&pks[1..]).verify(&authorities), Err(InsufficientTimeoutWeight));
+
+	// Two of the four authorities, with 80% of the weight.
+	assert_eq!(make_tc(&pks[..2]).verify(&authorities), Ok(()));
+
+	// Equally weighted authorities count one each.
+	let unweighted: AuthorityList = pks.iter().map(|id| (id.clone(), 1)).collect();
+	assert!(!is_quorum(&pks[..2], &unweighted));
+	assert!(is_quorum(&pks[..3], &unweighted));
+}
+
+#[test]
+fn tc_should_embed_the_highest_high_qc() {
+	let TestEnv { keystore, weighted_authorities, test_block, view, .. } = create_test_env();
+	let authorities =
+		weighted_authorities.iter().map(|a| a.0.clone()).collect::<Vec<AuthorityId>>();
+
+	let certify = |qc_view| {
+		let proposal =
+			generate_proposal_with_block(keystore.clone(), &authorities[0], &test_block, qc_view);
+		let mut qc =
+			QC::<TestBlock> { proposal_hash: proposal.digest(), view: qc_view, votes: Vec::new() };
+		for signer in authorities.iter() {
+			let vote = generate_vote_with_proposal(keystore.clone(), signer, &proposal, qc_view);
+			qc.add_votes(signer.clone(), vote.signature.unwrap());
+		}
+		qc
+	};
+	let (low, high) = (certify(2), certify(4));
+
+	// The first authority knows the QC of view 4, the others only the QC of view 2.
+	let mut tc =
+		TC::<TestBlock> { view, votes: Vec::new(), high_qc: high.clone(), _phantom: PhantomData };
+	for (signer, qc) in authorities.iter().zip([&high, &low, &low]) {
+		let timeout = generate_timeout_with_qc(keystore.clone(), signer, view, qc.clone());
+		tc.votes.push((signer.clone(), timeout.signature.unwrap(), qc.view));
+	}
+	assert_eq!(tc.verify(&weighted_authorities), Ok(()));
+
+	// An aggregator embedding a lower QC to rewind the chain.
+	let rewound = TC::<TestBlock> { high_qc: low, ..tc.clone() };
+	assert_eq!(rewound.verify(&weighted_authorities), Err(TcHighQcMismatch));
+
+	// A QC claiming the highest view without the votes to back it.
+	let forged = TC::<TestBlock> {
+		high_qc: QC::<TestBlock> { votes: high.votes[..1].to_vec(), ..high.clone() },
+		..tc
+	};
+	assert_eq!(forged.verify(&weighted_authorities), Err(InsufficientQuorum));
+}
+
+#[test]
+fn proposal_should_be_for_the_view_after_its_justification() {
+	let TestEnv { keystore, weighted_authorities, test_block, .. } = create_test_env();
+	let authorities =
+		weighted_authorities.iter().map(|a| a.0.clone()).collect::<Vec<AuthorityId>>();
+
+	let proposal = generate_proposal_with_block(keystore.clone(), &authorities[0], &test_block, 4);
+	let mut qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view: 4, votes: Vec::new() };
+	for signer in authorities.iter() {
+		let vote = generate_vote_with_proposal(keystore.clone(), signer, &proposal, 4);
+		qc.add_votes(signer.clone(), vote.signature.unwrap());
+	}
+
+	// View 6 timed out after the QC of view 4.
+	let mut tc =
+		TC::<TestBlock> { view: 6, votes: Vec::new(), high_qc: qc.clone(), _phantom: PhantomData };
+	for signer in authorities.iter() {
+		let timeout = generate_timeout_with_qc(keystore.clone(), signer, 6, qc.clone());
+		tc.votes.push((signer.clone(), timeout.signature.unwrap(), qc.view));
+	}
+
+	let propose = |tc: Option<TC<TestBlock>>, view| {
+		let payload = Payload::<TestBlock> {
+			block_hash: test_block.hash(),
+			block_number: test_block.header.number,
+		};
+		let mut proposal =
+			Proposal::<TestBlock>::new(qc.clone(), tc, payload, view, authorities[1].clone(), None);
+		proposal.signature = Some(
+			keystore
+				.sr25519_sign(
+					HOTSTUFF_KEY_TYPE,
+					authorities[1].as_ref(),
+					proposal.digest().as_bytes(),
+				)
+				.unwrap()
+				.unwrap()
+				.into(),
+		);
+		proposal
+	};
+
+	assert_eq!(propose(None, 5).verify(&weighted_authorities), Ok(()));
+	assert_eq!(propose(None, 7).verify(&weighted_authorities), Err(MissingTimeoutJustification));
+	assert_eq!(propose(Some(tc.clone()), 7).verify(&weighted_authorities), Ok(()));
+	// A stale TC doesn't justify a later view.
+	assert_eq!(
+		propose(Some(tc), 9).verify(&weighted_authorities),
+		Err(MissingTimeoutJustification)
+	);
+}
+
+#[test]
+fn zero_weight_votes_should_be_valid_but_not_count() {
+	let TestEnv { keystore, pks, test_block, view, .. } = create_test_env();
+	// The fourth authority joined but isn't staked yet.
+	let authorities: AuthorityList =
+		pks.iter().zip([1, 1, 1, 0]).map(|(id, weight)| (id.clone(), weight)).collect();
+
+	let proposal = generate_proposal_with_block(keystore.clone(), &pks[0], &test_block, view);
+	let make_qc = |signers: &[&AuthorityId]| {
+		let mut qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view, votes: Vec::new() };
+		for signer in signers {
+			let vote = generate_vote_with_proposal(keystore.clone(), signer, &proposal, view);
+			qc.add_votes((*signer).clone(), vote.signature.unwrap());
+		}
+		qc
+	};
+
+	// The quorum is three of the three weighted authorities, as without the zero-weight one.
+	assert!(!is_quorum(&pks[..2], &authorities));
+	assert!(is_quorum(&pks[..3], &authorities));
+	assert_eq!(make_qc(&[&pks[0], &pks[1], &pks[3]]).verify(&authorities), Err(InsufficientQuorum));
+	assert_eq!(make_qc(&[&pks[0], &pks[1], &pks[2]]).verify(&authorities), Ok(()));
+	assert_eq!(make_qc(&[&pks[3], &pks[0], &pks[1], &pks[2]]).verify(&authorities), Ok(()));
+
+	let vote = generate_vote_with_proposal(keystore.clone(), &pks[3], &proposal, view);
+	assert_eq!(vote.verify(&authorities), Ok(()));
+}
+
+type KeccakBlock =
+	sp_runtime::generic::Block<sp_runtime::generic::Header<u64, Keccak256>, TestExtrinsic>;
+
+// A proposal of `signer` and its vote for it, both signed under the hasher of `B`.
+fn sign_round<B: BlockT<Hash = H256>>(
+	keystore: &KeystorePtr,
+	signer: &AuthorityId,
+	view: ViewNumber,
+) -> (Proposal<B>, Vote<B>) {
+	let sign = |digest: H256| -> Option<AuthoritySignature> {
+		keystore
+			.sr25519_sign(HOTSTUFF_KEY_TYPE, signer.as_ref(), digest.as_bytes())
+			.unwrap()
+			.map(Into::into)
+	};
+	let payload = Payload::<B> { block_hash: H256::repeat_byte(1), block_number: 1u32.into() };
+
+	let mut proposal = Proposal::<B>::new(QC::default(), None, payload, view, signer.clone(), None);
+	proposal.signature = sign(proposal.digest());
+	let mut vote = Vote::<B>::new(proposal.digest(), view, signer.clone());
+	vote.signature = sign(vote.digest());
+
+	(proposal, vote)
+}
+
+#[test]
+fn digests_should_follow_the_block_hasher() {
+	let TestEnv { keystore, weighted_authorities, view, .. } = create_test_env();
+	let signer = weighted_authorities[0].0.clone();
+
+	let (keccak_proposal, keccak_vote) = sign_round::<KeccakBlock>(&keystore, &signer, view);
+	let (blake2_proposal, blake2_vote) = sign_round::<TestBlock>(&keystore, &signer, view);
+	assert_eq!(keccak_proposal.verify(&weighted_authorities), Ok(()));
+	assert_eq!(keccak_vote.verify(&weighted_authorities), Ok(()));
+	assert_ne!(keccak_proposal.digest(), blake2_proposal.digest());
+
+	// The same messages signed under keccak don't verify under blake2.
+	let mut mixed_proposal = blake2_proposal;
+	mixed_proposal.signature = keccak_proposal.signature;
+	assert_eq!(mixed_proposal.verify(&weighted_authorities), Err(InvalidSignature(signer.clone())));
+
+	let mut mixed_vote = Vote::<TestBlock>::new(keccak_vote.proposal_hash, view, signer.clone());
+	mixed_vote.signature = keccak_vote.signature;
+	assert_eq!(mixed_vote.verify(&weighted_authorities), Err(InvalidSignature(signer.clone())));
+
+	// Nor the other way around.
+	let mut mixed_vote = Vote::<KeccakBlock>::new(blake2_vote.proposal_hash, view, signer.clone());
+	mixed_vote.signature = blake2_vote.signature;
+	assert_eq!(mixed_vote.verify(&weighted_authorities), Err(InvalidSignature(signer)));
+}
+
+#[test]
+fn historical_qc_should_verify_against_the_set_of_its_view() {
+	let TestEnv { keystore, pks, weighted_authorities, test_block, view } = create_test_env();
+
+	// The first authority leaves the set at the view after the QC and the fourth joins.
+	let current = pks[1..4].iter().map(|id| (id.clone(), 1)).collect::<AuthorityList>();
+	let activation_view = view + 1;
+	let resolve = |at: ViewNumber| {
+		if at < activation_view {
+			weighted_authorities.clone()
+		} else {
+			current.clone()
+		}
+	};
+
+	let proposal = generate_proposal_with_block(keystore.clone(), &pks[0], &test_block, view);
+	let mut qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view, votes: Vec::new() };
+	for (voter, _) in weighted_authorities.iter() {
+		let vote = generate_vote_with_proposal(keystore.clone(), voter, &proposal, view);
+		qc.add_votes(voter.clone(), vote.signature.unwrap());
+	}
+
+	let context = SigningContext::default();
+	assert_eq!(qc.verify_with_resolver(resolve, None, &context), Ok(()));
+	assert_eq!(qc.verify_with(&current, &context), Err(UnknownAuthority(pks[0].clone())));
+	assert_eq!(
+		qc.verify_with_resolver(|_| current.clone(), None, &context),
+		Err(UnknownAuthority(pks[0].clone()))
+	);
+}
+
+// Test timeouts and TCs of the first view of a new authority set, carrying the QC of the last view
+// of the previous set, verify against the set of each view.
+#[test]
+fn timeout_across_a_set_change_should_verify_its_qc_against_the_previous_set() {
+	let TestEnv { keystore, pks, weighted_authorities, test_block, view } = create_test_env();
+
+	let current = pks[1..4].iter().map(|id| (id.clone(), 1)).collect::<AuthorityList>();
+	let activation_view = view + 1;
+	let resolve = |at: ViewNumber| {
+		if at < activation_view {
+			weighted_authorities.clone()
+		} else {
+			current.clone()
+		}
+	};
+
+	let proposal = generate_proposal_with_block(keystore.clone(), &pks[0], &test_block, view);
+	let mut qc = QC::<TestBlock> { proposal_hash: proposal.digest(), view, votes: Vec::new() };
+	for (voter, _) in weighted_authorities.iter() {
+		let vote = generate_vote_with_proposal(keystore.clone(), voter, &proposal, view);
+		qc.add_votes(voter.clone(), vote.signature.unwrap());
+	}
+
+	let timeouts = pks[1..4]
+		.iter()
+		.map(|voter| generate_timeout_with_qc(keystore.clone(), voter, activation_view, qc.clone()))
+		.collect::<Vec<_>>();
+	let context = SigningContext::default();
+	assert_eq!(timeouts[2].verify_with_resolver(resolve, None, &context), Ok(()));
+	assert_eq!(
+		timeouts[2].verify_with(&current, None, &context),
+		Err(UnknownAuthority(pks[0].clone()))
+	);
+
+	let tc = TC::<TestBlock> {
+		view: activation_view,
+		votes: timeouts
+			.iter()
+			.map(|timeout| (timeout.voter.clone(), timeout.signature.clone().unwrap(), view))
+			.collect(),
+		high_qc: qc,
+		_phantom: PhantomData,
+	};
+	assert_eq!(tc.verify_with_resolver(resolve, None, &context), Ok(()));
+	assert_eq!(tc.verify_with(&current, None, &context), Err(UnknownAuthority(pks[0].clone())));
+}
+
+// Test a leader outside the sampled committee of its view needs no vote of its own: the vote it
+// carries is neither verified nor counted, while a member's still is.
+#[test]
+fn self_vote_of_a_leader_outside_the_committee_should_be_ignored() {
+	let TestEnv { keystore, pks, test_block, .. } = create_test_env();
+	let authorities = pks.iter().map(|id| (id.clone(), 1)).collect::<AuthorityList>();
+	let sampler = CommitteeSampler::new(3, b"hotstuff/message_tests".to_vec());
+	let author = pks[0].clone();
+	let outside = |view| !sampler.committee(&authorities, view).iter().any(|(id, _)| *id == author);
+	let view = (1..).find(|view| outside(*view)).unwrap();
+	let inside = (1..).find(|view| !outside(*view)).unwrap();
+
+	let with_self_vote = |view, vote_view| {
+		let mut proposal =
+			generate_proposal_with_block(keystore.clone(), &author, &test_block, view);
+		let vote = generate_vote_with_proposal(keystore.clone(), &author, &proposal, vote_view);
+		proposal.self_vote = Some(Box::new(vote));
+		proposal
+	};
+	let context = SigningContext::default();
+
+	// Even a self vote for another view is ignored outside the committee.
+	let proposal = with_self_vote(view, view + 1);
+	let committee = sampler.committee(&authorities, view);
+	assert_eq!(proposal.verify_with(&authorities, Some(&sampler), &context), Ok(()));
+	assert!(proposal.committee_self_vote(&committee).is_none());
+
+	let proposal = with_self_vote(inside, inside + 1);
+	assert_eq!(proposal.verify_with(&authorities, Some(&sampler), &context), Err(InvalidSelfVote));
+	let proposal = with_self_vote(inside, inside);
+	let committee = sampler.committee(&authorities, inside);
+	assert_eq!(proposal.verify_with(&authorities, Some(&sampler), &context), Ok(()));
+	assert!(proposal.committee_self_vote(&committee).is_some());
+}
@@ -0,0 +1,105 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+const KEYS: [Sr25519Keyring; 6] = [
+	Sr25519Keyring::Alice,
+	Sr25519Keyring::Bob,
+	Sr25519Keyring::Charlie,
+	Sr25519Keyring::Dave,
+	Sr25519Keyring::Eve,
+	Sr25519Keyring::Ferdie,
+];
+
+fn make_qc(view: ViewNumber, voters: usize) -> QC<TestBlock> {
+	let mut qc =
+		QC::<TestBlock> { proposal_hash: H256::from_low_u64_be(view), view, votes: Vec::new() };
+	for key in KEYS[..voters].iter() {
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	}
+	qc
+}
+
+#[test]
+fn qc_votes_should_be_recorded() {
+	let registry = Registry::new();
+	let mut metrics = Metrics::register(&registry).expect("metrics register");
+
+	// A quorum of 6 authorities is 5 votes, more are redundant.
+	for (view, voters) in [(1, 5), (2, 6), (3, 5), (4, 6), (5, 6)] {
+		metrics.observe_qc(&make_qc(view, voters));
+	}
+	// The QC of a view seen again in a later proposal.
+	metrics.observe_qc(&make_qc(5, 6));
+
+	assert_eq!(metrics.qc_votes.get_sample_count(), 5);
+	assert_eq!(metrics.qc_votes.get_sample_sum(), 28.0);
+
+	let families = registry.gather();
+	let histogram = families
+		.iter()
+		.find(|family| family.get_name() == "substrate_hotstuff_qc_votes")
+		.expect("histogram is registered")
+		.get_metric()[0]
+		.get_histogram();
+	// Buckets of 1, 2, 4 and 8 votes.
+	let counts = histogram
+		.get_bucket()
+		.iter()
+		.map(|b| b.get_cumulative_count())
+		.collect::<Vec<_>>();
+	assert_eq!(counts[..4], [0, 0, 0, 5]);
+}
+
+#[test]
+fn empty_proposals_should_be_counted() {
+	let registry = Registry::new();
+	let metrics = Metrics::register(&registry).expect("metrics register");
+
+	for _ in 0..3 {
+		metrics.observe_empty_proposal();
+	}
+
+	let families = registry.gather();
+	let counter = families
+		.iter()
+		.find(|family| family.get_name() == "substrate_hotstuff_empty_proposals")
+		.expect("counter is registered")
+		.get_metric()[0]
+		.get_counter();
+	assert_eq!(counter.get_value(), 3.0);
+}
+
+#[test]
+fn message_sizes_should_be_recorded_before_and_after_compression() {
+	let registry = Registry::new();
+	let metrics = Metrics::register(&registry).expect("metrics register");
+
+	// Small votes aren't worth compressing, a large proposal is.
+	metrics.observe_message_size("vote", 100, None);
+	metrics.observe_message_size("vote", 120, None);
+	metrics.observe_message_size("proposal", 4000, Some(1000));
+
+	let families = registry.gather();
+	let family = families
+		.iter()
+		.find(|family| family.get_name() == "substrate_hotstuff_message_bytes")
+		.expect("histogram is registered");
+	// Both stages of the votes, and of the proposal.
+	assert_eq!(family.get_metric().len(), 4);
+
+	let sizes = |kind: &str, stage: &str, compressed: &str| {
+		let histogram = metrics.message_bytes.with_label_values(&[kind, stage, compressed]);
+		(histogram.get_sample_count(), histogram.get_sample_sum())
+	};
+	assert_eq!(sizes("vote", "encoded", "false"), (2, 220.0));
+	assert_eq!(sizes("vote", "sent", "false"), (2, 220.0));
+	assert_eq!(sizes("vote", "sent", "true"), (0, 0.0));
+	assert_eq!(sizes("proposal", "encoded", "true"), (1, 4000.0));
+	assert_eq!(sizes("proposal", "sent", "true"), (1, 1000.0));
+}
@@ -0,0 +1,288 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::{TestXt, H256};
+
+use crate::{
+	message::{Payload, Proposal, Vote, QC},
+	reputation::SYNC_REQUEST_LIMIT,
+};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+struct NoopContext;
+
+impl ValidatorContext<TestBlock> for NoopContext {
+	fn broadcast_topic(&mut self, _topic: H256, _force: bool) {}
+	fn broadcast_message(&mut self, _topic: H256, _message: Vec<u8>, _force: bool) {}
+	fn send_message(&mut self, _who: &PeerId, _message: Vec<u8>) {}
+	fn send_topic(&mut self, _who: &PeerId, _topic: H256, _force: bool) {}
+}
+
+fn encoded_vote(proposal_hash: H256, view: ViewNumber) -> Vec<u8> {
+	let vote = Vote::<TestBlock>::new(proposal_hash, view, Sr25519Keyring::Alice.public().into());
+	GossipMessage::<TestBlock> { set_id: 0, message: ConsensusMessage::Vote(vote) }.encode()
+}
+
+#[test]
+fn votes_of_a_known_qc_should_be_received_but_not_forwarded() {
+	use sc_network_gossip::Validator;
+
+	let (validator, _) = GossipValidator::<TestBlock>::new(Default::default(), Default::default());
+	let topic = ConsensusMessage::<TestBlock>::gossip_topic();
+	let (peer, proposal) = (PeerId::random(), H256::repeat_byte(1));
+	let vote = encoded_vote(proposal, 3);
+	let other_vote = encoded_vote(H256::repeat_byte(2), 3);
+	validator.set_view(3);
+
+	let forwarded =
+		|data: &[u8]| validator.message_allowed()(&peer, MessageIntent::Broadcast, &topic, data);
+	assert!(forwarded(&vote));
+
+	validator.note_certified(3, proposal);
+	assert!(!forwarded(&vote));
+	assert!(forwarded(&other_vote));
+
+	// The vote still reaches the worker.
+	assert!(matches!(
+		validator.validate(&mut NoopContext, &peer, &vote),
+		ValidationResult::ProcessAndKeep(_)
+	));
+
+	// The QCs are forgotten with their views.
+	validator.set_view(5);
+	assert!(!validator.is_certified(3, &proposal));
+}
+
+fn encoded_proposal(view: ViewNumber) -> (H256, Vec<u8>) {
+	let payload =
+		Payload::<TestBlock> { block_hash: H256::from_low_u64_be(view), block_number: view };
+	let author = Sr25519Keyring::Bob.public().into();
+	let proposal = Proposal::<TestBlock>::new(QC::default(), None, payload, view, author, None);
+	let digest = proposal.digest();
+	(
+		digest,
+		GossipMessage::<TestBlock> { set_id: 0, message: ConsensusMessage::Propose(proposal) }
+			.encode(),
+	)
+}
+
+#[test]
+fn sync_requests_should_not_be_stale() {
+	use sc_network_gossip::Validator;
+
+	let (validator, _) = GossipValidator::<TestBlock>::new(Default::default(), Default::default());
+	let topic = ConsensusMessage::<TestBlock>::gossip_topic();
+	let peer = PeerId::random();
+	let (digest, response) = encoded_proposal(1);
+	let (_, unrequested) = encoded_proposal(2);
+	let request = encoded_sync_request(digest);
+	validator.set_view(5);
+	let processed = |data: &[u8]| {
+		!matches!(validator.validate(&mut NoopContext, &peer, data), ValidationResult::Discard)
+	};
+
+	// The node asks for the proposal of view 1, which is taken though it's of a past view, but
+	// not passed on.
+	assert!(validator.message_allowed()(&peer, MessageIntent::Broadcast, &topic, &request));
+	assert!(!validator.message_expired()(topic, &request));
+	assert!(matches!(
+		validator.validate(&mut NoopContext, &peer, &response),
+		ValidationResult::ProcessAndDiscard(_)
+	));
+	assert!(!processed(&unrequested));
+
+	// The request gets stale with the view it was made at.
+	validator.set_view(7);
+	assert!(validator.message_expired()(topic, &request));
+	assert!(!processed(&response));
+}
+
+fn encoded_sync_request(digest: H256) -> Vec<u8> {
+	let requester = Sr25519Keyring::Alice.public().into();
+	GossipMessage::<TestBlock> {
+		set_id: 0,
+		message: ConsensusMessage::SyncRequest(digest, requester),
+	}
+	.encode()
+}
+
+#[test]
+fn sync_requests_should_be_answered_to_their_sender_once() {
+	use sc_network_gossip::Validator;
+
+	let (validator, reports) =
+		GossipValidator::<TestBlock>::new(Default::default(), Default::default());
+	let (requester, forger) = (PeerId::random(), PeerId::random());
+	let (digest, _) = encoded_proposal(1);
+	let request = encoded_sync_request(digest);
+	validator.set_view(5);
+
+	// A request is taken but not propagated, it's answered to its sender.
+	assert!(matches!(
+		validator.validate(&mut NoopContext, &requester, &request),
+		ValidationResult::ProcessAndDiscard(_)
+	));
+	assert_eq!(validator.take_sync_requesters(&digest), vec![requester]);
+
+	// Repeated, or replayed by a forger over and over, it isn't answered again.
+	assert!(matches!(
+		validator.validate(&mut NoopContext, &requester, &request),
+		ValidationResult::Discard
+	));
+	for _ in 0..2 * SYNC_REQUEST_LIMIT {
+		validator.validate(&mut NoopContext, &forger, &request);
+	}
+	assert_eq!(validator.take_sync_requesters(&digest), vec![forger]);
+	assert_eq!(validator.take_sync_requesters(&digest), vec![]);
+
+	// The peers repeating requests get their reputation lowered, the forger down to a ban.
+	drop(validator);
+	let reported = futures::executor::block_on_stream(reports).collect::<Vec<_>>();
+	assert!(!reported.is_empty());
+	assert!(reported.iter().all(|report| report.who == forger));
+}
+
+// Keeps the messages sent to each peer.
+#[derive(Default)]
+struct RecordingContext {
+	sent: Vec<(PeerId, Vec<u8>)>,
+}
+
+impl ValidatorContext<TestBlock> for RecordingContext {
+	fn broadcast_topic(&mut self, _topic: H256, _force: bool) {}
+	fn broadcast_message(&mut self, _topic: H256, _message: Vec<u8>, _force: bool) {}
+	fn send_message(&mut self, who: &PeerId, message: Vec<u8>) {
+		self.sent.push((*who, message));
+	}
+	fn send_topic(&mut self, _who: &PeerId, _topic: H256, _force: bool) {}
+}
+
+#[test]
+fn peers_without_a_common_signature_scheme_should_be_dropped() {
+	use crate::scheme::{SchemeConfig, SignatureScheme::*};
+	use sc_network_gossip::Validator;
+
+	let node = |supported| {
+		GossipValidator::<TestBlock>::new(Default::default(), SchemeConfig { supported }).0
+	};
+	// Migrating from sr25519 to ed25519.
+	let local = node(vec![Sr25519, Ed25519]);
+	let (local_id, overlapping_id, disjoint_id) =
+		(PeerId::random(), PeerId::random(), PeerId::random());
+	let overlapping = node(vec![Ed25519]);
+	let disjoint = node(vec![Ecdsa]);
+
+	// Each node sends its handshake to the other as they connect.
+	let handshake = |validator: &GossipValidator<TestBlock>| {
+		let mut context = RecordingContext::default();
+		validator.new_peer(&mut context, &local_id, ObservedRole::Authority);
+		context.sent.pop().expect("handshake sent").1
+	};
+	for (id, peer) in [(overlapping_id, &overlapping), (disjoint_id, &disjoint)] {
+		let mut context = RecordingContext::default();
+		local.new_peer(&mut context, &id, ObservedRole::Authority);
+		assert_eq!(context.sent.len(), 1);
+		assert!(matches!(
+			peer.validate(&mut NoopContext, &local_id, &context.sent[0].1),
+			ValidationResult::Discard
+		));
+		assert!(matches!(
+			local.validate(&mut NoopContext, &id, &handshake(peer)),
+			ValidationResult::Discard
+		));
+	}
+
+	let vote = encoded_vote(H256::repeat_byte(1), 0);
+	assert!(matches!(
+		local.validate(&mut NoopContext, &overlapping_id, &vote),
+		ValidationResult::ProcessAndKeep(_)
+	));
+	assert!(matches!(
+		local.validate(&mut NoopContext, &disjoint_id, &vote),
+		ValidationResult::Discard
+	));
+	assert_eq!(local.schemes.lock().dropped(), 1);
+
+	// The disjoint node drops the messages of the local node in turn.
+	assert!(matches!(
+		disjoint.validate(&mut NoopContext, &local_id, &vote),
+		ValidationResult::Discard
+	));
+	assert!(matches!(
+		overlapping.validate(&mut NoopContext, &local_id, &vote),
+		ValidationResult::ProcessAndKeep(_)
+	));
+}
+
+#[test]
+fn simulated_delivery_should_release_messages_after_their_delay() {
+	let now = tokio::time::Instant::now();
+	let delay = Duration::from_millis(50);
+	let mut delivery = SimulatedDelivery::new(delay, 7);
+	delivery.hold(1, None, now);
+	delivery.hold(2, None, now + Duration::from_millis(10));
+
+	assert_eq!(delivery.release(now + Duration::from_millis(49)), None);
+	assert_eq!(delivery.release(now + delay), Some(1));
+	assert_eq!(delivery.release(now + delay), None);
+	assert_eq!(delivery.release(now + Duration::from_millis(60)), Some(2));
+	assert_eq!(delivery.held(), 0);
+
+	// A seed draws the same jitter on every run.
+	let jittered = |seed| {
+		let mut delivery = SimulatedDelivery::new(delay, seed).with_jitter(delay);
+		(0..20).for_each(|message| delivery.hold(message, None, now));
+		let mut released = Vec::new();
+		for millis in 0..=100 {
+			while let Some(message) = delivery.release(now + Duration::from_millis(millis)) {
+				released.push(message);
+			}
+		}
+		released
+	};
+	assert_eq!(jittered(7), jittered(7));
+	assert_ne!(jittered(7), jittered(8));
+	assert_eq!(jittered(7).len(), 20);
+}
+
+#[test]
+fn simulated_delivery_should_delay_each_link_by_its_latency() {
+	let now = tokio::time::Instant::now();
+	let (fast, slow) = (PeerId::random(), PeerId::random());
+	let mut delivery = SimulatedDelivery::new(Duration::from_millis(20), 7)
+		.with_link_delay(slow, Duration::from_millis(500));
+	delivery.hold("slow", Some(&slow), now);
+	delivery.hold("fast", Some(&fast), now);
+	delivery.hold("unknown", None, now);
+
+	// The peers without a link of their own, and unknown senders, get the default delay.
+	assert_eq!(delivery.release(now + Duration::from_millis(20)), Some("fast"));
+	assert_eq!(delivery.release(now + Duration::from_millis(20)), Some("unknown"));
+	assert_eq!(delivery.release(now + Duration::from_millis(499)), None);
+	assert_eq!(delivery.release(now + Duration::from_millis(500)), Some("slow"));
+}
+
+#[test]
+fn reordering_delivery_should_shuffle_the_messages_by_seed() {
+	let now = tokio::time::Instant::now();
+	let window = Duration::from_millis(100);
+	let released = |seed| {
+		let mut delivery = SimulatedDelivery::reordering(window, seed);
+		(0..20).for_each(|message| delivery.hold(message, None, now));
+		let mut released = Vec::new();
+		while let Some(message) = delivery.release(now + window) {
+			released.push(message);
+		}
+		released
+	};
+
+	// Every message is released within the window, out of the order of arrival.
+	let order = released(3);
+	let mut sorted = order.clone();
+	sorted.sort();
+	assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+	assert_ne!(order, sorted);
+	assert_eq!(order, released(3));
+}
@@ -0,0 +1,118 @@
+use super::*;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn mock_pool(ready: usize) -> (Arc<AtomicUsize>, PoolStatus) {
+	let transactions = Arc::new(AtomicUsize::new(ready));
+	let status = {
+		let transactions = transactions.clone();
+		PoolStatus::new(move || transactions.load(Ordering::Relaxed))
+	};
+	(transactions, status)
+}
+
+#[test]
+fn empty_proposals_with_transactions_ready_should_warn() {
+	let (_, pool) = mock_pool(3);
+	let mut monitor = EmptyProposalMonitor::new(Some(pool), 4);
+
+	// A proposal with a block resets the count.
+	let warnings = [true, true, false, true, true, true, true, true]
+		.into_iter()
+		.map(|empty| monitor.note_proposal(empty))
+		.collect::<Vec<_>>();
+	assert_eq!(warnings, [false, false, false, false, false, false, true, false]);
+	assert_eq!(monitor.empty_proposals(), 7);
+}
+
+#[test]
+fn empty_proposals_with_an_empty_pool_should_not_warn() {
+	let (transactions, pool) = mock_pool(0);
+	let mut monitor = EmptyProposalMonitor::new(Some(pool), 2);
+	assert!((0..10).all(|_| !monitor.note_proposal(true)));
+
+	// Transactions waiting once the pool was empty again.
+	transactions.store(1, Ordering::Relaxed);
+	assert!(!monitor.note_proposal(true));
+	transactions.store(0, Ordering::Relaxed);
+	assert!(!monitor.note_proposal(true));
+	transactions.store(1, Ordering::Relaxed);
+	assert!(!monitor.note_proposal(true));
+	assert!(monitor.note_proposal(true));
+	assert_eq!(monitor.empty_proposals(), 14);
+
+	// Without the pool nothing is known to wait.
+	let mut monitor = EmptyProposalMonitor::new(None, 1);
+	assert!(!monitor.note_proposal(true));
+}
+
+#[test]
+fn dropped_pool_should_be_taken_as_empty() {
+	let pool = Arc::new(AtomicUsize::new(3));
+	let status = PoolStatus::from_weak(Arc::downgrade(&pool), |pool| pool.load(Ordering::Relaxed));
+	let mut monitor = EmptyProposalMonitor::new(Some(status), 2);
+	assert!(!monitor.note_proposal(true));
+	assert!(!monitor.is_pool_unavailable());
+
+	// The proposals go on without the pool, and without a warning.
+	drop(pool);
+	assert!((0..5).all(|_| !monitor.note_proposal(true)));
+	assert!(monitor.is_pool_unavailable());
+	assert_eq!(monitor.empty_proposals(), 6);
+}
+
+#[test]
+fn pool_available_again_should_be_watched_again() {
+	let ready = Arc::new(parking_lot::Mutex::new(Some(3)));
+	let status = {
+		let ready = ready.clone();
+		PoolStatus::fallible(move || *ready.lock())
+	};
+	let mut monitor = EmptyProposalMonitor::new(Some(status), 2);
+	assert!(!monitor.note_proposal(true));
+
+	*ready.lock() = None;
+	assert!(!monitor.note_proposal(true));
+	assert!(monitor.is_pool_unavailable());
+
+	// The count starts over once the pool is back.
+	*ready.lock() = Some(3);
+	assert!(!monitor.note_proposal(true));
+	assert!(!monitor.is_pool_unavailable());
+	assert!(monitor.note_proposal(true));
+}
+
+#[test]
+fn proposal_interval_should_shrink_as_the_pool_fills() {
+	let pacing = ProposalPacing {
+		min_interval: Duration::from_millis(100),
+		max_interval: Duration::from_millis(1100),
+		pool_capacity: 1000,
+	};
+	let (transactions, pool) = mock_pool(0);
+	let interval = |pool: &PoolStatus| pacing.interval(pool.ready());
+
+	// The emptier the pool, the longer the leader waits to batch transactions.
+	let mut intervals = Vec::new();
+	for ready in [0, 250, 500, 1000, 5000] {
+		transactions.store(ready, Ordering::Relaxed);
+		intervals.push(interval(&pool).as_millis());
+	}
+	assert_eq!(intervals, [1100, 850, 600, 100, 100]);
+	// An unavailable pool is taken as empty.
+	assert_eq!(pacing.interval(None), pacing.max_interval);
+
+	// A pool filling while the leader waits makes it due once the least wait is over.
+	transactions.store(10, Ordering::Relaxed);
+	assert!(!pacing.remaining(Duration::from_millis(50), pool.ready()).is_zero());
+	transactions.store(1000, Ordering::Relaxed);
+	assert_eq!(
+		pacing.remaining(Duration::from_millis(50), pool.ready()),
+		Duration::from_millis(50)
+	);
+	assert!(pacing.remaining(Duration::from_millis(100), pool.ready()).is_zero());
+
+	// Inverted bounds leave only the least wait.
+	let pacing = ProposalPacing { max_interval: Duration::ZERO, ..pacing };
+	assert_eq!(pacing.interval(Some(0)), pacing.min_interval);
+}
@@ -0,0 +1,62 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+
+#[test]
+fn error_codes_should_be_distinct_and_stable() {
+	use HotstuffError::*;
+
+	let alice: AuthorityId = Sr25519Keyring::Alice.public().into();
+	let errors = [
+		(AuthorityReuse(alice.clone()), 1),
+		(InsufficientQuorum, 2),
+		(InsufficientTimeoutWeight, 3),
+		(InvalidSignature(alice.clone()), 4),
+		(NonCanonicalSignature(alice.clone()), 5),
+		(NullSignature, 6),
+		(UnknownAuthority(alice.clone()), 7),
+		(NotAuthority, 8),
+		(WrongProposer, 9),
+		(ProposalEquivocation(alice.clone()), 10),
+		(ProposalNoParent, 11),
+		(ExpiredVote, 12),
+		(InvalidTC, 13),
+		(AuthoritySetRegression(3), 14),
+		(InvalidAuthoritySet(String::new()), 15),
+		(UncommittedSetTransition(String::new()), 16),
+		(MissingTimeoutJustification, 17),
+		(FinalizeBlock(String::new()), 18),
+		(InvalidJustification(String::new()), 19),
+		(UncommittableBlock(String::new()), 20),
+		(ReorgTooDeep(String::new()), 21),
+		(AuditLog(String::new()), 22),
+		(SaveProposal(String::new()), 23),
+		(ClientError(String::new()), 24),
+		(Other(String::new()), 25),
+		(TcHighQcMismatch, 26),
+		(SigningKeyOverlap(4), 27),
+		(InvalidCheckpoint(String::new()), 28),
+		(OversizedMetadata(0), 29),
+		(DoesNotExtendCommitted(String::new()), 30),
+		(InvalidSelfVote, 31),
+		(ProposalOutsideClockWindow(0), 32),
+		(InvalidBlockContents(String::new()), 33),
+		(MissingSigningKey(alice.clone()), 34),
+		(SelfNotInAuthoritySet(alice.clone()), 35),
+		(BrokenQcChain(String::new()), 36),
+		(DuplicateAuthorityInSet(alice.clone()), 37),
+		(NonContiguousBlocks(String::new()), 38),
+		(KeystoreSelfTestFailed(alice), 39),
+		(SetIdOutsideTransition(2), 40),
+		(NilCertificate, 41),
+		(Journal(String::new()), 42),
+	];
+
+	for (error, code) in errors.iter() {
+		assert_eq!(error.error_code(), *code, "code of {:?} changed", error);
+	}
+	let mut codes = errors.iter().map(|(error, _)| error.error_code()).collect::<Vec<_>>();
+	codes.sort();
+	codes.dedup();
+	assert_eq!(codes.len(), errors.len());
+}
@@ -0,0 +1,88 @@
+use super::*;
+
+#[test]
+fn invalid_messages_should_ban_their_sender() {
+	let mut scores = PeerScores::new(Default::default());
+	let (spammer, relay) = (PeerId::random(), PeerId::random());
+	let now = Instant::now();
+
+	// The spammer sends forged messages, the relay sends valid ones.
+	let mut bans = Vec::new();
+	for i in 0..8u8 {
+		scores.record_sender([i; 32], spammer);
+		scores.record_sender([i + 100; 32], relay);
+		bans.push(scores.report_sender(&[i; 32], Misbehavior::InvalidSignature, now));
+	}
+
+	assert!(bans[..5].iter().all(Option::is_none));
+	assert_eq!(bans[5], Some((spammer, BAN)));
+	assert!(scores.score(&spammer, now) < ReputationConfig::default().ban_threshold);
+	assert_eq!(scores.score(&relay, now), 0.0);
+
+	// A message whose sender is forgotten blames nobody.
+	assert_eq!(scores.report_sender(&[200; 32], Misbehavior::InvalidSignature, now), None);
+}
+
+#[test]
+fn score_should_decay_so_one_error_never_bans() {
+	let mut scores = PeerScores::new(Default::default());
+	let peer = PeerId::random();
+	let now = Instant::now();
+	let half_life = ReputationConfig::default().half_life;
+
+	assert_eq!(scores.report(peer, Misbehavior::MalformedMessage, now), None);
+	assert_eq!(scores.score(&peer, now), -50.0);
+	assert_eq!(scores.score(&peer, now + half_life), -25.0);
+
+	// A malformed message every half life never adds up to more than twice its cost.
+	for i in 1..100 {
+		let at = now + half_life * i;
+		assert_eq!(scores.report(peer, Misbehavior::MalformedMessage, at), None);
+	}
+	assert!(scores.score(&peer, now + half_life * 99) >= -100.0);
+}
+
+#[test]
+fn senders_should_be_forgotten_beyond_capacity() {
+	let mut scores = PeerScores::new(Default::default());
+	let peer = PeerId::random();
+
+	for i in 0..=SENDER_CAPACITY as u32 {
+		let mut key = [0u8; 32];
+		key[..4].copy_from_slice(&i.to_le_bytes());
+		scores.record_sender(key, peer);
+	}
+
+	assert_eq!(scores.senders.len(), SENDER_CAPACITY);
+	assert!(!scores.senders.contains_key(&[0u8; 32]));
+}
+
+#[test]
+fn sync_requests_should_be_answered_once_within_the_limit() {
+	let mut limiter = SyncRequestLimiter::<u64>::default();
+	let (spammer, peer) = (PeerId::random(), PeerId::random());
+	let now = Instant::now();
+
+	// Each peer is answered once for a proposal, a repeated request isn't answered again.
+	assert!(limiter.admit(spammer, 1, now));
+	assert!(limiter.admit(peer, 1, now));
+	assert!(!limiter.admit(spammer, 1, now));
+	assert_eq!(limiter.take_requesters(&1), vec![spammer, peer]);
+	assert_eq!(limiter.take_requesters(&1), vec![]);
+	assert!(!limiter.admit(spammer, 1, now + SYNC_REQUEST_WINDOW / 2));
+
+	// A peer asking for many proposals is cut off at the limit, others aren't.
+	let admitted = (2..100).filter(|digest| limiter.admit(spammer, *digest, now)).count();
+	assert_eq!(admitted, SYNC_REQUEST_LIMIT - 1);
+	assert!(limiter.admit(peer, 2, now));
+
+	// Past the window the peer is answered again, and the requests never answered are dropped.
+	let later = now + SYNC_REQUEST_WINDOW;
+	assert!(limiter.admit(spammer, 1, later));
+	assert_eq!(limiter.take_requesters(&2), vec![]);
+	assert_eq!(limiter.take_requesters(&1), vec![spammer]);
+
+	limiter.admit(peer, 3, later);
+	limiter.forget_peer(&peer);
+	assert_eq!(limiter.take_requesters(&3), vec![]);
+}
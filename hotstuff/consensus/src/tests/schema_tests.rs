@@ -0,0 +1,75 @@
+use super::*;
+
+use sp_runtime::testing::{Block as RawBlock, TestXt};
+
+type TestBlock = RawBlock<TestXt<(), ()>>;
+
+fn field_names(schema: &Value, message: &str) -> Vec<String> {
+	schema["messages"][message]["fields"]
+		.as_array()
+		.unwrap_or_else(|| panic!("{} has fields", message))
+		.iter()
+		.map(|field| field["name"].as_str().expect("fields are named").to_string())
+		.collect()
+}
+
+#[test]
+fn schema_should_list_the_fields_of_each_message_in_order() {
+	let schema = message_schema::<TestBlock>();
+	assert_eq!(schema["encoding"], "SCALE");
+
+	let expected: [(&str, &[&str]); 6] = [
+		(
+			"Proposal",
+			&[
+				"qc",
+				"tc",
+				"payload",
+				"view",
+				"author",
+				"signature",
+				"metadata",
+				"timestamp",
+				"self_vote",
+				"qc_timestamps",
+			],
+		),
+		("Vote", &["proposal_hash", "view", "voter", "signature", "timestamp", "high_qc"]),
+		("QC", &["proposal_hash", "view", "votes"]),
+		("TC", &["view", "votes", "high_qc"]),
+		("Timeout", &["high_qc", "view", "voter", "signature"]),
+		("GossipMessage", &["set_id", "message"]),
+	];
+	for (message, fields) in expected {
+		assert_eq!(field_names(&schema, message), fields, "fields of {}", message);
+	}
+
+	// Every field refers to a described type, and the views are plain integers.
+	let types = schema["types"].as_array().expect("types are listed");
+	let resolve = |id: &Value| {
+		types
+			.iter()
+			.find(|ty| ty["id"] == *id)
+			.unwrap_or_else(|| panic!("type {} is described", id))
+	};
+	for (message, _) in expected {
+		for field in schema["messages"][message]["fields"].as_array().unwrap() {
+			resolve(&field["type"]);
+		}
+	}
+	let view = &schema["messages"]["Vote"]["fields"][1];
+	assert_eq!(resolve(&view["type"])["def"], json!({ "primitive": "u64" }));
+
+	// The wire envelope lists each kind of consensus message.
+	let message = &schema["messages"]["GossipMessage"]["fields"][1];
+	let variants = resolve(&message["type"])["def"]["variant"]
+		.as_array()
+		.expect("consensus messages are variants")
+		.iter()
+		.map(|variant| variant["name"].as_str().unwrap())
+		.collect::<Vec<_>>();
+	assert_eq!(
+		variants,
+		["Propose", "Vote", "Timeout", "TC", "SyncRequest", "Phantom", "Handshake"]
+	);
+}
@@ -0,0 +1,36 @@
+use super::*;
+
+use SignatureScheme::*;
+
+#[test]
+fn peer_should_be_accepted_until_it_advertises_no_common_scheme() {
+	let mut negotiation = SchemeNegotiation::new(Default::default());
+	let peer = PeerId::random();
+
+	// An older node sends no handshake.
+	assert!(negotiation.accepts(&peer));
+
+	negotiation.note_peer(peer, vec![Ed25519, Ecdsa]);
+	assert!(!negotiation.accepts(&peer));
+	assert!(!negotiation.accepts(&peer));
+	assert_eq!(negotiation.dropped(), 2);
+
+	negotiation.note_peer(peer, vec![Ed25519, Sr25519]);
+	assert!(negotiation.accepts(&peer));
+
+	negotiation.note_peer(peer, vec![Ecdsa]);
+	negotiation.forget_peer(&peer);
+	assert!(negotiation.accepts(&peer));
+	assert_eq!(negotiation.dropped(), 2);
+}
+
+#[test]
+fn active_scheme_should_be_that_of_the_authority_keys() {
+	assert_eq!(SignatureScheme::active(), Sr25519);
+	assert_eq!(SignatureScheme::of(AuthorityId::CRYPTO_ID), Some(Sr25519));
+	assert_eq!(SignatureScheme::of(ed25519::CRYPTO_ID), Some(Ed25519));
+	assert_eq!(SignatureScheme::of(CryptoTypeId(*b"bls3")), None);
+
+	// A node verifies the signatures of its own authority set unless configured otherwise.
+	assert_eq!(SchemeConfig::default().supported, vec![SignatureScheme::active()]);
+}
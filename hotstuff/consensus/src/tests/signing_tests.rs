@@ -0,0 +1,38 @@
+use super::*;
+
+use sp_keyring::Sr25519Keyring;
+use sp_keystore::{testing::MemoryKeystore, Keystore};
+
+fn keystore_of(key: Sr25519Keyring) -> KeystorePtr {
+	let keystore: KeystorePtr = MemoryKeystore::new().into();
+	keystore
+		.sr25519_generate_new(AuthorityId::ID, Some(&key.to_seed()))
+		.expect("key is generated");
+	keystore
+}
+
+#[test]
+fn keystore_holding_the_key_should_pass_the_selftest() {
+	let alice: AuthorityId = Sr25519Keyring::Alice.public().into();
+	let keystore = keystore_of(Sr25519Keyring::Alice);
+	assert_eq!(keystore_selftest(&keystore, &alice), Ok(()));
+
+	// On demand, the authority the node signs for by default.
+	let status = SigningStatus::shared();
+	status.write().set_authority(Some(alice.clone()));
+	let selftest = KeystoreSelfTest::new(keystore, status);
+	assert_eq!(selftest.run(None), Ok(()));
+	assert_eq!(selftest.run(Some(alice)), Ok(()));
+}
+
+#[test]
+fn keystore_missing_the_key_should_fail_the_selftest() {
+	let bob: AuthorityId = Sr25519Keyring::Bob.public().into();
+	let keystore = keystore_of(Sr25519Keyring::Alice);
+	assert_eq!(keystore_selftest(&keystore, &bob), Err(MissingSigningKey(bob.clone())));
+
+	// A node signing for no authority has nothing to test.
+	let selftest = KeystoreSelfTest::new(keystore, SigningStatus::shared());
+	assert_eq!(selftest.run(None), Err(NotAuthority));
+	assert_eq!(selftest.run(Some(bob.clone())), Err(MissingSigningKey(bob)));
+}
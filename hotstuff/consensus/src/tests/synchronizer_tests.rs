@@ -0,0 +1,149 @@
+use super::*;
+
+#[tokio::test]
+async fn adaptive_timeout_should_converge_to_round_latency() {
+	let mut timer = Timer::adaptive(3000);
+	// Starts at the configured duration.
+	assert_eq!(timer.timeout(), Duration::from_millis(3000));
+
+	for _ in 0..100 {
+		timer.record_round_latency(Duration::from_millis(200));
+	}
+	let converged = timer.timeout();
+	assert!(converged >= Duration::from_millis(790) && converged <= Duration::from_millis(810));
+
+	// A single outlier moves the timeout by a bounded amount.
+	timer.record_round_latency(Duration::from_secs(600));
+	assert!(timer.timeout() < converged * 2);
+
+	// Sustained latencies never push the timeout out of range.
+	for _ in 0..100 {
+		timer.record_round_latency(Duration::from_secs(600));
+	}
+	assert_eq!(timer.timeout(), Duration::from_millis(12000));
+
+	for _ in 0..100 {
+		timer.record_round_latency(Duration::ZERO);
+	}
+	assert_eq!(timer.timeout(), Duration::from_millis(750));
+}
+
+#[tokio::test]
+async fn fixed_timeout_should_ignore_round_latency() {
+	let mut timer = Timer::new(3000);
+
+	timer.record_round_latency(Duration::from_millis(200));
+	timer.reset();
+
+	assert_eq!(timer.timeout(), Duration::from_millis(3000));
+}
+
+#[tokio::test]
+async fn reset_should_restart_the_deadline() {
+	let mut timer = Timer::new(3000);
+	timer.reset();
+
+	let remaining = timer.remaining();
+	assert!(remaining <= Duration::from_millis(3000) && remaining > Duration::from_millis(2000));
+	assert!(timer.deadline() > Instant::now());
+}
+
+#[tokio::test]
+async fn skew_tolerance_should_extend_the_timeout_within_bounds() {
+	let timer = Timer::new(3000).with_skew_tolerance(500);
+	assert_eq!(timer.timeout(), Duration::from_millis(3500));
+
+	// The tolerance never exceeds the configured duration.
+	let timer = Timer::new(3000).with_skew_tolerance(60_000);
+	assert_eq!(timer.timeout(), Duration::from_millis(6000));
+
+	let timer = Timer::adaptive(3000).with_skew_tolerance(500);
+	assert_eq!(timer.timeout(), Duration::from_millis(3500));
+}
+
+#[tokio::test]
+async fn drastically_different_timeouts_should_hint_at_skew() {
+	let timer = Timer::new(3000);
+
+	assert!(!timer.skew_suspected(Duration::from_millis(3000)));
+	assert!(!timer.skew_suspected(Duration::from_millis(1500)));
+	assert!(!timer.skew_suspected(Duration::from_millis(6000)));
+	// Peers' clocks run much faster or slower than the local one.
+	assert!(timer.skew_suspected(Duration::from_millis(900)));
+	assert!(timer.skew_suspected(Duration::from_millis(9500)));
+}
+
+#[test]
+fn committed_qcs_should_survive_a_restart() {
+	use substrate_test_runtime_client::{runtime::Block, Backend, TestClient};
+
+	let client = Arc::new(substrate_test_runtime_client::new());
+	let qc = |view| QC::<Block> { view, ..Default::default() };
+
+	let mut synchronizer =
+		Synchronizer::<Block, Backend, TestClient>::new(client.clone()).with_qc_retention(2);
+	for view in 1..=3 {
+		synchronizer.save_committed_qc(&qc(view)).unwrap();
+	}
+	// An older QC than the last one stored is not kept.
+	synchronizer.save_committed_qc(&qc(2)).unwrap();
+	drop(synchronizer);
+
+	let synchronizer = Synchronizer::<Block, Backend, TestClient>::new(client).with_qc_retention(2);
+	// The oldest is pruned beyond the retention.
+	assert_eq!(synchronizer.qc_for_view(1).unwrap(), None);
+	assert_eq!(synchronizer.qc_for_view(2).unwrap(), Some(qc(2)));
+	assert_eq!(synchronizer.qc_for_view(3).unwrap(), Some(qc(3)));
+	assert_eq!(synchronizer.qc_for_view(4).unwrap(), None);
+}
+
+#[tokio::test]
+async fn sync_requests_should_stay_under_the_cap() {
+	let limit = SyncRequestLimit {
+		max_in_flight: 4,
+		timeout: Duration::from_millis(500),
+		initial_backoff: Duration::from_millis(100),
+		max_backoff: Duration::from_millis(400),
+		max_attempts: 3,
+	};
+	let mut requests = SyncRequests::<u64>::new(limit);
+	let start = Instant::now();
+
+	// Proposals certifying fifty missing ancestors arrive together.
+	for digest in 0..50 {
+		requests.request(digest, start);
+	}
+	requests.request(1, start);
+	assert_eq!(requests.poll(start), [0, 1, 2, 3]);
+	assert_eq!((requests.in_flight(), requests.queued()), (4, 46));
+
+	// Each received ancestor frees a slot for the next fetch, while the peers never answer the
+	// fetch of ancestor 0.
+	let mut sent = vec![1, 2, 3];
+	let mut received = 0;
+	while let Some(digest) = sent.pop() {
+		assert!(requests.received(&digest));
+		received += 1;
+		sent.extend(requests.poll(start));
+		assert!(requests.in_flight() <= 4);
+	}
+	assert_eq!(received, 49);
+	assert!(!requests.received(&7));
+	assert_eq!((requests.in_flight(), requests.queued()), (1, 0));
+
+	// A failed fetch frees its slot at once, and is sent again after a backoff.
+	let at = |millis| start + Duration::from_millis(millis);
+	assert_eq!(requests.next_deadline(), Some(at(500)));
+	assert!(requests.poll(at(500)).is_empty());
+	assert_eq!((requests.in_flight(), requests.queued()), (0, 1));
+	assert_eq!(requests.next_deadline(), Some(at(600)));
+	assert_eq!(requests.poll(at(600)), [0]);
+
+	// The backoff doubles, and the fetch is given up after its last attempt.
+	assert!(requests.poll(at(1100)).is_empty());
+	assert!(requests.poll(at(1299)).is_empty());
+	assert_eq!(requests.poll(at(1300)), [0]);
+	assert!(requests.poll(at(1800)).is_empty());
+	assert_eq!((requests.in_flight(), requests.queued()), (0, 0));
+	assert_eq!(requests.next_deadline(), None);
+}
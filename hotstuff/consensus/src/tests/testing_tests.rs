@@ -0,0 +1,37 @@
+use super::*;
+
+use sp_core::crypto::ByteArray;
+use sp_keyring::Sr25519Keyring;
+
+#[test]
+fn deterministic_keystore_should_give_the_same_authorities() {
+	let seeds = ["//User0", "//User1", "//User2"];
+	let (keystore, authorities) = deterministic_keystore(&seeds);
+	let (_, again) = deterministic_keystore(&seeds);
+	assert_eq!(authorities, again);
+	assert_eq!(authorities.len(), 3);
+
+	// The public key of each seed is known across runs.
+	let (_, alice) = deterministic_keystore(&["//Alice"]);
+	assert_eq!(alice, vec![AuthorityId::from(Sr25519Keyring::Alice.public())]);
+	assert_eq!(authorities[0].to_string(), "5D7Ln8a9JrbgrEoSBHTHxhZAbrPwM4jYHTe2zMj9jKRhemau");
+
+	for authority in authorities.iter() {
+		assert!(keystore.has_keys(&[(authority.to_raw_vec(), HOTSTUFF_KEY_TYPE)]));
+	}
+}
+
+#[test]
+fn latency_summary_should_take_the_nearest_rank() {
+	let samples = (1..=200).rev().map(Duration::from_millis).collect::<Vec<_>>();
+	let summary = LatencySummary::new(samples);
+	assert_eq!(summary.p50, Duration::from_millis(100));
+	assert_eq!(summary.p95, Duration::from_millis(190));
+	assert_eq!(summary.p99, Duration::from_millis(198));
+
+	// Each percentile of a single sample is that sample.
+	let seven = Duration::from_millis(7);
+	let single = LatencySummary::new(vec![seven]);
+	assert_eq!(single, LatencySummary { p50: seven, p95: seven, p99: seven });
+	assert_eq!(single.to_string(), "p50 7ms, p95 7ms, p99 7ms");
+}
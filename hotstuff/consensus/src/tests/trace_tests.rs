@@ -0,0 +1,39 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+type Block = sp_runtime::testing::Block<TestXt<(), ()>>;
+
+#[test]
+fn committed_view_should_not_be_skipped() {
+	let mut traces = ViewTraces::<Block>::default();
+	traces.note_proposal(3, H256::repeat_byte(3), Sr25519Keyring::Alice.public().into());
+	traces.note_vote(3, VoteDecision::Cast);
+	traces.note_tc(3);
+	assert_eq!(traces.get(3).unwrap().outcome, Some(ViewOutcome::Skipped));
+
+	// A timed out view whose proposal still commits.
+	traces.note_commit(3);
+	traces.note_tc(3);
+	let trace = traces.get(3).unwrap();
+	assert!(trace.tc_formed);
+	assert_eq!(trace.outcome, Some(ViewOutcome::Committed));
+}
+
+#[test]
+fn traces_should_keep_the_last_views() {
+	let mut traces = ViewTraces::<Block>::new(2);
+	for view in 1..=3 {
+		traces.note_qc(view);
+	}
+	assert!(traces.get(1).is_none());
+	assert!(traces.get(2).unwrap().qc_formed && traces.get(3).unwrap().qc_formed);
+
+	// Too old to be traced any more.
+	traces.note_vote(1, VoteDecision::Withheld(VoteWithheld::StaleView));
+	assert!(traces.get(1).is_none());
+	traces.note_qc(4);
+	assert!(traces.get(2).is_none());
+}
@@ -0,0 +1,72 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+use hotstuff_primitives::AuthorityId;
+
+use crate::{message::Payload, primitives::ViewNumber};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+fn make_proposal(parent: Option<&Proposal<TestBlock>>, view: ViewNumber) -> Proposal<TestBlock> {
+	let qc = parent.map_or_else(QC::<TestBlock>::default, |parent| QC::<TestBlock> {
+		proposal_hash: parent.digest(),
+		view: parent.view,
+		votes: Vec::new(),
+	});
+	let author: AuthorityId = Sr25519Keyring::Alice.public().into();
+	let payload =
+		Payload::<TestBlock> { block_hash: H256::from_low_u64_be(view), block_number: view };
+
+	Proposal::<TestBlock>::new(qc, None, payload, view, author, None)
+}
+
+// The proposals of `views`, each extending the one before, from the last one.
+fn make_chain(views: &[ViewNumber]) -> Vec<Proposal<TestBlock>> {
+	let mut chain: Vec<Proposal<TestBlock>> = Vec::new();
+	for view in views {
+		let proposal = make_proposal(chain.last(), *view);
+		chain.push(proposal);
+	}
+	chain.reverse();
+	chain
+}
+
+#[test]
+fn both_variants_should_commit_on_the_happy_path() {
+	// The proposal of view 4 certifies the one of view 3, which certifies the one of view 2.
+	let chain = make_chain(&[1, 2, 3, 4]);
+
+	let (committed, child, qc) = ConsensusVariant::Chained.committed(&chain).expect("commits");
+	assert_eq!((committed.view, child.view), (2, 3));
+	assert_eq!(qc.proposal_hash, child.digest());
+
+	// A view later than the chained form.
+	let (committed, child, qc) = ConsensusVariant::ThreePhase.committed(&chain).expect("commits");
+	assert_eq!((committed.view, child.view), (1, 2));
+	assert_eq!(qc.proposal_hash, child.digest());
+	assert_eq!(child.qc.proposal_hash, committed.digest());
+
+	// The three-phase form needs one more certified proposal than the chained one.
+	assert!(ConsensusVariant::Chained.committed(&chain[1..]).is_some());
+	assert!(ConsensusVariant::ThreePhase.committed(&chain[1..]).is_none());
+}
+
+#[test]
+fn views_skipped_in_the_chain_should_not_commit() {
+	// The view 3 timed out.
+	let chain = make_chain(&[1, 2, 4, 5]);
+
+	assert_eq!(ConsensusVariant::Chained.committed(&chain).map(|(p, _, _)| p.view), None);
+	assert!(ConsensusVariant::ThreePhase.committed(&chain).is_none());
+
+	let chain = make_chain(&[1, 2, 4, 5, 6]);
+	assert_eq!(ConsensusVariant::Chained.committed(&chain).map(|(p, _, _)| p.view), Some(4));
+	assert!(ConsensusVariant::ThreePhase.committed(&chain).is_none());
+
+	let chain = make_chain(&[1, 2, 4, 5, 6, 7]);
+	assert_eq!(ConsensusVariant::ThreePhase.committed(&chain).map(|(p, _, _)| p.view), Some(4));
+}
@@ -0,0 +1,256 @@
+use super::*;
+
+use sp_core::H256;
+use sp_keyring::Sr25519Keyring;
+use sp_runtime::testing::TestXt;
+
+use crate::{
+	message::{Payload, QC},
+	primitives::{HotstuffError::*, ViewNumber},
+	synchronizer::Timer,
+};
+
+type TestExtrinsic = TestXt<(), ()>;
+type TestBlock = sp_runtime::testing::Block<TestExtrinsic>;
+
+const KEYS: [Sr25519Keyring; 4] =
+	[Sr25519Keyring::Alice, Sr25519Keyring::Bob, Sr25519Keyring::Charlie, Sr25519Keyring::Dave];
+
+fn authorities() -> AuthorityList {
+	KEYS.iter().map(|key| (key.public().into(), 1)).collect()
+}
+
+fn make_vote(key: Sr25519Keyring, view: ViewNumber) -> Vote<TestBlock> {
+	let mut vote = Vote::<TestBlock>::new(H256::from_low_u64_be(view), view, key.public().into());
+	vote.signature = Some(key.sign(vote.digest().as_ref()).into());
+	vote
+}
+
+// A QC signed by a quorum of `KEYS`.
+fn make_qc(view: ViewNumber) -> QC<TestBlock> {
+	let mut qc =
+		QC::<TestBlock> { proposal_hash: H256::from_low_u64_be(view), view, votes: Vec::new() };
+	for key in KEYS[..3].iter() {
+		qc.add_votes(key.public().into(), key.sign(qc.digest().as_ref()).into());
+	}
+	qc
+}
+
+fn make_proposal(qc: QC<TestBlock>, view: ViewNumber) -> Proposal<TestBlock> {
+	let author = KEYS[view as usize % KEYS.len()];
+	let payload =
+		Payload::<TestBlock> { block_hash: H256::from_low_u64_be(view), block_number: view };
+	let mut proposal =
+		Proposal::<TestBlock>::new(qc, None, payload, view, author.public().into(), None);
+	proposal.signature = Some(author.sign(proposal.digest().as_ref()).into());
+	proposal
+}
+
+#[tokio::test]
+async fn verification_results_should_match_their_messages() {
+	let mut pool = VerificationPool::<TestBlock>::new(
+		4,
+		64,
+		VerifierSet::new(authorities()),
+		None,
+		Default::default(),
+	);
+
+	for view in 1..=16 {
+		// Every third vote comes from an authority outside the set.
+		let key = if view % 3 == 0 { Sr25519Keyring::Eve } else { Sr25519Keyring::Alice };
+		let vote = make_vote(key, view);
+
+		// Every third proposal carries the signature of a vote.
+		let mut proposal = make_proposal(QC::default(), view);
+		if view % 3 == 0 {
+			proposal.signature = vote.signature.clone();
+		}
+
+		pool.submit(Verifiable::Proposal(proposal));
+		pool.submit(Verifiable::Vote(vote));
+	}
+
+	let mut verified = 0;
+	while pool.pending() > 0 {
+		let (message, result) = pool.next().await.expect("verification pool is alive");
+		match message {
+			Verifiable::Proposal(proposal) if proposal.view % 3 == 0 => {
+				assert_eq!(result, Err(InvalidSignature(proposal.author)))
+			},
+			Verifiable::Vote(vote) if vote.view % 3 == 0 => {
+				assert_eq!(result, Err(UnknownAuthority(vote.voter)))
+			},
+			_ => assert_eq!(result, Ok(())),
+		}
+		verified += 1;
+	}
+
+	assert_eq!(verified, 32);
+}
+
+async fn verify(
+	pool: &mut VerificationPool<TestBlock>,
+	vote: Vote<TestBlock>,
+) -> Result<(), HotstuffError> {
+	pool.submit(Verifiable::Vote(vote));
+	pool.next().await.expect("verification pool is alive").1
+}
+
+#[tokio::test]
+async fn messages_should_be_verified_against_the_activated_set() {
+	let mut pool = VerificationPool::<TestBlock>::new(
+		2,
+		16,
+		VerifierSet::new(authorities()),
+		None,
+		Default::default(),
+	);
+
+	let eve = make_vote(Sr25519Keyring::Eve, 6);
+	assert_eq!(verify(&mut pool, eve.clone()).await, Err(UnknownAuthority(eve.voter.clone())));
+
+	// Eve takes the place of Dave from view 5.
+	let mut joined = authorities();
+	joined[3] = (Sr25519Keyring::Eve.public().into(), 1);
+	pool.set_authorities(VerifierSet {
+		set_id: 1,
+		authorities: joined,
+		previous: Some((5, authorities())),
+	});
+	assert_eq!(pool.set_id(), 1);
+
+	assert_eq!(verify(&mut pool, eve).await, Ok(()));
+	let dave = make_vote(Sr25519Keyring::Dave, 6);
+	assert_eq!(verify(&mut pool, dave.clone()).await, Err(UnknownAuthority(dave.voter)));
+	// Before the change, the votes of Dave still count.
+	assert_eq!(verify(&mut pool, make_vote(Sr25519Keyring::Dave, 4)).await, Ok(()));
+}
+
+#[tokio::test]
+async fn timer_should_fire_while_signatures_are_queued() {
+	// Each proposal costs four signature checks.
+	let proposal = make_proposal(make_qc(1), 2);
+	let total = 200;
+
+	let mut pool = VerificationPool::<TestBlock>::new(
+		1,
+		256,
+		VerifierSet::new(authorities()),
+		None,
+		Default::default(),
+	);
+	for _ in 0..total {
+		pool.submit(Verifiable::Proposal(proposal.clone()));
+	}
+
+	let mut timer = Timer::new(10);
+	// The first tick of a new timer completes immediately.
+	timer.reset();
+
+	let mut verified = 0;
+	loop {
+		tokio::select! {
+			_ = &mut timer => break,
+			Some(_) = pool.next() => verified += 1,
+		}
+	}
+
+	// The timer was handled while most of the proposals were still queued.
+	assert!(verified < total);
+	assert_eq!(pool.pending(), total - verified);
+}
+
+#[tokio::test]
+async fn saturated_pool_should_bound_its_queue_without_losing_messages() {
+	let (depth, total) = (8, 64);
+	let mut pool = VerificationPool::<TestBlock>::new(
+		2,
+		depth,
+		VerifierSet::new(authorities()),
+		None,
+		Default::default(),
+	);
+
+	// Votes of an authority outside the set are rejected without checking a signature.
+	let mut votes = (1..=total).map(|view| make_vote(Sr25519Keyring::Eve, view));
+	let mut verified = std::collections::BTreeSet::new();
+	loop {
+		if !pool.is_full() {
+			if let Some(vote) = votes.next() {
+				pool.submit(Verifiable::Vote(vote));
+				assert!(pool.pending() <= depth);
+				continue;
+			}
+		}
+		if pool.pending() == 0 {
+			break;
+		}
+
+		let (message, result) = pool.next().await.expect("verification pool is alive");
+		let Verifiable::Vote(vote) = message else { panic!("only votes are submitted") };
+		assert_eq!(result, Err(UnknownAuthority(vote.voter)));
+		assert!(verified.insert(vote.view));
+	}
+
+	assert_eq!(verified, (1..=total).collect());
+}
+
+#[tokio::test]
+async fn overloaded_pool_should_shed_old_votes_and_keep_current_proposals() {
+	let mut pool = VerificationPool::<TestBlock>::new(
+		1,
+		4,
+		VerifierSet::new(authorities()),
+		None,
+		Default::default(),
+	);
+	let mut overload = OverloadMonitor::new(Duration::from_millis(100));
+	let current: ViewNumber = 8;
+
+	// Saturate the pool with votes of past views.
+	for view in 1..=4 {
+		pool.submit(Verifiable::Vote(make_vote(Sr25519Keyring::Alice, view)));
+	}
+	assert!(pool.is_full());
+
+	let start = Instant::now();
+	assert_eq!(overload.observe(pool.pending(), pool.capacity(), start), None);
+	let old_vote = ConsensusMessage::Vote(make_vote(Sr25519Keyring::Bob, 2));
+	assert!(!overload.should_shed(&old_vote, current, pool.is_full()));
+
+	// Backpressure lasting past the grace period overloads the node.
+	let later = start + Duration::from_millis(100);
+	assert_eq!(
+		overload.observe(pool.pending(), pool.capacity(), later),
+		Some(OverloadTransition::Overloaded)
+	);
+	assert_eq!(overload.observe(pool.pending(), pool.capacity(), later), None);
+
+	assert!(overload.should_shed(&old_vote, current, pool.is_full()));
+	let current_vote = ConsensusMessage::Vote(make_vote(Sr25519Keyring::Bob, current));
+	assert!(overload.should_shed(&current_vote, current, true));
+	assert!(!overload.should_shed(&current_vote, current, false));
+	let old_proposal = ConsensusMessage::Propose(make_proposal(make_qc(2), 3));
+	assert!(overload.should_shed(&old_proposal, current, pool.is_full()));
+	assert_eq!(overload.shed(), 3);
+
+	// The proposal of the current view is kept, and verified without waiting behind the queue.
+	let proposal = make_proposal(make_qc(current - 1), current);
+	let message = ConsensusMessage::Propose(proposal.clone());
+	assert!(!overload.should_shed(&message, current, pool.is_full()));
+	assert_eq!(proposal.verify_with(&authorities(), None, &Default::default()), Ok(()));
+	assert!(pool.pending() > 0);
+
+	// Draining the queue to half its capacity recovers.
+	let (_, result) = pool.next().await.expect("verification pool is alive");
+	assert_eq!(result, Ok(()));
+	assert_eq!(overload.observe(pool.pending(), pool.capacity(), later), None);
+	let (_, result) = pool.next().await.expect("verification pool is alive");
+	assert_eq!(result, Ok(()));
+	assert_eq!(
+		overload.observe(pool.pending(), pool.capacity(), later),
+		Some(OverloadTransition::Recovered)
+	);
+	assert!(!overload.should_shed(&old_vote, current, pool.is_full()));
+}
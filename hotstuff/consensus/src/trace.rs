@@ -0,0 +1,137 @@
+// What the voter decided at each recent view and why, a summary for auditing the node above the
+// raw events.
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use sp_runtime::traits::Block as BlockT;
+
+use hotstuff_primitives::AuthorityId;
+
+use crate::primitives::ViewNumber;
+
+#[cfg(test)]
+#[path = "tests/trace_tests.rs"]
+pub mod trace_tests;
+
+// Views traced by default.
+pub const VIEW_TRACE_RETENTION: usize = 128;
+
+/// Why the node didn't vote for the proposal of a view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VoteWithheld {
+	/// The node isn't an authority.
+	NotAuthority,
+	/// The node isn't in the voting committee of the view.
+	NotInCommittee,
+	/// The node already voted at the view or a later one.
+	AlreadyVoted,
+	/// The proposal arrived after the node left its view.
+	StaleView,
+	/// The voter is paused.
+	Paused,
+	/// The leader of the view equivocated.
+	Equivocation,
+	/// The vote couldn't be signed, such as for a missing key.
+	SigningFailed,
+}
+
+/// The vote of the node at a view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "decision", content = "reason", rename_all = "camelCase")]
+pub enum VoteDecision {
+	Cast,
+	Withheld(VoteWithheld),
+}
+
+/// How a view ended. A view timed out first may still commit later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ViewOutcome {
+	Committed,
+	Skipped,
+}
+
+/// The decisions of the node at a view.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", bound = "")]
+pub struct ViewTrace<B: BlockT> {
+	pub view: ViewNumber,
+	/// The digest and author of the proposal seen.
+	pub proposal: Option<(B::Hash, AuthorityId)>,
+	/// None until the node handled the proposal.
+	pub vote: Option<VoteDecision>,
+	pub qc_formed: bool,
+	pub tc_formed: bool,
+	/// None while the view may still commit or time out.
+	pub outcome: Option<ViewOutcome>,
+}
+
+impl<B: BlockT> ViewTrace<B> {
+	fn new(view: ViewNumber) -> Self {
+		Self { view, proposal: None, vote: None, qc_formed: false, tc_formed: false, outcome: None }
+	}
+}
+
+/// The traces of the last views.
+#[derive(Debug, Clone)]
+pub struct ViewTraces<B: BlockT> {
+	retention: usize,
+	traces: BTreeMap<ViewNumber, ViewTrace<B>>,
+}
+
+impl<B: BlockT> Default for ViewTraces<B> {
+	fn default() -> Self {
+		Self::new(VIEW_TRACE_RETENTION)
+	}
+}
+
+impl<B: BlockT> ViewTraces<B> {
+	pub fn new(retention: usize) -> Self {
+		Self { retention, traces: BTreeMap::new() }
+	}
+
+	pub fn get(&self, view: ViewNumber) -> Option<&ViewTrace<B>> {
+		self.traces.get(&view)
+	}
+
+	pub fn note_proposal(&mut self, view: ViewNumber, digest: B::Hash, author: AuthorityId) {
+		self.update(view, |trace| trace.proposal = Some((digest, author)));
+	}
+
+	pub fn note_vote(&mut self, view: ViewNumber, decision: VoteDecision) {
+		self.update(view, |trace| trace.vote = Some(decision));
+	}
+
+	pub fn note_qc(&mut self, view: ViewNumber) {
+		self.update(view, |trace| trace.qc_formed = true);
+	}
+
+	// A TC skips its view, unless the view committed already.
+	pub fn note_tc(&mut self, view: ViewNumber) {
+		self.update(view, |trace| {
+			trace.tc_formed = true;
+			trace.outcome.get_or_insert(ViewOutcome::Skipped);
+		});
+	}
+
+	pub fn note_commit(&mut self, view: ViewNumber) {
+		self.update(view, |trace| trace.outcome = Some(ViewOutcome::Committed));
+	}
+
+	// Views older than every retained one are dropped once the traces are full.
+	fn update(&mut self, view: ViewNumber, f: impl FnOnce(&mut ViewTrace<B>)) {
+		let full = self.traces.len() >= self.retention;
+		if full
+			&& !self.traces.contains_key(&view)
+			&& self.traces.keys().next().map_or(true, |oldest| view < *oldest)
+		{
+			return;
+		}
+
+		f(self.traces.entry(view).or_insert_with(|| ViewTrace::new(view)));
+		while self.traces.len() > self.retention {
+			self.traces.pop_first();
+		}
+	}
+}
@@ -0,0 +1,49 @@
+// The phase structure of consensus, which decides how many certified proposals in a row are
+// needed to commit the first of them.
+use sp_runtime::traits::Block as BlockT;
+
+use crate::message::{Proposal, QC};
+
+#[cfg(test)]
+#[path = "tests/variant_tests.rs"]
+pub mod variant_tests;
+
+/// Every authority must run the same variant. Both keep the voting rules of the chained form,
+/// which are safe for either commit rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsensusVariant {
+	/// Two-phase chained HotStuff: a proposal commits once its child is certified, the child
+	/// being of the next view.
+	#[default]
+	Chained,
+	/// Classic HotStuff with its prepare, pre-commit and commit phases pipelined: a proposal
+	/// commits once its child and grandchild are certified, each of the view after the one
+	/// before. Commits take a view longer.
+	ThreePhase,
+}
+
+impl ConsensusVariant {
+	/// Certified proposals in a row which commit the first of them.
+	pub fn chain_length(&self) -> usize {
+		match self {
+			Self::Chained => 2,
+			Self::ThreePhase => 3,
+		}
+	}
+
+	/// The proposal committed by `chain`, a proposal followed by its ancestors from its parent,
+	/// with the child of the committed proposal and the QC certifying the child.
+	pub fn committed<'a, B: BlockT>(
+		&self,
+		chain: &'a [Proposal<B>],
+	) -> Option<(&'a Proposal<B>, &'a Proposal<B>, &'a QC<B>)> {
+		// The ancestors of the first proposal, each certified by the QC of the one before.
+		let certified = chain.get(1..=self.chain_length())?;
+		if !certified.windows(2).all(|pair| pair[0].view == pair[1].view + 1) {
+			return None;
+		}
+
+		let length = certified.len();
+		Some((&certified[length - 1], &certified[length - 2], &chain[length - 2].qc))
+	}
+}
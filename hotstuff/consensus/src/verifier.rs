@@ -0,0 +1,288 @@
+// Verify the signatures of incoming messages on dedicated threads, so that the consensus loop
+// stays free to handle timers while a burst of messages is being checked, and shed the messages
+// of least value while the threads can't keep up.
+use std::{
+	sync::{mpsc, Arc},
+	thread,
+	time::Duration,
+};
+
+use parking_lot::{Mutex, RwLock};
+use sp_runtime::traits::Block as BlockT;
+use tokio::{
+	sync::mpsc::{unbounded_channel, UnboundedReceiver},
+	time::Instant,
+};
+
+use hotstuff_primitives::AuthorityList;
+
+use crate::{
+	committee::{voting_committee, CommitteeSampler},
+	message::{ConsensusMessage, Proposal, SigningContext, Vote},
+	primitives::{HotstuffError, SetId, ViewNumber},
+};
+
+#[cfg(test)]
+#[path = "tests/verifier_tests.rs"]
+pub mod verifier_tests;
+
+/// A message whose signatures can be verified off the consensus loop.
+#[derive(Debug, Clone)]
+pub enum Verifiable<B: BlockT> {
+	Proposal(Proposal<B>),
+	Vote(Vote<B>),
+}
+
+impl<B: BlockT> Verifiable<B> {
+	// Verify the message against the authorities `resolve` returns for the views it involves.
+	pub fn verify<F>(
+		&self,
+		resolve: F,
+		sampler: Option<&CommitteeSampler>,
+		context: &SigningContext,
+	) -> Result<(), HotstuffError>
+	where
+		F: Fn(ViewNumber) -> AuthorityList,
+	{
+		match self {
+			Verifiable::Proposal(proposal) => {
+				proposal.verify_with_resolver(resolve, sampler, context)
+			},
+			Verifiable::Vote(vote) => {
+				let authorities = resolve(vote.view);
+				vote.verify_with(&voting_committee(&authorities, sampler, vote.view), context)
+			},
+		}
+	}
+}
+
+impl<B: BlockT> From<Verifiable<B>> for ConsensusMessage<B> {
+	fn from(message: Verifiable<B>) -> Self {
+		match message {
+			Verifiable::Proposal(proposal) => ConsensusMessage::Propose(proposal),
+			Verifiable::Vote(vote) => ConsensusMessage::Vote(vote),
+		}
+	}
+}
+
+pub type Verified<B> = (Verifiable<B>, Result<(), HotstuffError>);
+
+/// The authorities the verification pool checks messages against: the current set, and the set
+/// before the last change for the views before it activated.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifierSet {
+	pub set_id: SetId,
+	pub authorities: AuthorityList,
+	pub previous: Option<(ViewNumber, AuthorityList)>,
+}
+
+impl VerifierSet {
+	pub fn new(authorities: AuthorityList) -> Self {
+		Self { set_id: 0, authorities, previous: None }
+	}
+
+	pub fn authorities_at(&self, view: ViewNumber) -> AuthorityList {
+		match &self.previous {
+			Some((activation_view, previous)) if view < *activation_view => previous.clone(),
+			_ => self.authorities.clone(),
+		}
+	}
+}
+
+pub struct VerificationPool<B: BlockT> {
+	// Idle threads take the next message from this shared queue, so a burst is spread over all
+	// of them.
+	task_tx: mpsc::SyncSender<Verifiable<B>>,
+	// Every result carries the message it was computed for, so results completing out of order
+	// on different threads can't be attributed to the wrong message.
+	result_rx: UnboundedReceiver<Verified<B>>,
+	pending: usize,
+	// Bound of `pending`, which keeps the memory of a burst bounded.
+	capacity: usize,
+	// Swapped by the worker as an authority set change activates, each message is verified
+	// against the set at the time a thread takes it.
+	authorities: Arc<RwLock<Arc<VerifierSet>>>,
+}
+
+impl<B: BlockT> VerificationPool<B> {
+	pub fn new(
+		threads: usize,
+		queue_depth: usize,
+		authorities: VerifierSet,
+		sampler: Option<CommitteeSampler>,
+		context: SigningContext,
+	) -> Self {
+		let capacity = queue_depth.max(1);
+		let (task_tx, task_rx) = mpsc::sync_channel::<Verifiable<B>>(capacity);
+		let (result_tx, result_rx) = unbounded_channel();
+		let task_rx = Arc::new(Mutex::new(task_rx));
+		let authorities = Arc::new(RwLock::new(Arc::new(authorities)));
+		let sampler = Arc::new(sampler);
+		let context = Arc::new(context);
+
+		for index in 0..threads.max(1) {
+			let task_rx = task_rx.clone();
+			let result_tx = result_tx.clone();
+			let authorities = authorities.clone();
+			let sampler = sampler.clone();
+			let context = context.clone();
+
+			thread::Builder::new()
+				.name(format!("hotstuff-verifier-{}", index))
+				.spawn(move || loop {
+					// The channel is closed once the pool is dropped.
+					let message = match task_rx.lock().recv() {
+						Ok(message) => message,
+						Err(_) => break,
+					};
+					let set = authorities.read().clone();
+					let result = message.verify(
+						|view| set.authorities_at(view),
+						sampler.as_ref().as_ref(),
+						&context,
+					);
+					if result_tx.send((message, result)).is_err() {
+						break;
+					}
+				})
+				.expect("spawn hotstuff verification thread failed");
+		}
+
+		Self { task_tx, result_rx, pending: 0, capacity, authorities }
+	}
+
+	pub fn set_id(&self) -> SetId {
+		self.authorities.read().set_id
+	}
+
+	// Verify the messages taken from now on against `authorities`.
+	pub fn set_authorities(&self, authorities: VerifierSet) {
+		*self.authorities.write() = Arc::new(authorities);
+	}
+
+	// Whether `capacity` messages are pending. Callers stop submitting until `next` returns one,
+	// pushing back on the network instead of growing the queue.
+	pub fn is_full(&self) -> bool {
+		self.pending >= self.capacity
+	}
+
+	// Blocks while the queue is full, see `is_full`.
+	pub fn submit(&mut self, message: Verifiable<B>) {
+		if self.task_tx.send(message).is_ok() {
+			self.pending += 1;
+		}
+	}
+
+	// Number of messages submitted but not yet returned by `next`.
+	pub fn pending(&self) -> usize {
+		self.pending
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.capacity
+	}
+
+	pub async fn next(&mut self) -> Option<Verified<B>> {
+		let verified = self.result_rx.recv().await;
+		if verified.is_some() {
+			self.pending -= 1;
+		}
+		verified
+	}
+}
+
+// Wait for the next verified message, never resolves if verification is not offloaded.
+pub async fn next_verified<B: BlockT>(
+	pool: &mut Option<VerificationPool<B>>,
+) -> Option<Verified<B>> {
+	match pool {
+		Some(pool) => pool.next().await,
+		None => futures::future::pending().await,
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverloadTransition {
+	/// The pool stayed saturated for the grace period.
+	Overloaded,
+	/// The queue of the pool drained.
+	Recovered,
+}
+
+/// Tells when the verification pool is overloaded: saturated, which pushes back on the network,
+/// for longer than a grace period. An overloaded node keeps reading messages but sheds those of
+/// least value, so the proposals and timeouts of the current view still get verified. The pool
+/// is saturated from the time it's full until its queue drains below half its capacity.
+pub struct OverloadMonitor {
+	grace: Duration,
+	saturated_since: Option<Instant>,
+	overloaded: bool,
+	shed: u64,
+}
+
+impl OverloadMonitor {
+	pub fn new(grace: Duration) -> Self {
+		Self { grace, saturated_since: None, overloaded: false, shed: 0 }
+	}
+
+	pub fn is_overloaded(&self) -> bool {
+		self.overloaded
+	}
+
+	/// The messages shed since the node started.
+	pub fn shed(&self) -> u64 {
+		self.shed
+	}
+
+	/// Note the pool has `pending` messages of its `capacity` at `now`.
+	pub fn observe(
+		&mut self,
+		pending: usize,
+		capacity: usize,
+		now: Instant,
+	) -> Option<OverloadTransition> {
+		if pending <= capacity / 2 {
+			self.saturated_since = None;
+			if self.overloaded {
+				self.overloaded = false;
+				return Some(OverloadTransition::Recovered);
+			}
+			return None;
+		}
+
+		if pending >= capacity {
+			let saturated_since = *self.saturated_since.get_or_insert(now);
+			if !self.overloaded && now.saturating_duration_since(saturated_since) >= self.grace {
+				self.overloaded = true;
+				return Some(OverloadTransition::Overloaded);
+			}
+		}
+		None
+	}
+
+	/// Whether an overloaded node sheds `message` received at `view`: the votes and proposals
+	/// of past views, the votes of later views while the pool is `full`, and sync requests.
+	/// Proposals of the current view or later, timeouts and TCs are always kept.
+	pub fn should_shed<B: BlockT>(
+		&mut self,
+		message: &ConsensusMessage<B>,
+		view: ViewNumber,
+		full: bool,
+	) -> bool {
+		if !self.overloaded {
+			return false;
+		}
+
+		let shed = match message {
+			ConsensusMessage::Propose(proposal) => proposal.view < view,
+			// A nil vote counts towards a TC, like the timeouts kept.
+			ConsensusMessage::Vote(vote) => !vote.is_nil() && (vote.view < view || full),
+			ConsensusMessage::SyncRequest(..) => true,
+			_ => false,
+		};
+		if shed {
+			self.shed += 1;
+		}
+		shed
+	}
+}
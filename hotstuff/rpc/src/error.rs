@@ -0,0 +1,36 @@
+//! Errors of the Hotstuff RPC methods.
+use jsonrpsee::{
+	core::Error as JsonRpseeError,
+	types::error::{CallError, ErrorObject},
+};
+
+use hotstuff_consensus::primitives::HotstuffError;
+
+/// Offset of the Hotstuff error codes, clear of the codes reserved by JSON-RPC.
+pub const BASE_ERROR: i32 = 9000;
+
+/// An error of a Hotstuff RPC method, returned with the stable code of the error.
+#[derive(Debug)]
+pub struct Error(pub HotstuffError);
+
+impl From<HotstuffError> for Error {
+	fn from(error: HotstuffError) -> Self {
+		Error(error)
+	}
+}
+
+impl From<Error> for JsonRpseeError {
+	fn from(error: Error) -> Self {
+		let code = BASE_ERROR + error.0.error_code();
+		// The variant is the message, and what it carries is the data.
+		let debug = format!("{:?}", error.0);
+		let (message, data) = match debug.split_once('(') {
+			Some((variant, detail)) => {
+				(variant.to_string(), Some(detail.trim_end_matches(')').to_string()))
+			},
+			None => (debug, None),
+		};
+
+		JsonRpseeError::Call(CallError::Custom(ErrorObject::owned(code, message, data)))
+	}
+}
@@ -0,0 +1,203 @@
+//! RPC API for Hotstuff.
+use std::fmt;
+
+use futures::{stream, FutureExt};
+use jsonrpsee::{
+	core::{error::SubscriptionClosed, RpcResult},
+	proc_macros::rpc,
+	types::SubscriptionResult,
+	SubscriptionSink,
+};
+use sc_rpc::SubscriptionTaskExecutor;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use tokio::sync::broadcast::error::RecvError;
+
+use hotstuff_consensus::{
+	block_tree::{BlockTreeView, ForkInfo, SharedBlockTree},
+	certificates::{QcSummary, SharedCertificates, TcSummary},
+	events::{ConsensusEvent, EventSink},
+	pacemaker::{PacemakerState, SharedPacemaker},
+	participation::SharedVoteParticipation,
+	primitives::HotstuffError,
+	signing::{KeystoreSelfTest, SharedSigningStatus, SigningStatus},
+};
+use hotstuff_primitives::AuthorityId;
+
+mod error;
+
+pub use error::{Error, BASE_ERROR};
+
+#[cfg(test)]
+#[path = "tests/rpc_tests.rs"]
+mod rpc_tests;
+
+/// Provides RPC methods for inspecting the Hotstuff voter.
+#[rpc(server)]
+pub trait HotstuffApi<Event, Hash, Number> {
+	/// Returns the known proposals as a tree rooted at the last committed proposal.
+	#[method(name = "hotstuff_blockTree")]
+	fn block_tree(&self) -> RpcResult<BlockTreeView<Hash, Number>>;
+
+	/// Returns each branch above the last committed proposal with its tip, highest certified
+	/// view and depth, the deepest first. A branch staying deep points to a network problem.
+	#[method(name = "hotstuff_activeForks")]
+	fn active_forks(&self) -> RpcResult<Vec<ForkInfo<Hash, Number>>>;
+
+	/// Returns whether the node signs as an authority of the current set, and the views and
+	/// counts of what it signed.
+	#[method(name = "hotstuff_signingStatus")]
+	fn signing_status(&self) -> RpcResult<SigningStatus>;
+
+	/// Returns the share of the recent views each authority could vote in which it voted in.
+	#[method(name = "hotstuff_participation")]
+	fn participation(&self) -> RpcResult<Vec<(AuthorityId, f64)>>;
+
+	/// Returns the view, timeout and time left of the pacemaker, and how often in a row it timed
+	/// out.
+	#[method(name = "hotstuff_pacemaker")]
+	fn pacemaker(&self) -> RpcResult<PacemakerState>;
+
+	/// Returns the QC the voter is locked on, none before the voter starts.
+	#[method(name = "hotstuff_lockedQc")]
+	fn locked_qc(&self) -> RpcResult<Option<QcSummary<Hash>>>;
+
+	/// Returns the highest TC of the voter, none once a QC of its view or a later one is known.
+	#[method(name = "hotstuff_highTc")]
+	fn high_tc(&self) -> RpcResult<Option<TcSummary<Hash>>>;
+
+	/// Signs a known message with the key of `authority`, by default the authority the node
+	/// signs for, and verifies the signature, to check the keystore can sign before the node
+	/// joins consensus. Only served by nodes which enable it.
+	#[method(name = "hotstuff_keystoreSelftest")]
+	fn keystore_selftest(&self, authority: Option<AuthorityId>) -> RpcResult<()>;
+
+	/// Streams the events of the voter, such as view changes, commits, timeouts and QC
+	/// formations. A subscriber falling too far behind is dropped with a lag notice.
+	#[subscription(
+		name = "hotstuff_subscribeEvents" => "hotstuff_event",
+		unsubscribe = "hotstuff_unsubscribeEvents",
+		item = Event
+	)]
+	fn subscribe_events(&self);
+}
+
+// Ends the subscription of a subscriber which missed events.
+struct Lagged(u64);
+
+impl fmt::Display for Lagged {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Subscriber lagged behind, {} events were dropped", self.0)
+	}
+}
+
+/// Provides RPC methods for inspecting the Hotstuff voter.
+pub struct Hotstuff<Block: BlockT> {
+	block_tree: SharedBlockTree<Block>,
+	events: EventSink<Block>,
+	signing_status: SharedSigningStatus,
+	participation: SharedVoteParticipation,
+	pacemaker: SharedPacemaker,
+	certificates: SharedCertificates<Block>,
+	keystore_selftest: Option<KeystoreSelfTest>,
+	executor: SubscriptionTaskExecutor,
+}
+
+impl<Block: BlockT> Hotstuff<Block> {
+	/// Prepare a new [`Hotstuff`] RPC handler.
+	pub fn new(
+		block_tree: SharedBlockTree<Block>,
+		events: EventSink<Block>,
+		signing_status: SharedSigningStatus,
+		participation: SharedVoteParticipation,
+		pacemaker: SharedPacemaker,
+		certificates: SharedCertificates<Block>,
+		executor: SubscriptionTaskExecutor,
+	) -> Self {
+		Self {
+			block_tree,
+			events,
+			signing_status,
+			participation,
+			pacemaker,
+			certificates,
+			keystore_selftest: None,
+			executor,
+		}
+	}
+
+	/// Serve `hotstuff_keystoreSelftest` with `selftest`. It signs with the keys of the node, so
+	/// it's meant for nodes allowing unsafe RPC methods.
+	pub fn with_keystore_selftest(mut self, selftest: KeystoreSelfTest) -> Self {
+		self.keystore_selftest = Some(selftest);
+		self
+	}
+}
+
+impl<Block: BlockT> HotstuffApiServer<ConsensusEvent<Block>, Block::Hash, NumberFor<Block>>
+	for Hotstuff<Block>
+{
+	fn block_tree(&self) -> RpcResult<BlockTreeView<Block::Hash, NumberFor<Block>>> {
+		Ok(self.block_tree.read().view())
+	}
+
+	fn active_forks(&self) -> RpcResult<Vec<ForkInfo<Block::Hash, NumberFor<Block>>>> {
+		Ok(self.block_tree.read().active_forks())
+	}
+
+	fn signing_status(&self) -> RpcResult<SigningStatus> {
+		Ok(self.signing_status.read().clone())
+	}
+
+	fn participation(&self) -> RpcResult<Vec<(AuthorityId, f64)>> {
+		Ok(self.participation.read().participation())
+	}
+
+	fn pacemaker(&self) -> RpcResult<PacemakerState> {
+		Ok(self.pacemaker.state())
+	}
+
+	fn locked_qc(&self) -> RpcResult<Option<QcSummary<Block::Hash>>> {
+		Ok(self
+			.certificates
+			.read()
+			.as_ref()
+			.map(|certificates| certificates.locked_qc.clone()))
+	}
+
+	fn high_tc(&self) -> RpcResult<Option<TcSummary<Block::Hash>>> {
+		Ok(self
+			.certificates
+			.read()
+			.as_ref()
+			.and_then(|certificates| certificates.high_tc.clone()))
+	}
+
+	fn keystore_selftest(&self, authority: Option<AuthorityId>) -> RpcResult<()> {
+		let selftest = self.keystore_selftest.as_ref().ok_or_else(|| {
+			Error(HotstuffError::Other("the keystore self-test isn't served".to_string()))
+		})?;
+		selftest.run(authority).map_err(|e| Error(e).into())
+	}
+
+	fn subscribe_events(&self, mut sink: SubscriptionSink) -> SubscriptionResult {
+		// The voter never waits for a subscriber. Once one misses events it's dropped.
+		let events = stream::unfold(self.events.subscribe(), |mut events| async move {
+			match events.recv().await {
+				Ok(event) => Some((Ok(event), events)),
+				Err(RecvError::Lagged(missed)) => Some((Err(Lagged(missed)), events)),
+				Err(RecvError::Closed) => None,
+			}
+		});
+
+		let fut = async move {
+			if let SubscriptionClosed::Failed(notice) =
+				sink.pipe_from_try_stream(Box::pin(events)).await
+			{
+				sink.close(notice);
+			}
+		};
+
+		self.executor.spawn("hotstuff-rpc-subscription", Some("rpc"), fut.boxed());
+		Ok(())
+	}
+}
@@ -0,0 +1,201 @@
+use super::*;
+
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use futures::future::BoxFuture;
+use jsonrpsee::{
+	core::server::rpc_module::Subscription,
+	types::{error::CallError, EmptyServerParams as EmptyParams},
+};
+use serde_json::{json, Value};
+use sp_core::{sr25519, traits::SpawnNamed, H256};
+use sp_runtime::testing::{Block as RawBlock, TestXt};
+
+use hotstuff_consensus::{
+	block_tree::BlockTree,
+	certificates::Certificates,
+	clock::CommitTime,
+	message::{Payload, Proposal, QC, TC},
+	participation::VoteParticipation,
+};
+
+type Block = RawBlock<TestXt<(), ()>>;
+
+// Runs the subscription tasks on the runtime of the test, whose single thread only gets to them
+// when the test awaits.
+#[derive(Clone)]
+struct TestExecutor;
+
+impl SpawnNamed for TestExecutor {
+	fn spawn_blocking(
+		&self,
+		_: &'static str,
+		_: Option<&'static str>,
+		task: BoxFuture<'static, ()>,
+	) {
+		tokio::spawn(task);
+	}
+
+	fn spawn(&self, _: &'static str, _: Option<&'static str>, task: BoxFuture<'static, ()>) {
+		tokio::spawn(task);
+	}
+}
+
+fn setup() -> (EventSink<Block>, jsonrpsee::RpcModule<Hotstuff<Block>>) {
+	let (events, _, rpc) = setup_with_certificates();
+	(events, rpc)
+}
+
+fn setup_with_certificates(
+) -> (EventSink<Block>, SharedCertificates<Block>, jsonrpsee::RpcModule<Hotstuff<Block>>) {
+	let events = EventSink::new();
+	let certificates = SharedCertificates::<Block>::default();
+	let rpc = Hotstuff::new(
+		Default::default(),
+		events.clone(),
+		SigningStatus::shared(),
+		VoteParticipation::shared(),
+		SharedPacemaker::new(),
+		certificates.clone(),
+		Arc::new(TestExecutor),
+	);
+	(events, certificates, rpc.into_rpc())
+}
+
+async fn next_event(sub: &mut Subscription) -> Value {
+	let event = tokio::time::timeout(Duration::from_secs(5), sub.next::<Value>()).await;
+	event.expect("event arrives in time").expect("subscription is open").unwrap().0
+}
+
+#[tokio::test]
+async fn subscription_should_stream_commits() {
+	let (events, rpc) = setup();
+	let mut sub = rpc.subscribe("hotstuff_subscribeEvents", EmptyParams::new()).await.unwrap();
+
+	events.emit(ConsensusEvent::QcFormed { view: 3, proposal_hash: H256::repeat_byte(3) });
+	events.emit(ConsensusEvent::Committed {
+		view: 3,
+		block_hash: H256::repeat_byte(1),
+		block_number: 1,
+		time: CommitTime { timestamp: 1_700_000_000_000, local: false },
+	});
+
+	assert_eq!(next_event(&mut sub).await["event"], "qcFormed");
+	assert_eq!(
+		next_event(&mut sub).await,
+		json!({
+			"event": "committed",
+			"view": 3,
+			"blockHash": format!("{:?}", H256::repeat_byte(1)),
+			"blockNumber": 1,
+			"time": { "timestamp": 1_700_000_000_000u64, "local": false },
+		})
+	);
+}
+
+#[tokio::test]
+async fn lagging_subscriber_should_be_dropped() {
+	let (events, rpc) = setup();
+	let mut sub = rpc.subscribe("hotstuff_subscribeEvents", EmptyParams::new()).await.unwrap();
+
+	// The subscription task doesn't run while the voter emits more events than are buffered.
+	for view in 0..200 {
+		events.emit(ConsensusEvent::ViewChanged { view });
+	}
+
+	let delivered = tokio::time::timeout(Duration::from_secs(5), async {
+		let mut delivered = 0;
+		while let Some(event) = sub.next::<Value>().await {
+			event.unwrap();
+			delivered += 1;
+		}
+		delivered
+	})
+	.await;
+	assert_eq!(delivered, Ok(0));
+}
+
+#[tokio::test]
+async fn certificates_should_be_reported_once_published() {
+	let (_, certificates, rpc) = setup_with_certificates();
+
+	// Nothing is reported before the voter publishes its certificates.
+	let locked_qc = rpc.call::<_, Value>("hotstuff_lockedQc", EmptyParams::new()).await.unwrap();
+	assert_eq!(locked_qc, Value::Null);
+
+	let qc = QC::<Block> { proposal_hash: H256::repeat_byte(2), view: 2, votes: vec![] };
+	let tc = TC::<Block> { view: 4, votes: vec![], high_qc: qc.clone(), _phantom: PhantomData };
+	*certificates.write() =
+		Some(Certificates { locked_qc: (&qc).into(), high_tc: Some((&tc).into()) });
+
+	let locked_qc = rpc.call::<_, Value>("hotstuff_lockedQc", EmptyParams::new()).await.unwrap();
+	let expected_qc = json!({
+		"view": 2,
+		"proposalHash": format!("{:?}", H256::repeat_byte(2)),
+		"signers": 0,
+	});
+	assert_eq!(locked_qc, expected_qc);
+
+	let high_tc = rpc.call::<_, Value>("hotstuff_highTc", EmptyParams::new()).await.unwrap();
+	assert_eq!(high_tc, json!({ "view": 4, "highQc": expected_qc, "signers": 0 }));
+}
+
+#[tokio::test]
+async fn active_forks_should_be_reported() {
+	let block_tree = BlockTree::<Block>::shared();
+	let rpc = Hotstuff::new(
+		block_tree.clone(),
+		EventSink::new(),
+		SigningStatus::shared(),
+		VoteParticipation::shared(),
+		SharedPacemaker::new(),
+		Default::default(),
+		Arc::new(TestExecutor),
+	)
+	.into_rpc();
+
+	// Nothing is above the genesis QC yet.
+	let forks = rpc.call::<_, Value>("hotstuff_activeForks", EmptyParams::new()).await.unwrap();
+	assert_eq!(forks, json!([]));
+
+	// A proposal of view 1 extended by two proposals of distinct blocks.
+	let author: AuthorityId = sr25519::Public::from_raw([1; 32]).into();
+	let authorities = vec![(author.clone(), 1)];
+	let propose = |qc: QC<Block>, view, byte| {
+		let payload = Payload::<Block> { block_hash: H256::repeat_byte(byte), block_number: view };
+		Proposal::<Block>::new(qc, None, payload, view, author.clone(), None)
+	};
+	let parent = propose(QC::default(), 1, 1);
+	let parent_qc = QC::<Block> { proposal_hash: parent.digest(), view: 1, votes: vec![] };
+	let (fork_a, fork_b) = (propose(parent_qc.clone(), 2, 2), propose(parent_qc, 3, 3));
+	for proposal in [&parent, &fork_a, &fork_b] {
+		block_tree.write().insert(proposal, &authorities);
+	}
+
+	let forks = rpc.call::<_, Value>("hotstuff_activeForks", EmptyParams::new()).await.unwrap();
+	let fork = |proposal: &Proposal<Block>, byte: u8| {
+		json!({
+			"tip": format!("{:?}", proposal.digest()),
+			"tipView": proposal.view,
+			"blockHash": format!("{:?}", H256::repeat_byte(byte)),
+			"blockNumber": proposal.view,
+			"highQcView": 1,
+			"depth": 2,
+		})
+	};
+	assert_eq!(forks, json!([fork(&fork_a, 2), fork(&fork_b, 3)]));
+}
+
+#[tokio::test]
+async fn keystore_selftest_should_be_refused_unless_served() {
+	let (_, rpc) = setup();
+
+	let error = rpc.call::<_, Value>("hotstuff_keystoreSelftest", [None::<AuthorityId>]).await;
+	match error {
+		Err(jsonrpsee::core::Error::Call(CallError::Custom(error))) => {
+			assert_eq!(error.code(), BASE_ERROR + HotstuffError::Other(String::new()).error_code());
+			assert_eq!(error.message(), "Other");
+		},
+		other => panic!("the self-test isn't served, got {:?}", other),
+	}
+}
@@ -16,6 +16,26 @@ use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
 
 pub use sc_rpc_api::DenyUnsafe;
 
+/// Extra dependencies for Hotstuff.
+pub struct HotstuffDeps {
+	/// Block tree shared with the Hotstuff voter.
+	pub block_tree: hotstuff_consensus::block_tree::SharedBlockTree<Block>,
+	/// Consensus events emitted by the Hotstuff voter.
+	pub events: hotstuff_consensus::events::EventSink<Block>,
+	/// What the Hotstuff voter signs.
+	pub signing_status: hotstuff_consensus::signing::SharedSigningStatus,
+	/// Vote participation of the authorities, measured by the Hotstuff voter.
+	pub participation: hotstuff_consensus::participation::SharedVoteParticipation,
+	/// State of the pacemaker, published by the Hotstuff voter.
+	pub pacemaker: hotstuff_consensus::pacemaker::SharedPacemaker,
+	/// Locked QC and highest TC, published by the Hotstuff voter.
+	pub certificates: hotstuff_consensus::certificates::SharedCertificates<Block>,
+	/// Self-test of the keystore of the Hotstuff voter, served to unsafe callers only.
+	pub keystore_selftest: hotstuff_consensus::signing::KeystoreSelfTest,
+	/// Executor to drive the subscription manager in the Hotstuff RPC handler.
+	pub subscription_executor: sc_rpc::SubscriptionTaskExecutor,
+}
+
 /// Full client dependencies.
 pub struct FullDeps<C, P> {
 	/// The client instance to use.
@@ -24,6 +44,8 @@ pub struct FullDeps<C, P> {
 	pub pool: Arc<P>,
 	/// Whether to deny unsafe calls
 	pub deny_unsafe: DenyUnsafe,
+	/// Hotstuff specific dependencies.
+	pub hotstuff: HotstuffDeps,
 }
 
 /// Instantiate all full RPC extensions.
@@ -39,14 +61,38 @@ where
 	C::Api: BlockBuilder<Block>,
 	P: TransactionPool + 'static,
 {
+	use hotstuff_rpc::{Hotstuff, HotstuffApiServer};
 	use pallet_transaction_payment_rpc::{TransactionPayment, TransactionPaymentApiServer};
 	use substrate_frame_rpc_system::{System, SystemApiServer};
 
 	let mut module = RpcModule::new(());
-	let FullDeps { client, pool, deny_unsafe } = deps;
+	let FullDeps { client, pool, deny_unsafe, hotstuff } = deps;
+	let HotstuffDeps {
+		block_tree,
+		events,
+		signing_status,
+		participation,
+		pacemaker,
+		certificates,
+		keystore_selftest,
+		subscription_executor,
+	} = hotstuff;
 
 	module.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
 	module.merge(TransactionPayment::new(client).into_rpc())?;
+	let mut hotstuff_rpc = Hotstuff::new(
+		block_tree,
+		events,
+		signing_status,
+		participation,
+		pacemaker,
+		certificates,
+		subscription_executor,
+	);
+	if deny_unsafe.check_if_safe().is_ok() {
+		hotstuff_rpc = hotstuff_rpc.with_keystore_selftest(keystore_selftest);
+	}
+	module.merge(hotstuff_rpc.into_rpc())?;
 
 	// Extend this RPC with a custom API by using the following syntax.
 	// `YourRpcStruct` should have a reference to a client, which is needed
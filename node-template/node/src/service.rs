@@ -7,7 +7,7 @@ use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
 pub use sc_executor::NativeElseWasmExecutor;
 use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
-use sc_transaction_pool_api::OffchainTransactionPoolFactory;
+use sc_transaction_pool_api::{OffchainTransactionPoolFactory, TransactionPool};
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
 
 use node_template_runtime::{self, opaque::Block, RuntimeApi};
@@ -92,6 +92,12 @@ pub fn new_partial(
 
 	let (hotstuff_block_import, grandpa_link) =
 		hotstuff_consensus::block_import(client.clone(), &client)?;
+	// Verify justifications in the signing context of the voter.
+	let hotstuff_block_import =
+		hotstuff_block_import.with_config(&hotstuff_consensus::config::Config {
+			fork_id: config.chain_spec.fork_id().map(ToString::to_string),
+			..Default::default()
+		});
 
 	let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
 
@@ -191,6 +197,9 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 	let backoff_authoring_blocks: Option<()> = None;
 
 	let role = config.role.clone();
+	let hotstuff_fork_id = config.chain_spec.fork_id().map(ToString::to_string);
+	let hotstuff_audit_log =
+		config.base_path.config_dir(config.chain_spec.id()).join("hotstuff_audit.log");
 	let force_authoring = config.force_authoring;
 	let _name = config.network.node_name.clone();
 	let prometheus_registry = config.prometheus_registry().cloned();
@@ -198,10 +207,33 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 	let rpc_extensions_builder = {
 		let client = client.clone();
 		let pool = transaction_pool.clone();
+		let block_tree = hotstuff_link.shared_block_tree();
+		let events = hotstuff_link.event_sink();
+		let signing_status = hotstuff_link.shared_signing_status();
+		let participation = hotstuff_link.shared_vote_participation();
+		let pacemaker = hotstuff_link.shared_pacemaker();
+		let certificates = hotstuff_link.shared_certificates();
+		let keystore_selftest = hotstuff_consensus::signing::KeystoreSelfTest::new(
+			keystore_container.keystore(),
+			signing_status.clone(),
+		);
 
-		Box::new(move |deny_unsafe, _| {
-			let deps =
-				crate::rpc::FullDeps { client: client.clone(), pool: pool.clone(), deny_unsafe };
+		Box::new(move |deny_unsafe, subscription_executor| {
+			let deps = crate::rpc::FullDeps {
+				client: client.clone(),
+				pool: pool.clone(),
+				deny_unsafe,
+				hotstuff: crate::rpc::HotstuffDeps {
+					block_tree: block_tree.clone(),
+					events: events.clone(),
+					signing_status: signing_status.clone(),
+					participation: participation.clone(),
+					pacemaker: pacemaker.clone(),
+					certificates: certificates.clone(),
+					keystore_selftest: keystore_selftest.clone(),
+					subscription_executor,
+				},
+			};
 			crate::rpc::create_full(deps).map_err(Into::into)
 		})
 	};
@@ -277,6 +309,18 @@ pub fn new_full(config: Configuration) -> Result<TaskManager, ServiceError> {
 			Arc::new(sync_service),
 			hotstuff_protocol_name,
 			keystore_container.keystore(),
+			hotstuff_consensus::config::Config {
+				audit_log_path: Some(hotstuff_audit_log),
+				prometheus_registry: prometheus_registry.clone(),
+				ordered_signing: true,
+				fork_id: hotstuff_fork_id,
+				// Held weakly, so the voter goes on with empty proposals once the pool is gone.
+				pool_status: Some(hotstuff_consensus::pool::PoolStatus::from_weak(
+					Arc::downgrade(&transaction_pool),
+					|pool| pool.status().ready,
+				)),
+				..Default::default()
+			},
 		)?;
 
 		task_manager